@@ -5,22 +5,79 @@ use mongodb;
 use mongodb::Client;
 use mongodb::options::{ClientOptions, ServerApi, ServerApiVersion};
 use shuttle_runtime::SecretStore;
+use std::sync::Arc;
+use std::time::Duration;
 use auth_module::auth_router_builder::AuthRouterBuilder;
-use auth_module::utils::settings::AuthSettings;
+use auth_module::datastore::mongo::tokens::MongoTokenDatastore;
+use auth_module::layer::revocation::{TokenRevocationCheck, TokenRevocationChecker};
+use auth_module::utils::access_rules::AccessRules;
+use auth_module::utils::auth_config::AuthConfig;
+use auth_module::utils::authorization_server::AuthorizationServer;
+use auth_module::utils::key_ring::KeyRing;
+use auth_module::utils::oidc::OidcProvider;
+use auth_module::utils::password_hashing::PasswordHashing;
+use auth_module::utils::password_policy::PasswordPolicy;
+use auth_module::utils::password_reset_sender::LogPasswordResetSender;
+use auth_module::utils::token_codec::{JwtCodec, PasetoLocalCodec, ReferenceTokenCodec};
+use auth_module::utils::trusted_proxies::TrustedProxies;
+use auth_module::utils::webauthn::RelyingParty;
 use user_module::user_router_builder::UserRouterBuilder;
 use base64::Engine;
 use base64::engine::general_purpose;
+use chrono::TimeDelta;
+use crate::utils::connect_info_service::ConnectInfoService;
 
 #[shuttle_runtime::main]
-async fn main(#[shuttle_runtime::Secrets] secrets: SecretStore) -> shuttle_axum::ShuttleAxum {
+async fn main(#[shuttle_runtime::Secrets] secrets: SecretStore) -> Result<ConnectInfoService, shuttle_runtime::Error> {
     let mongodb_uri = secrets.get("MONGODB_CLUSTER_URI").expect("No MONGODB_CLUSTER_URI found in Secret.toml. See README");
 
     let paseto_secret_key = secrets.get("PASETO_SECRET_KEY").expect("No PASETO_SECRET_KEY found in Secret.toml. See README");
     let paseto_public_key = secrets.get("PASETO_PUBLIC_KEY").expect("No PASETO_PUBLIC_KEY found in Secret.toml. See README");
 
+    let mut key_ring = KeyRing::new();
+    key_ring.add_signing_key(&general_purpose::STANDARD.decode(paseto_secret_key).expect("Unable decode PASETO_SECRET_KEY")).expect("Invalid PASETO_SECRET_KEY");
+    key_ring.add_verification_key(&general_purpose::STANDARD.decode(paseto_public_key).expect("Unable decode PASETO_PUBLIC_KEY")).expect("Invalid PASETO_PUBLIC_KEY");
 
-    AuthSettings::set_secret_key(&general_purpose::STANDARD.decode(paseto_secret_key).expect("Unable decode key to init AuthSettings"));
-    AuthSettings::set_public_key(&general_purpose::STANDARD.decode(paseto_public_key).expect("Unable decode key to init AuthSettings"));
+    // Optional : public keys of the previous secret keys, tokens they signed are accepted until they expire
+    if let Some(paseto_previous_public_keys) = secrets.get("PASETO_PREVIOUS_PUBLIC_KEYS") {
+        for paseto_previous_public_key in paseto_previous_public_keys.split(',').map(str::trim).filter(|public_key| !public_key.is_empty()) {
+            key_ring.add_verification_key(&general_purpose::STANDARD.decode(paseto_previous_public_key).expect("Unable decode PASETO_PREVIOUS_PUBLIC_KEYS")).expect("Invalid PASETO_PREVIOUS_PUBLIC_KEYS");
+        }
+    }
+    let mut auth_config = AuthConfig::new(key_ring);
+
+    // Optional : lifetimes of the tokens, and the issuer and audience they are bound to
+    if let Some(access_token_lifetime) = secrets.get("ACCESS_TOKEN_LIFETIME_SECONDS") {
+        auth_config = auth_config.with_access_token_lifetime(TimeDelta::seconds(access_token_lifetime.parse().expect("ACCESS_TOKEN_LIFETIME_SECONDS must be a number of seconds")));
+    }
+    if let Some(refresh_token_lifetime) = secrets.get("REFRESH_TOKEN_LIFETIME_SECONDS") {
+        auth_config = auth_config.with_refresh_token_lifetime(TimeDelta::seconds(refresh_token_lifetime.parse().expect("REFRESH_TOKEN_LIFETIME_SECONDS must be a number of seconds")));
+    }
+    if let Some(token_issuer) = secrets.get("TOKEN_ISSUER") {
+        auth_config = auth_config.with_issuer(&token_issuer);
+    }
+    if let Some(token_audience) = secrets.get("TOKEN_AUDIENCE") {
+        auth_config = auth_config.with_audience(&token_audience);
+    }
+
+    // Optional : Argon2id cost parameters and pepper of the password hashing
+    let mut password_hashing = PasswordHashing::default();
+    if let Some(argon2_params) = secrets.get("ARGON2_PARAMS") {
+        let argon2_params: Vec<u32> = argon2_params.split(',').map(|param| param.trim().parse().expect("ARGON2_PARAMS must be numbers")).collect();
+        let [memory_cost_kib, time_cost, parallelism] = argon2_params[..] else {
+            panic!("ARGON2_PARAMS must be \"memory_cost_kib,time_cost,parallelism\"");
+        };
+        password_hashing = password_hashing.with_argon2_params(memory_cost_kib, time_cost, parallelism);
+    }
+    if let Some(password_pepper) = secrets.get("PASSWORD_PEPPER") {
+        password_hashing = password_hashing.with_pepper(&general_purpose::STANDARD.decode(password_pepper).expect("Unable decode PASSWORD_PEPPER"));
+    }
+    auth_config = auth_config.with_password_hashing(password_hashing);
+
+    // Optional : deny-list of breached or common passwords, one by line
+    if let Some(password_deny_list_file) = secrets.get("PASSWORD_DENY_LIST_FILE") {
+        auth_config = auth_config.with_password_policy(PasswordPolicy::default().with_deny_list_file(password_deny_list_file).expect("Unable read PASSWORD_DENY_LIST_FILE"));
+    }
 
     let mut client_options =
         ClientOptions::parse(mongodb_uri).await.expect("Unable to parse MONGODB_CLUSTER_URI.");
@@ -30,14 +87,94 @@ async fn main(#[shuttle_runtime::Secrets] secrets: SecretStore) -> shuttle_axum:
     // Get a handle to the cluster
     let mongodb_client_cluster = Client::with_options(client_options).expect("Unable to connect mongodb DATABASE.");
 
+    // Optional : format of the tokens, PASETO v4.public by default
+    match secrets.get("TOKEN_FORMAT").as_deref() {
+        None | Some("paseto_public") => {}
+        Some("paseto_local") => {
+            let paseto_local_key = secrets.get("PASETO_LOCAL_KEY").expect("No PASETO_LOCAL_KEY found in Secret.toml. See README");
+            let token_codec = PasetoLocalCodec::new(&general_purpose::STANDARD.decode(paseto_local_key).expect("Unable decode PASETO_LOCAL_KEY")).expect("Invalid PASETO_LOCAL_KEY");
+            auth_config = auth_config.with_token_codec(Arc::new(token_codec));
+        }
+        Some("jwt") => auth_config = auth_config.with_token_codec(Arc::new(JwtCodec)),
+        Some("reference") => {
+            let token_datastore = MongoTokenDatastore::new(&mongodb_client_cluster.database(&secrets.get("MONGODB_AUTH_DATABASE").unwrap_or("auth".to_string())));
+            auth_config = auth_config.with_token_codec(Arc::new(ReferenceTokenCodec::new(token_datastore)));
+        }
+        Some(token_format) => panic!("Unknown TOKEN_FORMAT {token_format}, expected paseto_public, paseto_local, jwt or reference"),
+    }
+
+    let mut auth_router_module = AuthRouterBuilder::new(&mongodb_client_cluster.database(&secrets.get("MONGODB_AUTH_DATABASE").unwrap_or("auth".to_string())), auth_config.clone());
+    let mut user_router_module = UserRouterBuilder::new(&mongodb_client_cluster.database(&secrets.get("MONGODB_AUTH_DATABASE").unwrap_or("auth".to_string())), &mongodb_client_cluster.database(&secrets.get("MONGODB_USER_DATABASE").unwrap_or("users".to_string())), auth_config);
+
+    // Optional : privileges of the routes set by a rules file, with the overrides of the environment
+    if let Some(access_rules_file) = secrets.get("ACCESS_RULES_FILE") {
+        let access_rules = AccessRules::from_file(access_rules_file, secrets.get("ENVIRONMENT").as_deref()).expect("Unable load ACCESS_RULES_FILE");
+        access_rules.check_modules(&["auth", "user"]).expect("Invalid ACCESS_RULES_FILE");
+
+        auth_router_module = auth_router_module.with_access_rules(&access_rules).expect("Invalid ACCESS_RULES_FILE");
+        user_router_module = user_router_module.with_access_rules(&access_rules).expect("Invalid ACCESS_RULES_FILE");
+    }
+
+    // Optional : check revocation of access tokens, cached during the TTL given
+    if let Some(revocation_cache_ttl) = secrets.get("ACCESS_TOKEN_REVOCATION_CACHE_TTL_SECONDS") {
+        let revocation_cache_ttl = revocation_cache_ttl.parse().expect("ACCESS_TOKEN_REVOCATION_CACHE_TTL_SECONDS must be a number of seconds");
+        let token_datastore = MongoTokenDatastore::new(&mongodb_client_cluster.database(&secrets.get("MONGODB_AUTH_DATABASE").unwrap_or("auth".to_string())));
+        let revocation_check: Arc<dyn TokenRevocationCheck> = Arc::new(TokenRevocationChecker::new(token_datastore, Duration::from_secs(revocation_cache_ttl)));
+
+        auth_router_module = auth_router_module.with_revocation_check(revocation_check.clone());
+        user_router_module = user_router_module.with_revocation_check(revocation_check);
+    }
+
+    // Optional : write password reset tokens in a file (local development only), password resets are refused without it
+    if let Some(password_reset_log_file) = secrets.get("PASSWORD_RESET_LOG_FILE") {
+        auth_router_module = auth_router_module.with_password_reset_sender(Arc::new(LogPasswordResetSender::with_file(password_reset_log_file)));
+    }
+
+    // Optional : site passkeys are bound to, both required outside of local development
+    if let (Some(webauthn_rp_id), Some(webauthn_origin)) = (secrets.get("WEBAUTHN_RP_ID"), secrets.get("WEBAUTHN_ORIGIN")) {
+        let webauthn_rp_name = secrets.get("WEBAUTHN_RP_NAME").unwrap_or(webauthn_rp_id.clone());
+        auth_router_module = auth_router_module.with_webauthn_relying_party(RelyingParty::new(&webauthn_rp_id, &webauthn_rp_name, &webauthn_origin));
+    }
+
+    // Optional : reverse proxies whose X-Forwarded-For header gives the client IP of the login throttling
+    if let Some(trusted_proxies) = secrets.get("TRUSTED_PROXIES") {
+        auth_router_module = auth_router_module.with_trusted_proxies(trusted_proxies.parse::<TrustedProxies>().expect("TRUSTED_PROXIES must be IP addresses or CIDR ranges"));
+    }
+
+    // Optional : name displayed by authenticator apps for TOTP
+    if let Some(totp_issuer) = secrets.get("TOTP_ISSUER") {
+        auth_router_module = auth_router_module.with_totp_issuer(&totp_issuer);
+    }
 
-    let auth_router_module = AuthRouterBuilder::new(&mongodb_client_cluster.database(&secrets.get("MONGODB_AUTH_DATABASE").unwrap_or("auth".to_string())));
-    let user_router_module = UserRouterBuilder::new(&mongodb_client_cluster.database(&secrets.get("MONGODB_AUTH_DATABASE").unwrap_or("auth".to_string())), &mongodb_client_cluster.database(&secrets.get("MONGODB_USER_DATABASE").unwrap_or("users".to_string())));
+    // Optional : OpenID providers users can login with, their profile is created by the user module
+    if let Some(oidc_providers) = secrets.get("OIDC_PROVIDERS") {
+        for oidc_provider in oidc_providers.split(',').map(str::trim).filter(|oidc_provider| !oidc_provider.is_empty()) {
+            let oidc_secret = |name: &str| {
+                let key = format!("OIDC_{}_{}", oidc_provider.to_uppercase(), name);
+                secrets.get(&key).unwrap_or_else(|| panic!("No {key} found in Secret.toml. See README"))
+            };
+            auth_router_module = auth_router_module.with_oidc_provider(OidcProvider::new(
+                oidc_provider,
+                &oidc_secret("ISSUER"),
+                &oidc_secret("CLIENT_ID"),
+                &oidc_secret("CLIENT_SECRET"),
+                &oidc_secret("REDIRECT_URI"),
+            ));
+        }
+        auth_router_module = auth_router_module.with_oidc_user_provisioning(user_router_module.oidc_user_provisioning());
+    }
 
+    // OpenID provider of third-party apps, with the claims of the user module
+    let mut authorization_server = AuthorizationServer::new(&secrets.get("OIDC_ISSUER").unwrap_or("http://localhost:8000/auth".to_string()))
+        .with_user_claims_provider(user_router_module.user_claims_provider());
+    if let Some(oidc_authorization_page) = secrets.get("OIDC_AUTHORIZATION_PAGE") {
+        authorization_server = authorization_server.with_authorization_page(&oidc_authorization_page);
+    }
+    auth_router_module = auth_router_module.with_authorization_server(authorization_server);
 
     let app: Router<()> = Router::new()
         .nest("/auth", auth_router_module.into_router())
         .nest("/user", user_router_module.into_router());
 
-    Ok(app.into())
+    Ok(ConnectInfoService(app))
 }
@@ -0,0 +1,19 @@
+use std::net::SocketAddr;
+use axum::Router;
+use shuttle_runtime::{CustomError, Error};
+
+/// Serve the router with the address of the peer, read by the auth module as the client IP
+///
+/// `shuttle_axum::AxumService` doesn't give it to the handlers.
+pub struct ConnectInfoService(pub Router);
+
+#[shuttle_runtime::async_trait]
+impl shuttle_runtime::Service for ConnectInfoService {
+    async fn bind(self, addr: SocketAddr) -> Result<(), Error> {
+        let listener = shuttle_runtime::tokio::net::TcpListener::bind(addr).await.map_err(CustomError::new)?;
+
+        axum::serve(listener, self.0.into_make_service_with_connect_info::<SocketAddr>()).await.map_err(CustomError::new)?;
+
+        Ok(())
+    }
+}
@@ -1 +1,2 @@
 pub mod api_state;
+pub mod connect_info_service;
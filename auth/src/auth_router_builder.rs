@@ -1,37 +1,215 @@
+use crate::controller::authorization_server::{authorize, get_consents, get_json_web_key_set, get_openid_configuration, get_user_info, revoke_consent};
+use crate::controller::change_password::change_password;
 use crate::controller::create_credentials::create_credentials;
-use crate::controller::login::login;
+use crate::controller::key_ring::get_public_keys;
+use crate::controller::login::{login, login_mfa};
+use crate::controller::login_attempts::clear_login_attempts;
+use crate::controller::logout::{logout, logout_everywhere};
+use crate::controller::oauth_clients::{delete_oauth_client, get_oauth_clients, introspect, register_oauth_client, token};
+use crate::controller::oidc::{finish_oidc_login, start_oidc_login};
+use crate::controller::personal_access_tokens::{create_personal_access_token, get_personal_access_tokens, revoke_personal_access_token};
+use crate::controller::password_reset::{confirm_password_reset, request_password_reset};
 use crate::controller::refresh_tokens::refresh_tokens;
+use crate::controller::roles::{delete_role, get_roles, get_user_roles, save_role, update_user_roles};
+use crate::controller::sessions::{get_sessions, revoke_session};
+use crate::controller::totp::{confirm_totp, disable_totp, enrol_totp};
+use crate::controller::webauthn::{delete_webauthn_credential, finish_webauthn_login, finish_webauthn_registration, get_webauthn_credentials, start_webauthn_login, start_webauthn_registration};
+use crate::datastore::mongo::login_attempts::MongoLoginAttemptDatastore;
+use crate::datastore::mongo::oauth_authorizations::MongoOAuthAuthorizationDatastore;
+use crate::datastore::mongo::oauth_clients::MongoOAuthClientDatastore;
+use crate::datastore::mongo::oidc::MongoOidcDatastore;
+use crate::datastore::mongo::password_resets::MongoPasswordResetDatastore;
+use crate::datastore::mongo::personal_access_tokens::MongoPersonalAccessTokenDatastore;
+use crate::datastore::mongo::roles::MongoRoleDatastore;
 use crate::datastore::mongo::tokens::MongoTokenDatastore;
+use crate::datastore::mongo::totp::MongoTotpDatastore;
+use crate::datastore::mongo::webauthn::MongoWebAuthnDatastore;
 use crate::datastore::mongo::users::MongoAuthDatastore;
 use crate::datastore::{AuthDatastore, TokenDatastore};
-use crate::services::AuthService;
-use axum::routing::post;
+use crate::services::{AuthService, AuthorizationServerService, IntrospectionService, LoginAttemptsService, OAuthClientService, OidcService, PasswordResetService, PersonalAccessTokenService, RoleService, TotpService, WebAuthnService};
+use axum::routing::{delete, get, post, put};
 use axum::{Extension, Router};
 use mongodb::Database;
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::sync::Arc;
 use crate::entities::Privileges;
 use crate::layer::claims::AuthGuardLayer;
+use crate::layer::personal_access_tokens::{PersonalAccessTokenCheck, PersonalAccessTokenChecker};
+use crate::layer::revocation::TokenRevocationCheck;
+use crate::layer::roles::{RoleCheck, RoleChecker};
+use crate::utils::access_rules::{AccessRules, AccessRulesError, ModuleActions};
+use crate::utils::auth_config::AuthConfig;
+use crate::utils::authorization_server::AuthorizationServer;
+use crate::utils::login_throttling::LoginThrottling;
+use crate::utils::oidc::{NoUserProvisioning, OidcProvider, OidcUserProvisioning};
+use crate::utils::password_reset_sender::PasswordResetSender;
+use crate::utils::trusted_proxies::TrustedProxies;
+use crate::utils::webauthn::RelyingParty;
 
 trait AuthServiceProvider<AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore> {
     fn get_auth_service(&self) -> Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>;
 }
 
+/// Routes of the auth module, named after their handler in the rules file. See `AccessRules`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
 pub enum AuthActions {
-    Subscribe,
-    CreateRole,
-    Get(String),
+    CreateCredentials,
+    Login,
+    LoginMfa,
+    RefreshTokens,
+    Logout,
+    LogoutEverywhere,
+    ChangePassword,
+    GetSessions,
+    RevokeSession,
+    GetUserRoles,
+    UpdateUserRoles,
+    GetRoles,
+    SaveRole,
+    DeleteRole,
+    RequestPasswordReset,
+    ConfirmPasswordReset,
+    EnrolTotp,
+    ConfirmTotp,
+    DisableTotp,
+    StartWebauthnRegistration,
+    FinishWebauthnRegistration,
+    StartWebauthnLogin,
+    FinishWebauthnLogin,
+    GetWebauthnCredentials,
+    DeleteWebauthnCredential,
+    CreatePersonalAccessToken,
+    GetPersonalAccessTokens,
+    RevokePersonalAccessToken,
+    Token,
+    Introspect,
+    Authorize,
+    GetUserInfo,
+    GetConsents,
+    RevokeConsent,
+    GetOpenidConfiguration,
+    GetJsonWebKeySet,
+    GetPublicKeys,
+    RegisterOauthClient,
+    GetOauthClients,
+    DeleteOauthClient,
+    StartOidcLogin,
+    FinishOidcLogin,
+    ClearLoginAttempts,
+}
+
+impl ModuleActions for AuthActions {
+    const MODULE: &'static str = "auth";
+
+    fn default_privileges(&self) -> Privileges {
+        match self {
+            AuthActions::CreateCredentials
+            | AuthActions::Login
+            | AuthActions::LoginMfa
+            | AuthActions::StartWebauthnLogin
+            | AuthActions::FinishWebauthnLogin
+            | AuthActions::StartOidcLogin
+            | AuthActions::FinishOidcLogin => Privileges::Anonymous,
+            AuthActions::RefreshTokens
+            | AuthActions::RequestPasswordReset
+            | AuthActions::ConfirmPasswordReset
+            | AuthActions::Token
+            | AuthActions::Introspect
+            | AuthActions::GetOpenidConfiguration
+            | AuthActions::GetJsonWebKeySet
+            | AuthActions::GetPublicKeys => Privileges::Allow,
+            AuthActions::Logout
+            | AuthActions::LogoutEverywhere
+            | AuthActions::ChangePassword
+            | AuthActions::GetSessions
+            | AuthActions::RevokeSession
+            | AuthActions::EnrolTotp
+            | AuthActions::ConfirmTotp
+            | AuthActions::DisableTotp
+            | AuthActions::StartWebauthnRegistration
+            | AuthActions::FinishWebauthnRegistration
+            | AuthActions::GetWebauthnCredentials
+            | AuthActions::DeleteWebauthnCredential
+            | AuthActions::CreatePersonalAccessToken
+            | AuthActions::GetPersonalAccessTokens
+            | AuthActions::RevokePersonalAccessToken
+            | AuthActions::Authorize
+            | AuthActions::GetConsents
+            | AuthActions::RevokeConsent => Privileges::Authenticated,
+            AuthActions::GetUserRoles
+            | AuthActions::UpdateUserRoles
+            | AuthActions::GetRoles
+            | AuthActions::ClearLoginAttempts => Privileges::AdminPrivileges,
+            AuthActions::SaveRole
+            | AuthActions::DeleteRole
+            | AuthActions::RegisterOauthClient
+            | AuthActions::GetOauthClients
+            | AuthActions::DeleteOauthClient => Privileges::SuperAdminPrivileges,
+            AuthActions::GetUserInfo => Privileges::Scope(AuthorizationServer::OPENID_SCOPE.to_string()),
+        }
+    }
 }
 
 pub struct AuthRouterBuilder<AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore> {
     auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>,
+    rules: HashMap<AuthActions, Privileges>,
+    revocation_check: Option<Arc<dyn TokenRevocationCheck>>,
+    password_reset_datastore: MongoPasswordResetDatastore,
+    password_reset_sender: Option<Arc<dyn PasswordResetSender>>,
+    login_attempt_datastore: MongoLoginAttemptDatastore,
+    login_throttling: LoginThrottling,
+    trusted_proxies: TrustedProxies,
+    totp_datastore: MongoTotpDatastore,
+    totp_issuer: String,
+    webauthn_datastore: MongoWebAuthnDatastore,
+    relying_party: RelyingParty,
+    personal_access_token_datastore: MongoPersonalAccessTokenDatastore,
+    personal_access_token_check: Arc<dyn PersonalAccessTokenCheck>,
+    role_datastore: MongoRoleDatastore,
+    role_check: Option<Arc<dyn RoleCheck>>,
+    oauth_client_datastore: MongoOAuthClientDatastore,
+    oidc_datastore: MongoOidcDatastore,
+    oidc_providers: Vec<Arc<OidcProvider>>,
+    oidc_user_provisioning: Arc<dyn OidcUserProvisioning>,
+    oauth_authorization_datastore: MongoOAuthAuthorizationDatastore,
+    authorization_server: AuthorizationServer,
+    auth_config: AuthConfig,
 }
 
 impl AuthRouterBuilder<MongoAuthDatastore, MongoTokenDatastore> {
-    pub fn new(mongo_db: &Database) -> Self {
+    const DEFAULT_TOTP_ISSUER: &'static str = "Auth";
+
+    pub fn new(mongo_db: &Database, auth_config: AuthConfig) -> Self {
         let auth_datastore = MongoAuthDatastore::new(mongo_db);
         let token_datastore = MongoTokenDatastore::new(mongo_db);
+        let personal_access_token_datastore = MongoPersonalAccessTokenDatastore::new(mongo_db);
+        let role_datastore = MongoRoleDatastore::new(mongo_db);
         Self {
-            auth_service: Arc::new(AuthService::new(auth_datastore, token_datastore)),
+            personal_access_token_check: Arc::new(PersonalAccessTokenChecker::new(auth_datastore.clone(), personal_access_token_datastore.clone())),
+            auth_service: Arc::new(AuthService::new(auth_datastore, token_datastore, auth_config.clone())),
+            rules: HashMap::new(),
+            revocation_check: None,
+            password_reset_datastore: MongoPasswordResetDatastore::new(mongo_db),
+            password_reset_sender: None,
+            login_attempt_datastore: MongoLoginAttemptDatastore::new(mongo_db),
+            login_throttling: LoginThrottling::default(),
+            trusted_proxies: TrustedProxies::default(),
+            totp_datastore: MongoTotpDatastore::new(mongo_db),
+            totp_issuer: Self::DEFAULT_TOTP_ISSUER.to_string(),
+            webauthn_datastore: MongoWebAuthnDatastore::new(mongo_db),
+            relying_party: RelyingParty::default(),
+            personal_access_token_datastore,
+            role_check: Some(Arc::new(RoleChecker::new(role_datastore.clone(), RoleChecker::<MongoRoleDatastore>::DEFAULT_CACHE_TTL))),
+            role_datastore,
+            oauth_client_datastore: MongoOAuthClientDatastore::new(mongo_db),
+            oidc_datastore: MongoOidcDatastore::new(mongo_db),
+            oidc_providers: Vec::new(),
+            oidc_user_provisioning: Arc::new(NoUserProvisioning),
+            oauth_authorization_datastore: MongoOAuthAuthorizationDatastore::new(mongo_db),
+            authorization_server: AuthorizationServer::default(),
+            auth_config,
         }
     }
 }
@@ -47,20 +225,282 @@ where
     AuthDatastoreImpl: AuthDatastore + 'static + Clone + Send + Sync,
     TokenDatastoreImpl: TokenDatastore + 'static + Clone + Send + Sync,
 {
+    /// Privileges of the routes set by a rules file, an action unknown to the auth module is rejected
+    pub fn with_access_rules(mut self, access_rules: &AccessRules) -> Result<Self, AccessRulesError> {
+        self.rules = access_rules.module_rules::<AuthActions>()?;
+        Ok(self)
+    }
+
+    /// Reject revoked access tokens on authenticated routes. See `TokenRevocationChecker`
+    pub fn with_revocation_check(mut self, revocation_check: Arc<dyn TokenRevocationCheck>) -> Self {
+        self.revocation_check = Some(revocation_check);
+        self
+    }
+
+    /// Role definitions of runtime roles and permissions, read from the `roles` collection by default
+    pub fn with_role_check(mut self, role_check: Arc<dyn RoleCheck>) -> Self {
+        self.role_check = Some(role_check);
+        self
+    }
+
+    /// Deliver password reset tokens. Password resets are refused by default, see `LogPasswordResetSender` for local development
+    pub fn with_password_reset_sender(mut self, password_reset_sender: Arc<dyn PasswordResetSender>) -> Self {
+        self.password_reset_sender = Some(password_reset_sender);
+        self
+    }
+
+    /// Limits of failed logins before locking a username or a client IP
+    pub fn with_login_throttling(mut self, login_throttling: LoginThrottling) -> Self {
+        self.login_throttling = login_throttling;
+        self
+    }
+
+    /// Reverse proxies whose `X-Forwarded-For` header gives the client IP, the peer address is used otherwise
+    pub fn with_trusted_proxies(mut self, trusted_proxies: TrustedProxies) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+
+    /// Name displayed by authenticator apps for the TOTP of this service
+    pub fn with_totp_issuer(mut self, totp_issuer: &str) -> Self {
+        self.totp_issuer = totp_issuer.to_string();
+        self
+    }
+
+    /// Site passkeys are bound to. `localhost` by default, see `RelyingParty`
+    pub fn with_webauthn_relying_party(mut self, relying_party: RelyingParty) -> Self {
+        self.relying_party = relying_party;
+        self
+    }
+
+    /// Allow login with an upstream OpenID provider, at `/oidc/{provider}/authorize`
+    pub fn with_oidc_provider(mut self, oidc_provider: OidcProvider) -> Self {
+        self.oidc_providers.push(Arc::new(oidc_provider));
+        self
+    }
+
+    /// Create the profile of users at their first OpenID login, nothing is done by default
+    pub fn with_oidc_user_provisioning(mut self, oidc_user_provisioning: Arc<dyn OidcUserProvisioning>) -> Self {
+        self.oidc_user_provisioning = oidc_user_provisioning;
+        self
+    }
+
+    /// Issuer, authorization page and user claims of the OpenID provider of third-party apps. See `AuthorizationServer`
+    pub fn with_authorization_server(mut self, authorization_server: AuthorizationServer) -> Self {
+        self.authorization_server = authorization_server;
+        self
+    }
+
     pub fn into_router(self) -> Router {
+        let revocation_check = self.revocation_check;
+        let password_reset_service = Arc::new(PasswordResetService::new(self.auth_service.clone(), self.password_reset_datastore, self.password_reset_sender));
+        let login_attempts_service = Arc::new(LoginAttemptsService::new(self.login_attempt_datastore, self.login_throttling));
+        let totp_service = Arc::new(TotpService::new(self.auth_service.clone(), self.totp_datastore, self.totp_issuer));
+        let webauthn_service = Arc::new(WebAuthnService::new(self.auth_service.clone(), self.webauthn_datastore, self.relying_party));
+        let personal_access_token_service = Arc::new(PersonalAccessTokenService::new(self.personal_access_token_datastore));
+        let role_service = Arc::new(RoleService::new(self.auth_service.clone(), self.role_datastore));
+        let role_check = self.role_check;
+        let personal_access_token_check = self.personal_access_token_check;
+        let authorization_server_service = Arc::new(AuthorizationServerService::new(self.oauth_client_datastore.clone(), self.oauth_authorization_datastore, self.authorization_server, self.auth_config.clone()));
+        let introspection_service = Arc::new(IntrospectionService::new(self.auth_service.clone(), self.oauth_client_datastore.clone()));
+        let oauth_client_service = Arc::new(OAuthClientService::new(self.oauth_client_datastore, self.auth_config.clone()));
+        let oidc_service = Arc::new(OidcService::new(self.auth_service.clone(), self.oidc_datastore, self.oidc_providers, self.oidc_user_provisioning));
+        let auth_config = self.auth_config;
+        let rules = self.rules;
+        let privileges = |action: AuthActions| rules.get(&action).cloned().unwrap_or_else(|| action.default_privileges());
+        let guard = |privileges| AuthGuardLayer::new(auth_config.clone(), privileges)
+            .with_revocation_check(revocation_check.clone())
+            .with_role_check(role_check.clone());
+        // Account management requires a session opened by login, personal access tokens can only list and revoke themselves
+        let personal_access_token_guard = |privileges| guard(privileges).with_personal_access_token_check(Some(personal_access_token_check.clone()));
+
         Router::new()
             .route(
                 "/create_credentials",
-                post(create_credentials::<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>).layer(AuthGuardLayer { privileges: Privileges::Anonymous }),
+                post(create_credentials::<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>).layer(guard(privileges(AuthActions::CreateCredentials))),
             )
             .route(
                 "/login",
-                post(login::<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>).layer(AuthGuardLayer { privileges: Privileges::Anonymous }),
+                post(login::<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>, LoginAttemptsService<MongoLoginAttemptDatastore>, TotpService<AuthDatastoreImpl, TokenDatastoreImpl, MongoTotpDatastore>>).layer(guard(privileges(AuthActions::Login))),
+            )
+            .route(
+                "/login/mfa",
+                post(login_mfa::<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>, LoginAttemptsService<MongoLoginAttemptDatastore>, TotpService<AuthDatastoreImpl, TokenDatastoreImpl, MongoTotpDatastore>>).layer(guard(privileges(AuthActions::LoginMfa))),
             )
             .route(
                 "/refresh_token",
-                post(refresh_tokens::<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>).layer(AuthGuardLayer { privileges: Privileges::Allow }),
+                post(refresh_tokens::<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>).layer(guard(privileges(AuthActions::RefreshTokens))),
+            )
+            .route(
+                "/logout",
+                post(logout::<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>).layer(guard(privileges(AuthActions::Logout))),
+            )
+            .route(
+                "/logout_everywhere",
+                post(logout_everywhere::<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>).layer(guard(privileges(AuthActions::LogoutEverywhere))),
+            )
+            .route(
+                "/password",
+                post(change_password::<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>).layer(guard(privileges(AuthActions::ChangePassword))),
+            )
+            .route(
+                "/sessions",
+                get(get_sessions::<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>).layer(guard(privileges(AuthActions::GetSessions))),
+            )
+            .route(
+                "/sessions/{session_id}",
+                delete(revoke_session::<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>).layer(guard(privileges(AuthActions::RevokeSession))),
+            )
+            .route(
+                "/users/{username}/roles",
+                get(get_user_roles::<RoleService<AuthDatastoreImpl, TokenDatastoreImpl, MongoRoleDatastore>>).layer(guard(privileges(AuthActions::GetUserRoles))),
+            )
+            .route(
+                "/users/{username}/roles",
+                put(update_user_roles::<RoleService<AuthDatastoreImpl, TokenDatastoreImpl, MongoRoleDatastore>>).layer(guard(privileges(AuthActions::UpdateUserRoles))),
+            )
+            .route(
+                "/roles",
+                get(get_roles::<RoleService<AuthDatastoreImpl, TokenDatastoreImpl, MongoRoleDatastore>>).layer(guard(privileges(AuthActions::GetRoles))),
+            )
+            .route(
+                "/roles/{name}",
+                put(save_role::<RoleService<AuthDatastoreImpl, TokenDatastoreImpl, MongoRoleDatastore>>).layer(guard(privileges(AuthActions::SaveRole))),
+            )
+            .route(
+                "/roles/{name}",
+                delete(delete_role::<RoleService<AuthDatastoreImpl, TokenDatastoreImpl, MongoRoleDatastore>>).layer(guard(privileges(AuthActions::DeleteRole))),
+            )
+            .route(
+                "/password_reset",
+                post(request_password_reset::<PasswordResetService<AuthDatastoreImpl, TokenDatastoreImpl, MongoPasswordResetDatastore>>).layer(guard(privileges(AuthActions::RequestPasswordReset))),
+            )
+            .route(
+                "/password_reset/confirm",
+                post(confirm_password_reset::<PasswordResetService<AuthDatastoreImpl, TokenDatastoreImpl, MongoPasswordResetDatastore>>).layer(guard(privileges(AuthActions::ConfirmPasswordReset))),
+            )
+            .route(
+                "/totp/enrol",
+                post(enrol_totp::<TotpService<AuthDatastoreImpl, TokenDatastoreImpl, MongoTotpDatastore>>).layer(guard(privileges(AuthActions::EnrolTotp))),
+            )
+            .route(
+                "/totp/confirm",
+                post(confirm_totp::<TotpService<AuthDatastoreImpl, TokenDatastoreImpl, MongoTotpDatastore>>).layer(guard(privileges(AuthActions::ConfirmTotp))),
+            )
+            .route(
+                "/totp/disable",
+                post(disable_totp::<TotpService<AuthDatastoreImpl, TokenDatastoreImpl, MongoTotpDatastore>>).layer(guard(privileges(AuthActions::DisableTotp))),
+            )
+            .route(
+                "/webauthn/register/start",
+                post(start_webauthn_registration::<WebAuthnService<AuthDatastoreImpl, TokenDatastoreImpl, MongoWebAuthnDatastore>>).layer(guard(privileges(AuthActions::StartWebauthnRegistration))),
+            )
+            .route(
+                "/webauthn/register/finish",
+                post(finish_webauthn_registration::<WebAuthnService<AuthDatastoreImpl, TokenDatastoreImpl, MongoWebAuthnDatastore>>).layer(guard(privileges(AuthActions::FinishWebauthnRegistration))),
+            )
+            .route(
+                "/webauthn/login/start",
+                post(start_webauthn_login::<WebAuthnService<AuthDatastoreImpl, TokenDatastoreImpl, MongoWebAuthnDatastore>>).layer(guard(privileges(AuthActions::StartWebauthnLogin))),
+            )
+            .route(
+                "/webauthn/login/finish",
+                post(finish_webauthn_login::<WebAuthnService<AuthDatastoreImpl, TokenDatastoreImpl, MongoWebAuthnDatastore>>).layer(guard(privileges(AuthActions::FinishWebauthnLogin))),
+            )
+            .route(
+                "/webauthn/credentials",
+                get(get_webauthn_credentials::<WebAuthnService<AuthDatastoreImpl, TokenDatastoreImpl, MongoWebAuthnDatastore>>).layer(guard(privileges(AuthActions::GetWebauthnCredentials))),
+            )
+            .route(
+                "/webauthn/credentials/{credential_id}",
+                delete(delete_webauthn_credential::<WebAuthnService<AuthDatastoreImpl, TokenDatastoreImpl, MongoWebAuthnDatastore>>).layer(guard(privileges(AuthActions::DeleteWebauthnCredential))),
+            )
+            .route(
+                "/personal_access_tokens",
+                post(create_personal_access_token::<PersonalAccessTokenService<MongoPersonalAccessTokenDatastore>>).layer(guard(privileges(AuthActions::CreatePersonalAccessToken))),
+            )
+            .route(
+                "/personal_access_tokens",
+                get(get_personal_access_tokens::<PersonalAccessTokenService<MongoPersonalAccessTokenDatastore>>).layer(personal_access_token_guard(privileges(AuthActions::GetPersonalAccessTokens))),
+            )
+            .route(
+                "/personal_access_tokens/{token_id}",
+                delete(revoke_personal_access_token::<PersonalAccessTokenService<MongoPersonalAccessTokenDatastore>>).layer(personal_access_token_guard(privileges(AuthActions::RevokePersonalAccessToken))),
+            )
+            .route(
+                "/token",
+                post(token::<OAuthClientService<MongoOAuthClientDatastore>, AuthorizationServerService<MongoOAuthClientDatastore, MongoOAuthAuthorizationDatastore>>).layer(guard(privileges(AuthActions::Token))),
+            )
+            .route(
+                "/introspect",
+                post(introspect::<IntrospectionService<AuthDatastoreImpl, TokenDatastoreImpl, MongoOAuthClientDatastore>>).layer(guard(privileges(AuthActions::Introspect))),
+            )
+            .route(
+                "/authorize",
+                post(authorize::<AuthorizationServerService<MongoOAuthClientDatastore, MongoOAuthAuthorizationDatastore>>).layer(guard(privileges(AuthActions::Authorize))),
+            )
+            .route(
+                "/userinfo",
+                get(get_user_info::<AuthorizationServerService<MongoOAuthClientDatastore, MongoOAuthAuthorizationDatastore>>)
+                    .post(get_user_info::<AuthorizationServerService<MongoOAuthClientDatastore, MongoOAuthAuthorizationDatastore>>)
+                    .layer(guard(privileges(AuthActions::GetUserInfo))),
+            )
+            .route(
+                "/consents",
+                get(get_consents::<AuthorizationServerService<MongoOAuthClientDatastore, MongoOAuthAuthorizationDatastore>>).layer(guard(privileges(AuthActions::GetConsents))),
+            )
+            .route(
+                "/consents/{client_id}",
+                delete(revoke_consent::<AuthorizationServerService<MongoOAuthClientDatastore, MongoOAuthAuthorizationDatastore>>).layer(guard(privileges(AuthActions::RevokeConsent))),
+            )
+            .route(
+                "/.well-known/openid-configuration",
+                get(get_openid_configuration::<AuthorizationServerService<MongoOAuthClientDatastore, MongoOAuthAuthorizationDatastore>>).layer(guard(privileges(AuthActions::GetOpenidConfiguration))),
+            )
+            .route(
+                "/.well-known/jwks.json",
+                get(get_json_web_key_set::<AuthorizationServerService<MongoOAuthClientDatastore, MongoOAuthAuthorizationDatastore>>).layer(guard(privileges(AuthActions::GetJsonWebKeySet))),
+            )
+            .route(
+                "/.well-known/paserk.json",
+                get(get_public_keys).layer(guard(privileges(AuthActions::GetPublicKeys))),
+            )
+            .route(
+                "/clients",
+                post(register_oauth_client::<OAuthClientService<MongoOAuthClientDatastore>>).layer(guard(privileges(AuthActions::RegisterOauthClient))),
+            )
+            .route(
+                "/clients",
+                get(get_oauth_clients::<OAuthClientService<MongoOAuthClientDatastore>>).layer(guard(privileges(AuthActions::GetOauthClients))),
+            )
+            .route(
+                "/clients/{client_id}",
+                delete(delete_oauth_client::<OAuthClientService<MongoOAuthClientDatastore>>).layer(guard(privileges(AuthActions::DeleteOauthClient))),
+            )
+            .route(
+                "/oidc/{provider}/authorize",
+                get(start_oidc_login::<OidcService<AuthDatastoreImpl, TokenDatastoreImpl, MongoOidcDatastore>>).layer(guard(privileges(AuthActions::StartOidcLogin))),
+            )
+            .route(
+                "/oidc/{provider}/callback",
+                get(finish_oidc_login::<OidcService<AuthDatastoreImpl, TokenDatastoreImpl, MongoOidcDatastore>, AuthService<AuthDatastoreImpl, TokenDatastoreImpl>, TotpService<AuthDatastoreImpl, TokenDatastoreImpl, MongoTotpDatastore>>).layer(guard(privileges(AuthActions::FinishOidcLogin))),
             )
             .layer(Extension(self.auth_service))
+            .route(
+                "/login_attempts/{username}",
+                delete(clear_login_attempts::<LoginAttemptsService<MongoLoginAttemptDatastore>>).layer(guard(privileges(AuthActions::ClearLoginAttempts))),
+            )
+            .layer(Extension(password_reset_service))
+            .layer(Extension(login_attempts_service))
+            .layer(Extension(totp_service))
+            .layer(Extension(webauthn_service))
+            .layer(Extension(personal_access_token_service))
+            .layer(Extension(role_service))
+            .layer(Extension(oauth_client_service))
+            .layer(Extension(introspection_service))
+            .layer(Extension(oidc_service))
+            .layer(Extension(authorization_server_service))
+            .layer(Extension(auth_config.clone()))
+            .layer(Extension(self.trusted_proxies))
     }
 }
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+use axum::extract::Path;
+use axum::{Extension, Json};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use jsonwebtoken::jwk::JwkSet;
+use crate::entities::{AuthMethod, AuthSession};
+use crate::entities::error::AuthError;
+use crate::services::AuthAuthorizationCodeService;
+use crate::views::payload::AuthorizationRequestPayload;
+use crate::views::response::{AuthorizationBody, OAuthConsentDetails, OpenIdConfiguration, UserInfoBody};
+
+/// Authorization request forwarded by the authorization page, for the user logged in on it
+pub async fn authorize<AuthorizationServerServiceImpl: AuthAuthorizationCodeService>(authorization_server_service: Extension<Arc<AuthorizationServerServiceImpl>>, Extension(auth_session): Extension<AuthSession>, Json(payload): Json<AuthorizationRequestPayload>) -> Result<Json<AuthorizationBody>, Response> {
+    if auth_session.auth_method != AuthMethod::AccessToken {
+        return Err(AuthError::Unauthorized.into_response());
+    }
+
+    Ok(Json(authorization_server_service.authorize(&auth_session.username, payload).await.map_err(IntoResponse::into_response)?))
+}
+
+pub async fn get_consents<AuthorizationServerServiceImpl: AuthAuthorizationCodeService>(authorization_server_service: Extension<Arc<AuthorizationServerServiceImpl>>, Extension(auth_session): Extension<AuthSession>) -> Result<Json<Vec<OAuthConsentDetails>>, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    Ok(Json(authorization_server_service.get_consents(&auth_session.username).await?))
+}
+
+/// Tokens already given to the app stay valid until they expire
+pub async fn revoke_consent<AuthorizationServerServiceImpl: AuthAuthorizationCodeService>(authorization_server_service: Extension<Arc<AuthorizationServerServiceImpl>>, Extension(auth_session): Extension<AuthSession>, Path(client_id): Path<String>) -> Result<StatusCode, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    authorization_server_service.revoke_consent(&auth_session.username, &client_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Only for access tokens given to third-party apps with the `openid` scope
+pub async fn get_user_info<AuthorizationServerServiceImpl: AuthAuthorizationCodeService>(authorization_server_service: Extension<Arc<AuthorizationServerServiceImpl>>, Extension(auth_session): Extension<AuthSession>) -> Result<Json<UserInfoBody>, AuthError> {
+    let AuthMethod::DelegatedAccess(service_principal) = &auth_session.auth_method else {
+        return Err(AuthError::Unauthorized);
+    };
+
+    Ok(Json(authorization_server_service.get_user_info(&auth_session.username, &service_principal.scopes).await?))
+}
+
+pub async fn get_openid_configuration<AuthorizationServerServiceImpl: AuthAuthorizationCodeService>(authorization_server_service: Extension<Arc<AuthorizationServerServiceImpl>>) -> Json<OpenIdConfiguration> {
+    Json(authorization_server_service.get_openid_configuration())
+}
+
+pub async fn get_json_web_key_set<AuthorizationServerServiceImpl: AuthAuthorizationCodeService>(authorization_server_service: Extension<Arc<AuthorizationServerServiceImpl>>) -> Json<JwkSet> {
+    Json(authorization_server_service.get_json_web_key_set())
+}
@@ -0,0 +1,17 @@
+use std::sync::Arc;
+use axum::{Extension, Json};
+use axum::http::StatusCode;
+use crate::entities::AuthSession;
+use crate::entities::error::AuthError;
+use crate::services::AuthChangePasswordService;
+use crate::views::payload::ChangePasswordPayload;
+
+pub async fn change_password<AuthServiceImpl: AuthChangePasswordService>(auth_service: Extension<Arc<AuthServiceImpl>>, Extension(auth_session): Extension<AuthSession>, Json(payload): Json<ChangePasswordPayload>) -> Result<StatusCode, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    auth_service.change_password(&auth_session, payload).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
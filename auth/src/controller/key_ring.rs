@@ -0,0 +1,10 @@
+use axum::{Extension, Json};
+use crate::utils::auth_config::AuthConfig;
+use crate::views::response::{PublicKeyDetails, PublicKeySetBody};
+
+/// Public keys accepted on token verification, the active one signing the next tokens
+pub async fn get_public_keys(Extension(auth_config): Extension<AuthConfig>) -> Json<PublicKeySetBody> {
+    Json(PublicKeySetBody {
+        keys: auth_config.key_ring().published_keys().into_iter().map(PublicKeyDetails::from).collect(),
+    })
+}
@@ -1,38 +1,76 @@
 use std::sync::Arc;
 use axum::{Extension, Json};
+use crate::entities::ClientInformation;
 use crate::entities::error::AuthError;
-use crate::services::{AuthTokensService, AuthValidCredentialsService};
-use crate::views::payload::{LoginPayload};
-use crate::views::response::AuthBody;
+use crate::services::{AuthLoginAttemptsService, AuthTokensService, AuthTotpService, AuthValidCredentialsService};
+use crate::views::payload::{LoginPayload, MfaLoginPayload};
+use crate::views::response::{AuthBody, LoginBody};
 
-pub async fn login<AuthServiceImpl: AuthTokensService + AuthValidCredentialsService>(auth_service: Extension<Arc<AuthServiceImpl>>, Json(payload): Json<LoginPayload>) -> Result<Json<AuthBody>, AuthError> {
-    let user = auth_service.is_valid_credentials(payload.username, payload.password).await?;
-    let tokens = auth_service.generate_token(&user).await?;
+pub async fn login<AuthServiceImpl: AuthTokensService + AuthValidCredentialsService, LoginAttemptsServiceImpl: AuthLoginAttemptsService, TotpServiceImpl: AuthTotpService>(auth_service: Extension<Arc<AuthServiceImpl>>, login_attempts_service: Extension<Arc<LoginAttemptsServiceImpl>>, totp_service: Extension<Arc<TotpServiceImpl>>, client_information: ClientInformation, Json(payload): Json<LoginPayload>) -> Result<Json<LoginBody>, AuthError> {
+    login_attempts_service.check_login_allowed(&payload.username, &client_information).await?;
+
+    let user = match auth_service.is_valid_credentials(payload.username.clone(), payload.password).await {
+        Ok(user) => user,
+        Err(AuthError::WrongCredentials) => {
+            login_attempts_service.record_login_failure(&payload.username, &client_information).await?;
+            return Err(AuthError::WrongCredentials);
+        }
+        Err(error) => return Err(error),
+    };
+
+    // Tokens are only given by `/login/mfa` when the user has a second factor, failures are kept until then
+    if totp_service.is_totp_enabled(&user.username).await? {
+        return Ok(Json(LoginBody::MfaRequired(totp_service.generate_mfa_ticket(&user.username).await?)));
+    }
+
+    let tokens = auth_service.generate_token(&user, &client_information).await?;
+    login_attempts_service.clear_login_failures(&user.username).await?;
+
+    Ok(Json(LoginBody::Tokens(tokens)))
+}
+
+/// Second step of the login for users with a second factor. Wrong codes count as failed logins
+pub async fn login_mfa<AuthServiceImpl: AuthTokensService, LoginAttemptsServiceImpl: AuthLoginAttemptsService, TotpServiceImpl: AuthTotpService>(auth_service: Extension<Arc<AuthServiceImpl>>, login_attempts_service: Extension<Arc<LoginAttemptsServiceImpl>>, totp_service: Extension<Arc<TotpServiceImpl>>, client_information: ClientInformation, Json(payload): Json<MfaLoginPayload>) -> Result<Json<AuthBody>, AuthError> {
+    let username = totp_service.verify_mfa_ticket(&payload.mfa_ticket).await?;
+    login_attempts_service.check_login_allowed(&username, &client_information).await?;
+
+    let user = match totp_service.verify_mfa_code(&username, &payload.code).await {
+        Ok(user) => user,
+        Err(AuthError::WrongCredentials) => {
+            login_attempts_service.record_login_failure(&username, &client_information).await?;
+            return Err(AuthError::WrongCredentials);
+        }
+        Err(error) => return Err(error),
+    };
+    login_attempts_service.clear_login_failures(&user.username).await?;
+
+    let tokens = auth_service.generate_token(&user, &client_information).await?;
 
     Ok(Json(tokens))
 }
 
 #[cfg(test)]
 mod test {
+    use crate::utils::auth_config::AuthConfig;
     use std::future;
     use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
     use axum::{Extension, Json};
     use fake::{Fake, Faker};
     use fake::faker::internet::en::{Password, Username};
     use mockall::predicate::eq;
     use mongodb::bson::oid::ObjectId;
     use once_cell::sync::Lazy;
-    use crate::controller::login::login;
+    use crate::controller::login::{login, login_mfa};
     use crate::datastore::{MockAuthDatastore, MockTokenDatastore};
-    use crate::entities::{Token, UserCredentials};
+    use crate::entities::{ClientInformation, Token, UserCredentials};
     use crate::entities::error::AuthError;
-    use crate::services::{MockAuthService};
-    use crate::utils::settings::AuthSettings;
-    use crate::views::payload::LoginPayload;
+    use crate::services::{MockAuthLoginAttemptsService, MockAuthService, MockAuthTotpService};
+    use crate::views::response::{LoginBody, MfaTicketBody};
+    use crate::views::payload::{LoginPayload, MfaLoginPayload};
 
     #[tokio::test]
     async fn test_unit_login() {
-        AuthSettings::init_fake();
 
         let mut mock_auth_datastore = MockAuthDatastore::new();
         let mut mock_tokens_datastore = MockTokenDatastore::new();
@@ -42,7 +80,7 @@ mod test {
         mock_auth_datastore.expect_get_user_by_username().with(eq(username.clone())).times(1).returning(|username| {
             return Box::pin(future::ready(Ok(Some(UserCredentials {
                 username: username.to_string(),
-                password: UserCredentials::hash_password(PASSWORD.clone()),
+                password: UserCredentials::hash_password(PASSWORD.clone(), &AuthConfig::fake()),
                 ..Faker.fake::<UserCredentials>()
             }))));
         });
@@ -55,9 +93,17 @@ mod test {
         });
 
 
-        let extension = MockAuthService::new(mock_auth_datastore, mock_tokens_datastore);
+        let mut mock_login_attempts_service = MockAuthLoginAttemptsService::new();
+        mock_login_attempts_service.expect_check_login_allowed().times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_login_attempts_service.expect_record_login_failure().times(0);
+        mock_login_attempts_service.expect_clear_login_failures().with(eq(username.clone())).times(1).returning(|_| Box::pin(future::ready(Ok(()))));
+
+        let mut mock_totp_service = MockAuthTotpService::new();
+        mock_totp_service.expect_is_totp_enabled().times(1).returning(|_| Box::pin(future::ready(Ok(false))));
 
-        assert!(login(Extension(Arc::new(extension)), Json(LoginPayload {
+        let extension = MockAuthService::new(mock_auth_datastore, mock_tokens_datastore, AuthConfig::fake());
+
+        assert!(login(Extension(Arc::new(extension)), Extension(Arc::new(mock_login_attempts_service)), Extension(Arc::new(mock_totp_service)), ClientInformation::default(), Json(LoginPayload {
             username: username.clone(),
             password: PASSWORD.clone()
         })).await.is_ok());
@@ -66,7 +112,6 @@ mod test {
 
     #[tokio::test]
     async fn test_unit_bad_login() {
-        AuthSettings::init_fake();
 
         let mut mock_auth_datastore = MockAuthDatastore::new();
         let mut mock_tokens_datastore = MockTokenDatastore::new();
@@ -79,11 +124,179 @@ mod test {
 
         mock_tokens_datastore.expect_add_tokens().times(0);
 
-        let extension = MockAuthService::new(mock_auth_datastore, mock_tokens_datastore);
+        let mut mock_login_attempts_service = MockAuthLoginAttemptsService::new();
+        mock_login_attempts_service.expect_check_login_allowed().times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_login_attempts_service.expect_record_login_failure().with(eq(username.clone()), eq(ClientInformation::default())).times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_login_attempts_service.expect_clear_login_failures().times(0);
+
+        let mock_totp_service = MockAuthTotpService::new();
+
+        let extension = MockAuthService::new(mock_auth_datastore, mock_tokens_datastore, AuthConfig::fake());
 
-        assert_eq!(login(Extension(Arc::new(extension)), Json(LoginPayload {
+        assert_eq!(login(Extension(Arc::new(extension)), Extension(Arc::new(mock_login_attempts_service)), Extension(Arc::new(mock_totp_service)), ClientInformation::default(), Json(LoginPayload {
             username: username.clone(),
             password: password.clone()
         })).await.unwrap_err().to_string(), AuthError::WrongCredentials.to_string());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_unit_login_locked() {
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        let username: String = Username().fake();
+        let password: String = Password(10..500).fake();
+
+        mock_auth_datastore.expect_get_user_by_username().times(0);
+
+        let mut mock_login_attempts_service = MockAuthLoginAttemptsService::new();
+        mock_login_attempts_service.expect_check_login_allowed().times(1).returning(|_, _| Box::pin(future::ready(Err(AuthError::AccountLocked(30)))));
+
+        let mock_totp_service = MockAuthTotpService::new();
+
+        let extension = MockAuthService::new(mock_auth_datastore, MockTokenDatastore::new(), AuthConfig::fake());
+
+        assert_eq!(login(Extension(Arc::new(extension)), Extension(Arc::new(mock_login_attempts_service)), Extension(Arc::new(mock_totp_service)), ClientInformation::default(), Json(LoginPayload {
+            username,
+            password
+        })).await.unwrap_err(), AuthError::AccountLocked(30));
+    }
+
+    #[tokio::test]
+    async fn test_unit_login_mfa_required() {
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        let username: String = Username().fake();
+        static PASSWORD: Lazy<String> = Lazy::new(|| Password(10..500).fake());
+
+        mock_auth_datastore.expect_get_user_by_username().times(1).returning(|username| {
+            Box::pin(future::ready(Ok(Some(UserCredentials {
+                username: username.to_string(),
+                password: UserCredentials::hash_password(PASSWORD.clone(), &AuthConfig::fake()),
+                ..Faker.fake::<UserCredentials>()
+            }))))
+        });
+        mock_tokens_datastore.expect_add_tokens().times(0);
+
+        let mut mock_login_attempts_service = MockAuthLoginAttemptsService::new();
+        mock_login_attempts_service.expect_check_login_allowed().times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_login_attempts_service.expect_clear_login_failures().times(0);
+
+        let mut mock_totp_service = MockAuthTotpService::new();
+        mock_totp_service.expect_is_totp_enabled().with(eq(username.clone())).times(1).returning(|_| Box::pin(future::ready(Ok(true))));
+        mock_totp_service.expect_generate_mfa_ticket().times(1).returning(|_| Box::pin(future::ready(Ok(MfaTicketBody { mfa_ticket: "mfa_ticket".to_string(), expired_at: "expired_at".to_string() }))));
+
+        let extension = MockAuthService::new(mock_auth_datastore, mock_tokens_datastore, AuthConfig::fake());
+
+        let Json(login_body) = login(Extension(Arc::new(extension)), Extension(Arc::new(mock_login_attempts_service)), Extension(Arc::new(mock_totp_service)), ClientInformation::default(), Json(LoginPayload {
+            username,
+            password: PASSWORD.clone()
+        })).await.unwrap();
+
+        assert_eq!(login_body, LoginBody::MfaRequired(MfaTicketBody { mfa_ticket: "mfa_ticket".to_string(), expired_at: "expired_at".to_string() }));
+    }
+
+    #[tokio::test]
+    async fn test_unit_login_mfa() {
+
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        mock_tokens_datastore.expect_add_tokens().times(1).returning(|token| {
+            Box::pin(future::ready(Ok(Token {
+                id: Some(ObjectId::new()),
+                ..token
+            })))
+        });
+
+        let mut mock_login_attempts_service = MockAuthLoginAttemptsService::new();
+        mock_login_attempts_service.expect_check_login_allowed().with(eq("username"), eq(ClientInformation::default())).times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_login_attempts_service.expect_record_login_failure().times(0);
+        mock_login_attempts_service.expect_clear_login_failures().with(eq("username")).times(1).returning(|_| Box::pin(future::ready(Ok(()))));
+
+        let mut mock_totp_service = MockAuthTotpService::new();
+        mock_totp_service.expect_verify_mfa_ticket().with(eq("mfa_ticket")).times(1).returning(|_| Box::pin(future::ready(Ok("username".to_string()))));
+        mock_totp_service.expect_verify_mfa_code().with(eq("username"), eq("123456")).times(1).returning(|username, _| Box::pin(future::ready(Ok(UserCredentials {
+            username: username.to_string(),
+            ..Faker.fake::<UserCredentials>()
+        }))));
+
+        let extension = MockAuthService::new(MockAuthDatastore::new(), mock_tokens_datastore, AuthConfig::fake());
+
+        assert!(login_mfa(Extension(Arc::new(extension)), Extension(Arc::new(mock_login_attempts_service)), Extension(Arc::new(mock_totp_service)), ClientInformation::default(), Json(MfaLoginPayload {
+            mfa_ticket: "mfa_ticket".to_string(),
+            code: "123456".to_string(),
+        })).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_unit_login_mfa_wrong_code() {
+        let mut mock_login_attempts_service = MockAuthLoginAttemptsService::new();
+        mock_login_attempts_service.expect_check_login_allowed().times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_login_attempts_service.expect_record_login_failure().with(eq("username"), eq(ClientInformation::default())).times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_login_attempts_service.expect_clear_login_failures().times(0);
+
+        let mut mock_totp_service = MockAuthTotpService::new();
+        mock_totp_service.expect_verify_mfa_ticket().times(1).returning(|_| Box::pin(future::ready(Ok("username".to_string()))));
+        mock_totp_service.expect_verify_mfa_code().times(1).returning(|_, _| Box::pin(future::ready(Err(AuthError::WrongCredentials))));
+
+        let extension = MockAuthService::new(MockAuthDatastore::new(), MockTokenDatastore::new(), AuthConfig::fake());
+
+        assert_eq!(login_mfa(Extension(Arc::new(extension)), Extension(Arc::new(mock_login_attempts_service)), Extension(Arc::new(mock_totp_service)), ClientInformation::default(), Json(MfaLoginPayload {
+            mfa_ticket: "mfa_ticket".to_string(),
+            code: "000000".to_string(),
+        })).await.unwrap_err(), AuthError::WrongCredentials);
+    }
+
+    #[tokio::test]
+    async fn test_unit_password_login_keeps_mfa_failures() {
+        static PASSWORD: Lazy<String> = Lazy::new(|| Password(10..500).fake());
+        const MAX_FAILURES: u32 = 2;
+        let failures = Arc::new(AtomicU32::new(0));
+
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        mock_auth_datastore.expect_get_user_by_username().times(2).returning(|username| Box::pin(future::ready(Ok(Some(UserCredentials {
+            username: username.to_string(),
+            password: UserCredentials::hash_password(PASSWORD.clone(), &AuthConfig::fake()),
+            ..Faker.fake::<UserCredentials>()
+        })))));
+
+        // Lock the username once the failures reach the maximum, like `LoginAttemptsService`
+        let mut mock_login_attempts_service = MockAuthLoginAttemptsService::new();
+        let check_failures = failures.clone();
+        mock_login_attempts_service.expect_check_login_allowed().returning(move |_, _| Box::pin(future::ready(match check_failures.load(Ordering::SeqCst) {
+            MAX_FAILURES.. => Err(AuthError::AccountLocked(30)),
+            _ => Ok(()),
+        })));
+        let record_failures = failures.clone();
+        mock_login_attempts_service.expect_record_login_failure().returning(move |_, _| {
+            record_failures.fetch_add(1, Ordering::SeqCst);
+            Box::pin(future::ready(Ok(())))
+        });
+        mock_login_attempts_service.expect_clear_login_failures().times(0);
+
+        let mut mock_totp_service = MockAuthTotpService::new();
+        mock_totp_service.expect_is_totp_enabled().returning(|_| Box::pin(future::ready(Ok(true))));
+        mock_totp_service.expect_generate_mfa_ticket().times(2).returning(|_| Box::pin(future::ready(Ok(MfaTicketBody { mfa_ticket: "mfa_ticket".to_string(), expired_at: "expired_at".to_string() }))));
+        mock_totp_service.expect_verify_mfa_ticket().times(2).returning(|_| Box::pin(future::ready(Ok("username".to_string()))));
+        mock_totp_service.expect_verify_mfa_code().times(2).returning(|_, _| Box::pin(future::ready(Err(AuthError::WrongCredentials))));
+
+        let auth_service = Arc::new(MockAuthService::new(mock_auth_datastore, MockTokenDatastore::new(), AuthConfig::fake()));
+        let login_attempts_service = Arc::new(mock_login_attempts_service);
+        let totp_service = Arc::new(mock_totp_service);
+
+        for _ in 0..MAX_FAILURES {
+            let login_result = login(Extension(auth_service.clone()), Extension(login_attempts_service.clone()), Extension(totp_service.clone()), ClientInformation::default(), Json(LoginPayload {
+                username: "username".to_string(),
+                password: PASSWORD.clone()
+            })).await;
+            assert!(matches!(login_result, Ok(Json(LoginBody::MfaRequired(_)))));
+
+            assert_eq!(login_mfa(Extension(auth_service.clone()), Extension(login_attempts_service.clone()), Extension(totp_service.clone()), ClientInformation::default(), Json(MfaLoginPayload {
+                mfa_ticket: "mfa_ticket".to_string(),
+                code: "000000".to_string(),
+            })).await.unwrap_err(), AuthError::WrongCredentials);
+        }
+
+        assert_eq!(login(Extension(auth_service), Extension(login_attempts_service), Extension(totp_service), ClientInformation::default(), Json(LoginPayload {
+            username: "username".to_string(),
+            password: PASSWORD.clone()
+        })).await.unwrap_err(), AuthError::AccountLocked(30));
+    }
+}
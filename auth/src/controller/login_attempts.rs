@@ -0,0 +1,13 @@
+use std::sync::Arc;
+use axum::Extension;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use crate::entities::error::AuthError;
+use crate::services::AuthLoginAttemptsService;
+
+/// Unlock the username given, for administrators
+pub async fn clear_login_attempts<LoginAttemptsServiceImpl: AuthLoginAttemptsService>(login_attempts_service: Extension<Arc<LoginAttemptsServiceImpl>>, Path(username): Path<String>) -> Result<StatusCode, AuthError> {
+    login_attempts_service.clear_login_failures(&username).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
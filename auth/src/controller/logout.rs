@@ -0,0 +1,23 @@
+use std::sync::Arc;
+use axum::Extension;
+use axum::http::StatusCode;
+use crate::entities::AuthSession;
+use crate::entities::error::AuthError;
+use crate::services::AuthRevokeTokensService;
+
+pub async fn logout<AuthServiceImpl: AuthRevokeTokensService>(auth_service: Extension<Arc<AuthServiceImpl>>, Extension(auth_session): Extension<AuthSession>) -> Result<StatusCode, AuthError> {
+    auth_service.revoke_session(&auth_session).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn logout_everywhere<AuthServiceImpl: AuthRevokeTokensService>(auth_service: Extension<Arc<AuthServiceImpl>>, Extension(auth_session): Extension<AuthSession>) -> Result<StatusCode, AuthError> {
+    // Anonymous session have no token to revoke
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    auth_service.revoke_all_sessions(&auth_session.username).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,3 +1,16 @@
 pub(crate) mod login;
 pub(crate) mod create_credentials;
-pub(crate) mod refresh_tokens;
\ No newline at end of file
+pub(crate) mod refresh_tokens;
+pub(crate) mod logout;
+pub(crate) mod sessions;
+pub(crate) mod change_password;
+pub(crate) mod roles;
+pub(crate) mod password_reset;
+pub(crate) mod login_attempts;
+pub(crate) mod totp;
+pub(crate) mod webauthn;
+pub(crate) mod personal_access_tokens;
+pub(crate) mod oauth_clients;
+pub(crate) mod oidc;
+pub(crate) mod authorization_server;
+pub(crate) mod key_ring;
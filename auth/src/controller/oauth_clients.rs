@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use axum::extract::Path;
+use axum::{Extension, Form, Json};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum_extra::headers::Authorization;
+use axum_extra::headers::authorization::Basic;
+use axum_extra::TypedHeader;
+use crate::entities::error::{AuthError, OAuthError};
+use crate::services::{AuthAuthorizationCodeService, AuthClientCredentialsService, AuthTokenIntrospectionService};
+use crate::views::payload::{IntrospectionPayload, RegisterOAuthClientPayload, TokenRequestPayload};
+use crate::views::response::{OAuthClientBody, OAuthClientDetails};
+
+const CLIENT_CREDENTIALS_GRANT_TYPE: &str = "client_credentials";
+const AUTHORIZATION_CODE_GRANT_TYPE: &str = "authorization_code";
+
+/// Client authenticated with HTTP Basic or with the form fields
+fn client_credentials(basic_authorization: Option<TypedHeader<Authorization<Basic>>>, client_id: Option<String>, client_secret: Option<String>) -> Result<(String, String), OAuthError> {
+    match (basic_authorization, client_id, client_secret) {
+        (Some(TypedHeader(Authorization(basic))), None, None) => Ok((basic.username().to_string(), basic.password().to_string())),
+        (None, Some(client_id), Some(client_secret)) => Ok((client_id, client_secret)),
+        // RFC 6749 : the client must use only one authentication method
+        (Some(_), _, _) => Err(OAuthError::InvalidRequest),
+        (None, _, _) => Err(OAuthError::InvalidClient),
+    }
+}
+
+/// Register a confidential client, for super administrators
+pub async fn register_oauth_client<OAuthClientServiceImpl: AuthClientCredentialsService>(oauth_client_service: Extension<Arc<OAuthClientServiceImpl>>, Json(payload): Json<RegisterOAuthClientPayload>) -> Result<(StatusCode, Json<OAuthClientBody>), AuthError> {
+    Ok((StatusCode::CREATED, Json(oauth_client_service.register_client(payload).await?)))
+}
+
+pub async fn get_oauth_clients<OAuthClientServiceImpl: AuthClientCredentialsService>(oauth_client_service: Extension<Arc<OAuthClientServiceImpl>>) -> Result<Json<Vec<OAuthClientDetails>>, AuthError> {
+    Ok(Json(oauth_client_service.get_clients().await?))
+}
+
+pub async fn delete_oauth_client<OAuthClientServiceImpl: AuthClientCredentialsService>(oauth_client_service: Extension<Arc<OAuthClientServiceImpl>>, Path(client_id): Path<String>) -> Result<StatusCode, AuthError> {
+    oauth_client_service.delete_client(&client_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Token endpoint of the `client_credentials` and `authorization_code` grants. The client authenticates with HTTP Basic or with the form fields
+pub async fn token<OAuthClientServiceImpl: AuthClientCredentialsService, AuthorizationServerServiceImpl: AuthAuthorizationCodeService>(oauth_client_service: Extension<Arc<OAuthClientServiceImpl>>, authorization_server_service: Extension<Arc<AuthorizationServerServiceImpl>>, basic_authorization: Option<TypedHeader<Authorization<Basic>>>, Form(payload): Form<TokenRequestPayload>) -> Result<Response, OAuthError> {
+    if payload.grant_type != CLIENT_CREDENTIALS_GRANT_TYPE && payload.grant_type != AUTHORIZATION_CODE_GRANT_TYPE {
+        return Err(OAuthError::UnsupportedGrantType);
+    }
+
+    let (client_id, client_secret) = client_credentials(basic_authorization, payload.client_id, payload.client_secret)?;
+
+    if payload.grant_type == CLIENT_CREDENTIALS_GRANT_TYPE {
+        let token_body = oauth_client_service.issue_client_credentials_token(&client_id, &client_secret, payload.scope).await?;
+
+        return Ok(([(header::CACHE_CONTROL, "no-store")], Json(token_body)).into_response());
+    }
+
+    let (Some(code), Some(redirect_uri), Some(code_verifier)) = (payload.code, payload.redirect_uri, payload.code_verifier) else {
+        return Err(OAuthError::InvalidRequest);
+    };
+    let token_body = authorization_server_service.issue_authorization_code_token(&client_id, &client_secret, &code, &redirect_uri, &code_verifier).await?;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(token_body)).into_response())
+}
+
+/// Introspection endpoint of RFC 7662, for services and gateways checking tokens without the keys
+pub async fn introspect<IntrospectionServiceImpl: AuthTokenIntrospectionService>(introspection_service: Extension<Arc<IntrospectionServiceImpl>>, basic_authorization: Option<TypedHeader<Authorization<Basic>>>, Form(payload): Form<IntrospectionPayload>) -> Result<Response, OAuthError> {
+    let (client_id, client_secret) = client_credentials(basic_authorization, payload.client_id, payload.client_secret)?;
+    let introspection_body = introspection_service.introspect(&client_id, &client_secret, &payload.token).await?;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(introspection_body)).into_response())
+}
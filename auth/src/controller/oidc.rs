@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use axum::extract::{Path, Query};
+use axum::{Extension, Json};
+use axum::response::Redirect;
+use crate::entities::ClientInformation;
+use crate::entities::error::AuthError;
+use crate::services::{AuthOidcService, AuthTokensService, AuthTotpService};
+use crate::views::payload::OidcCallbackPayload;
+use crate::views::response::LoginBody;
+
+/// Redirect the user to the login page of the provider
+pub async fn start_oidc_login<OidcServiceImpl: AuthOidcService>(oidc_service: Extension<Arc<OidcServiceImpl>>, Path(provider): Path<String>) -> Result<Redirect, AuthError> {
+    Ok(Redirect::to(&oidc_service.start_login(&provider).await?))
+}
+
+/// Redirection URI registered at the provider. The provider replaces the password, not the TOTP second factor of the user
+pub async fn finish_oidc_login<OidcServiceImpl: AuthOidcService, AuthServiceImpl: AuthTokensService, TotpServiceImpl: AuthTotpService>(oidc_service: Extension<Arc<OidcServiceImpl>>, auth_service: Extension<Arc<AuthServiceImpl>>, totp_service: Extension<Arc<TotpServiceImpl>>, Path(provider): Path<String>, client_information: ClientInformation, Query(payload): Query<OidcCallbackPayload>) -> Result<Json<LoginBody>, AuthError> {
+    let user = oidc_service.finish_login(&provider, payload).await?;
+
+    if totp_service.is_totp_enabled(&user.username).await? {
+        return Ok(Json(LoginBody::MfaRequired(totp_service.generate_mfa_ticket(&user.username).await?)));
+    }
+
+    Ok(Json(LoginBody::Tokens(auth_service.generate_token(&user, &client_information).await?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use std::sync::Arc;
+    use axum::extract::{Path, Query};
+    use axum::{Extension, Json};
+    use fake::{Fake, Faker};
+    use mockall::predicate::eq;
+    use mongodb::bson::oid::ObjectId;
+    use crate::controller::oidc::finish_oidc_login;
+    use crate::datastore::{MockAuthDatastore, MockTokenDatastore};
+    use crate::entities::{ClientInformation, Token, UserCredentials};
+    use crate::services::{MockAuthOidcService, MockAuthService, MockAuthTotpService};
+    use crate::views::payload::OidcCallbackPayload;
+    use crate::views::response::{LoginBody, MfaTicketBody};
+
+    fn callback_payload() -> Query<OidcCallbackPayload> {
+        Query(OidcCallbackPayload { code: Some("code".to_string()), state: "state".to_string(), error: None })
+    }
+
+    fn mock_oidc_service() -> MockAuthOidcService {
+        let mut mock_oidc_service = MockAuthOidcService::new();
+        mock_oidc_service.expect_finish_login().times(1).returning(|_, _| Box::pin(future::ready(Ok(UserCredentials {
+            username: "username".to_string(),
+            ..Faker.fake::<UserCredentials>()
+        }))));
+        mock_oidc_service
+    }
+
+    #[tokio::test]
+    async fn test_finish_oidc_login_mfa_required() {
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        mock_tokens_datastore.expect_add_tokens().times(0);
+
+        let mut mock_totp_service = MockAuthTotpService::new();
+        mock_totp_service.expect_is_totp_enabled().with(eq("username")).times(1).returning(|_| Box::pin(future::ready(Ok(true))));
+        mock_totp_service.expect_generate_mfa_ticket().with(eq("username")).times(1).returning(|_| Box::pin(future::ready(Ok(MfaTicketBody { mfa_ticket: "mfa_ticket".to_string(), expired_at: "expired_at".to_string() }))));
+
+        let auth_service = MockAuthService::new(MockAuthDatastore::new(), mock_tokens_datastore, AuthConfig::fake());
+
+        let Json(login_body) = finish_oidc_login(Extension(Arc::new(mock_oidc_service())), Extension(Arc::new(auth_service)), Extension(Arc::new(mock_totp_service)), Path("mock".to_string()), ClientInformation::default(), callback_payload()).await.unwrap();
+
+        assert_eq!(login_body, LoginBody::MfaRequired(MfaTicketBody { mfa_ticket: "mfa_ticket".to_string(), expired_at: "expired_at".to_string() }));
+    }
+
+    #[tokio::test]
+    async fn test_finish_oidc_login_without_second_factor() {
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        mock_tokens_datastore.expect_add_tokens().times(1).returning(|token| Box::pin(future::ready(Ok(Token { id: Some(ObjectId::new()), ..token }))));
+
+        let mut mock_totp_service = MockAuthTotpService::new();
+        mock_totp_service.expect_is_totp_enabled().times(1).returning(|_| Box::pin(future::ready(Ok(false))));
+        mock_totp_service.expect_generate_mfa_ticket().times(0);
+
+        let auth_service = MockAuthService::new(MockAuthDatastore::new(), mock_tokens_datastore, AuthConfig::fake());
+
+        let Json(login_body) = finish_oidc_login(Extension(Arc::new(mock_oidc_service())), Extension(Arc::new(auth_service)), Extension(Arc::new(mock_totp_service)), Path("mock".to_string()), ClientInformation::default(), callback_payload()).await.unwrap();
+
+        assert!(matches!(login_body, LoginBody::Tokens(auth_body) if !auth_body.token.is_empty()));
+    }
+}
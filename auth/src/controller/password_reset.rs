@@ -0,0 +1,19 @@
+use std::sync::Arc;
+use axum::{Extension, Json};
+use axum::http::StatusCode;
+use crate::entities::error::AuthError;
+use crate::services::AuthPasswordResetService;
+use crate::views::payload::{PasswordResetConfirmPayload, PasswordResetRequestPayload};
+
+/// Always accepted, whether the username exists or not
+pub async fn request_password_reset<PasswordResetServiceImpl: AuthPasswordResetService>(password_reset_service: Extension<Arc<PasswordResetServiceImpl>>, Json(payload): Json<PasswordResetRequestPayload>) -> Result<StatusCode, AuthError> {
+    password_reset_service.request_password_reset(&payload.username).await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+pub async fn confirm_password_reset<PasswordResetServiceImpl: AuthPasswordResetService>(password_reset_service: Extension<Arc<PasswordResetServiceImpl>>, Json(payload): Json<PasswordResetConfirmPayload>) -> Result<StatusCode, AuthError> {
+    password_reset_service.confirm_password_reset(payload).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+use axum::extract::Path;
+use axum::{Extension, Json};
+use axum::http::StatusCode;
+use crate::entities::{AuthMethod, AuthSession};
+use crate::entities::error::AuthError;
+use crate::services::AuthPersonalAccessTokensService;
+use crate::views::payload::CreatePersonalAccessTokenPayload;
+use crate::views::response::{PersonalAccessTokenBody, PersonalAccessTokenDetails};
+
+/// Only a session opened by login can create personal access tokens, not another personal access token
+pub async fn create_personal_access_token<PersonalAccessTokenServiceImpl: AuthPersonalAccessTokensService>(personal_access_token_service: Extension<Arc<PersonalAccessTokenServiceImpl>>, Extension(auth_session): Extension<AuthSession>, Json(payload): Json<CreatePersonalAccessTokenPayload>) -> Result<(StatusCode, Json<PersonalAccessTokenBody>), AuthError> {
+    if auth_session.auth_method != AuthMethod::AccessToken {
+        return Err(AuthError::Unauthorized);
+    }
+
+    Ok((StatusCode::CREATED, Json(personal_access_token_service.create_personal_access_token(&auth_session.username, payload).await?)))
+}
+
+pub async fn get_personal_access_tokens<PersonalAccessTokenServiceImpl: AuthPersonalAccessTokensService>(personal_access_token_service: Extension<Arc<PersonalAccessTokenServiceImpl>>, Extension(auth_session): Extension<AuthSession>) -> Result<Json<Vec<PersonalAccessTokenDetails>>, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    Ok(Json(personal_access_token_service.get_personal_access_tokens(&auth_session.username).await?))
+}
+
+pub async fn revoke_personal_access_token<PersonalAccessTokenServiceImpl: AuthPersonalAccessTokensService>(personal_access_token_service: Extension<Arc<PersonalAccessTokenServiceImpl>>, Extension(auth_session): Extension<AuthSession>, Path(token_id): Path<String>) -> Result<StatusCode, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    personal_access_token_service.revoke_personal_access_token(&auth_session.username, &token_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
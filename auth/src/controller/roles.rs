@@ -0,0 +1,32 @@
+use std::sync::Arc;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use crate::entities::AuthSession;
+use crate::entities::error::AuthError;
+use crate::services::AuthRolesService;
+use crate::views::payload::{SaveRolePayload, UpdateUserRolesPayload};
+use crate::views::response::{RoleDetails, UserRolesDetails};
+
+pub async fn get_user_roles<RoleServiceImpl: AuthRolesService>(role_service: Extension<Arc<RoleServiceImpl>>, Path(username): Path<String>) -> Result<Json<UserRolesDetails>, AuthError> {
+    Ok(Json(role_service.get_user_roles(&username).await?))
+}
+
+/// Grant roles to the username given, for administrators. The sessions of the user are revoked
+pub async fn update_user_roles<RoleServiceImpl: AuthRolesService>(role_service: Extension<Arc<RoleServiceImpl>>, Extension(auth_session): Extension<AuthSession>, Path(username): Path<String>, Json(update_user_roles_payload): Json<UpdateUserRolesPayload>) -> Result<Json<UserRolesDetails>, AuthError> {
+    Ok(Json(role_service.update_user_roles(&auth_session, &username, update_user_roles_payload.roles).await?))
+}
+
+pub async fn get_roles<RoleServiceImpl: AuthRolesService>(role_service: Extension<Arc<RoleServiceImpl>>) -> Result<Json<Vec<RoleDetails>>, AuthError> {
+    Ok(Json(role_service.get_roles().await?))
+}
+
+pub async fn save_role<RoleServiceImpl: AuthRolesService>(role_service: Extension<Arc<RoleServiceImpl>>, Path(name): Path<String>, Json(save_role_payload): Json<SaveRolePayload>) -> Result<Json<RoleDetails>, AuthError> {
+    Ok(Json(role_service.save_role(&name, save_role_payload).await?))
+}
+
+pub async fn delete_role<RoleServiceImpl: AuthRolesService>(role_service: Extension<Arc<RoleServiceImpl>>, Path(name): Path<String>) -> Result<StatusCode, AuthError> {
+    role_service.delete_role(&name).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+use axum::extract::Path;
+use axum::{Extension, Json};
+use axum::http::StatusCode;
+use crate::entities::AuthSession;
+use crate::entities::error::AuthError;
+use crate::services::AuthSessionsService;
+use crate::views::response::SessionDetails;
+
+pub async fn get_sessions<AuthServiceImpl: AuthSessionsService>(auth_service: Extension<Arc<AuthServiceImpl>>, Extension(auth_session): Extension<AuthSession>) -> Result<Json<Vec<SessionDetails>>, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    Ok(Json(auth_service.get_sessions(&auth_session).await?))
+}
+
+pub async fn revoke_session<AuthServiceImpl: AuthSessionsService>(auth_service: Extension<Arc<AuthServiceImpl>>, Extension(auth_session): Extension<AuthSession>, Path(session_id): Path<String>) -> Result<StatusCode, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    auth_service.revoke_session_by_id(&auth_session.username, &session_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
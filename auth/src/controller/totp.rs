@@ -0,0 +1,36 @@
+use std::sync::Arc;
+use axum::{Extension, Json};
+use axum::http::StatusCode;
+use crate::entities::AuthSession;
+use crate::entities::error::AuthError;
+use crate::services::AuthTotpService;
+use crate::views::payload::TotpCodePayload;
+use crate::views::response::TotpEnrolmentBody;
+
+pub async fn enrol_totp<TotpServiceImpl: AuthTotpService>(totp_service: Extension<Arc<TotpServiceImpl>>, Extension(auth_session): Extension<AuthSession>) -> Result<Json<TotpEnrolmentBody>, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    Ok(Json(totp_service.enrol_totp(&auth_session.username).await?))
+}
+
+pub async fn confirm_totp<TotpServiceImpl: AuthTotpService>(totp_service: Extension<Arc<TotpServiceImpl>>, Extension(auth_session): Extension<AuthSession>, Json(payload): Json<TotpCodePayload>) -> Result<StatusCode, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    totp_service.confirm_totp(&auth_session.username, &payload.code).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn disable_totp<TotpServiceImpl: AuthTotpService>(totp_service: Extension<Arc<TotpServiceImpl>>, Extension(auth_session): Extension<AuthSession>, Json(payload): Json<TotpCodePayload>) -> Result<StatusCode, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    totp_service.disable_totp(&auth_session.username, &payload.code).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+use axum::extract::Path;
+use axum::{Extension, Json};
+use axum::http::StatusCode;
+use crate::entities::{AuthSession, ClientInformation};
+use crate::entities::error::AuthError;
+use crate::services::AuthWebAuthnService;
+use crate::views::payload::{WebAuthnAssertionPayload, WebAuthnLoginStartPayload, WebAuthnRegistrationPayload};
+use crate::views::response::{AuthBody, WebAuthnCreationOptions, WebAuthnCredentialDetails, WebAuthnRequestOptions};
+
+pub async fn start_webauthn_registration<WebAuthnServiceImpl: AuthWebAuthnService>(webauthn_service: Extension<Arc<WebAuthnServiceImpl>>, Extension(auth_session): Extension<AuthSession>) -> Result<Json<WebAuthnCreationOptions>, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    Ok(Json(webauthn_service.start_registration(&auth_session.username).await?))
+}
+
+pub async fn finish_webauthn_registration<WebAuthnServiceImpl: AuthWebAuthnService>(webauthn_service: Extension<Arc<WebAuthnServiceImpl>>, Extension(auth_session): Extension<AuthSession>, Json(payload): Json<WebAuthnRegistrationPayload>) -> Result<(StatusCode, Json<WebAuthnCredentialDetails>), AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    Ok((StatusCode::CREATED, Json(webauthn_service.finish_registration(&auth_session.username, payload).await?)))
+}
+
+pub async fn start_webauthn_login<WebAuthnServiceImpl: AuthWebAuthnService>(webauthn_service: Extension<Arc<WebAuthnServiceImpl>>, Json(payload): Json<WebAuthnLoginStartPayload>) -> Result<Json<WebAuthnRequestOptions>, AuthError> {
+    Ok(Json(webauthn_service.start_authentication(payload.username).await?))
+}
+
+pub async fn finish_webauthn_login<WebAuthnServiceImpl: AuthWebAuthnService>(webauthn_service: Extension<Arc<WebAuthnServiceImpl>>, client_information: ClientInformation, Json(payload): Json<WebAuthnAssertionPayload>) -> Result<Json<AuthBody>, AuthError> {
+    Ok(Json(webauthn_service.finish_authentication(payload, &client_information).await?))
+}
+
+pub async fn get_webauthn_credentials<WebAuthnServiceImpl: AuthWebAuthnService>(webauthn_service: Extension<Arc<WebAuthnServiceImpl>>, Extension(auth_session): Extension<AuthSession>) -> Result<Json<Vec<WebAuthnCredentialDetails>>, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    Ok(Json(webauthn_service.get_credentials(&auth_session.username).await?))
+}
+
+pub async fn delete_webauthn_credential<WebAuthnServiceImpl: AuthWebAuthnService>(webauthn_service: Extension<Arc<WebAuthnServiceImpl>>, Extension(auth_session): Extension<AuthSession>, Path(credential_id): Path<String>) -> Result<StatusCode, AuthError> {
+    if auth_session.token_identifier.is_none() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    webauthn_service.delete_credential(&auth_session.username, &credential_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,8 +1,9 @@
+    use mongodb::bson::DateTime;
     use mongodb::bson::oid::ObjectId;
     use once_cell::sync::Lazy;
     use tokio::sync::Mutex;
-    use crate::datastore::AuthDatastoreError;
-    use crate::entities::UserCredentials;
+    use crate::datastore::{AuthDatastoreError, LoginAttemptDatastoreError, OAuthAuthorizationDatastoreError, OAuthClientDatastoreError, PasswordResetDatastoreError, PersonalAccessTokenDatastoreError, RoleDatastoreError, TokenDatastoreError, TotpDatastoreError, WebAuthnDatastoreError};
+    use crate::entities::{AuthorizationCode, LoginAttempts, OAuthClient, OAuthConsent, OidcLink, OidcLoginState, PasswordReset, PersonalAccessToken, ReferenceToken, Role, Roles, Token, TotpCredentials, UserCredentials, WebAuthnChallenge, WebAuthnCredential};
 
     
     #[derive(Clone)]
@@ -32,4 +33,464 @@
 
             return None;
         }
-    }
\ No newline at end of file
+
+        pub async fn update_password(&self, username: &str, password_hash: &str) -> Result<(), AuthDatastoreError> {
+            let mut user_list = USER_LIST.lock().await;
+            let user_credentials = user_list.iter_mut()
+                .find(|user_credentials| user_credentials.username == username)
+                .ok_or(AuthDatastoreError::InternalError)?;
+
+            user_credentials.password = password_hash.to_string();
+            user_credentials.last_modified_at = DateTime::now();
+
+            Ok(())
+        }
+
+        pub async fn update_roles(&self, username: &str, roles: &[Roles]) -> Result<(), AuthDatastoreError> {
+            let mut user_list = USER_LIST.lock().await;
+            let user_credentials = user_list.iter_mut()
+                .find(|user_credentials| user_credentials.username == username)
+                .ok_or(AuthDatastoreError::InternalError)?;
+
+            user_credentials.roles = roles.to_vec();
+            user_credentials.last_modified_at = DateTime::now();
+
+            Ok(())
+        }
+
+        pub async fn update_roles_keeping_one_of(&self, username: &str, roles: &[Roles], kept_roles: &[Roles]) -> Result<bool, AuthDatastoreError> {
+            let mut user_list = USER_LIST.lock().await;
+            let has_other_holders = user_list.iter()
+                .any(|user_credentials| user_credentials.username != username && user_credentials.roles.iter().any(|role| kept_roles.contains(role)));
+            if !has_other_holders {
+                return Ok(false);
+            }
+
+            let user_credentials = user_list.iter_mut()
+                .find(|user_credentials| user_credentials.username == username)
+                .ok_or(AuthDatastoreError::InternalError)?;
+            user_credentials.roles = roles.to_vec();
+            user_credentials.last_modified_at = DateTime::now();
+
+            Ok(true)
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct TokenMemoryDriver {
+    }
+
+    static TOKEN_LIST: Lazy<Mutex<Vec<Token>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    impl TokenMemoryDriver {
+
+        pub async fn add_tokens(&self, token: Token) -> Result<Token, TokenDatastoreError> {
+            let new_token = Token {
+                id: Some(ObjectId::new()),
+                ..token
+            };
+
+            TOKEN_LIST.lock().await.push(new_token.clone());
+
+            Ok(new_token)
+        }
+
+        pub async fn find_token(&self, predicate: impl Fn(&Token) -> bool) -> Option<Token> {
+            TOKEN_LIST.lock().await.iter().find(|token| predicate(token)).cloned()
+        }
+
+        pub async fn get_tokens_for_user(&self, username: &str) -> Vec<Token> {
+            TOKEN_LIST.lock().await.iter().filter(|token| token.username == username).cloned().collect()
+        }
+
+        pub async fn revoke_token(&self, token_identifier: &str) -> Result<(), TokenDatastoreError> {
+            let mut token_list = TOKEN_LIST.lock().await;
+            let token = token_list.iter_mut()
+                .find(|token| token.token_refresh_identifiers == token_identifier)
+                .ok_or(TokenDatastoreError::InternalError)?;
+
+            token.revoked_at = Some(DateTime::now());
+
+            Ok(())
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct ReferenceTokenMemoryDriver {
+    }
+
+    static REFERENCE_TOKEN_LIST: Lazy<Mutex<Vec<ReferenceToken>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    impl ReferenceTokenMemoryDriver {
+
+        pub async fn add_reference_token(&self, reference_token: ReferenceToken) -> ReferenceToken {
+            let reference_token = ReferenceToken { id: Some(ObjectId::new()), ..reference_token };
+            REFERENCE_TOKEN_LIST.lock().await.push(reference_token.clone());
+
+            reference_token
+        }
+
+        pub async fn get_reference_token(&self, token_hash: &str) -> Option<ReferenceToken> {
+            REFERENCE_TOKEN_LIST.lock().await.iter().find(|reference_token| reference_token.token_hash == token_hash).cloned()
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct PasswordResetMemoryDriver {
+    }
+
+    static PASSWORD_RESET_LIST: Lazy<Mutex<Vec<PasswordReset>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    impl PasswordResetMemoryDriver {
+
+        pub async fn add_password_reset(&self, password_reset: PasswordReset) -> Result<PasswordReset, PasswordResetDatastoreError> {
+            let new_password_reset = PasswordReset {
+                id: Some(ObjectId::new()),
+                ..password_reset
+            };
+
+            PASSWORD_RESET_LIST.lock().await.push(new_password_reset.clone());
+
+            Ok(new_password_reset)
+        }
+
+        pub async fn get_password_reset(&self, reset_token_hash: &str) -> Option<PasswordReset> {
+            PASSWORD_RESET_LIST.lock().await.iter().find(|password_reset| password_reset.reset_token_hash == reset_token_hash).cloned()
+        }
+
+        pub async fn use_password_reset(&self, reset_token_hash: &str) -> Result<(), PasswordResetDatastoreError> {
+            let mut password_reset_list = PASSWORD_RESET_LIST.lock().await;
+            let password_reset = password_reset_list.iter_mut()
+                .find(|password_reset| password_reset.reset_token_hash == reset_token_hash && password_reset.used_at.is_none())
+                .ok_or(PasswordResetDatastoreError::InternalError)?;
+
+            password_reset.used_at = Some(DateTime::now());
+
+            Ok(())
+        }
+
+        pub async fn delete_password_reset(&self, reset_token_hash: &str) {
+            PASSWORD_RESET_LIST.lock().await.retain(|password_reset| password_reset.reset_token_hash != reset_token_hash);
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct LoginAttemptMemoryDriver {
+    }
+
+    static LOGIN_ATTEMPT_LIST: Lazy<Mutex<Vec<LoginAttempts>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    impl LoginAttemptMemoryDriver {
+
+        pub async fn get_login_attempts(&self, key: &str) -> Option<LoginAttempts> {
+            LOGIN_ATTEMPT_LIST.lock().await.iter().find(|login_attempts| login_attempts.key == key).cloned()
+        }
+
+        pub async fn add_login_failure(&self, key: &str) -> LoginAttempts {
+            let mut login_attempt_list = LOGIN_ATTEMPT_LIST.lock().await;
+
+            if let Some(login_attempts) = login_attempt_list.iter_mut().find(|login_attempts| login_attempts.key == key) {
+                login_attempts.failures += 1;
+                login_attempts.last_failure_at = DateTime::now();
+
+                return login_attempts.clone();
+            }
+
+            let login_attempts = LoginAttempts {
+                id: Some(ObjectId::new()),
+                key: key.to_string(),
+                failures: 1,
+                last_failure_at: DateTime::now(),
+                locked_until: None,
+            };
+            login_attempt_list.push(login_attempts.clone());
+
+            login_attempts
+        }
+
+        pub async fn lock_login_attempts(&self, key: &str, locked_until: DateTime) -> Result<(), LoginAttemptDatastoreError> {
+            let mut login_attempt_list = LOGIN_ATTEMPT_LIST.lock().await;
+            let login_attempts = login_attempt_list.iter_mut()
+                .find(|login_attempts| login_attempts.key == key)
+                .ok_or(LoginAttemptDatastoreError::InternalError)?;
+
+            login_attempts.locked_until = Some(locked_until);
+
+            Ok(())
+        }
+
+        pub async fn clear_login_attempts(&self, key: &str) {
+            LOGIN_ATTEMPT_LIST.lock().await.retain(|login_attempts| login_attempts.key != key);
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct TotpMemoryDriver {
+    }
+
+    static TOTP_LIST: Lazy<Mutex<Vec<TotpCredentials>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    impl TotpMemoryDriver {
+
+        pub async fn get_totp(&self, username: &str) -> Option<TotpCredentials> {
+            TOTP_LIST.lock().await.iter().find(|totp_credentials| totp_credentials.username == username).cloned()
+        }
+
+        pub async fn save_totp(&self, totp_credentials: TotpCredentials) {
+            let mut totp_list = TOTP_LIST.lock().await;
+            totp_list.retain(|saved_totp_credentials| saved_totp_credentials.username != totp_credentials.username);
+            totp_list.push(TotpCredentials { id: Some(ObjectId::new()), ..totp_credentials });
+        }
+
+        pub async fn update_totp<F: FnOnce(&mut TotpCredentials) -> bool>(&self, username: &str, update: F) -> Result<(), TotpDatastoreError> {
+            let mut totp_list = TOTP_LIST.lock().await;
+            let totp_credentials = totp_list.iter_mut()
+                .find(|totp_credentials| totp_credentials.username == username)
+                .ok_or(TotpDatastoreError::InternalError)?;
+
+            if update(totp_credentials) {
+                Ok(())
+            } else {
+                Err(TotpDatastoreError::InternalError)
+            }
+        }
+
+        pub async fn delete_totp(&self, username: &str) {
+            TOTP_LIST.lock().await.retain(|totp_credentials| totp_credentials.username != username);
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct WebAuthnMemoryDriver {
+    }
+
+    static WEBAUTHN_CREDENTIAL_LIST: Lazy<Mutex<Vec<WebAuthnCredential>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    static WEBAUTHN_CHALLENGE_LIST: Lazy<Mutex<Vec<WebAuthnChallenge>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    impl WebAuthnMemoryDriver {
+
+        pub async fn add_challenge(&self, webauthn_challenge: WebAuthnChallenge) {
+            let mut webauthn_challenge_list = WEBAUTHN_CHALLENGE_LIST.lock().await;
+            webauthn_challenge_list.retain(|saved_webauthn_challenge| saved_webauthn_challenge.expired_at > DateTime::now());
+            webauthn_challenge_list.push(WebAuthnChallenge { id: Some(ObjectId::new()), ..webauthn_challenge });
+        }
+
+        pub async fn take_challenge(&self, challenge: &str) -> Option<WebAuthnChallenge> {
+            let mut webauthn_challenge_list = WEBAUTHN_CHALLENGE_LIST.lock().await;
+            let position = webauthn_challenge_list.iter().position(|webauthn_challenge| webauthn_challenge.challenge == challenge)?;
+
+            Some(webauthn_challenge_list.remove(position))
+        }
+
+        pub async fn add_credential(&self, webauthn_credential: WebAuthnCredential) -> WebAuthnCredential {
+            let webauthn_credential = WebAuthnCredential { id: Some(ObjectId::new()), ..webauthn_credential };
+            WEBAUTHN_CREDENTIAL_LIST.lock().await.push(webauthn_credential.clone());
+
+            webauthn_credential
+        }
+
+        pub async fn get_credential(&self, credential_id: &str) -> Option<WebAuthnCredential> {
+            WEBAUTHN_CREDENTIAL_LIST.lock().await.iter().find(|webauthn_credential| webauthn_credential.credential_id == credential_id).cloned()
+        }
+
+        pub async fn get_credentials(&self, username: &str) -> Vec<WebAuthnCredential> {
+            WEBAUTHN_CREDENTIAL_LIST.lock().await.iter().filter(|webauthn_credential| webauthn_credential.username == username).cloned().collect()
+        }
+
+        pub async fn update_credential_usage(&self, credential_id: &str, sign_count: u32) -> Result<(), WebAuthnDatastoreError> {
+            let mut webauthn_credential_list = WEBAUTHN_CREDENTIAL_LIST.lock().await;
+            let webauthn_credential = webauthn_credential_list.iter_mut()
+                .find(|webauthn_credential| webauthn_credential.credential_id == credential_id)
+                .ok_or(WebAuthnDatastoreError::InternalError)?;
+
+            webauthn_credential.sign_count = sign_count;
+            webauthn_credential.last_used_at = Some(DateTime::now());
+
+            Ok(())
+        }
+
+        pub async fn delete_credential(&self, username: &str, credential_id: &str) -> Result<(), WebAuthnDatastoreError> {
+            let mut webauthn_credential_list = WEBAUTHN_CREDENTIAL_LIST.lock().await;
+            let position = webauthn_credential_list.iter()
+                .position(|webauthn_credential| webauthn_credential.username == username && webauthn_credential.credential_id == credential_id)
+                .ok_or(WebAuthnDatastoreError::InternalError)?;
+            webauthn_credential_list.remove(position);
+
+            Ok(())
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct PersonalAccessTokenMemoryDriver {
+    }
+
+    static PERSONAL_ACCESS_TOKEN_LIST: Lazy<Mutex<Vec<PersonalAccessToken>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    impl PersonalAccessTokenMemoryDriver {
+
+        pub async fn add_personal_access_token(&self, personal_access_token: PersonalAccessToken) -> PersonalAccessToken {
+            let personal_access_token = PersonalAccessToken { id: Some(ObjectId::new()), ..personal_access_token };
+            PERSONAL_ACCESS_TOKEN_LIST.lock().await.push(personal_access_token.clone());
+
+            personal_access_token
+        }
+
+        pub async fn get_personal_access_token(&self, token_hash: &str) -> Option<PersonalAccessToken> {
+            PERSONAL_ACCESS_TOKEN_LIST.lock().await.iter().find(|personal_access_token| personal_access_token.token_hash == token_hash).cloned()
+        }
+
+        pub async fn get_personal_access_tokens(&self, username: &str) -> Vec<PersonalAccessToken> {
+            PERSONAL_ACCESS_TOKEN_LIST.lock().await.iter().filter(|personal_access_token| personal_access_token.username == username).cloned().collect()
+        }
+
+        pub async fn revoke_personal_access_token(&self, username: &str, token_id: &str) -> Result<(), PersonalAccessTokenDatastoreError> {
+            let mut personal_access_token_list = PERSONAL_ACCESS_TOKEN_LIST.lock().await;
+            let personal_access_token = personal_access_token_list.iter_mut()
+                .find(|personal_access_token| personal_access_token.username == username && personal_access_token.token_id == token_id && personal_access_token.revoked_at.is_none())
+                .ok_or(PersonalAccessTokenDatastoreError::InternalError)?;
+
+            personal_access_token.revoked_at = Some(DateTime::now());
+
+            Ok(())
+        }
+
+        pub async fn update_last_used(&self, token_id: &str) {
+            if let Some(personal_access_token) = PERSONAL_ACCESS_TOKEN_LIST.lock().await.iter_mut().find(|personal_access_token| personal_access_token.token_id == token_id) {
+                personal_access_token.last_used_at = Some(DateTime::now());
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct OAuthClientMemoryDriver {
+    }
+
+    static OAUTH_CLIENT_LIST: Lazy<Mutex<Vec<OAuthClient>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    impl OAuthClientMemoryDriver {
+
+        pub async fn add_client(&self, oauth_client: OAuthClient) -> OAuthClient {
+            let oauth_client = OAuthClient { id: Some(ObjectId::new()), ..oauth_client };
+            OAUTH_CLIENT_LIST.lock().await.push(oauth_client.clone());
+
+            oauth_client
+        }
+
+        pub async fn get_client(&self, client_id: &str) -> Option<OAuthClient> {
+            OAUTH_CLIENT_LIST.lock().await.iter().find(|oauth_client| oauth_client.client_id == client_id).cloned()
+        }
+
+        pub async fn get_clients(&self) -> Vec<OAuthClient> {
+            OAUTH_CLIENT_LIST.lock().await.clone()
+        }
+
+        pub async fn delete_client(&self, client_id: &str) -> Result<(), OAuthClientDatastoreError> {
+            let mut oauth_client_list = OAUTH_CLIENT_LIST.lock().await;
+            let position = oauth_client_list.iter()
+                .position(|oauth_client| oauth_client.client_id == client_id)
+                .ok_or(OAuthClientDatastoreError::InternalError)?;
+
+            oauth_client_list.remove(position);
+
+            Ok(())
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct OidcMemoryDriver {
+    }
+
+    static OIDC_LOGIN_STATE_LIST: Lazy<Mutex<Vec<OidcLoginState>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    static OIDC_LINK_LIST: Lazy<Mutex<Vec<OidcLink>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    impl OidcMemoryDriver {
+
+        pub async fn add_login_state(&self, oidc_login_state: OidcLoginState) {
+            let mut oidc_login_state_list = OIDC_LOGIN_STATE_LIST.lock().await;
+            oidc_login_state_list.retain(|saved_oidc_login_state| saved_oidc_login_state.expired_at > DateTime::now());
+            oidc_login_state_list.push(OidcLoginState { id: Some(ObjectId::new()), ..oidc_login_state });
+        }
+
+        pub async fn take_login_state(&self, state: &str) -> Option<OidcLoginState> {
+            let mut oidc_login_state_list = OIDC_LOGIN_STATE_LIST.lock().await;
+            let position = oidc_login_state_list.iter().position(|oidc_login_state| oidc_login_state.state == state)?;
+
+            Some(oidc_login_state_list.remove(position))
+        }
+
+        pub async fn get_link(&self, provider: &str, subject: &str) -> Option<OidcLink> {
+            OIDC_LINK_LIST.lock().await.iter().find(|oidc_link| oidc_link.provider == provider && oidc_link.subject == subject).cloned()
+        }
+
+        pub async fn add_link(&self, oidc_link: OidcLink) -> OidcLink {
+            let oidc_link = OidcLink { id: Some(ObjectId::new()), ..oidc_link };
+            OIDC_LINK_LIST.lock().await.push(oidc_link.clone());
+
+            oidc_link
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct OAuthAuthorizationMemoryDriver {
+    }
+
+    static AUTHORIZATION_CODE_LIST: Lazy<Mutex<Vec<AuthorizationCode>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    static OAUTH_CONSENT_LIST: Lazy<Mutex<Vec<OAuthConsent>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    impl OAuthAuthorizationMemoryDriver {
+
+        pub async fn add_authorization_code(&self, authorization_code: AuthorizationCode) {
+            AUTHORIZATION_CODE_LIST.lock().await.push(AuthorizationCode { id: Some(ObjectId::new()), ..authorization_code });
+        }
+
+        pub async fn take_authorization_code(&self, code_hash: &str) -> Option<AuthorizationCode> {
+            let mut authorization_code_list = AUTHORIZATION_CODE_LIST.lock().await;
+            let position = authorization_code_list.iter().position(|authorization_code| authorization_code.code_hash == code_hash)?;
+
+            Some(authorization_code_list.remove(position))
+        }
+
+        pub async fn get_consent(&self, username: &str, client_id: &str) -> Option<OAuthConsent> {
+            OAUTH_CONSENT_LIST.lock().await.iter().find(|oauth_consent| oauth_consent.username == username && oauth_consent.client_id == client_id).cloned()
+        }
+
+        pub async fn get_consents(&self, username: &str) -> Vec<OAuthConsent> {
+            OAUTH_CONSENT_LIST.lock().await.iter().filter(|oauth_consent| oauth_consent.username == username).cloned().collect()
+        }
+
+        pub async fn save_consent(&self, oauth_consent: OAuthConsent) {
+            let mut oauth_consent_list = OAUTH_CONSENT_LIST.lock().await;
+            oauth_consent_list.retain(|saved_consent| saved_consent.username != oauth_consent.username || saved_consent.client_id != oauth_consent.client_id);
+            oauth_consent_list.push(OAuthConsent { id: Some(ObjectId::new()), ..oauth_consent });
+        }
+
+        pub async fn delete_consent(&self, username: &str, client_id: &str) -> Result<(), OAuthAuthorizationDatastoreError> {
+            let mut oauth_consent_list = OAUTH_CONSENT_LIST.lock().await;
+            let position = oauth_consent_list.iter()
+                .position(|oauth_consent| oauth_consent.username == username && oauth_consent.client_id == client_id)
+                .ok_or(OAuthAuthorizationDatastoreError::InternalError)?;
+
+            oauth_consent_list.remove(position);
+
+            Ok(())
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct RoleMemoryDriver {
+    }
+
+    static ROLE_LIST: Lazy<Mutex<Vec<Role>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    impl RoleMemoryDriver {
+
+        pub async fn get_roles(&self) -> Vec<Role> {
+            ROLE_LIST.lock().await.clone()
+        }
+
+        pub async fn save_role(&self, role: Role) {
+            let mut role_list = ROLE_LIST.lock().await;
+            role_list.retain(|saved_role| saved_role.name != role.name);
+            role_list.push(Role { id: Some(ObjectId::new()), ..role });
+        }
+
+        pub async fn delete_role(&self, name: &Roles) -> Result<(), RoleDatastoreError> {
+            let mut role_list = ROLE_LIST.lock().await;
+            let position = role_list.iter()
+                .position(|role| &role.name == name)
+                .ok_or(RoleDatastoreError::InternalError)?;
+
+            role_list.remove(position);
+
+            Ok(())
+        }
+    }
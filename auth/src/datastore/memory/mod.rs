@@ -5,9 +5,12 @@ pub mod memory_driver;
 
 #[cfg(test)]
 mod test {
-    use crate::datastore::{AuthDatastore, AuthDatastoreError};
-    use crate::datastore::memory::memory_driver::AuthMemoryDriver;
-    use crate::entities::UserCredentials;
+    use fake::{Fake, Faker};
+    use mongodb::bson::DateTime;
+    use crate::datastore::{AuthDatastore, AuthDatastoreError, LoginAttemptDatastore, LoginAttemptDatastoreError, OAuthAuthorizationDatastore, OAuthAuthorizationDatastoreError, OAuthClientDatastore, OAuthClientDatastoreError, OidcDatastore, OidcDatastoreError, PasswordResetDatastore, PasswordResetDatastoreError, PersonalAccessTokenDatastore, PersonalAccessTokenDatastoreError, RoleDatastore, RoleDatastoreError, TokenDatastore, TokenDatastoreError, TotpDatastore, TotpDatastoreError, WebAuthnDatastore, WebAuthnDatastoreError};
+    use crate::utils::auth_config::AuthConfig;
+    use crate::datastore::memory::memory_driver::{AuthMemoryDriver, LoginAttemptMemoryDriver, OAuthAuthorizationMemoryDriver, OAuthClientMemoryDriver, OidcMemoryDriver, PasswordResetMemoryDriver, PersonalAccessTokenMemoryDriver, ReferenceTokenMemoryDriver, RoleMemoryDriver, TokenMemoryDriver, TotpMemoryDriver, WebAuthnMemoryDriver};
+    use crate::entities::{AuthorizationCode, LoginAttempts, OAuthClient, OAuthConsent, OidcLink, OidcLoginState, PasswordReset, PersonalAccessToken, ReferenceToken, Role, Roles, Token, TotpCredentials, UserCredentials, WebAuthnCeremony, WebAuthnChallenge, WebAuthnCredential};
 
     #[derive(Clone)]
     pub struct AuthDatastoreMemory {
@@ -28,5 +31,591 @@ mod test {
         async fn get_user_by_username(&self, username: &str) -> Result<Option<UserCredentials>, AuthDatastoreError> {
             Ok(self.auth_memory_driver.get_user_by_username(username).await)
         }
+
+        async fn update_password(&self, username: &str, password_hash: &str) -> Result<(), AuthDatastoreError> {
+            self.auth_memory_driver.update_password(username, password_hash).await
+        }
+
+        async fn update_roles(&self, username: &str, roles: &[Roles]) -> Result<(), AuthDatastoreError> {
+            self.auth_memory_driver.update_roles(username, roles).await
+        }
+
+        async fn update_roles_keeping_one_of(&self, username: &str, roles: &[Roles], kept_roles: &[Roles]) -> Result<bool, AuthDatastoreError> {
+            self.auth_memory_driver.update_roles_keeping_one_of(username, roles, kept_roles).await
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct TokenDatastoreMemory {
+        token_memory_driver: TokenMemoryDriver,
+        reference_token_memory_driver: ReferenceTokenMemoryDriver,
+    }
+
+    /// Use memory to emulate tokens datastore
+    /// It's designed for integration test usage only
+    impl TokenDatastore for TokenDatastoreMemory {
+        async fn add_tokens(&self, token: Token) -> Result<Token, TokenDatastoreError> {
+            if token.id.is_some() {
+                return Err(TokenDatastoreError::ProvidersError)
+            }
+
+            self.token_memory_driver.add_tokens(token).await
+        }
+
+        async fn get_token(&self, token_identifier: &str) -> Result<Option<Token>, TokenDatastoreError> {
+            Ok(self.token_memory_driver.find_token(|token| token.token_refresh_identifiers == token_identifier).await)
+        }
+
+        async fn get_token_by_access_identifier(&self, token_access_identifier: &str) -> Result<Option<Token>, TokenDatastoreError> {
+            Ok(self.token_memory_driver.find_token(|token| token.token_access_identifiers == token_access_identifier).await)
+        }
+
+        async fn get_tokens_for_user(&self, username: &str) -> Result<Vec<Token>, TokenDatastoreError> {
+            Ok(self.token_memory_driver.get_tokens_for_user(username).await)
+        }
+
+        async fn revoke_token(&self, token_identifier: &str) -> Result<(), TokenDatastoreError> {
+            self.token_memory_driver.revoke_token(token_identifier).await
+        }
+
+        async fn add_reference_token(&self, reference_token: ReferenceToken) -> Result<ReferenceToken, TokenDatastoreError> {
+            if reference_token.id.is_some() {
+                return Err(TokenDatastoreError::ProvidersError)
+            }
+
+            Ok(self.reference_token_memory_driver.add_reference_token(reference_token).await)
+        }
+
+        async fn get_reference_token(&self, token_hash: &str) -> Result<Option<ReferenceToken>, TokenDatastoreError> {
+            Ok(self.reference_token_memory_driver.get_reference_token(token_hash).await)
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct PasswordResetDatastoreMemory {
+        password_reset_memory_driver: PasswordResetMemoryDriver
+    }
+
+    /// Use memory to emulate password resets datastore
+    /// It's designed for integration test usage only
+    impl PasswordResetDatastore for PasswordResetDatastoreMemory {
+        async fn add_password_reset(&self, password_reset: PasswordReset) -> Result<PasswordReset, PasswordResetDatastoreError> {
+            if password_reset.id.is_some() {
+                return Err(PasswordResetDatastoreError::ProvidersError)
+            }
+
+            self.password_reset_memory_driver.add_password_reset(password_reset).await
+        }
+
+        async fn get_password_reset(&self, reset_token_hash: &str) -> Result<Option<PasswordReset>, PasswordResetDatastoreError> {
+            Ok(self.password_reset_memory_driver.get_password_reset(reset_token_hash).await)
+        }
+
+        async fn use_password_reset(&self, reset_token_hash: &str) -> Result<(), PasswordResetDatastoreError> {
+            self.password_reset_memory_driver.use_password_reset(reset_token_hash).await
+        }
+
+        async fn delete_password_reset(&self, reset_token_hash: &str) -> Result<(), PasswordResetDatastoreError> {
+            self.password_reset_memory_driver.delete_password_reset(reset_token_hash).await;
+            Ok(())
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct LoginAttemptDatastoreMemory {
+        login_attempt_memory_driver: LoginAttemptMemoryDriver
+    }
+
+    /// Use memory to emulate login attempts datastore
+    /// It's designed for integration test usage only
+    impl LoginAttemptDatastore for LoginAttemptDatastoreMemory {
+        async fn get_login_attempts(&self, key: &str) -> Result<Option<LoginAttempts>, LoginAttemptDatastoreError> {
+            Ok(self.login_attempt_memory_driver.get_login_attempts(key).await)
+        }
+
+        async fn add_login_failure(&self, key: &str) -> Result<LoginAttempts, LoginAttemptDatastoreError> {
+            Ok(self.login_attempt_memory_driver.add_login_failure(key).await)
+        }
+
+        async fn lock_login_attempts(&self, key: &str, locked_until: DateTime) -> Result<(), LoginAttemptDatastoreError> {
+            self.login_attempt_memory_driver.lock_login_attempts(key, locked_until).await
+        }
+
+        async fn clear_login_attempts(&self, key: &str) -> Result<(), LoginAttemptDatastoreError> {
+            self.login_attempt_memory_driver.clear_login_attempts(key).await;
+            Ok(())
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct TotpDatastoreMemory {
+        totp_memory_driver: TotpMemoryDriver
+    }
+
+    /// Use memory to emulate TOTP datastore
+    /// It's designed for integration test usage only
+    impl TotpDatastore for TotpDatastoreMemory {
+        async fn get_totp(&self, username: &str) -> Result<Option<TotpCredentials>, TotpDatastoreError> {
+            Ok(self.totp_memory_driver.get_totp(username).await)
+        }
+
+        async fn save_totp(&self, totp_credentials: TotpCredentials) -> Result<(), TotpDatastoreError> {
+            self.totp_memory_driver.save_totp(totp_credentials).await;
+            Ok(())
+        }
+
+        async fn enable_totp(&self, username: &str) -> Result<(), TotpDatastoreError> {
+            self.totp_memory_driver.update_totp(username, |totp_credentials| {
+                totp_credentials.enabled = true;
+                true
+            }).await
+        }
+
+        async fn delete_totp(&self, username: &str) -> Result<(), TotpDatastoreError> {
+            self.totp_memory_driver.delete_totp(username).await;
+            Ok(())
+        }
+
+        async fn use_totp_step(&self, username: &str, step: i64) -> Result<(), TotpDatastoreError> {
+            self.totp_memory_driver.update_totp(username, |totp_credentials| {
+                if totp_credentials.last_used_step.is_some_and(|last_used_step| last_used_step >= step) {
+                    return false;
+                }
+                totp_credentials.last_used_step = Some(step);
+                true
+            }).await
+        }
+
+        async fn use_recovery_code(&self, username: &str, recovery_code_hash: &str) -> Result<(), TotpDatastoreError> {
+            self.totp_memory_driver.update_totp(username, |totp_credentials| {
+                let recovery_codes_count = totp_credentials.recovery_code_hashes.len();
+                totp_credentials.recovery_code_hashes.retain(|saved_recovery_code_hash| saved_recovery_code_hash != recovery_code_hash);
+                totp_credentials.recovery_code_hashes.len() < recovery_codes_count
+            }).await
+        }
+
+        async fn save_mfa_ticket(&self, username: &str, ticket_identifier: &str) -> Result<(), TotpDatastoreError> {
+            self.totp_memory_driver.update_totp(username, |totp_credentials| {
+                totp_credentials.mfa_ticket_identifier = Some(ticket_identifier.to_string());
+                true
+            }).await
+        }
+
+        async fn use_mfa_ticket(&self, username: &str, ticket_identifier: &str) -> Result<(), TotpDatastoreError> {
+            self.totp_memory_driver.update_totp(username, |totp_credentials| {
+                if totp_credentials.mfa_ticket_identifier.as_deref() != Some(ticket_identifier) {
+                    return false;
+                }
+                totp_credentials.mfa_ticket_identifier = None;
+                true
+            }).await
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct WebAuthnDatastoreMemory {
+        webauthn_memory_driver: WebAuthnMemoryDriver
+    }
+
+    /// Use memory to emulate WebAuthn datastore
+    /// It's designed for integration test usage only
+    impl WebAuthnDatastore for WebAuthnDatastoreMemory {
+        async fn add_challenge(&self, webauthn_challenge: WebAuthnChallenge) -> Result<(), WebAuthnDatastoreError> {
+            self.webauthn_memory_driver.add_challenge(webauthn_challenge).await;
+            Ok(())
+        }
+
+        async fn take_challenge(&self, challenge: &str) -> Result<Option<WebAuthnChallenge>, WebAuthnDatastoreError> {
+            Ok(self.webauthn_memory_driver.take_challenge(challenge).await)
+        }
+
+        async fn add_credential(&self, webauthn_credential: WebAuthnCredential) -> Result<WebAuthnCredential, WebAuthnDatastoreError> {
+            Ok(self.webauthn_memory_driver.add_credential(webauthn_credential).await)
+        }
+
+        async fn get_credential(&self, credential_id: &str) -> Result<Option<WebAuthnCredential>, WebAuthnDatastoreError> {
+            Ok(self.webauthn_memory_driver.get_credential(credential_id).await)
+        }
+
+        async fn get_credentials(&self, username: &str) -> Result<Vec<WebAuthnCredential>, WebAuthnDatastoreError> {
+            Ok(self.webauthn_memory_driver.get_credentials(username).await)
+        }
+
+        async fn update_credential_usage(&self, credential_id: &str, sign_count: u32) -> Result<(), WebAuthnDatastoreError> {
+            self.webauthn_memory_driver.update_credential_usage(credential_id, sign_count).await
+        }
+
+        async fn delete_credential(&self, username: &str, credential_id: &str) -> Result<(), WebAuthnDatastoreError> {
+            self.webauthn_memory_driver.delete_credential(username, credential_id).await
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct PersonalAccessTokenDatastoreMemory {
+        personal_access_token_memory_driver: PersonalAccessTokenMemoryDriver
+    }
+
+    /// Use memory to emulate personal access token datastore
+    /// It's designed for integration test usage only
+    impl PersonalAccessTokenDatastore for PersonalAccessTokenDatastoreMemory {
+        async fn add_personal_access_token(&self, personal_access_token: PersonalAccessToken) -> Result<PersonalAccessToken, PersonalAccessTokenDatastoreError> {
+            Ok(self.personal_access_token_memory_driver.add_personal_access_token(personal_access_token).await)
+        }
+
+        async fn get_personal_access_token(&self, token_hash: &str) -> Result<Option<PersonalAccessToken>, PersonalAccessTokenDatastoreError> {
+            Ok(self.personal_access_token_memory_driver.get_personal_access_token(token_hash).await)
+        }
+
+        async fn get_personal_access_tokens(&self, username: &str) -> Result<Vec<PersonalAccessToken>, PersonalAccessTokenDatastoreError> {
+            Ok(self.personal_access_token_memory_driver.get_personal_access_tokens(username).await)
+        }
+
+        async fn revoke_personal_access_token(&self, username: &str, token_id: &str) -> Result<(), PersonalAccessTokenDatastoreError> {
+            self.personal_access_token_memory_driver.revoke_personal_access_token(username, token_id).await
+        }
+
+        async fn update_last_used(&self, token_id: &str) -> Result<(), PersonalAccessTokenDatastoreError> {
+            self.personal_access_token_memory_driver.update_last_used(token_id).await;
+            Ok(())
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct OAuthClientDatastoreMemory {
+        oauth_client_memory_driver: OAuthClientMemoryDriver
+    }
+
+    /// Use memory to emulate OAuth client datastore
+    /// It's designed for integration test usage only
+    impl OAuthClientDatastore for OAuthClientDatastoreMemory {
+        async fn add_client(&self, oauth_client: OAuthClient) -> Result<OAuthClient, OAuthClientDatastoreError> {
+            Ok(self.oauth_client_memory_driver.add_client(oauth_client).await)
+        }
+
+        async fn get_client(&self, client_id: &str) -> Result<Option<OAuthClient>, OAuthClientDatastoreError> {
+            Ok(self.oauth_client_memory_driver.get_client(client_id).await)
+        }
+
+        async fn get_clients(&self) -> Result<Vec<OAuthClient>, OAuthClientDatastoreError> {
+            Ok(self.oauth_client_memory_driver.get_clients().await)
+        }
+
+        async fn delete_client(&self, client_id: &str) -> Result<(), OAuthClientDatastoreError> {
+            self.oauth_client_memory_driver.delete_client(client_id).await
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct OidcDatastoreMemory {
+        oidc_memory_driver: OidcMemoryDriver
+    }
+
+    /// Use memory to emulate OpenID login datastore
+    /// It's designed for integration test usage only
+    impl OidcDatastore for OidcDatastoreMemory {
+        async fn add_login_state(&self, oidc_login_state: OidcLoginState) -> Result<(), OidcDatastoreError> {
+            self.oidc_memory_driver.add_login_state(oidc_login_state).await;
+            Ok(())
+        }
+
+        async fn take_login_state(&self, state: &str) -> Result<Option<OidcLoginState>, OidcDatastoreError> {
+            Ok(self.oidc_memory_driver.take_login_state(state).await)
+        }
+
+        async fn get_link(&self, provider: &str, subject: &str) -> Result<Option<OidcLink>, OidcDatastoreError> {
+            Ok(self.oidc_memory_driver.get_link(provider, subject).await)
+        }
+
+        async fn add_link(&self, oidc_link: OidcLink) -> Result<OidcLink, OidcDatastoreError> {
+            Ok(self.oidc_memory_driver.add_link(oidc_link).await)
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct RoleDatastoreMemory {
+        role_memory_driver: RoleMemoryDriver
+    }
+
+    /// Use memory to emulate role datastore
+    /// It's designed for integration test usage only
+    impl RoleDatastore for RoleDatastoreMemory {
+        async fn get_roles(&self) -> Result<Vec<Role>, RoleDatastoreError> {
+            Ok(self.role_memory_driver.get_roles().await)
+        }
+
+        async fn save_role(&self, role: Role) -> Result<(), RoleDatastoreError> {
+            self.role_memory_driver.save_role(role).await;
+            Ok(())
+        }
+
+        async fn delete_role(&self, name: &Roles) -> Result<(), RoleDatastoreError> {
+            self.role_memory_driver.delete_role(name).await
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct OAuthAuthorizationDatastoreMemory {
+        oauth_authorization_memory_driver: OAuthAuthorizationMemoryDriver
+    }
+
+    /// Use memory to emulate OAuth authorization datastore
+    /// It's designed for integration test usage only
+    impl OAuthAuthorizationDatastore for OAuthAuthorizationDatastoreMemory {
+        async fn add_authorization_code(&self, authorization_code: AuthorizationCode) -> Result<(), OAuthAuthorizationDatastoreError> {
+            self.oauth_authorization_memory_driver.add_authorization_code(authorization_code).await;
+            Ok(())
+        }
+
+        async fn take_authorization_code(&self, code_hash: &str) -> Result<Option<AuthorizationCode>, OAuthAuthorizationDatastoreError> {
+            Ok(self.oauth_authorization_memory_driver.take_authorization_code(code_hash).await)
+        }
+
+        async fn get_consent(&self, username: &str, client_id: &str) -> Result<Option<OAuthConsent>, OAuthAuthorizationDatastoreError> {
+            Ok(self.oauth_authorization_memory_driver.get_consent(username, client_id).await)
+        }
+
+        async fn get_consents(&self, username: &str) -> Result<Vec<OAuthConsent>, OAuthAuthorizationDatastoreError> {
+            Ok(self.oauth_authorization_memory_driver.get_consents(username).await)
+        }
+
+        async fn save_consent(&self, oauth_consent: OAuthConsent) -> Result<(), OAuthAuthorizationDatastoreError> {
+            self.oauth_authorization_memory_driver.save_consent(oauth_consent).await;
+            Ok(())
+        }
+
+        async fn delete_consent(&self, username: &str, client_id: &str) -> Result<(), OAuthAuthorizationDatastoreError> {
+            self.oauth_authorization_memory_driver.delete_consent(username, client_id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_auth_datastore_update_password() {
+        let auth_datastore = AuthDatastoreMemory { auth_memory_driver: AuthMemoryDriver {} };
+        let user = auth_datastore.add_user(Faker.fake()).await.expect("Unable add user in memory");
+        let new_password_hash = UserCredentials::hash_password("my_new_password".to_string(), &AuthConfig::fake());
+
+        auth_datastore.update_password(&user.username, &new_password_hash).await.expect("Unable update password in memory");
+
+        let user_updated = auth_datastore.get_user_by_username(&user.username).await.unwrap().unwrap();
+        assert!(user_updated.verify_password("my_new_password", &AuthConfig::fake()).is_ok());
+        assert!(user_updated.last_modified_at >= user.last_modified_at);
+        assert!(auth_datastore.update_password("unknown_username", &new_password_hash).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_auth_datastore_update_roles() {
+        let auth_datastore = AuthDatastoreMemory { auth_memory_driver: AuthMemoryDriver {} };
+        let user = auth_datastore.add_user(UserCredentials { roles: vec![Roles::User], ..Faker.fake() }).await.expect("Unable add user in memory");
+        let roles = vec![Roles::Moderator, Roles::Custom("support".to_string())];
+
+        auth_datastore.update_roles(&user.username, &roles).await.expect("Unable update roles in memory");
+
+        let user_updated = auth_datastore.get_user_by_username(&user.username).await.unwrap().unwrap();
+        assert_eq!(user_updated.roles, roles);
+        assert!(auth_datastore.update_roles("unknown_username", &[Roles::Admin]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_auth_datastore_update_roles_keeping_one_of() {
+        let auth_datastore = AuthDatastoreMemory { auth_memory_driver: AuthMemoryDriver {} };
+        let keeper = Roles::Custom(format!("keeper_{}", uuid::Uuid::new_v4()));
+        let first_user = auth_datastore.add_user(UserCredentials { roles: vec![keeper.clone()], ..Faker.fake() }).await.expect("Unable add user in memory");
+        let second_user = auth_datastore.add_user(UserCredentials { roles: vec![keeper.clone()], ..Faker.fake() }).await.expect("Unable add user in memory");
+
+        assert!(auth_datastore.update_roles_keeping_one_of(&first_user.username, &[Roles::User], std::slice::from_ref(&keeper)).await.unwrap());
+        assert!(!auth_datastore.update_roles_keeping_one_of(&second_user.username, &[Roles::User], std::slice::from_ref(&keeper)).await.unwrap());
+
+        let second_user = auth_datastore.get_user_by_username(&second_user.username).await.unwrap().unwrap();
+        assert_eq!(second_user.roles, vec![keeper]);
+    }
+
+    #[tokio::test]
+    async fn test_memory_role_datastore_save_and_delete() {
+        let role_datastore = RoleDatastoreMemory { role_memory_driver: RoleMemoryDriver {} };
+        let name = Roles::Custom(Faker.fake());
+
+        role_datastore.save_role(Role::new(name.clone(), Some(Roles::User), vec!["user:read".to_string()])).await.expect("Unable save role in memory");
+        role_datastore.save_role(Role::new(name.clone(), Some(Roles::User), vec!["user:ban".to_string()])).await.expect("Unable save role in memory");
+        let saved_roles: Vec<Role> = role_datastore.get_roles().await.unwrap().into_iter().filter(|role| role.name == name).collect();
+        assert_eq!(saved_roles.len(), 1);
+        assert_eq!(saved_roles[0].permissions, vec!["user:ban".to_string()]);
+
+        role_datastore.delete_role(&name).await.unwrap();
+        assert_eq!(role_datastore.delete_role(&name).await, Err(RoleDatastoreError::InternalError));
+    }
+
+    #[tokio::test]
+    async fn test_memory_token_datastore_revoke_token() {
+        let token_datastore = TokenDatastoreMemory { token_memory_driver: TokenMemoryDriver {}, reference_token_memory_driver: ReferenceTokenMemoryDriver {} };
+        let token = token_datastore.add_tokens(Faker.fake()).await.expect("Unable add token in memory");
+
+        let token_found = token_datastore.get_token_by_access_identifier(&token.token_access_identifiers).await.unwrap();
+        assert_eq!(token_found, Some(token.clone()));
+
+        token_datastore.revoke_token(&token.token_refresh_identifiers).await.expect("Unable revoke token in memory");
+
+        let token_revoked = token_datastore.get_token(&token.token_refresh_identifiers).await.unwrap().unwrap();
+        assert!(token_revoked.revoked_at.is_some());
+        assert_eq!(token_datastore.revoke_token("unknown_identifier").await, Err(TokenDatastoreError::InternalError));
+    }
+
+    #[tokio::test]
+    async fn test_memory_token_datastore_reference_token() {
+        let token_datastore = TokenDatastoreMemory { token_memory_driver: TokenMemoryDriver {}, reference_token_memory_driver: ReferenceTokenMemoryDriver {} };
+        let (reference_token, token) = ReferenceToken::generate(r#"{"sub":"access"}"#.to_string(), DateTime::now());
+
+        let reference_token = token_datastore.add_reference_token(reference_token).await.expect("Unable add reference token in memory");
+
+        assert_eq!(token_datastore.get_reference_token(&ReferenceToken::hash_token(&token)).await.unwrap(), Some(reference_token.clone()));
+        assert_eq!(token_datastore.get_reference_token(&ReferenceToken::hash_token("ref_unknown")).await.unwrap(), None);
+        assert_eq!(token_datastore.add_reference_token(reference_token).await, Err(TokenDatastoreError::ProvidersError));
+    }
+
+    #[tokio::test]
+    async fn test_memory_password_reset_datastore_single_use() {
+        let password_reset_datastore = PasswordResetDatastoreMemory { password_reset_memory_driver: PasswordResetMemoryDriver {} };
+        let (password_reset, reset_token) = PasswordReset::generate("username");
+        password_reset_datastore.add_password_reset(password_reset).await.expect("Unable add password reset in memory");
+
+        let reset_token_hash = PasswordReset::hash_reset_token(&reset_token);
+        assert!(password_reset_datastore.get_password_reset(&reset_token_hash).await.unwrap().unwrap().is_valid());
+
+        password_reset_datastore.use_password_reset(&reset_token_hash).await.expect("Unable use password reset in memory");
+
+        assert!(!password_reset_datastore.get_password_reset(&reset_token_hash).await.unwrap().unwrap().is_valid());
+        assert_eq!(password_reset_datastore.use_password_reset(&reset_token_hash).await, Err(PasswordResetDatastoreError::InternalError));
+    }
+
+    #[tokio::test]
+    async fn test_memory_login_attempt_datastore_count_and_clear() {
+        let login_attempt_datastore = LoginAttemptDatastoreMemory { login_attempt_memory_driver: LoginAttemptMemoryDriver {} };
+        let key = LoginAttempts::username_key(&uuid::Uuid::new_v4().to_string());
+
+        login_attempt_datastore.add_login_failure(&key).await.unwrap();
+        let login_attempts = login_attempt_datastore.add_login_failure(&key).await.unwrap();
+        assert_eq!(login_attempts.failures, 2);
+
+        login_attempt_datastore.lock_login_attempts(&key, DateTime::from_millis(DateTime::now().timestamp_millis() + 60_000)).await.unwrap();
+        assert!(login_attempt_datastore.get_login_attempts(&key).await.unwrap().unwrap().locked_for_seconds().is_some());
+
+        login_attempt_datastore.clear_login_attempts(&key).await.unwrap();
+        assert_eq!(login_attempt_datastore.get_login_attempts(&key).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_memory_totp_datastore_use_step_and_recovery_code() {
+        let totp_datastore = TotpDatastoreMemory { totp_memory_driver: TotpMemoryDriver {} };
+        let username = uuid::Uuid::new_v4().to_string();
+        let (totp_credentials, recovery_codes) = TotpCredentials::generate(&username);
+        let recovery_code_hash = TotpCredentials::hash_recovery_code(&recovery_codes[0]);
+
+        totp_datastore.save_totp(totp_credentials).await.expect("Unable save TOTP in memory");
+        totp_datastore.enable_totp(&username).await.unwrap();
+        assert!(totp_datastore.get_totp(&username).await.unwrap().unwrap().enabled);
+
+        totp_datastore.use_totp_step(&username, 10).await.unwrap();
+        assert_eq!(totp_datastore.use_totp_step(&username, 10).await, Err(TotpDatastoreError::InternalError));
+
+        totp_datastore.use_recovery_code(&username, &recovery_code_hash).await.unwrap();
+        assert_eq!(totp_datastore.use_recovery_code(&username, &recovery_code_hash).await, Err(TotpDatastoreError::InternalError));
+
+        totp_datastore.delete_totp(&username).await.unwrap();
+        assert_eq!(totp_datastore.get_totp(&username).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_memory_webauthn_datastore_challenge_and_credentials() {
+        let webauthn_datastore = WebAuthnDatastoreMemory { webauthn_memory_driver: WebAuthnMemoryDriver {} };
+        let username = uuid::Uuid::new_v4().to_string();
+        let webauthn_challenge = WebAuthnChallenge::generate(Some(&username), WebAuthnCeremony::Registration);
+
+        webauthn_datastore.add_challenge(webauthn_challenge.clone()).await.unwrap();
+        assert!(webauthn_datastore.take_challenge(&webauthn_challenge.challenge).await.unwrap().is_some());
+        assert!(webauthn_datastore.take_challenge(&webauthn_challenge.challenge).await.unwrap().is_none());
+
+        let webauthn_credential = webauthn_datastore.add_credential(WebAuthnCredential {
+            id: None,
+            username: username.clone(),
+            credential_id: uuid::Uuid::new_v4().to_string(),
+            public_key: "public_key".to_string(),
+            sign_count: 0,
+            name: "name".to_string(),
+            created_at: DateTime::now(),
+            last_used_at: None,
+        }).await.expect("Unable add WebAuthn credential in memory");
+
+        webauthn_datastore.update_credential_usage(&webauthn_credential.credential_id, 3).await.unwrap();
+        let webauthn_credentials = webauthn_datastore.get_credentials(&username).await.unwrap();
+        assert_eq!(webauthn_credentials.len(), 1);
+        assert_eq!(webauthn_credentials[0].sign_count, 3);
+
+        assert_eq!(webauthn_datastore.delete_credential("other_username", &webauthn_credential.credential_id).await, Err(WebAuthnDatastoreError::InternalError));
+        webauthn_datastore.delete_credential(&username, &webauthn_credential.credential_id).await.unwrap();
+        assert_eq!(webauthn_datastore.get_credential(&webauthn_credential.credential_id).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_memory_personal_access_token_datastore_revoke() {
+        let personal_access_token_datastore = PersonalAccessTokenDatastoreMemory { personal_access_token_memory_driver: PersonalAccessTokenMemoryDriver {} };
+        let username = uuid::Uuid::new_v4().to_string();
+        let (personal_access_token, token) = PersonalAccessToken::generate(&username, "ci", None);
+
+        personal_access_token_datastore.add_personal_access_token(personal_access_token.clone()).await.expect("Unable add personal access token in memory");
+        personal_access_token_datastore.update_last_used(&personal_access_token.token_id).await.unwrap();
+        let stored_personal_access_token = personal_access_token_datastore.get_personal_access_token(&PersonalAccessToken::hash_token(&token)).await.unwrap().unwrap();
+        assert!(stored_personal_access_token.last_used_at.is_some());
+
+        assert_eq!(personal_access_token_datastore.revoke_personal_access_token("other_username", &personal_access_token.token_id).await, Err(PersonalAccessTokenDatastoreError::InternalError));
+        personal_access_token_datastore.revoke_personal_access_token(&username, &personal_access_token.token_id).await.unwrap();
+        assert_eq!(personal_access_token_datastore.revoke_personal_access_token(&username, &personal_access_token.token_id).await, Err(PersonalAccessTokenDatastoreError::InternalError));
+        assert!(!personal_access_token_datastore.get_personal_access_tokens(&username).await.unwrap()[0].is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_memory_oauth_client_datastore_delete() {
+        let oauth_client_datastore = OAuthClientDatastoreMemory { oauth_client_memory_driver: OAuthClientMemoryDriver {} };
+        let (oauth_client, client_secret) = OAuthClient::generate("billing", vec!["users:read".to_string()], Vec::new());
+
+        oauth_client_datastore.add_client(oauth_client.clone()).await.expect("Unable add OAuth client in memory");
+        let stored_oauth_client = oauth_client_datastore.get_client(&oauth_client.client_id).await.unwrap().unwrap();
+        assert!(stored_oauth_client.is_valid_secret(&client_secret));
+
+        oauth_client_datastore.delete_client(&oauth_client.client_id).await.unwrap();
+        assert_eq!(oauth_client_datastore.get_client(&oauth_client.client_id).await, Ok(None));
+        assert_eq!(oauth_client_datastore.delete_client(&oauth_client.client_id).await, Err(OAuthClientDatastoreError::InternalError));
+    }
+
+    #[tokio::test]
+    async fn test_memory_oidc_datastore_take_login_state_once() {
+        let oidc_datastore = OidcDatastoreMemory { oidc_memory_driver: OidcMemoryDriver {} };
+        let oidc_login_state = OidcLoginState::generate("provider");
+
+        oidc_datastore.add_login_state(oidc_login_state.clone()).await.expect("Unable add OpenID login state in memory");
+        let taken_login_state = oidc_datastore.take_login_state(&oidc_login_state.state).await.unwrap().unwrap();
+
+        assert!(taken_login_state.is_valid("provider"));
+        assert!(!taken_login_state.is_valid("other_provider"));
+        assert_eq!(oidc_datastore.take_login_state(&oidc_login_state.state).await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_memory_oauth_authorization_datastore_code_and_consent() {
+        let oauth_authorization_datastore = OAuthAuthorizationDatastoreMemory { oauth_authorization_memory_driver: OAuthAuthorizationMemoryDriver {} };
+        let username: String = Faker.fake();
+        let (authorization_code, code) = AuthorizationCode::generate("client_id", &username, "https://app.example.com/callback", vec!["openid".to_string()], "code_challenge", None);
+
+        oauth_authorization_datastore.add_authorization_code(authorization_code.clone()).await.expect("Unable add authorization code in memory");
+        let taken_authorization_code = oauth_authorization_datastore.take_authorization_code(&AuthorizationCode::hash_code(&code)).await.unwrap().unwrap();
+        assert_eq!(taken_authorization_code.username, username);
+        assert_eq!(oauth_authorization_datastore.take_authorization_code(&AuthorizationCode::hash_code(&code)).await, Ok(None));
+
+        let oauth_consent = OAuthConsent { id: None, username: username.clone(), client_id: "client_id".to_string(), scopes: vec!["openid".to_string()], created_at: DateTime::now(), last_modified_at: DateTime::now() };
+        oauth_authorization_datastore.save_consent(oauth_consent.clone()).await.unwrap();
+        oauth_authorization_datastore.save_consent(OAuthConsent { scopes: vec!["openid".to_string(), "email".to_string()], ..oauth_consent }).await.unwrap();
+        assert_eq!(oauth_authorization_datastore.get_consents(&username).await.unwrap().len(), 1);
+        assert!(oauth_authorization_datastore.get_consent(&username, "client_id").await.unwrap().unwrap().covers(&["email".to_string()]));
+
+        oauth_authorization_datastore.delete_consent(&username, "client_id").await.unwrap();
+        assert_eq!(oauth_authorization_datastore.delete_consent(&username, "client_id").await, Err(OAuthAuthorizationDatastoreError::InternalError));
     }
 }
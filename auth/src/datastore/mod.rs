@@ -1,6 +1,7 @@
-use crate::entities::{Token, UserCredentials};
+use crate::entities::{AuthorizationCode, LoginAttempts, OAuthClient, OAuthConsent, OidcLink, OidcLoginState, PasswordReset, PersonalAccessToken, ReferenceToken, Role, Roles, Token, TotpCredentials, UserCredentials, WebAuthnChallenge, WebAuthnCredential};
 #[cfg(test)]
 use mockall::{automock, predicate::*};
+use mongodb::bson::DateTime;
 use thiserror::Error;
 
 pub mod memory;
@@ -47,6 +48,29 @@ pub trait AuthDatastore {
     /// * `Result<Option<UserCredentials>, AuthDatastoreError>` - On success, returns an Option containing the UserCredentials if the user is found,
     ///   or None if the user is not found. On failure, returns an error of type AuthDatastoreError.
     fn get_user_by_username(&self, username: &str) -> impl std::future::Future<Output = Result<Option<UserCredentials>, AuthDatastoreError>> + Send;
+
+    /// Replaces the password of a user and updates its `last_modified_at` date.
+    ///
+    /// # Arguments
+    ///
+    /// * `username` - A string slice containing the username of the user to update.
+    /// * `password_hash` - The new password, already hashed.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), AuthDatastoreError>` - On failure, or if the user is not found, returns an error of type AuthDatastoreError.
+    fn update_password(&self, username: &str, password_hash: &str) -> impl std::future::Future<Output = Result<(), AuthDatastoreError>> + Send;
+
+    /// Replaces the roles of a user and updates its `last_modified_at` date.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), AuthDatastoreError>` - On failure, or if the user is not found, returns an error of type AuthDatastoreError.
+    fn update_roles(&self, username: &str, roles: &[Roles]) -> impl std::future::Future<Output = Result<(), AuthDatastoreError>> + Send;
+
+    /// Replace the roles of the user only if another user has one of `kept_roles`, e.g. to keep at least one SuperAdmin.
+    /// The check and the update are atomic : concurrent updates can't remove the last holders. Return false when nothing is updated
+    fn update_roles_keeping_one_of(&self, username: &str, roles: &[Roles], kept_roles: &[Roles]) -> impl std::future::Future<Output = Result<bool, AuthDatastoreError>> + Send;
 }
 
 
@@ -68,8 +92,12 @@ pub enum TokenDatastoreError {
 pub trait TokenDatastore {
     fn add_tokens(&self, token: Token) -> impl std::future::Future<Output = Result<Token, TokenDatastoreError>> + Send;
     fn get_token(&self, token_identifier: &str) -> impl std::future::Future<Output = Result<Option<Token>, TokenDatastoreError>> + Send;
+    fn get_token_by_access_identifier(&self, token_access_identifier: &str) -> impl std::future::Future<Output = Result<Option<Token>, TokenDatastoreError>> + Send;
     fn get_tokens_for_user(&self, username: &str) -> impl std::future::Future<Output = Result<Vec<Token>, TokenDatastoreError>> + Send;
     fn revoke_token(&self, token_identifier: &str) -> impl std::future::Future<Output = Result<(), TokenDatastoreError>> + Send;
+    /// Store the claims of an opaque token. See `ReferenceTokenCodec`
+    fn add_reference_token(&self, reference_token: ReferenceToken) -> impl std::future::Future<Output = Result<ReferenceToken, TokenDatastoreError>> + Send;
+    fn get_reference_token(&self, token_hash: &str) -> impl std::future::Future<Output = Result<Option<ReferenceToken>, TokenDatastoreError>> + Send;
 }
 
 #[cfg(test)]
@@ -77,4 +105,186 @@ impl Clone for MockTokenDatastore {
     fn clone(&self) -> Self {
         todo!("It's a fake implementation of Clone")
     }
-}
\ No newline at end of file
+}
+#[derive(Debug, Error, PartialEq)]
+pub enum PasswordResetDatastoreError {
+    #[error("Unable processing request. Error with external services")]
+    InternalError,
+    #[error("The third-party service is not responding")]
+    ProvidersError
+}
+
+/// Store the password resets requested by users, looked up by the hash of their reset token
+#[cfg_attr(test, automock)]
+pub trait PasswordResetDatastore {
+    fn add_password_reset(&self, password_reset: PasswordReset) -> impl std::future::Future<Output = Result<PasswordReset, PasswordResetDatastoreError>> + Send;
+    fn get_password_reset(&self, reset_token_hash: &str) -> impl std::future::Future<Output = Result<Option<PasswordReset>, PasswordResetDatastoreError>> + Send;
+    /// Mark the password reset as used. Fails if it was already used, so a reset token can't be used twice
+    fn use_password_reset(&self, reset_token_hash: &str) -> impl std::future::Future<Output = Result<(), PasswordResetDatastoreError>> + Send;
+    fn delete_password_reset(&self, reset_token_hash: &str) -> impl std::future::Future<Output = Result<(), PasswordResetDatastoreError>> + Send;
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum LoginAttemptDatastoreError {
+    #[error("Unable processing request. Error with external services")]
+    InternalError,
+    #[error("The third-party service is not responding")]
+    ProvidersError
+}
+
+/// Count failed logins by key (username or client IP)
+#[cfg_attr(test, automock)]
+pub trait LoginAttemptDatastore {
+    fn get_login_attempts(&self, key: &str) -> impl std::future::Future<Output = Result<Option<LoginAttempts>, LoginAttemptDatastoreError>> + Send;
+    /// Increment the failures of the key, created if it doesn't exist, and return the updated attempts
+    fn add_login_failure(&self, key: &str) -> impl std::future::Future<Output = Result<LoginAttempts, LoginAttemptDatastoreError>> + Send;
+    fn lock_login_attempts(&self, key: &str, locked_until: DateTime) -> impl std::future::Future<Output = Result<(), LoginAttemptDatastoreError>> + Send;
+    /// Remove the failures and the lock of the key. Succeed if the key doesn't exist
+    fn clear_login_attempts(&self, key: &str) -> impl std::future::Future<Output = Result<(), LoginAttemptDatastoreError>> + Send;
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TotpDatastoreError {
+    #[error("Unable processing request. Error with external services")]
+    InternalError,
+    #[error("The third-party service is not responding")]
+    ProvidersError
+}
+
+/// Store the TOTP second factor of users, one by username
+#[cfg_attr(test, automock)]
+pub trait TotpDatastore {
+    fn get_totp(&self, username: &str) -> impl std::future::Future<Output = Result<Option<TotpCredentials>, TotpDatastoreError>> + Send;
+    /// Replace the TOTP of the user, created if it doesn't exist
+    fn save_totp(&self, totp_credentials: TotpCredentials) -> impl std::future::Future<Output = Result<(), TotpDatastoreError>> + Send;
+    fn enable_totp(&self, username: &str) -> impl std::future::Future<Output = Result<(), TotpDatastoreError>> + Send;
+    fn delete_totp(&self, username: &str) -> impl std::future::Future<Output = Result<(), TotpDatastoreError>> + Send;
+    /// Save the time step of the code used. Fails if a code of this step or a later one was already used
+    fn use_totp_step(&self, username: &str, step: i64) -> impl std::future::Future<Output = Result<(), TotpDatastoreError>> + Send;
+    /// Remove the recovery code. Fails if it doesn't exist, so a recovery code can't be used twice
+    fn use_recovery_code(&self, username: &str, recovery_code_hash: &str) -> impl std::future::Future<Output = Result<(), TotpDatastoreError>> + Send;
+    /// Save the identifier of the MFA ticket given, replacing the previous one
+    fn save_mfa_ticket(&self, username: &str, ticket_identifier: &str) -> impl std::future::Future<Output = Result<(), TotpDatastoreError>> + Send;
+    /// Remove the identifier of the MFA ticket. Fails if it's not the last ticket given, so a ticket can't be used twice
+    fn use_mfa_ticket(&self, username: &str, ticket_identifier: &str) -> impl std::future::Future<Output = Result<(), TotpDatastoreError>> + Send;
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum WebAuthnDatastoreError {
+    #[error("Unable processing request. Error with external services")]
+    InternalError,
+    #[error("The third-party service is not responding")]
+    ProvidersError
+}
+
+/// Store the WebAuthn credentials of users and the challenges of ceremonies in progress
+#[cfg_attr(test, automock)]
+pub trait WebAuthnDatastore {
+    /// Save a pending challenge and purge the expired ones, challenges are created by anonymous requests
+    fn add_challenge(&self, webauthn_challenge: WebAuthnChallenge) -> impl std::future::Future<Output = Result<(), WebAuthnDatastoreError>> + Send;
+    /// Remove and return the challenge, so a challenge can't be used twice
+    fn take_challenge(&self, challenge: &str) -> impl std::future::Future<Output = Result<Option<WebAuthnChallenge>, WebAuthnDatastoreError>> + Send;
+    fn add_credential(&self, webauthn_credential: WebAuthnCredential) -> impl std::future::Future<Output = Result<WebAuthnCredential, WebAuthnDatastoreError>> + Send;
+    fn get_credential(&self, credential_id: &str) -> impl std::future::Future<Output = Result<Option<WebAuthnCredential>, WebAuthnDatastoreError>> + Send;
+    fn get_credentials(&self, username: &str) -> impl std::future::Future<Output = Result<Vec<WebAuthnCredential>, WebAuthnDatastoreError>> + Send;
+    /// Save the signature counter of the authenticator and the date of use
+    fn update_credential_usage(&self, credential_id: &str, sign_count: u32) -> impl std::future::Future<Output = Result<(), WebAuthnDatastoreError>> + Send;
+    /// Fails if the user has no credential with this identifier
+    fn delete_credential(&self, username: &str, credential_id: &str) -> impl std::future::Future<Output = Result<(), WebAuthnDatastoreError>> + Send;
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PersonalAccessTokenDatastoreError {
+    #[error("Unable processing request. Error with external services")]
+    InternalError,
+    #[error("The third-party service is not responding")]
+    ProvidersError
+}
+
+/// Store the personal access tokens of users, looked up by the hash of the token
+#[cfg_attr(test, automock)]
+pub trait PersonalAccessTokenDatastore {
+    fn add_personal_access_token(&self, personal_access_token: PersonalAccessToken) -> impl std::future::Future<Output = Result<PersonalAccessToken, PersonalAccessTokenDatastoreError>> + Send;
+    fn get_personal_access_token(&self, token_hash: &str) -> impl std::future::Future<Output = Result<Option<PersonalAccessToken>, PersonalAccessTokenDatastoreError>> + Send;
+    fn get_personal_access_tokens(&self, username: &str) -> impl std::future::Future<Output = Result<Vec<PersonalAccessToken>, PersonalAccessTokenDatastoreError>> + Send;
+    /// Fails if the user has no active personal access token with this identifier
+    fn revoke_personal_access_token(&self, username: &str, token_id: &str) -> impl std::future::Future<Output = Result<(), PersonalAccessTokenDatastoreError>> + Send;
+    fn update_last_used(&self, token_id: &str) -> impl std::future::Future<Output = Result<(), PersonalAccessTokenDatastoreError>> + Send;
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum OAuthClientDatastoreError {
+    #[error("Unable processing request. Error with external services")]
+    InternalError,
+    #[error("The third-party service is not responding")]
+    ProvidersError
+}
+
+/// Store the confidential clients allowed to use the `client_credentials` grant
+#[cfg_attr(test, automock)]
+pub trait OAuthClientDatastore {
+    fn add_client(&self, oauth_client: OAuthClient) -> impl std::future::Future<Output = Result<OAuthClient, OAuthClientDatastoreError>> + Send;
+    fn get_client(&self, client_id: &str) -> impl std::future::Future<Output = Result<Option<OAuthClient>, OAuthClientDatastoreError>> + Send;
+    fn get_clients(&self) -> impl std::future::Future<Output = Result<Vec<OAuthClient>, OAuthClientDatastoreError>> + Send;
+    /// Fails if no client has this identifier
+    fn delete_client(&self, client_id: &str) -> impl std::future::Future<Output = Result<(), OAuthClientDatastoreError>> + Send;
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum OidcDatastoreError {
+    #[error("Unable processing request. Error with external services")]
+    InternalError,
+    #[error("The third-party service is not responding")]
+    ProvidersError
+}
+
+/// Store the pending logins with upstream OpenID providers and the accounts linked to local users
+#[cfg_attr(test, automock)]
+pub trait OidcDatastore {
+    /// Save a pending login and purge the expired ones, logins are started by anonymous requests
+    fn add_login_state(&self, oidc_login_state: OidcLoginState) -> impl std::future::Future<Output = Result<(), OidcDatastoreError>> + Send;
+    /// Remove and return the login state, so a callback can't be replayed
+    fn take_login_state(&self, state: &str) -> impl std::future::Future<Output = Result<Option<OidcLoginState>, OidcDatastoreError>> + Send;
+    fn get_link(&self, provider: &str, subject: &str) -> impl std::future::Future<Output = Result<Option<OidcLink>, OidcDatastoreError>> + Send;
+    fn add_link(&self, oidc_link: OidcLink) -> impl std::future::Future<Output = Result<OidcLink, OidcDatastoreError>> + Send;
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum OAuthAuthorizationDatastoreError {
+    #[error("Unable processing request. Error with external services")]
+    InternalError,
+    #[error("The third-party service is not responding")]
+    ProvidersError
+}
+
+/// Store the authorization codes given to third-party apps and the consents of users
+#[cfg_attr(test, automock)]
+pub trait OAuthAuthorizationDatastore {
+    fn add_authorization_code(&self, authorization_code: AuthorizationCode) -> impl std::future::Future<Output = Result<(), OAuthAuthorizationDatastoreError>> + Send;
+    /// Remove and return the authorization code, so it can't be exchanged twice
+    fn take_authorization_code(&self, code_hash: &str) -> impl std::future::Future<Output = Result<Option<AuthorizationCode>, OAuthAuthorizationDatastoreError>> + Send;
+    fn get_consent(&self, username: &str, client_id: &str) -> impl std::future::Future<Output = Result<Option<OAuthConsent>, OAuthAuthorizationDatastoreError>> + Send;
+    fn get_consents(&self, username: &str) -> impl std::future::Future<Output = Result<Vec<OAuthConsent>, OAuthAuthorizationDatastoreError>> + Send;
+    /// Replace the consent of the user for this client, if any
+    fn save_consent(&self, oauth_consent: OAuthConsent) -> impl std::future::Future<Output = Result<(), OAuthAuthorizationDatastoreError>> + Send;
+    /// Fails if the user gave no consent to this client
+    fn delete_consent(&self, username: &str, client_id: &str) -> impl std::future::Future<Output = Result<(), OAuthAuthorizationDatastoreError>> + Send;
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RoleDatastoreError {
+    #[error("Unable processing request. Error with external services")]
+    InternalError,
+    #[error("The third-party service is not responding")]
+    ProvidersError
+}
+
+/// Store the roles defined at runtime. Built-in roles are only stored once their permissions are changed, see `Role::with_defaults`
+#[cfg_attr(test, automock)]
+pub trait RoleDatastore {
+    fn get_roles(&self) -> impl std::future::Future<Output = Result<Vec<Role>, RoleDatastoreError>> + Send;
+    /// Replace the role of the same name, if any
+    fn save_role(&self, role: Role) -> impl std::future::Future<Output = Result<(), RoleDatastoreError>> + Send;
+    /// Fails if the role is not stored
+    fn delete_role(&self, name: &Roles) -> impl std::future::Future<Output = Result<(), RoleDatastoreError>> + Send;
+}
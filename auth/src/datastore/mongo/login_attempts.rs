@@ -0,0 +1,58 @@
+use mongodb::{Collection, Database};
+use mongodb::bson::{DateTime, doc};
+use mongodb::options::ReturnDocument;
+use crate::datastore::{LoginAttemptDatastore, LoginAttemptDatastoreError};
+use crate::entities::LoginAttempts;
+
+/// Store failed logins in their own collection, one document by key
+#[derive(Clone)]
+pub struct MongoLoginAttemptDatastore {
+    collection: Collection<LoginAttempts>
+}
+
+impl MongoLoginAttemptDatastore {
+    const DEFAULT_COLLECTION_NAME: &'static str = "login_attempts";
+
+    pub fn new(database: &Database) -> Self {
+        Self {
+            collection: database.collection::<LoginAttempts>(Self::DEFAULT_COLLECTION_NAME)
+        }
+    }
+}
+
+impl LoginAttemptDatastore for MongoLoginAttemptDatastore {
+    async fn get_login_attempts(&self, key: &str) -> Result<Option<LoginAttempts>, LoginAttemptDatastoreError> {
+        self.collection.find_one(doc! { "key": key }).await.map_err(|_| LoginAttemptDatastoreError::ProvidersError)
+    }
+
+    async fn add_login_failure(&self, key: &str) -> Result<LoginAttempts, LoginAttemptDatastoreError> {
+        self.collection.find_one_and_update(
+            doc! { "key": key },
+            doc! { "$inc": doc! { "failures": 1 }, "$set": doc! { "last_failure_at": DateTime::now() }}
+        )
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|_| LoginAttemptDatastoreError::ProvidersError)?
+            .ok_or(LoginAttemptDatastoreError::InternalError)
+    }
+
+    async fn lock_login_attempts(&self, key: &str, locked_until: DateTime) -> Result<(), LoginAttemptDatastoreError> {
+        let result = self.collection.update_one(
+            doc! { "key": key },
+            doc! { "$set": doc! { "locked_until": locked_until }}
+        ).await.map_err(|_| LoginAttemptDatastoreError::ProvidersError)?;
+
+        if result.matched_count == 1 {
+            Ok(())
+        } else {
+            Err(LoginAttemptDatastoreError::InternalError)
+        }
+    }
+
+    async fn clear_login_attempts(&self, key: &str) -> Result<(), LoginAttemptDatastoreError> {
+        self.collection.delete_one(doc! { "key": key }).await.map_err(|_| LoginAttemptDatastoreError::ProvidersError)?;
+
+        Ok(())
+    }
+}
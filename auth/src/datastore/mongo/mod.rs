@@ -1,2 +1,11 @@
 pub mod users;
-pub mod tokens;
\ No newline at end of file
+pub mod tokens;
+pub mod password_resets;
+pub mod login_attempts;
+pub mod totp;
+pub mod webauthn;
+pub mod personal_access_tokens;
+pub mod oauth_clients;
+pub mod oidc;
+pub mod oauth_authorizations;
+pub mod roles;
\ No newline at end of file
@@ -0,0 +1,68 @@
+use futures::stream::TryStreamExt;
+use mongodb::{Collection, Database};
+use mongodb::bson::doc;
+use crate::datastore::{OAuthAuthorizationDatastore, OAuthAuthorizationDatastoreError};
+use crate::entities::{AuthorizationCode, OAuthConsent};
+
+/// Store authorization codes and consents in two collections
+#[derive(Clone)]
+pub struct MongoOAuthAuthorizationDatastore {
+    authorization_code_collection: Collection<AuthorizationCode>,
+    consent_collection: Collection<OAuthConsent>,
+}
+
+impl MongoOAuthAuthorizationDatastore {
+    const DEFAULT_AUTHORIZATION_CODE_COLLECTION_NAME: &'static str = "oauth_authorization_codes";
+    const DEFAULT_CONSENT_COLLECTION_NAME: &'static str = "oauth_consents";
+
+    pub fn new(database: &Database) -> Self {
+        Self {
+            authorization_code_collection: database.collection::<AuthorizationCode>(Self::DEFAULT_AUTHORIZATION_CODE_COLLECTION_NAME),
+            consent_collection: database.collection::<OAuthConsent>(Self::DEFAULT_CONSENT_COLLECTION_NAME),
+        }
+    }
+}
+
+impl OAuthAuthorizationDatastore for MongoOAuthAuthorizationDatastore {
+    async fn add_authorization_code(&self, authorization_code: AuthorizationCode) -> Result<(), OAuthAuthorizationDatastoreError> {
+        self.authorization_code_collection.insert_one(&authorization_code).await.map_err(|_| OAuthAuthorizationDatastoreError::ProvidersError)?;
+
+        Ok(())
+    }
+
+    async fn take_authorization_code(&self, code_hash: &str) -> Result<Option<AuthorizationCode>, OAuthAuthorizationDatastoreError> {
+        self.authorization_code_collection.find_one_and_delete(doc! { "code_hash": code_hash }).await.map_err(|_| OAuthAuthorizationDatastoreError::ProvidersError)
+    }
+
+    async fn get_consent(&self, username: &str, client_id: &str) -> Result<Option<OAuthConsent>, OAuthAuthorizationDatastoreError> {
+        self.consent_collection.find_one(doc! { "username": username, "client_id": client_id }).await.map_err(|_| OAuthAuthorizationDatastoreError::ProvidersError)
+    }
+
+    async fn get_consents(&self, username: &str) -> Result<Vec<OAuthConsent>, OAuthAuthorizationDatastoreError> {
+        self.consent_collection.find(doc! { "username": username })
+            .await
+            .map_err(|_| OAuthAuthorizationDatastoreError::ProvidersError)?
+            .try_collect()
+            .await
+            .map_err(|_| OAuthAuthorizationDatastoreError::InternalError)
+    }
+
+    async fn save_consent(&self, oauth_consent: OAuthConsent) -> Result<(), OAuthAuthorizationDatastoreError> {
+        self.consent_collection.replace_one(doc! { "username": &oauth_consent.username, "client_id": &oauth_consent.client_id }, &oauth_consent)
+            .upsert(true)
+            .await
+            .map_err(|_| OAuthAuthorizationDatastoreError::ProvidersError)?;
+
+        Ok(())
+    }
+
+    async fn delete_consent(&self, username: &str, client_id: &str) -> Result<(), OAuthAuthorizationDatastoreError> {
+        let result = self.consent_collection.delete_one(doc! { "username": username, "client_id": client_id }).await.map_err(|_| OAuthAuthorizationDatastoreError::ProvidersError)?;
+
+        if result.deleted_count == 1 {
+            Ok(())
+        } else {
+            Err(OAuthAuthorizationDatastoreError::InternalError)
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use futures::stream::TryStreamExt;
+use mongodb::{Collection, Database};
+use mongodb::bson::doc;
+use crate::datastore::{OAuthClientDatastore, OAuthClientDatastoreError};
+use crate::entities::OAuthClient;
+
+/// Store confidential clients in their own collection
+#[derive(Clone)]
+pub struct MongoOAuthClientDatastore {
+    collection: Collection<OAuthClient>
+}
+
+impl MongoOAuthClientDatastore {
+    const DEFAULT_COLLECTION_NAME: &'static str = "oauth_clients";
+
+    pub fn new(database: &Database) -> Self {
+        Self {
+            collection: database.collection::<OAuthClient>(Self::DEFAULT_COLLECTION_NAME)
+        }
+    }
+}
+
+impl OAuthClientDatastore for MongoOAuthClientDatastore {
+    async fn add_client(&self, oauth_client: OAuthClient) -> Result<OAuthClient, OAuthClientDatastoreError> {
+        let result = self.collection.insert_one(&oauth_client).await.map_err(|_| OAuthClientDatastoreError::ProvidersError)?;
+
+        Ok(OAuthClient {
+            id: result.inserted_id.as_object_id(),
+            ..oauth_client
+        })
+    }
+
+    async fn get_client(&self, client_id: &str) -> Result<Option<OAuthClient>, OAuthClientDatastoreError> {
+        self.collection.find_one(doc! { "client_id": client_id }).await.map_err(|_| OAuthClientDatastoreError::ProvidersError)
+    }
+
+    async fn get_clients(&self) -> Result<Vec<OAuthClient>, OAuthClientDatastoreError> {
+        self.collection.find(doc! {})
+            .await
+            .map_err(|_| OAuthClientDatastoreError::ProvidersError)?
+            .try_collect()
+            .await
+            .map_err(|_| OAuthClientDatastoreError::InternalError)
+    }
+
+    async fn delete_client(&self, client_id: &str) -> Result<(), OAuthClientDatastoreError> {
+        let result = self.collection.delete_one(doc! { "client_id": client_id }).await.map_err(|_| OAuthClientDatastoreError::ProvidersError)?;
+
+        if result.deleted_count == 1 {
+            Ok(())
+        } else {
+            Err(OAuthClientDatastoreError::InternalError)
+        }
+    }
+}
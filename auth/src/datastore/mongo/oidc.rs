@@ -0,0 +1,49 @@
+use mongodb::{Collection, Database};
+use mongodb::bson::{DateTime, doc};
+use crate::datastore::{OidcDatastore, OidcDatastoreError};
+use crate::entities::{OidcLink, OidcLoginState};
+
+/// Store pending OpenID logins and linked accounts in two collections
+#[derive(Clone)]
+pub struct MongoOidcDatastore {
+    login_state_collection: Collection<OidcLoginState>,
+    link_collection: Collection<OidcLink>,
+}
+
+impl MongoOidcDatastore {
+    const DEFAULT_LOGIN_STATE_COLLECTION_NAME: &'static str = "oidc_login_states";
+    const DEFAULT_LINK_COLLECTION_NAME: &'static str = "oidc_links";
+
+    pub fn new(database: &Database) -> Self {
+        Self {
+            login_state_collection: database.collection::<OidcLoginState>(Self::DEFAULT_LOGIN_STATE_COLLECTION_NAME),
+            link_collection: database.collection::<OidcLink>(Self::DEFAULT_LINK_COLLECTION_NAME),
+        }
+    }
+}
+
+impl OidcDatastore for MongoOidcDatastore {
+    async fn add_login_state(&self, oidc_login_state: OidcLoginState) -> Result<(), OidcDatastoreError> {
+        self.login_state_collection.delete_many(doc! { "expired_at": { "$lt": DateTime::now() } }).await.map_err(|_| OidcDatastoreError::ProvidersError)?;
+        self.login_state_collection.insert_one(&oidc_login_state).await.map_err(|_| OidcDatastoreError::ProvidersError)?;
+
+        Ok(())
+    }
+
+    async fn take_login_state(&self, state: &str) -> Result<Option<OidcLoginState>, OidcDatastoreError> {
+        self.login_state_collection.find_one_and_delete(doc! { "state": state }).await.map_err(|_| OidcDatastoreError::ProvidersError)
+    }
+
+    async fn get_link(&self, provider: &str, subject: &str) -> Result<Option<OidcLink>, OidcDatastoreError> {
+        self.link_collection.find_one(doc! { "provider": provider, "subject": subject }).await.map_err(|_| OidcDatastoreError::ProvidersError)
+    }
+
+    async fn add_link(&self, oidc_link: OidcLink) -> Result<OidcLink, OidcDatastoreError> {
+        let result = self.link_collection.insert_one(&oidc_link).await.map_err(|_| OidcDatastoreError::ProvidersError)?;
+
+        Ok(OidcLink {
+            id: result.inserted_id.as_object_id(),
+            ..oidc_link
+        })
+    }
+}
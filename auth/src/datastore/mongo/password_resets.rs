@@ -0,0 +1,58 @@
+use mongodb::{Collection, Database};
+use mongodb::bson::{Bson, DateTime, doc};
+use crate::datastore::{PasswordResetDatastore, PasswordResetDatastoreError};
+use crate::entities::PasswordReset;
+
+/// Store password resets in their own collection
+#[derive(Clone)]
+pub struct MongoPasswordResetDatastore {
+    collection: Collection<PasswordReset>
+}
+
+impl MongoPasswordResetDatastore {
+    const DEFAULT_COLLECTION_NAME: &'static str = "password_resets";
+
+    pub fn new(database: &Database) -> Self {
+        Self {
+            collection: database.collection::<PasswordReset>(Self::DEFAULT_COLLECTION_NAME)
+        }
+    }
+}
+
+impl PasswordResetDatastore for MongoPasswordResetDatastore {
+    async fn add_password_reset(&self, password_reset: PasswordReset) -> Result<PasswordReset, PasswordResetDatastoreError> {
+        let password_reset_inserted = self.collection.insert_one(&password_reset).await.map_err(|_| PasswordResetDatastoreError::ProvidersError)?;
+
+        if let Bson::ObjectId(inserted_id) = password_reset_inserted.inserted_id {
+            Ok(PasswordReset {
+                id: Some(inserted_id),
+                ..password_reset
+            })
+        } else {
+            Err(PasswordResetDatastoreError::ProvidersError)
+        }
+    }
+
+    async fn get_password_reset(&self, reset_token_hash: &str) -> Result<Option<PasswordReset>, PasswordResetDatastoreError> {
+        self.collection.find_one(doc! { "reset_token_hash": reset_token_hash }).await.map_err(|_| PasswordResetDatastoreError::ProvidersError)
+    }
+
+    async fn use_password_reset(&self, reset_token_hash: &str) -> Result<(), PasswordResetDatastoreError> {
+        let result = self.collection.update_one(
+            doc! { "reset_token_hash": reset_token_hash, "used_at": doc! { "$exists": false } },
+            doc! { "$set": doc! { "used_at": DateTime::now() }}
+        ).await.map_err(|_| PasswordResetDatastoreError::ProvidersError)?;
+
+        if result.modified_count == 1 {
+            Ok(())
+        } else {
+            Err(PasswordResetDatastoreError::InternalError)
+        }
+    }
+
+    async fn delete_password_reset(&self, reset_token_hash: &str) -> Result<(), PasswordResetDatastoreError> {
+        self.collection.delete_one(doc! { "reset_token_hash": reset_token_hash }).await.map_err(|_| PasswordResetDatastoreError::ProvidersError)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use futures::stream::TryStreamExt;
+use mongodb::{Collection, Database};
+use mongodb::bson::{DateTime, doc};
+use crate::datastore::{PersonalAccessTokenDatastore, PersonalAccessTokenDatastoreError};
+use crate::entities::PersonalAccessToken;
+
+/// Store personal access tokens in their own collection
+#[derive(Clone)]
+pub struct MongoPersonalAccessTokenDatastore {
+    collection: Collection<PersonalAccessToken>
+}
+
+impl MongoPersonalAccessTokenDatastore {
+    const DEFAULT_COLLECTION_NAME: &'static str = "personal_access_tokens";
+
+    pub fn new(database: &Database) -> Self {
+        Self {
+            collection: database.collection::<PersonalAccessToken>(Self::DEFAULT_COLLECTION_NAME)
+        }
+    }
+}
+
+impl PersonalAccessTokenDatastore for MongoPersonalAccessTokenDatastore {
+    async fn add_personal_access_token(&self, personal_access_token: PersonalAccessToken) -> Result<PersonalAccessToken, PersonalAccessTokenDatastoreError> {
+        let result = self.collection.insert_one(&personal_access_token).await.map_err(|_| PersonalAccessTokenDatastoreError::ProvidersError)?;
+
+        Ok(PersonalAccessToken {
+            id: result.inserted_id.as_object_id(),
+            ..personal_access_token
+        })
+    }
+
+    async fn get_personal_access_token(&self, token_hash: &str) -> Result<Option<PersonalAccessToken>, PersonalAccessTokenDatastoreError> {
+        self.collection.find_one(doc! { "token_hash": token_hash }).await.map_err(|_| PersonalAccessTokenDatastoreError::ProvidersError)
+    }
+
+    async fn get_personal_access_tokens(&self, username: &str) -> Result<Vec<PersonalAccessToken>, PersonalAccessTokenDatastoreError> {
+        self.collection.find(doc! { "username": username })
+            .await
+            .map_err(|_| PersonalAccessTokenDatastoreError::ProvidersError)?
+            .try_collect()
+            .await
+            .map_err(|_| PersonalAccessTokenDatastoreError::InternalError)
+    }
+
+    async fn revoke_personal_access_token(&self, username: &str, token_id: &str) -> Result<(), PersonalAccessTokenDatastoreError> {
+        let result = self.collection.update_one(
+            doc! { "username": username, "token_id": token_id, "revoked_at": doc! { "$exists": false }},
+            doc! { "$set": doc! { "revoked_at": DateTime::now() }}
+        ).await.map_err(|_| PersonalAccessTokenDatastoreError::ProvidersError)?;
+
+        if result.modified_count == 1 {
+            Ok(())
+        } else {
+            Err(PersonalAccessTokenDatastoreError::InternalError)
+        }
+    }
+
+    async fn update_last_used(&self, token_id: &str) -> Result<(), PersonalAccessTokenDatastoreError> {
+        self.collection.update_one(
+            doc! { "token_id": token_id },
+            doc! { "$set": doc! { "last_used_at": DateTime::now() }}
+        ).await.map_err(|_| PersonalAccessTokenDatastoreError::ProvidersError)?;
+
+        Ok(())
+    }
+}
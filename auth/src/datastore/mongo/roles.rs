@@ -0,0 +1,51 @@
+use futures::stream::TryStreamExt;
+use mongodb::{Collection, Database};
+use mongodb::bson::doc;
+use crate::datastore::{RoleDatastore, RoleDatastoreError};
+use crate::entities::{Role, Roles};
+
+/// Store the roles defined at runtime in their own collection, one document by role name
+#[derive(Clone)]
+pub struct MongoRoleDatastore {
+    collection: Collection<Role>
+}
+
+impl MongoRoleDatastore {
+    const DEFAULT_COLLECTION_NAME: &'static str = "roles";
+
+    pub fn new(database: &Database) -> Self {
+        Self {
+            collection: database.collection::<Role>(Self::DEFAULT_COLLECTION_NAME)
+        }
+    }
+}
+
+impl RoleDatastore for MongoRoleDatastore {
+    async fn get_roles(&self) -> Result<Vec<Role>, RoleDatastoreError> {
+        self.collection.find(doc! {})
+            .await
+            .map_err(|_| RoleDatastoreError::ProvidersError)?
+            .try_collect()
+            .await
+            .map_err(|_| RoleDatastoreError::InternalError)
+    }
+
+    async fn save_role(&self, role: Role) -> Result<(), RoleDatastoreError> {
+        self.collection.replace_one(doc! { "name": role.name.to_string() }, &role)
+            .upsert(true)
+            .await
+            .map_err(|_| RoleDatastoreError::ProvidersError)?;
+
+        Ok(())
+    }
+
+    async fn delete_role(&self, name: &Roles) -> Result<(), RoleDatastoreError> {
+        let result = self.collection.delete_one(doc! { "name": name.to_string() }).await.map_err(|_| RoleDatastoreError::ProvidersError)?;
+
+        if result.deleted_count == 1 {
+            Ok(())
+        } else {
+            Err(RoleDatastoreError::InternalError)
+        }
+    }
+}
@@ -2,7 +2,7 @@
 use mongodb::{Collection, Database};
 use mongodb::bson::{Bson, doc, DateTime};
 use crate::datastore::{TokenDatastoreError, TokenDatastore};
-use crate::entities::{Token};
+use crate::entities::{ReferenceToken, Token};
 use futures::stream::TryStreamExt;
 
 /// This DataStore is the main datastore use for this module
@@ -10,16 +10,19 @@ use futures::stream::TryStreamExt;
 /// This use mongodb driver to communicate with collection of user
 #[derive(Clone)]
 pub struct MongoTokenDatastore {
-    collection: Collection<Token>
+    collection: Collection<Token>,
+    reference_token_collection: Collection<ReferenceToken>,
 }
 
 
 impl MongoTokenDatastore {
     const DEFAULT_COLLECTION_NAME: &'static str = "tokens";
+    const REFERENCE_TOKEN_COLLECTION_NAME: &'static str = "reference_tokens";
 
     pub fn new(database: &Database) -> Self {
         return Self {
-            collection: database.collection::<Token>(Self::DEFAULT_COLLECTION_NAME)
+            collection: database.collection::<Token>(Self::DEFAULT_COLLECTION_NAME),
+            reference_token_collection: database.collection::<ReferenceToken>(Self::REFERENCE_TOKEN_COLLECTION_NAME),
         };
     }
 }
@@ -41,6 +44,10 @@ impl TokenDatastore for MongoTokenDatastore {
         self.collection.find_one(doc! { "token_refresh_identifiers": token_identifier }).await.map_err(|_| TokenDatastoreError::ProvidersError)
     }
 
+    async fn get_token_by_access_identifier(&self, token_access_identifier: &str) -> Result<Option<Token>, TokenDatastoreError> {
+        self.collection.find_one(doc! { "token_access_identifiers": token_access_identifier }).await.map_err(|_| TokenDatastoreError::ProvidersError)
+    }
+
     async fn get_tokens_for_user(&self, username: &str) -> Result<Vec<Token>, TokenDatastoreError> {
         self.collection.find(doc! { "username": username }).await.map_err(|_| TokenDatastoreError::ProvidersError)?.try_collect().await.map_err(|_| TokenDatastoreError::InternalError)
     }
@@ -56,4 +63,21 @@ impl TokenDatastore for MongoTokenDatastore {
             Err(TokenDatastoreError::InternalError)
         }
     }
+
+    async fn add_reference_token(&self, reference_token: ReferenceToken) -> Result<ReferenceToken, TokenDatastoreError> {
+        let reference_token_inserted = self.reference_token_collection.insert_one(&reference_token).await.map_err(|_| TokenDatastoreError::ProvidersError)?;
+
+        if let Bson::ObjectId(inserted_id) = reference_token_inserted.inserted_id {
+            Ok(ReferenceToken {
+                id: Some(inserted_id),
+                ..reference_token
+            })
+        } else {
+            Err(TokenDatastoreError::ProvidersError)
+        }
+    }
+
+    async fn get_reference_token(&self, token_hash: &str) -> Result<Option<ReferenceToken>, TokenDatastoreError> {
+        self.reference_token_collection.find_one(doc! { "token_hash": token_hash }).await.map_err(|_| TokenDatastoreError::ProvidersError)
+    }
 }
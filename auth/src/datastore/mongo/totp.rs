@@ -0,0 +1,94 @@
+use mongodb::{Collection, Database};
+use mongodb::bson::doc;
+use crate::datastore::{TotpDatastore, TotpDatastoreError};
+use crate::entities::TotpCredentials;
+
+/// Store the TOTP of users in their own collection, one document by username
+#[derive(Clone)]
+pub struct MongoTotpDatastore {
+    collection: Collection<TotpCredentials>
+}
+
+impl MongoTotpDatastore {
+    const DEFAULT_COLLECTION_NAME: &'static str = "totp";
+
+    pub fn new(database: &Database) -> Self {
+        Self {
+            collection: database.collection::<TotpCredentials>(Self::DEFAULT_COLLECTION_NAME)
+        }
+    }
+
+    fn expect_one_modified(modified_count: u64) -> Result<(), TotpDatastoreError> {
+        if modified_count == 1 {
+            Ok(())
+        } else {
+            Err(TotpDatastoreError::InternalError)
+        }
+    }
+}
+
+impl TotpDatastore for MongoTotpDatastore {
+    async fn get_totp(&self, username: &str) -> Result<Option<TotpCredentials>, TotpDatastoreError> {
+        self.collection.find_one(doc! { "username": username }).await.map_err(|_| TotpDatastoreError::ProvidersError)
+    }
+
+    async fn save_totp(&self, totp_credentials: TotpCredentials) -> Result<(), TotpDatastoreError> {
+        self.collection.replace_one(doc! { "username": &totp_credentials.username }, &totp_credentials)
+            .upsert(true)
+            .await
+            .map_err(|_| TotpDatastoreError::ProvidersError)?;
+
+        Ok(())
+    }
+
+    async fn enable_totp(&self, username: &str) -> Result<(), TotpDatastoreError> {
+        let result = self.collection.update_one(
+            doc! { "username": username },
+            doc! { "$set": doc! { "enabled": true }}
+        ).await.map_err(|_| TotpDatastoreError::ProvidersError)?;
+
+        Self::expect_one_modified(result.modified_count)
+    }
+
+    async fn delete_totp(&self, username: &str) -> Result<(), TotpDatastoreError> {
+        self.collection.delete_one(doc! { "username": username }).await.map_err(|_| TotpDatastoreError::ProvidersError)?;
+
+        Ok(())
+    }
+
+    async fn use_totp_step(&self, username: &str, step: i64) -> Result<(), TotpDatastoreError> {
+        let result = self.collection.update_one(
+            doc! { "username": username, "$or": [{ "last_used_step": { "$exists": false }}, { "last_used_step": { "$lt": step }}] },
+            doc! { "$set": doc! { "last_used_step": step }}
+        ).await.map_err(|_| TotpDatastoreError::ProvidersError)?;
+
+        Self::expect_one_modified(result.modified_count)
+    }
+
+    async fn use_recovery_code(&self, username: &str, recovery_code_hash: &str) -> Result<(), TotpDatastoreError> {
+        let result = self.collection.update_one(
+            doc! { "username": username, "recovery_code_hashes": recovery_code_hash },
+            doc! { "$pull": doc! { "recovery_code_hashes": recovery_code_hash }}
+        ).await.map_err(|_| TotpDatastoreError::ProvidersError)?;
+
+        Self::expect_one_modified(result.modified_count)
+    }
+
+    async fn save_mfa_ticket(&self, username: &str, ticket_identifier: &str) -> Result<(), TotpDatastoreError> {
+        let result = self.collection.update_one(
+            doc! { "username": username },
+            doc! { "$set": doc! { "mfa_ticket_identifier": ticket_identifier }}
+        ).await.map_err(|_| TotpDatastoreError::ProvidersError)?;
+
+        Self::expect_one_modified(result.modified_count)
+    }
+
+    async fn use_mfa_ticket(&self, username: &str, ticket_identifier: &str) -> Result<(), TotpDatastoreError> {
+        let result = self.collection.update_one(
+            doc! { "username": username, "mfa_ticket_identifier": ticket_identifier },
+            doc! { "$unset": doc! { "mfa_ticket_identifier": "" }}
+        ).await.map_err(|_| TotpDatastoreError::ProvidersError)?;
+
+        Self::expect_one_modified(result.modified_count)
+    }
+}
@@ -1,7 +1,7 @@
 use mongodb::{Collection, Database};
-use mongodb::bson::{Bson, doc};
+use mongodb::bson::{Bson, DateTime, doc};
 use crate::datastore::{AuthDatastore, AuthDatastoreError};
-use crate::entities::UserCredentials;
+use crate::entities::{Roles, UserCredentials};
 
 /// This DataStore is the main datastore use for this module
 ///
@@ -39,5 +39,62 @@ impl AuthDatastore for MongoAuthDatastore {
     async fn get_user_by_username(&self, username: &str) -> Result<Option<UserCredentials>, AuthDatastoreError> {
         self.collection.find_one(doc! { "username": username }).await.map_err(|_| AuthDatastoreError::ProvidersError)
     }
+
+    async fn update_password(&self, username: &str, password_hash: &str) -> Result<(), AuthDatastoreError> {
+        let result = self.collection.update_one(
+            doc! { "username": username },
+            doc! { "$set": doc! { "password": password_hash, "last_modified_at": DateTime::now() }}
+        ).await.map_err(|_| AuthDatastoreError::ProvidersError)?;
+
+        if result.matched_count == 1 {
+            Ok(())
+        } else {
+            Err(AuthDatastoreError::InternalError)
+        }
+    }
+
+    async fn update_roles(&self, username: &str, roles: &[Roles]) -> Result<(), AuthDatastoreError> {
+        let roles: Vec<String> = roles.iter().map(Roles::to_string).collect();
+        let result = self.collection.update_one(
+            doc! { "username": username },
+            doc! { "$set": doc! { "roles": roles, "last_modified_at": DateTime::now() }}
+        ).await.map_err(|_| AuthDatastoreError::ProvidersError)?;
+
+        if result.matched_count == 1 {
+            Ok(())
+        } else {
+            Err(AuthDatastoreError::InternalError)
+        }
+    }
+
+    /// Run in a transaction, it requires a replica set (e.g. MongoDB Atlas)
+    async fn update_roles_keeping_one_of(&self, username: &str, roles: &[Roles], kept_roles: &[Roles]) -> Result<bool, AuthDatastoreError> {
+        let roles: Vec<String> = roles.iter().map(Roles::to_string).collect();
+        let kept_roles: Vec<String> = kept_roles.iter().map(Roles::to_string).collect();
+        let mut session = self.collection.client().start_session().await.map_err(|_| AuthDatastoreError::ProvidersError)?;
+        session.start_transaction().await.map_err(|_| AuthDatastoreError::ProvidersError)?;
+
+        // Other holders are written, not only counted : concurrent updates removing the last two holders conflict and one fails
+        let other_holders = self.collection.update_many(
+            doc! { "username": { "$ne": username }, "roles": { "$in": kept_roles } },
+            doc! { "$set": doc! { "roles_checked_at": DateTime::now() }}
+        ).session(&mut session).await.map_err(|_| AuthDatastoreError::ProvidersError)?;
+        if other_holders.matched_count == 0 {
+            session.abort_transaction().await.map_err(|_| AuthDatastoreError::ProvidersError)?;
+            return Ok(false);
+        }
+
+        let result = self.collection.update_one(
+            doc! { "username": username },
+            doc! { "$set": doc! { "roles": roles, "last_modified_at": DateTime::now() }}
+        ).session(&mut session).await.map_err(|_| AuthDatastoreError::ProvidersError)?;
+        if result.matched_count != 1 {
+            session.abort_transaction().await.map_err(|_| AuthDatastoreError::ProvidersError)?;
+            return Err(AuthDatastoreError::InternalError);
+        }
+
+        session.commit_transaction().await.map_err(|_| AuthDatastoreError::ProvidersError)?;
+
+        Ok(true)
+    }
 }
- 
\ No newline at end of file
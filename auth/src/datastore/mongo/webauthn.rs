@@ -0,0 +1,84 @@
+use futures::stream::TryStreamExt;
+use mongodb::{Collection, Database};
+use mongodb::bson::{DateTime, doc};
+use crate::datastore::{WebAuthnDatastore, WebAuthnDatastoreError};
+use crate::entities::{WebAuthnChallenge, WebAuthnCredential};
+
+/// Store WebAuthn credentials and pending challenges in two collections
+#[derive(Clone)]
+pub struct MongoWebAuthnDatastore {
+    credentials_collection: Collection<WebAuthnCredential>,
+    challenges_collection: Collection<WebAuthnChallenge>,
+}
+
+impl MongoWebAuthnDatastore {
+    const DEFAULT_CREDENTIALS_COLLECTION_NAME: &'static str = "webauthn_credentials";
+    const DEFAULT_CHALLENGES_COLLECTION_NAME: &'static str = "webauthn_challenges";
+
+    pub fn new(database: &Database) -> Self {
+        Self {
+            credentials_collection: database.collection::<WebAuthnCredential>(Self::DEFAULT_CREDENTIALS_COLLECTION_NAME),
+            challenges_collection: database.collection::<WebAuthnChallenge>(Self::DEFAULT_CHALLENGES_COLLECTION_NAME),
+        }
+    }
+}
+
+impl WebAuthnDatastore for MongoWebAuthnDatastore {
+    async fn add_challenge(&self, webauthn_challenge: WebAuthnChallenge) -> Result<(), WebAuthnDatastoreError> {
+        self.challenges_collection.delete_many(doc! { "expired_at": { "$lt": DateTime::now() } }).await.map_err(|_| WebAuthnDatastoreError::ProvidersError)?;
+        self.challenges_collection.insert_one(webauthn_challenge).await.map_err(|_| WebAuthnDatastoreError::ProvidersError)?;
+
+        Ok(())
+    }
+
+    async fn take_challenge(&self, challenge: &str) -> Result<Option<WebAuthnChallenge>, WebAuthnDatastoreError> {
+        self.challenges_collection.find_one_and_delete(doc! { "challenge": challenge }).await.map_err(|_| WebAuthnDatastoreError::ProvidersError)
+    }
+
+    async fn add_credential(&self, webauthn_credential: WebAuthnCredential) -> Result<WebAuthnCredential, WebAuthnDatastoreError> {
+        let result = self.credentials_collection.insert_one(&webauthn_credential).await.map_err(|_| WebAuthnDatastoreError::ProvidersError)?;
+
+        Ok(WebAuthnCredential {
+            id: result.inserted_id.as_object_id(),
+            ..webauthn_credential
+        })
+    }
+
+    async fn get_credential(&self, credential_id: &str) -> Result<Option<WebAuthnCredential>, WebAuthnDatastoreError> {
+        self.credentials_collection.find_one(doc! { "credential_id": credential_id }).await.map_err(|_| WebAuthnDatastoreError::ProvidersError)
+    }
+
+    async fn get_credentials(&self, username: &str) -> Result<Vec<WebAuthnCredential>, WebAuthnDatastoreError> {
+        self.credentials_collection.find(doc! { "username": username })
+            .await
+            .map_err(|_| WebAuthnDatastoreError::ProvidersError)?
+            .try_collect()
+            .await
+            .map_err(|_| WebAuthnDatastoreError::InternalError)
+    }
+
+    async fn update_credential_usage(&self, credential_id: &str, sign_count: u32) -> Result<(), WebAuthnDatastoreError> {
+        let result = self.credentials_collection.update_one(
+            doc! { "credential_id": credential_id },
+            doc! { "$set": doc! { "sign_count": sign_count, "last_used_at": DateTime::now() }}
+        ).await.map_err(|_| WebAuthnDatastoreError::ProvidersError)?;
+
+        if result.matched_count == 1 {
+            Ok(())
+        } else {
+            Err(WebAuthnDatastoreError::InternalError)
+        }
+    }
+
+    async fn delete_credential(&self, username: &str, credential_id: &str) -> Result<(), WebAuthnDatastoreError> {
+        let result = self.credentials_collection.delete_one(doc! { "username": username, "credential_id": credential_id })
+            .await
+            .map_err(|_| WebAuthnDatastoreError::ProvidersError)?;
+
+        if result.deleted_count == 1 {
+            Ok(())
+        } else {
+            Err(WebAuthnDatastoreError::InternalError)
+        }
+    }
+}
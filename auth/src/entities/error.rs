@@ -1,4 +1,5 @@
 use thiserror::Error;
+use crate::utils::password_policy::PasswordPolicyError;
 #[derive(Error, Debug, PartialEq)]
 pub enum AuthError {
     #[error("Unauthorized")]
@@ -15,5 +16,37 @@ pub enum AuthError {
     ServerError,
     #[error("Content already exists")]
     Duplicated,
+    #[error("Content not found")]
+    NotFound,
+    #[error("{0}")]
+    WeakPassword(PasswordPolicyError),
+    #[error("Account temporarily locked, retry in {0} seconds")]
+    AccountLocked(i64),
+    #[error("Too many login attempts, retry in {0} seconds")]
+    TooManyAttempts(i64),
+    #[error("The last SuperAdmin can't be demoted")]
+    LastSuperAdmin,
+    #[error("Role not valid : unknown role or parent, parent cycle, or built-in role moved")]
+    InvalidRole,
 }
 
+/// Errors of the OAuth 2.0 token and authorization endpoints, answered with the codes of RFC 6749 sections 4.1.2.1 and 5.2
+#[derive(Error, Debug, PartialEq)]
+pub enum OAuthError {
+    #[error("invalid_request")]
+    InvalidRequest,
+    #[error("invalid_client")]
+    InvalidClient,
+    #[error("unsupported_grant_type")]
+    UnsupportedGrantType,
+    #[error("invalid_scope")]
+    InvalidScope,
+    #[error("invalid_grant")]
+    InvalidGrant,
+    #[error("unsupported_response_type")]
+    UnsupportedResponseType,
+    #[error("access_denied")]
+    AccessDenied,
+    #[error("server_error")]
+    ServerError,
+}
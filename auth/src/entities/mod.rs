@@ -3,13 +3,13 @@ use std::fmt::Display;
 use std::str::FromStr;
 use mongodb::bson::DateTime;
 use mongodb::bson::oid::ObjectId;
-use serde::{Deserialize, Serialize};
-use pbkdf2::password_hash::rand_core::OsRng;
-use pbkdf2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
-use pbkdf2::Pbkdf2;
+use serde::{Deserialize, Deserializer, Serialize};
+use rand::RngCore;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use sha2::{Digest, Sha256};
 
-#[cfg(test)]
-use chrono::Duration;
+use chrono::{Duration, TimeDelta};
 #[cfg(test)]
 use fake::{Dummy, Faker, Fake, faker::name::raw::Name, locales::EN};
 #[cfg(test)]
@@ -22,18 +22,62 @@ use thiserror::Error;
 use crate::entities::error::AuthError;
 use crate::entities::Privileges::{Anonymous, Authenticated, Deny};
 use crate::entities::Roles::{Admin, Moderator, SuperAdmin};
+use crate::utils::auth_config::AuthConfig;
 
 pub mod error;
 
 #[derive(Debug, Clone)]
 pub struct AuthSession {
     pub username: String,
-    pub role: Roles,
+    /// Empty for anonymous sessions and services
+    pub roles: Vec<Roles>,
+    /// Identifier (`jti`) of the access token used for this request. `None` for anonymous sessions
+    pub token_identifier: Option<String>,
+    pub auth_method: AuthMethod,
+}
+
+/// Credential used to authenticate the request
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthMethod {
+    Anonymous,
+    AccessToken,
+    /// `token_identifier` is then the identifier of the personal access token
+    PersonalAccessToken,
+    /// Backend service authenticated with the `client_credentials` grant, `username` is then its client id
+    ServicePrincipal(ServicePrincipal),
+    /// Third-party app acting on behalf of `username`, within the scopes the user consented to
+    DelegatedAccess(ServicePrincipal),
+}
+
+/// Confidential client acting on its own behalf, or on behalf of a user with `AuthMethod::DelegatedAccess`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServicePrincipal {
+    pub client_id: String,
+    pub scopes: Vec<String>,
+}
+
+impl ServicePrincipal {
+    /// Services have no role : they only reach routes open to all or requiring one of their scopes
+    pub fn is_authorized(&self, privileges: Privileges) -> bool {
+        match privileges {
+            Privileges::Allow => true,
+            Privileges::Scope(scope) => self.scopes.contains(&scope),
+            _ => false,
+        }
+    }
+}
+
+impl AuthSession {
+    /// Check the built-in roles of the session. Runtime roles and permissions are resolved by `AuthGuardLayer` with a `RoleCheck`
+    pub fn is_authorized(&self, privileges: Privileges) -> bool {
+        self.roles.iter().any(|role| role.is_authorized(privileges.clone()))
+    }
 }
 
 impl Display for AuthSession {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Username: {}\n; Role : {}\n", self.username, self.clone().role)
+        let roles: Vec<String> = self.roles.iter().map(Roles::to_string).collect();
+        write!(f, "Username: {}\n; Roles : {}\n", self.username, roles.join(", "))
     }
 }
 
@@ -46,15 +90,22 @@ pub enum Privileges {
     Authenticated,
     Anonymous,
     Deny,
+    /// Only service principals granted this scope
+    Scope(String),
+    /// Only users having a role which grants this permission (e.g. `user:ban`), directly or through its parents
+    Permission(String),
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
 pub enum Roles {
     SuperAdmin,
     Admin,
     Moderator,
     User,
     None,
+    /// Role defined at runtime in the `RoleDatastore`
+    Custom(String),
 }
 
 impl Roles {
@@ -67,8 +118,14 @@ impl Roles {
             Authenticated => true,
             Anonymous => false,
             Deny => false,
+            Privileges::Scope(_) => false,
+            Privileges::Permission(_) => false,
         }
     }
+
+    pub fn is_built_in(&self) -> bool {
+        !matches!(self, Roles::Custom(_))
+    }
 }
 
 
@@ -82,7 +139,23 @@ impl Dummy<Faker> for Roles {
 
 impl fmt::Display for Roles {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            Roles::Custom(name) => write!(f, "{}", name),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+/// Built-in role of this name, or else a runtime role
+impl From<String> for Roles {
+    fn from(name: String) -> Self {
+        name.parse().unwrap_or(Roles::Custom(name))
+    }
+}
+
+impl From<Roles> for String {
+    fn from(role: Roles) -> Self {
+        role.to_string()
     }
 }
 
@@ -113,22 +186,129 @@ pub struct UserCredentials {
     pub id: Option<ObjectId>,
     pub username: String,
     pub password: String,
-    pub roles: Roles,
+    #[serde(deserialize_with = "deserialize_roles")]
+    pub roles: Vec<Roles>,
     pub created_at: DateTime,
     pub last_modified_at: DateTime,
 }
 
 impl UserCredentials {
-    pub(crate) fn hash_password(password: String) -> String {
-        let salt = SaltString::generate(&mut OsRng);
+    /// Hash password to PHC string with the algorithm configured in `AuthConfig` ($argon2id$... by default)
+    pub(crate) fn hash_password(password: String, auth_config: &AuthConfig) -> String {
+        auth_config.password_hashing().hash_password(&password).expect("Cannot hash password")
+    }
+
+    /// Check the new password of the user against the `PasswordPolicy` configured in `AuthConfig`
+    pub(crate) fn validate_password(username: &str, password: &str, auth_config: &AuthConfig) -> Result<(), AuthError> {
+        auth_config.password_policy().validate(username, password).map_err(AuthError::WeakPassword)
+    }
 
-        // Hash password to PHC string ($pbkdf2-sha256$...)
-        Pbkdf2.hash_password(password.as_bytes(), &salt).unwrap().to_string()
+    pub fn verify_password(&self, password: &str, auth_config: &AuthConfig) -> Result<(), AuthError> {
+        auth_config.password_hashing().verify_password(password, &self.password)
     }
 
-    pub fn verify_password(&self, password_hash: &str) -> Result<(), AuthError> {
-        let self_password_hash = PasswordHash::new(&self.password).map_err(|_| AuthError::WrongCredentials)?;
-        Pbkdf2.verify_password(password_hash.as_bytes(), &self_password_hash).map_err(|_| AuthError::WrongCredentials)
+    /// Check if the password was hashed with a legacy algorithm or weaker parameters than configured
+    pub(crate) fn needs_password_rehash(&self, auth_config: &AuthConfig) -> bool {
+        auth_config.password_hashing().needs_rehash(&self.password)
+    }
+}
+
+/// Users stored before several roles were supported have a single role
+fn deserialize_roles<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Roles>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StoredRoles {
+        One(Roles),
+        Many(Vec<Roles>),
+    }
+
+    Ok(match StoredRoles::deserialize(deserializer)? {
+        StoredRoles::One(role) => vec![role],
+        StoredRoles::Many(roles) => roles,
+    })
+}
+
+/// Role stored in the `RoleDatastore`, granting its permissions and the ones of its parent
+///
+/// Built-in roles keep their place in the hierarchy, only their permissions can be changed
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct Role {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    pub(crate) name: Roles,
+    pub(crate) parent: Option<Roles>,
+    pub(crate) permissions: Vec<String>,
+    pub(crate) created_at: DateTime,
+    pub(crate) last_modified_at: DateTime,
+}
+
+impl Role {
+    pub fn new(name: Roles, parent: Option<Roles>, permissions: Vec<String>) -> Self {
+        let now = DateTime::now();
+
+        Self {
+            id: None,
+            name,
+            parent,
+            permissions,
+            created_at: now,
+            last_modified_at: now,
+        }
+    }
+
+    /// The built-in roles seeded by default, each one inherits the permissions of the role below it
+    pub fn defaults() -> Vec<Role> {
+        let permissions = |permissions: &[&str]| permissions.iter().map(|permission| permission.to_string()).collect();
+
+        vec![
+            Role::new(SuperAdmin, Some(Admin), permissions(&["role:write", "oauth_client:write"])),
+            Role::new(Admin, Some(Moderator), permissions(&["role:read", "user_role:write", "login_attempts:delete"])),
+            Role::new(Moderator, Some(Roles::User), permissions(&["user:read"])),
+            Role::new(Roles::User, None, permissions(&["profile:read", "profile:write"])),
+        ]
+    }
+
+    /// Roles stored, plus the default roles not stored yet
+    pub fn with_defaults(mut roles: Vec<Role>) -> Vec<Role> {
+        for default_role in Self::defaults() {
+            if !roles.iter().any(|role| role.name == default_role.name) {
+                roles.push(default_role);
+            }
+        }
+
+        roles
+    }
+
+    /// Definitions of the roles given and of their ancestors. Roles without definition grant nothing
+    pub fn resolve<'a>(roles: &[Roles], definitions: &'a [Role]) -> Vec<&'a Role> {
+        let mut resolved: Vec<&Role> = Vec::new();
+
+        for role in roles {
+            let mut next = Some(role);
+            while let Some(definition) = next.and_then(|name| definitions.iter().find(|definition| &definition.name == name)) {
+                // Parent cycles are rejected when roles are saved, it only stops at roles already resolved
+                if resolved.iter().any(|resolved_role| resolved_role.name == definition.name) {
+                    break;
+                }
+                resolved.push(definition);
+                next = definition.parent.as_ref();
+            }
+        }
+
+        resolved
+    }
+
+    /// Runtime roles get the privileges of their built-in ancestors, and every role the permissions of its ancestors
+    pub fn is_authorized(roles: &[Roles], definitions: &[Role], privileges: &Privileges) -> bool {
+        let resolved = Self::resolve(roles, definitions);
+
+        match privileges {
+            Privileges::Permission(permission) => resolved.iter().any(|role| role.permissions.contains(permission)),
+            _ => roles.iter()
+                .chain(resolved.iter().map(|role| &role.name))
+                .any(|role| role.is_authorized(privileges.clone())),
+        }
     }
 }
 
@@ -141,8 +321,8 @@ impl Dummy<Faker> for UserCredentials {
         Self {
             id: None,
             username: Name(EN).fake(),
-            password: UserCredentials::hash_password(password),
-            roles: Faker.fake(),
+            password: UserCredentials::hash_password(password, &AuthConfig::fake()),
+            roles: vec![Faker.fake()],
             created_at: now,
             last_modified_at: now,
         }
@@ -162,6 +342,24 @@ pub struct Token {
     pub(crate) revoked_at: Option<DateTime>,
     pub(crate) token_access_expired_at: DateTime,
     pub(crate) token_refresh_expired_at: DateTime,
+    /// Stable identifier of the session, kept across token refresh.
+    /// It's the lineage of rotated refresh tokens (token family)
+    #[serde(default)]
+    pub(crate) session_id: String,
+    /// Refresh token identifier of the token rotated to create this one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) parent_token_identifier: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) user_agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) client_ip: Option<String>,
+}
+
+/// Information about the client given at login, stored with the tokens to identify sessions
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientInformation {
+    pub user_agent: Option<String>,
+    pub client_ip: Option<String>,
 }
 
 #[cfg(test)]
@@ -178,14 +376,502 @@ impl Dummy<Faker> for Token {
             revoked_at: None,
             token_access_expired_at: DateTime::parse_rfc3339_str((chrono::Utc::now() + Duration::minutes(5)).to_rfc3339()).unwrap(),
             token_refresh_expired_at: DateTime::parse_rfc3339_str((chrono::Utc::now() + Duration::days(1)).to_rfc3339()).unwrap(),
+            session_id: uuid::Uuid::new_v4().to_string(),
+            parent_token_identifier: None,
+            user_agent: None,
+            client_ip: None,
         }
     }
 }
 
+/// Single-use token allowing a user to replace a forgotten password
+///
+/// Only the SHA-256 of the token is stored, the token itself is given to the user through a `PasswordResetSender`
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct PasswordReset {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    pub(crate) username: String,
+    pub(crate) reset_token_hash: String,
+    pub(crate) created_at: DateTime,
+    pub(crate) expired_at: DateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) used_at: Option<DateTime>,
+}
+
+impl PasswordReset {
+    const PASSWORD_RESET_LIFETIME: TimeDelta = Duration::minutes(15);
+
+    /// Create a new password reset for the user, returned with the clear reset token
+    pub(crate) fn generate(username: &str) -> (Self, String) {
+        let mut reset_token_bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut reset_token_bytes);
+        let reset_token: String = reset_token_bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        let password_reset = Self {
+            id: None,
+            username: username.to_string(),
+            reset_token_hash: Self::hash_reset_token(&reset_token),
+            created_at: DateTime::now(),
+            expired_at: DateTime::parse_rfc3339_str((chrono::Utc::now() + Self::PASSWORD_RESET_LIFETIME).to_rfc3339()).unwrap(),
+            used_at: None,
+        };
+
+        (password_reset, reset_token)
+    }
+
+    /// Reset tokens are random enough to be looked up by a hash without salt
+    pub(crate) fn hash_reset_token(reset_token: &str) -> String {
+        format!("{:x}", Sha256::digest(reset_token.as_bytes()))
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        self.used_at.is_none() && self.expired_at > DateTime::now()
+    }
+}
+
+/// Failed logins counted for a username or a client IP, see `LoginAttempts::username_key` and `LoginAttempts::client_ip_key`
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct LoginAttempts {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    pub(crate) key: String,
+    pub(crate) failures: u32,
+    pub(crate) last_failure_at: DateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) locked_until: Option<DateTime>,
+}
+
+impl LoginAttempts {
+    pub(crate) fn username_key(username: &str) -> String {
+        format!("username:{}", username)
+    }
+
+    pub(crate) fn client_ip_key(client_ip: &str) -> String {
+        format!("client_ip:{}", client_ip)
+    }
+
+    /// Seconds before the end of the lock, `None` when not locked
+    pub(crate) fn locked_for_seconds(&self) -> Option<i64> {
+        self.locked_until
+            .map(|locked_until| (locked_until.timestamp_millis() - DateTime::now().timestamp_millis() + 999) / 1000)
+            .filter(|seconds| *seconds > 0)
+    }
+}
+
+/// TOTP second factor of a user (RFC 6238). Inactive until confirmed with a first code
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct TotpCredentials {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    pub(crate) username: String,
+    /// Shared secret encoded in base32, required in clear to compute codes
+    pub(crate) secret: String,
+    pub(crate) enabled: bool,
+    /// SHA-256 of the one-time recovery codes not used yet
+    pub(crate) recovery_code_hashes: Vec<String>,
+    /// Last time step accepted, a code can't be used twice
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) last_used_step: Option<i64>,
+    /// Identifier of the last MFA ticket given at login and not exchanged yet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) mfa_ticket_identifier: Option<String>,
+    pub(crate) created_at: DateTime,
+}
+
+/// Public key of a WebAuthn authenticator (passkey) registered by a user
+///
+/// Identifiers and keys are stored encoded in base64url, as sent by browsers
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct WebAuthnCredential {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    pub(crate) username: String,
+    pub(crate) credential_id: String,
+    /// P-256 public key, SEC1 uncompressed point
+    pub(crate) public_key: String,
+    /// Signature counter of the authenticator, to detect cloned authenticators
+    pub(crate) sign_count: u32,
+    pub(crate) name: String,
+    pub(crate) created_at: DateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) last_used_at: Option<DateTime>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum WebAuthnCeremony {
+    Registration,
+    Authentication,
+}
+
+/// Challenge of a WebAuthn ceremony in progress, consumed by the first response
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct WebAuthnChallenge {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    pub(crate) challenge: String,
+    /// User registering a credential, or user expected to authenticate. `None` for discoverable credentials
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) username: Option<String>,
+    pub(crate) ceremony: WebAuthnCeremony,
+    pub(crate) created_at: DateTime,
+    pub(crate) expired_at: DateTime,
+}
+
+impl WebAuthnChallenge {
+    pub(crate) const WEBAUTHN_CHALLENGE_LIFETIME: TimeDelta = Duration::minutes(5);
+
+    pub(crate) fn generate(username: Option<&str>, ceremony: WebAuthnCeremony) -> Self {
+        let mut challenge_bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut challenge_bytes);
+
+        Self {
+            id: None,
+            challenge: URL_SAFE_NO_PAD.encode(challenge_bytes),
+            username: username.map(|username| username.to_string()),
+            ceremony,
+            created_at: DateTime::now(),
+            expired_at: DateTime::parse_rfc3339_str((chrono::Utc::now() + Self::WEBAUTHN_CHALLENGE_LIFETIME).to_rfc3339()).unwrap(),
+        }
+    }
+
+    pub(crate) fn is_valid(&self, ceremony: WebAuthnCeremony) -> bool {
+        self.ceremony == ceremony && self.expired_at > DateTime::now()
+    }
+}
+
+/// Authorization request sent to an upstream OpenID provider, waiting for its callback
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct OidcLoginState {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    /// Random `state` parameter, returned by the provider to the callback
+    pub(crate) state: String,
+    pub(crate) provider: String,
+    /// PKCE secret of the authorization code, only its SHA-256 is sent to the provider
+    pub(crate) code_verifier: String,
+    /// Expected in the ID token, against replayed ID tokens
+    pub(crate) nonce: String,
+    pub(crate) created_at: DateTime,
+    pub(crate) expired_at: DateTime,
+}
+
+impl OidcLoginState {
+    pub(crate) const OIDC_LOGIN_STATE_LIFETIME: TimeDelta = Duration::minutes(10);
+
+    pub(crate) fn generate(provider: &str) -> Self {
+        Self {
+            id: None,
+            state: Self::generate_random_string(),
+            provider: provider.to_string(),
+            code_verifier: Self::generate_random_string(),
+            nonce: Self::generate_random_string(),
+            created_at: DateTime::now(),
+            expired_at: DateTime::parse_rfc3339_str((chrono::Utc::now() + Self::OIDC_LOGIN_STATE_LIFETIME).to_rfc3339()).unwrap(),
+        }
+    }
+
+    fn generate_random_string() -> String {
+        let mut random_bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut random_bytes);
+
+        URL_SAFE_NO_PAD.encode(random_bytes)
+    }
+
+    /// PKCE `S256` code challenge of the code verifier
+    pub(crate) fn code_challenge(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.code_verifier.as_bytes()))
+    }
+
+    pub(crate) fn is_valid(&self, provider: &str) -> bool {
+        self.provider == provider && self.expired_at > DateTime::now()
+    }
+}
+
+/// Account of an upstream OpenID provider linked to a local user, created at its first login
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct OidcLink {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    pub(crate) provider: String,
+    /// `sub` claim of the ID token, unique and stable for a provider
+    pub(crate) subject: String,
+    pub(crate) username: String,
+    pub(crate) created_at: DateTime,
+}
+
+/// Long-lived token of a user for scripts and CI jobs, sent as bearer token like access tokens
+///
+/// Only the SHA-256 of the token is stored, the token itself is shown once at creation
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct PersonalAccessToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    /// Public identifier, used to list and revoke the token
+    pub(crate) token_id: String,
+    pub(crate) username: String,
+    pub(crate) name: String,
+    pub(crate) token_hash: String,
+    pub(crate) created_at: DateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) expired_at: Option<DateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) last_used_at: Option<DateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) revoked_at: Option<DateTime>,
+}
+
+impl PersonalAccessToken {
+    /// Distinguish personal access tokens from PASETO tokens in the `Authorization` header
+    pub(crate) const TOKEN_PREFIX: &'static str = "pat_";
+
+    /// Create a new personal access token for the user, returned with the clear token
+    pub(crate) fn generate(username: &str, name: &str, expired_at: Option<DateTime>) -> (Self, String) {
+        let mut token_bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut token_bytes);
+        let token = format!("{}{}", Self::TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(token_bytes));
+
+        let personal_access_token = Self {
+            id: None,
+            token_id: uuid::Uuid::new_v4().to_string(),
+            username: username.to_string(),
+            name: name.to_string(),
+            token_hash: Self::hash_token(&token),
+            created_at: DateTime::now(),
+            expired_at,
+            last_used_at: None,
+            revoked_at: None,
+        };
+
+        (personal_access_token, token)
+    }
+
+    pub(crate) fn is_personal_access_token(token: &str) -> bool {
+        token.starts_with(Self::TOKEN_PREFIX)
+    }
+
+    /// Personal access tokens are random enough to be looked up by a hash without salt
+    pub(crate) fn hash_token(token: &str) -> String {
+        format!("{:x}", Sha256::digest(token.as_bytes()))
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        self.revoked_at.is_none() && self.expired_at.is_none_or(|expired_at| expired_at > DateTime::now())
+    }
+}
+
+/// Opaque token given to clients in place of a signed token, its claims are kept on server side
+///
+/// Only the SHA-256 of the token is stored, the claims are resolved through the `TokenDatastore`
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct ReferenceToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    pub(crate) token_hash: String,
+    /// Claims of the token, serialized as the payload of a PASETO token
+    pub(crate) claims: String,
+    pub(crate) created_at: DateTime,
+    pub(crate) expired_at: DateTime,
+}
+
+impl ReferenceToken {
+    /// Distinguish reference tokens from personal access tokens in the `Authorization` header
+    pub(crate) const TOKEN_PREFIX: &'static str = "ref_";
+
+    /// Create a new reference token for the claims, returned with the clear token
+    pub(crate) fn generate(claims: String, expired_at: DateTime) -> (Self, String) {
+        let mut token_bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut token_bytes);
+        let token = format!("{}{}", Self::TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(token_bytes));
+
+        let reference_token = Self {
+            id: None,
+            token_hash: Self::hash_token(&token),
+            claims,
+            created_at: DateTime::now(),
+            expired_at,
+        };
+
+        (reference_token, token)
+    }
+
+    /// Reference tokens are random enough to be looked up by a hash without salt
+    pub(crate) fn hash_token(token: &str) -> String {
+        format!("{:x}", Sha256::digest(token.as_bytes()))
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        self.expired_at > DateTime::now()
+    }
+}
+
+/// Confidential client registered to get access tokens with the `client_credentials` grant,
+/// or on behalf of users with the `authorization_code` grant when it has redirection URIs
+///
+/// Only the SHA-256 of the client secret is stored, the secret is shown once at registration
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct OAuthClient {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    pub(crate) client_id: String,
+    pub(crate) name: String,
+    pub(crate) client_secret_hash: String,
+    pub(crate) scopes: Vec<String>,
+    /// Exact redirection URIs allowed by the authorization endpoint
+    #[serde(default)]
+    pub(crate) redirect_uris: Vec<String>,
+    pub(crate) created_at: DateTime,
+}
+
+impl OAuthClient {
+    /// Scope registered to the clients allowed to introspect tokens, e.g. API gateways
+    pub(crate) const INTROSPECT_SCOPE: &'static str = "introspect";
+
+    /// Register a new client allowed to request the scopes given, returned with the clear secret
+    pub(crate) fn generate(name: &str, scopes: Vec<String>, redirect_uris: Vec<String>) -> (Self, String) {
+        let mut secret_bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret_bytes);
+        let client_secret = URL_SAFE_NO_PAD.encode(secret_bytes);
+
+        let oauth_client = Self {
+            id: None,
+            client_id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            client_secret_hash: Self::hash_client_secret(&client_secret),
+            scopes,
+            redirect_uris,
+            created_at: DateTime::now(),
+        };
+
+        (oauth_client, client_secret)
+    }
+
+    /// Scopes are space separated in tokens and requests, so they can't contain whitespaces
+    pub(crate) fn split_scopes(scopes: &[String]) -> Vec<String> {
+        let mut split_scopes: Vec<String> = Vec::new();
+
+        for scope in scopes.iter().flat_map(|scope| scope.split_whitespace()) {
+            if !split_scopes.iter().any(|split_scope| split_scope == scope) {
+                split_scopes.push(scope.to_string());
+            }
+        }
+
+        split_scopes
+    }
+
+    pub(crate) fn hash_client_secret(client_secret: &str) -> String {
+        format!("{:x}", Sha256::digest(client_secret.as_bytes()))
+    }
+
+    pub(crate) fn is_valid_secret(&self, client_secret: &str) -> bool {
+        self.client_secret_hash == Self::hash_client_secret(client_secret)
+    }
+
+    pub(crate) fn can_introspect(&self) -> bool {
+        self.scopes.iter().any(|scope| scope == Self::INTROSPECT_SCOPE)
+    }
+
+    pub(crate) fn is_valid_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.iter().any(|client_redirect_uri| client_redirect_uri == redirect_uri)
+    }
+}
+
+/// Authorization code given to a third-party app by the authorization endpoint, exchanged once for its tokens
+///
+/// Only the SHA-256 of the code is stored, the app proves it started the request with its PKCE code verifier
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct AuthorizationCode {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    pub(crate) code_hash: String,
+    pub(crate) client_id: String,
+    pub(crate) username: String,
+    pub(crate) redirect_uri: String,
+    pub(crate) scopes: Vec<String>,
+    /// PKCE `S256` code challenge given by the app
+    pub(crate) code_challenge: String,
+    /// Copied in the ID token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) nonce: Option<String>,
+    pub(crate) created_at: DateTime,
+    pub(crate) expired_at: DateTime,
+}
+
+impl AuthorizationCode {
+    pub(crate) const AUTHORIZATION_CODE_LIFETIME: TimeDelta = Duration::minutes(1);
+
+    pub(crate) fn generate(client_id: &str, username: &str, redirect_uri: &str, scopes: Vec<String>, code_challenge: &str, nonce: Option<String>) -> (Self, String) {
+        let mut code_bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut code_bytes);
+        let code = URL_SAFE_NO_PAD.encode(code_bytes);
+
+        let authorization_code = Self {
+            id: None,
+            code_hash: Self::hash_code(&code),
+            client_id: client_id.to_string(),
+            username: username.to_string(),
+            redirect_uri: redirect_uri.to_string(),
+            scopes,
+            code_challenge: code_challenge.to_string(),
+            nonce,
+            created_at: DateTime::now(),
+            expired_at: DateTime::parse_rfc3339_str((chrono::Utc::now() + Self::AUTHORIZATION_CODE_LIFETIME).to_rfc3339()).unwrap(),
+        };
+
+        (authorization_code, code)
+    }
+
+    pub(crate) fn hash_code(code: &str) -> String {
+        format!("{:x}", Sha256::digest(code.as_bytes()))
+    }
+
+    /// The code must be exchanged by the client it was given to, with the same redirection URI, before expiration
+    pub(crate) fn is_valid(&self, client_id: &str, redirect_uri: &str, code_verifier: &str) -> bool {
+        self.client_id == client_id
+            && self.redirect_uri == redirect_uri
+            && self.expired_at > DateTime::now()
+            && self.code_challenge == URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+    }
+}
+
+/// Scopes a user allowed a third-party app to access, the user is only asked again for new scopes
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct OAuthConsent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<ObjectId>,
+    pub(crate) username: String,
+    pub(crate) client_id: String,
+    pub(crate) scopes: Vec<String>,
+    pub(crate) created_at: DateTime,
+    pub(crate) last_modified_at: DateTime,
+}
+
+impl OAuthConsent {
+    pub(crate) fn covers(&self, scopes: &[String]) -> bool {
+        scopes.iter().all(|scope| self.scopes.contains(scope))
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum TokenType {
     Access,
     Refresh,
+    /// Access token of a service principal, see `OAuthClient`
+    Service,
+    /// Access token of a third-party app on behalf of a user, see `AuthorizationCode`
+    Delegated,
 }
 
 impl fmt::Display for TokenType {
@@ -207,6 +893,8 @@ impl FromStr for TokenType {
         match s {
             "access" => Ok(TokenType::Access),
             "refresh" => Ok(TokenType::Refresh),
+            "service" => Ok(TokenType::Service),
+            "delegated" => Ok(TokenType::Delegated),
             _ => Err(Self::Err::NotTokenType(s.to_string()))
         }
     }
@@ -229,7 +917,7 @@ mod tests {
                 id: None,
                 username: Name(EN).fake(),
                 password: (8..20).fake::<String>(),
-                roles: Roles::SuperAdmin,
+                roles: vec![Roles::SuperAdmin],
                 created_at: NOW.to_owned(),
                 last_modified_at: NOW.to_owned(),
             }
@@ -242,7 +930,9 @@ mod tests {
             Token::Str("password"),
             Token::Str(&FAKE_USER_CREDENTIALS.password),
             Token::Str("roles"),
-            Token::UnitVariant { name: "Roles", variant: "SuperAdmin" },
+            Token::Seq { len: Some(1) },
+            Token::Str("SuperAdmin"),
+            Token::SeqEnd,
             Token::Str("created_at"),
             Token::Struct { name: "$date", len: 1 },
             Token::Str("$date"),
@@ -274,7 +964,7 @@ mod tests {
                 id: Some(OBJECT_ID.clone()),
                 username: Name(EN).fake(),
                 password: (8..20).fake::<String>(),
-                roles: Roles::SuperAdmin,
+                roles: vec![Roles::SuperAdmin],
                 created_at: NOW.to_owned(),
                 last_modified_at: NOW.to_owned(),
             }
@@ -293,7 +983,9 @@ mod tests {
             Token::Str("password"),
             Token::Str(&FAKE_USER_CREDENTIALS.password),
             Token::Str("roles"),
-            Token::UnitVariant { name: "Roles", variant: "SuperAdmin" },
+            Token::Seq { len: Some(1) },
+            Token::Str("SuperAdmin"),
+            Token::SeqEnd,
             Token::Str("created_at"),
             Token::Struct { name: "$date", len: 1 },
             Token::Str("$date"),
@@ -321,7 +1013,7 @@ mod tests {
         static EXPIRED_REFRESH_AT_STRING: Lazy<String> = Lazy::new(|| { TOKEN.token_refresh_expired_at.timestamp_millis().to_string() });
 
         assert_tokens(&TOKEN.clone().compact(), &[
-            Token::Struct { name: "Token", len: 6 },
+            Token::Struct { name: "Token", len: 7 },
             Token::Str("username"),
             Token::Str(&TOKEN.username),
             Token::Str("token_access_identifiers"),
@@ -352,6 +1044,8 @@ mod tests {
             Token::Str(&EXPIRED_REFRESH_AT_STRING),
             Token::StructEnd,
             Token::StructEnd,
+            Token::Str("session_id"),
+            Token::Str(&TOKEN.session_id),
             Token::StructEnd,
         ]);
     }
@@ -367,7 +1061,7 @@ mod tests {
         static REVOKED_AT: Lazy<String> = Lazy::new(|| { TOKEN.revoked_at.unwrap().timestamp_millis().to_string() });
 
         assert_tokens(&TOKEN.clone().compact(), &[
-            Token::Struct { name: "Token", len: 8 },
+            Token::Struct { name: "Token", len: 9 },
             Token::Str("_id"),
             Token::Some,
             Token::Struct { name: "$oid", len: 1 },
@@ -413,6 +1107,8 @@ mod tests {
             Token::Str(&EXPIRED_REFRESH_AT_STRING),
             Token::StructEnd,
             Token::StructEnd,
+            Token::Str("session_id"),
+            Token::Str(&TOKEN.session_id),
             Token::StructEnd,
         ]);
     }
@@ -422,13 +1118,25 @@ mod tests {
         let password: String = Password(10..500).fake();
 
         let user_credentials: UserCredentials = UserCredentials {
-            password: UserCredentials::hash_password(password.clone()),
+            password: UserCredentials::hash_password(password.clone(), &AuthConfig::fake()),
             ..Faker.fake()
         };
 
         assert_ne!(user_credentials.password, password);
-        assert!(user_credentials.verify_password(&password).is_ok());
-        assert!(user_credentials.verify_password(&(password + " ")).is_err());
+        assert!(user_credentials.verify_password(&password, &AuthConfig::fake()).is_ok());
+        assert!(user_credentials.verify_password(&(password + " "), &AuthConfig::fake()).is_err());
+    }
+
+    #[test]
+    fn test_password_reset_generate() {
+        let (password_reset, reset_token) = PasswordReset::generate("username");
+
+        assert_eq!(password_reset.username, "username");
+        assert_eq!(password_reset.reset_token_hash, PasswordReset::hash_reset_token(&reset_token));
+        assert_ne!(password_reset.reset_token_hash, reset_token);
+        assert!(password_reset.is_valid());
+        assert!(!PasswordReset { used_at: Some(DateTime::now()), ..password_reset.clone() }.is_valid());
+        assert!(!PasswordReset { expired_at: DateTime::now(), ..password_reset }.is_valid());
     }
 
     #[test]
@@ -440,4 +1148,48 @@ mod tests {
         assert_eq!(Roles::None.to_string().parse::<Roles>().unwrap(), Roles::None);
         assert_eq!("random".to_string().parse::<Roles>(), Err(ParseRoleError::NotRole("random".to_string())));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_custom_role_from_string() {
+        assert_eq!(Roles::from("Admin".to_string()), Admin);
+        assert_eq!(Roles::from("support".to_string()), Roles::Custom("support".to_string()));
+        assert_eq!(Roles::Custom("support".to_string()).to_string(), "support");
+        assert!(!Roles::Custom("support".to_string()).is_built_in());
+    }
+
+    #[test]
+    fn test_user_credentials_with_single_role_deserialization() {
+        let document = mongodb::bson::doc! { "username": "username", "password": "password", "roles": "Moderator", "created_at": DateTime::now(), "last_modified_at": DateTime::now() };
+
+        let user_credentials: UserCredentials = mongodb::bson::from_document(document).unwrap();
+
+        assert_eq!(user_credentials.roles, vec![Moderator]);
+    }
+
+    #[test]
+    fn test_role_is_authorized_with_parents() {
+        let support = Roles::Custom("support".to_string());
+        let roles = vec![support.clone()];
+        let definitions = Role::with_defaults(vec![Role::new(support, Some(Moderator), vec!["user:ban".to_string()])]);
+
+        assert!(Role::is_authorized(&roles, &definitions, &Privileges::ModeratorPrivileges));
+        assert!(!Role::is_authorized(&roles, &definitions, &Privileges::AdminPrivileges));
+        assert!(Role::is_authorized(&roles, &definitions, &Privileges::Permission("user:ban".to_string())));
+        assert!(Role::is_authorized(&roles, &definitions, &Privileges::Permission("profile:read".to_string())));
+        assert!(!Role::is_authorized(&roles, &definitions, &Privileges::Permission("role:read".to_string())));
+        assert!(Role::is_authorized(&[SuperAdmin], &definitions, &Privileges::Permission("user:read".to_string())));
+        assert!(!Role::is_authorized(&[Roles::Custom("unknown".to_string())], &definitions, &Privileges::Permission("profile:read".to_string())));
+    }
+
+    #[test]
+    fn test_service_principal_is_authorized() {
+        let service_principal = ServicePrincipal { client_id: "client_id".to_string(), scopes: vec!["users:read".to_string()] };
+
+        assert!(service_principal.is_authorized(Privileges::Allow));
+        assert!(service_principal.is_authorized(Privileges::Scope("users:read".to_string())));
+        assert!(!service_principal.is_authorized(Privileges::Scope("users:write".to_string())));
+        assert!(!service_principal.is_authorized(Authenticated));
+        assert!(!service_principal.is_authorized(Privileges::AdminPrivileges));
+        assert!(!SuperAdmin.is_authorized(Privileges::Scope("users:read".to_string())));
+    }
+}
@@ -1,6 +1,6 @@
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use axum::RequestPartsExt;
-use axum::extract::FromRequestParts;
 use axum::http::{Request};
 use axum::http::request::Parts;
 use axum::response::{IntoResponse, Response};
@@ -10,26 +10,26 @@ use axum_extra::TypedHeader;
 use futures_util::future::BoxFuture;
 use tower::{Layer, Service};
 use crate::entities::error::AuthError;
-use crate::entities::{AuthSession, Privileges, Roles};
+use crate::entities::{AuthMethod, AuthSession, PersonalAccessToken, Privileges, ServicePrincipal, TokenType};
+use crate::layer::personal_access_tokens::PersonalAccessTokenCheck;
+use crate::layer::revocation::TokenRevocationCheck;
+use crate::layer::guard_expression::{GuardContext, GuardExpression};
+use crate::layer::roles::RoleCheck;
 use crate::utils::auth_claims::{AuthClaims};
+use crate::utils::auth_config::AuthConfig;
 use crate::utils::validate_token::{IntoClaims, TokenString};
 
 const ANONYMOUS_USERNAME: &str = "anonymous";
 
-impl<S> FromRequestParts<S> for AuthClaims
-where
-    S: Send + Sync,
-{
-    type Rejection = AuthError;
-
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+impl AuthClaims {
+    async fn from_request_parts(parts: &mut Parts, auth_config: &AuthConfig) -> Result<Self, AuthError> {
         let TypedHeader(Authorization(bearer)) = parts
             .extract::<TypedHeader<Authorization<Bearer>>>()
             .await
             .map_err(|_| AuthError::MissingCredentials)?;
 
         let untrusted_token = TokenString(bearer.token().to_string());
-        let claims = untrusted_token.try_into_claims()?;
+        let claims = untrusted_token.try_into_claims(auth_config).await?;
 
         AuthClaims::try_from(&claims).map_err(|_| AuthError::InvalidToken)
     }
@@ -37,22 +37,120 @@ where
 
 #[derive(Clone)]
 pub struct AuthGuardLayer {
-    pub privileges: Privileges,
+    pub guard_expression: GuardExpression,
+    optional: bool,
+    auth_config: AuthConfig,
+    revocation_check: Option<Arc<dyn TokenRevocationCheck>>,
+    personal_access_token_check: Option<Arc<dyn PersonalAccessTokenCheck>>,
+    role_check: Option<Arc<dyn RoleCheck>>,
+}
+
+impl AuthGuardLayer {
+    /// Require privileges, or a `GuardExpression` combining them
+    pub fn new(auth_config: AuthConfig, guard_expression: impl Into<GuardExpression>) -> Self {
+        Self { guard_expression: guard_expression.into(), optional: false, auth_config, revocation_check: None, personal_access_token_check: None, role_check: None }
+    }
+
+    /// Optional authentication : never reject, the `AuthSession` is anonymous when the credentials are missing or not valid
+    pub fn optional(auth_config: AuthConfig) -> Self {
+        Self { optional: true, ..Self::new(auth_config, Privileges::Allow) }
+    }
+
+    /// Opt-in : reject access tokens revoked on server side (logout, revoked session...)
+    pub fn with_revocation_check(mut self, revocation_check: Option<Arc<dyn TokenRevocationCheck>>) -> Self {
+        self.revocation_check = revocation_check;
+        self
+    }
+
+    /// Opt-in : accept personal access tokens (`pat_...`) as bearer tokens alongside access tokens
+    pub fn with_personal_access_token_check(mut self, personal_access_token_check: Option<Arc<dyn PersonalAccessTokenCheck>>) -> Self {
+        self.personal_access_token_check = personal_access_token_check;
+        self
+    }
+
+    /// Role definitions of runtime roles and permissions. Without it, only the default roles are known
+    pub fn with_role_check(mut self, role_check: Option<Arc<dyn RoleCheck>>) -> Self {
+        self.role_check = role_check;
+        self
+    }
 }
 
 impl<S> Layer<S> for AuthGuardLayer {
     type Service = AuthGuardService<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        AuthGuardService { inner, privileges_required: self.privileges.clone() }
+        AuthGuardService { inner, guard_expression: self.guard_expression.clone(), optional: self.optional, auth_config: self.auth_config.clone(), revocation_check: self.revocation_check.clone(), personal_access_token_check: self.personal_access_token_check.clone(), role_check: self.role_check.clone() }
     }
 }
 
+/// Credentials read from the request, before checking the guard expression
+enum Authentication {
+    Session(AuthSession),
+    MissingCredentials,
+    InvalidCredentials,
+}
+
 #[derive(Clone)]
 pub struct AuthGuardService<S> {
     inner: S,
-    privileges_required: Privileges,
+    guard_expression: GuardExpression,
+    optional: bool,
+    auth_config: AuthConfig,
+    revocation_check: Option<Arc<dyn TokenRevocationCheck>>,
+    personal_access_token_check: Option<Arc<dyn PersonalAccessTokenCheck>>,
+    role_check: Option<Arc<dyn RoleCheck>>,
 }
+
+impl<S> AuthGuardService<S> {
+    async fn authenticate(parts: &mut Parts, auth_config: &AuthConfig, revocation_check: Option<Arc<dyn TokenRevocationCheck>>, personal_access_token_check: Option<Arc<dyn PersonalAccessTokenCheck>>) -> Result<Authentication, AuthError> {
+        if let Some(personal_access_token_check) = personal_access_token_check {
+            let personal_access_token = parts.extract::<TypedHeader<Authorization<Bearer>>>()
+                .await
+                .ok()
+                .map(|TypedHeader(Authorization(bearer))| bearer.token().to_string())
+                .filter(|token| PersonalAccessToken::is_personal_access_token(token));
+
+            if let Some(personal_access_token) = personal_access_token {
+                return match personal_access_token_check.authenticate(&personal_access_token).await {
+                    Ok(auth_session) => Ok(Authentication::Session(auth_session)),
+                    Err(AuthError::ServerError) => Err(AuthError::ServerError),
+                    Err(_) => Err(AuthError::Unauthorized),
+                };
+            }
+        }
+
+        let auth_claims = match AuthClaims::from_request_parts(parts, auth_config).await {
+            Ok(auth_claims) => auth_claims,
+            Err(AuthError::MissingCredentials) => return Ok(Authentication::MissingCredentials),
+            Err(_) => return Ok(Authentication::InvalidCredentials),
+        };
+
+        let auth_session = match auth_claims.claim_type {
+            // Service tokens aren't linked to a session : they can't be revoked and expire quickly instead
+            TokenType::Service => {
+                let service_principal = ServicePrincipal { client_id: auth_claims.username.clone(), scopes: auth_claims.scopes };
+                AuthSession { username: auth_claims.username, roles: Vec::new(), token_identifier: Some(auth_claims.token_identifier), auth_method: AuthMethod::ServicePrincipal(service_principal) }
+            }
+            // Like service tokens, delegated tokens aren't stored and expire quickly
+            TokenType::Delegated => {
+                let service_principal = ServicePrincipal { client_id: auth_claims.client_id.unwrap_or_default(), scopes: auth_claims.scopes };
+                AuthSession { username: auth_claims.username, roles: Vec::new(), token_identifier: Some(auth_claims.token_identifier), auth_method: AuthMethod::DelegatedAccess(service_principal) }
+            }
+            TokenType::Access => {
+                if let Some(revocation_check) = revocation_check {
+                    if revocation_check.is_revoked(&auth_claims.token_identifier).await? {
+                        return Err(AuthError::Unauthorized);
+                    }
+                }
+                AuthSession { username: auth_claims.username, roles: auth_claims.roles, token_identifier: Some(auth_claims.token_identifier), auth_method: AuthMethod::AccessToken }
+            }
+            TokenType::Refresh => return Err(AuthError::InvalidToken),
+        };
+
+        Ok(Authentication::Session(auth_session))
+    }
+}
+
 impl<S, B> Service<Request<B>> for AuthGuardService<S>
 where
     S: Service<Request<B>, Response=Response> + Send + 'static + Clone,
@@ -66,44 +164,112 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, mut request: Request<B>) -> Self::Future {
-        if self.privileges_required == Privileges::Deny {
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        if self.guard_expression.is_deny() {
             return Box::pin(async move {
                 Ok(AuthError::Unauthorized.into_response())
             });
         }
 
         let (mut parts, body) = request.into_parts();
-        let privileges_required = self.privileges_required.clone();
+        let guard_expression = self.guard_expression.clone();
+        let optional = self.optional;
+        let auth_config = self.auth_config.clone();
+        let revocation_check = self.revocation_check.clone();
+        let personal_access_token_check = self.personal_access_token_check.clone();
+        let role_check = self.role_check.clone();
         let mut svc = self.inner.clone();
 
         Box::pin(async move {
-            let auth_claims = AuthClaims::from_request_parts(&mut parts, &()).await;
-
-            match auth_claims {
-                Ok(auth_claims) => {
-                    if auth_claims.role.is_none() {
-                        return Ok(AuthError::InvalidToken.into_response());
-                    }
+            let anonymous_session = || AuthSession { username: ANONYMOUS_USERNAME.to_string(), roles: Vec::new(), token_identifier: None, auth_method: AuthMethod::Anonymous };
+            let authentication = Self::authenticate(&mut parts, &auth_config, revocation_check, personal_access_token_check).await;
 
-                    let user_role = auth_claims.role.unwrap_or_else(|| Roles::None);
+            let (auth_session, invalid_credentials) = match authentication {
+                Ok(Authentication::Session(auth_session)) => (auth_session, false),
+                Ok(Authentication::MissingCredentials) => (anonymous_session(), false),
+                Ok(Authentication::InvalidCredentials) => (anonymous_session(), true),
+                // Optional authentication ignores credentials not valid
+                Err(_) if optional => (anonymous_session(), true),
+                Err(error) => return Ok(error.into_response()),
+            };
 
-                    if !user_role.clone().is_authorized(privileges_required) {
-                        return Ok(AuthError::Unauthorized.into_response());
-                    }
-                    parts.extensions.insert(AuthSession { username: auth_claims.username, role: user_role });
-                }
-                Err(error) => {
-                    if privileges_required != Privileges::Anonymous && error != AuthError::MissingCredentials {
-                        return Ok(AuthError::Unauthorized.into_response());
-                    }
-                    parts.extensions.insert(AuthSession { username: ANONYMOUS_USERNAME.to_string(), role: Roles::None });
+            if !optional {
+                let context = GuardContext { auth_session: &auth_session, parts: &parts, role_check: &role_check, invalid_credentials };
+                match guard_expression.evaluate(&context).await {
+                    Ok(true) => {}
+                    Ok(false) => return Ok(AuthError::Unauthorized.into_response()),
+                    Err(error) => return Ok(error.into_response()),
                 }
             }
+            parts.extensions.insert(auth_session);
 
-            let request = Request::from_parts(parts.clone(), body);
-
-            svc.call(request).await
+            svc.call(Request::from_parts(parts, body)).await
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::{Extension, Router};
+    use fake::{Fake, Faker};
+    use tower::ServiceExt;
+    use crate::entities::{AuthMethod, AuthSession, Privileges, Roles, Token, UserCredentials};
+    use crate::layer::claims::AuthGuardLayer;
+    use crate::layer::guard_expression::GuardExpression;
+    use crate::utils::auth_config::AuthConfig;
+
+    fn router(auth_guard_layer: AuthGuardLayer) -> Router {
+        Router::new().route("/", get(|Extension(auth_session): Extension<AuthSession>| async move { auth_session.username }).layer(auth_guard_layer))
+    }
+
+    async fn call(router: Router, access_token: Option<&str>) -> (StatusCode, String) {
+        let mut request = Request::builder().uri("/");
+        if let Some(access_token) = access_token {
+            request = request.header("Authorization", format!("Bearer {access_token}"));
+        }
+        let response = router.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    async fn generate_access_token(roles: Vec<Roles>) -> (UserCredentials, String) {
+        let user = UserCredentials { roles, ..Faker.fake() };
+        let (access_token, _, _) = Token::generate_tokens(&AuthConfig::fake(), &user).await.expect("Unable generate tokens");
+
+        (user, access_token)
+    }
+
+    #[tokio::test]
+    async fn test_optional_authentication_never_rejects() {
+        let (user, access_token) = generate_access_token(vec![Roles::User]).await;
+
+        assert_eq!(call(router(AuthGuardLayer::optional(AuthConfig::fake())), Some(&access_token)).await, (StatusCode::OK, user.username));
+        assert_eq!(call(router(AuthGuardLayer::optional(AuthConfig::fake())), Some("v4.public.invalid")).await, (StatusCode::OK, "anonymous".to_string()));
+        assert_eq!(call(router(AuthGuardLayer::optional(AuthConfig::fake())), None).await, (StatusCode::OK, "anonymous".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_missing_credentials_rejected_by_authenticated_routes() {
+        assert_eq!(call(router(AuthGuardLayer::new(AuthConfig::fake(), Privileges::Authenticated)), None).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(call(router(AuthGuardLayer::new(AuthConfig::fake(), Privileges::Anonymous)), Some("v4.public.invalid")).await.0, StatusCode::OK);
+        assert_eq!(call(router(AuthGuardLayer::new(AuthConfig::fake(), Privileges::Allow)), Some("v4.public.invalid")).await.0, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_guard_expression_with_predicate() {
+        let (_, access_token) = generate_access_token(vec![Roles::User]).await;
+        let (_, admin_access_token) = generate_access_token(vec![Roles::Admin]).await;
+        let guard_expression = GuardExpression::any_of([
+            Privileges::AdminPrivileges.into(),
+            GuardExpression::predicate(|auth_session, parts| auth_session.auth_method == AuthMethod::AccessToken && parts.headers.contains_key("X-Owner")),
+        ]);
+
+        assert_eq!(call(router(AuthGuardLayer::new(AuthConfig::fake(), guard_expression.clone())), Some(&access_token)).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(call(router(AuthGuardLayer::new(AuthConfig::fake(), guard_expression)), Some(&admin_access_token)).await.0, StatusCode::OK);
+    }
+}
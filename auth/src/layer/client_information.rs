@@ -0,0 +1,149 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::header::USER_AGENT;
+use axum::http::HeaderMap;
+use axum::http::request::Parts;
+use crate::entities::ClientInformation;
+use crate::utils::trusted_proxies::TrustedProxies;
+
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+const REAL_IP_HEADER: &str = "x-real-ip";
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers.get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Entries of every `X-Forwarded-For` header, each proxy appends the address of its peer
+fn forwarded_for(headers: &HeaderMap) -> Vec<String> {
+    headers.get_all(FORWARDED_FOR_HEADER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty())
+        .collect()
+}
+
+/// Client IP is the address of the peer, requires serving the router with `into_make_service_with_connect_info`
+///
+/// Proxy headers are only read when the peer is a trusted proxy : the client is then the right-most
+/// entry of `X-Forwarded-For` not added by a trusted proxy, since the entries on its left are chosen by the client.
+/// A peer of a private network sending proxy headers is a proxy missing from `TrustedProxies` : its address is shared
+/// by every client, so the client IP is unknown.
+fn client_ip(parts: &Parts) -> Option<String> {
+    let ConnectInfo(peer_address) = parts.extensions.get::<ConnectInfo<SocketAddr>>()?;
+    let default_trusted_proxies = TrustedProxies::default();
+    let trusted_proxies = parts.extensions.get::<TrustedProxies>().unwrap_or(&default_trusted_proxies);
+
+    let mut client_ip = peer_address.ip();
+    let forwarded_for = forwarded_for(&parts.headers);
+    if !trusted_proxies.is_trusted(&client_ip) {
+        let has_proxy_headers = !forwarded_for.is_empty() || parts.headers.contains_key(REAL_IP_HEADER);
+        if has_proxy_headers && TrustedProxies::is_private_network(&client_ip) {
+            return None;
+        }
+        return Some(client_ip.to_string());
+    }
+
+    if forwarded_for.is_empty() {
+        if let Some(real_ip) = header_value(&parts.headers, REAL_IP_HEADER).and_then(|real_ip| real_ip.parse::<IpAddr>().ok()) {
+            client_ip = real_ip;
+        }
+    }
+    for forwarded_ip in forwarded_for.iter().rev() {
+        let Ok(forwarded_ip) = forwarded_ip.parse::<IpAddr>() else {
+            break;
+        };
+        client_ip = forwarded_ip;
+        if !trusted_proxies.is_trusted(&client_ip) {
+            break;
+        }
+    }
+
+    Some(client_ip.to_string())
+}
+
+impl<S> FromRequestParts<S> for ClientInformation
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self {
+            user_agent: header_value(&parts.headers, USER_AGENT.as_str()),
+            client_ip: client_ip(parts),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, SocketAddr};
+    use axum::extract::{ConnectInfo, FromRequestParts};
+    use axum::http::Request;
+    use crate::entities::ClientInformation;
+    use crate::utils::trusted_proxies::TrustedProxies;
+
+    const PROXY_IP: &str = "10.0.0.1";
+
+    async fn client_information_from(peer_ip: &str, forwarded_for: Option<&str>) -> ClientInformation {
+        let mut request = Request::builder().header("user-agent", "Mozilla/5.0");
+        if let Some(forwarded_for) = forwarded_for {
+            request = request.header("x-forwarded-for", forwarded_for);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        parts.extensions.insert(ConnectInfo(SocketAddr::new(peer_ip.parse().unwrap(), 443)));
+        parts.extensions.insert(TrustedProxies::new([PROXY_IP.parse::<IpAddr>().unwrap()]));
+
+        ClientInformation::from_request_parts(&mut parts, &()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_client_information_from_peer() {
+        let client_information = client_information_from("203.0.113.7", None).await;
+
+        assert_eq!(client_information.user_agent, Some("Mozilla/5.0".to_string()));
+        assert_eq!(client_information.client_ip, Some("203.0.113.7".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_for_ignored_from_untrusted_peer() {
+        let client_information = client_information_from("203.0.113.7", Some("198.51.100.1")).await;
+
+        assert_eq!(client_information.client_ip, Some("203.0.113.7".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_for_from_trusted_proxy() {
+        // The left-most entry is chosen by the client, the right-most one was added by the proxy
+        let client_information = client_information_from(PROXY_IP, Some("198.51.100.1, 203.0.113.7")).await;
+        assert_eq!(client_information.client_ip, Some("203.0.113.7".to_string()));
+
+        let client_information = client_information_from(PROXY_IP, Some("203.0.113.7, 10.0.0.1")).await;
+        assert_eq!(client_information.client_ip, Some("203.0.113.7".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_client_ip_unknown_behind_unconfigured_proxy() {
+        let client_information = client_information_from("10.0.0.2", Some("203.0.113.7")).await;
+        assert_eq!(client_information.client_ip, None);
+
+        // Local clients without proxy headers are still tracked
+        let client_information = client_information_from("10.0.0.2", None).await;
+        assert_eq!(client_information.client_ip, Some("10.0.0.2".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_client_information_without_connect_info() {
+        let (mut parts, _) = Request::builder().header("x-forwarded-for", "203.0.113.7").body(()).unwrap().into_parts();
+
+        let client_information = ClientInformation::from_request_parts(&mut parts, &()).await.unwrap();
+
+        assert_eq!(client_information, ClientInformation::default());
+    }
+}
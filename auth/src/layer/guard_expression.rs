@@ -0,0 +1,198 @@
+use std::sync::Arc;
+use axum::http::request::Parts;
+use futures_util::future::BoxFuture;
+use crate::entities::error::AuthError;
+use crate::entities::{AuthMethod, AuthSession, Privileges};
+use crate::layer::resource_owner::ResourceOwner;
+use crate::layer::roles::{is_authorized, RoleCheck};
+
+/// Hook of a `GuardExpression`, checking the session against the request (path, headers, extensions...)
+///
+/// It's object safe to be shared between routes
+pub trait GuardPredicate: Send + Sync {
+    fn check<'a>(&'a self, auth_session: &'a AuthSession, parts: &'a Parts) -> BoxFuture<'a, Result<bool, AuthError>>;
+}
+
+struct FnGuardPredicate<F>(F);
+
+impl<F> GuardPredicate for FnGuardPredicate<F>
+where
+    F: Fn(&AuthSession, &Parts) -> bool + Send + Sync,
+{
+    fn check<'a>(&'a self, auth_session: &'a AuthSession, parts: &'a Parts) -> BoxFuture<'a, Result<bool, AuthError>> {
+        Box::pin(async move { Ok((self.0)(auth_session, parts)) })
+    }
+}
+
+/// Requirement of `AuthGuardLayer`, privileges composed with combinators and predicates
+///
+/// e.g. "Moderator or owner" : `GuardExpression::any_of([Privileges::ModeratorPrivileges.into(), GuardExpression::predicate(is_owner)])`
+#[derive(Clone)]
+pub enum GuardExpression {
+    Privileges(Privileges),
+    /// At least one expression is satisfied, checked in order
+    AnyOf(Vec<GuardExpression>),
+    /// Every expression is satisfied, checked in order
+    AllOf(Vec<GuardExpression>),
+    Not(Box<GuardExpression>),
+    Predicate(Arc<dyn GuardPredicate>),
+}
+
+/// Session of the request checked by a `GuardExpression`
+pub(crate) struct GuardContext<'a> {
+    pub(crate) auth_session: &'a AuthSession,
+    pub(crate) parts: &'a Parts,
+    pub(crate) role_check: &'a Option<Arc<dyn RoleCheck>>,
+    /// A token was sent but not valid : the anonymous session only passes `Privileges::Anonymous`
+    pub(crate) invalid_credentials: bool,
+}
+
+impl GuardExpression {
+    pub fn any_of(expressions: impl IntoIterator<Item=GuardExpression>) -> Self {
+        Self::AnyOf(expressions.into_iter().collect())
+    }
+
+    pub fn all_of(expressions: impl IntoIterator<Item=GuardExpression>) -> Self {
+        Self::AllOf(expressions.into_iter().collect())
+    }
+
+    pub fn not(expression: impl Into<GuardExpression>) -> Self {
+        Self::Not(Box::new(expression.into()))
+    }
+
+    /// Synchronous predicate, implement `GuardPredicate` to check the datastore
+    pub fn predicate(predicate: impl Fn(&AuthSession, &Parts) -> bool + Send + Sync + 'static) -> Self {
+        Self::Predicate(Arc::new(FnGuardPredicate(predicate)))
+    }
+
+    /// Owner of the resource named by a path parameter (e.g. `username`), or users holding the privileges. See `ResourceOwner`
+    ///
+    /// `Privileges::Deny` denies owners too : the route is then closed to everyone
+    pub fn owner_or(path_parameter: &str, privileges: impl Into<GuardExpression>) -> Self {
+        let privileges = privileges.into();
+        if privileges.is_deny() {
+            return privileges;
+        }
+
+        Self::any_of([Self::Predicate(Arc::new(ResourceOwner::new(path_parameter))), privileges])
+    }
+
+    /// `Privileges::Deny` alone, rejected before reading the credentials
+    pub(crate) fn is_deny(&self) -> bool {
+        matches!(self, GuardExpression::Privileges(Privileges::Deny))
+    }
+
+    pub(crate) fn evaluate<'a>(&'a self, context: &'a GuardContext<'a>) -> BoxFuture<'a, Result<bool, AuthError>> {
+        Box::pin(async move {
+            match self {
+                GuardExpression::Privileges(privileges) => Self::is_authorized(context, privileges).await,
+                GuardExpression::AnyOf(expressions) => {
+                    for expression in expressions {
+                        if expression.evaluate(context).await? {
+                            return Ok(true);
+                        }
+                    }
+                    Ok(false)
+                }
+                GuardExpression::AllOf(expressions) => {
+                    for expression in expressions {
+                        if !expression.evaluate(context).await? {
+                            return Ok(false);
+                        }
+                    }
+                    Ok(true)
+                }
+                GuardExpression::Not(expression) => Ok(!expression.evaluate(context).await?),
+                GuardExpression::Predicate(predicate) => predicate.check(context.auth_session, context.parts).await,
+            }
+        })
+    }
+
+    async fn is_authorized(context: &GuardContext<'_>, privileges: &Privileges) -> Result<bool, AuthError> {
+        match &context.auth_session.auth_method {
+            AuthMethod::Anonymous => Ok(match privileges {
+                Privileges::Anonymous => true,
+                Privileges::Allow => !context.invalid_credentials,
+                _ => false,
+            }),
+            AuthMethod::ServicePrincipal(service_principal) | AuthMethod::DelegatedAccess(service_principal) => Ok(service_principal.is_authorized(privileges.clone())),
+            AuthMethod::AccessToken | AuthMethod::PersonalAccessToken => is_authorized(&context.auth_session.roles, privileges, context.role_check).await,
+        }
+    }
+}
+
+impl From<Privileges> for GuardExpression {
+    fn from(privileges: Privileges) -> Self {
+        GuardExpression::Privileges(privileges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+    use crate::entities::{AuthMethod, AuthSession, Privileges, Roles, ServicePrincipal};
+    use super::*;
+
+    fn parts(uri: &str) -> Parts {
+        Request::builder().uri(uri).body(()).unwrap().into_parts().0
+    }
+
+    fn auth_session(username: &str, roles: Vec<Roles>, auth_method: AuthMethod) -> AuthSession {
+        AuthSession { username: username.to_string(), roles, token_identifier: None, auth_method }
+    }
+
+    async fn evaluate(expression: &GuardExpression, auth_session: &AuthSession, uri: &str) -> Result<bool, AuthError> {
+        let parts = parts(uri);
+        let context = GuardContext { auth_session, parts: &parts, role_check: &None, invalid_credentials: false };
+
+        expression.evaluate(&context).await
+    }
+
+    fn moderator_or_owner() -> GuardExpression {
+        GuardExpression::any_of([
+            Privileges::ModeratorPrivileges.into(),
+            GuardExpression::predicate(|auth_session, parts| parts.uri.path() == format!("/users/{}", auth_session.username)),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_any_of_moderator_or_owner() {
+        let user = auth_session("username", vec![Roles::User], AuthMethod::AccessToken);
+        let moderator = auth_session("moderator", vec![Roles::Moderator], AuthMethod::AccessToken);
+
+        assert_eq!(evaluate(&moderator_or_owner(), &user, "/users/username").await, Ok(true));
+        assert_eq!(evaluate(&moderator_or_owner(), &user, "/users/other_username").await, Ok(false));
+        assert_eq!(evaluate(&moderator_or_owner(), &moderator, "/users/other_username").await, Ok(true));
+    }
+
+    #[tokio::test]
+    async fn test_all_of_and_not() {
+        let expression = GuardExpression::all_of([
+            Privileges::Authenticated.into(),
+            GuardExpression::not(Privileges::ModeratorPrivileges),
+        ]);
+        let user = auth_session("username", vec![Roles::User], AuthMethod::AccessToken);
+        let admin = auth_session("admin", vec![Roles::Admin], AuthMethod::AccessToken);
+        let anonymous = auth_session("anonymous", Vec::new(), AuthMethod::Anonymous);
+
+        assert_eq!(evaluate(&expression, &user, "/").await, Ok(true));
+        assert_eq!(evaluate(&expression, &admin, "/").await, Ok(false));
+        assert_eq!(evaluate(&expression, &anonymous, "/").await, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn test_privileges_of_anonymous_and_service_sessions() {
+        let anonymous = auth_session("anonymous", Vec::new(), AuthMethod::Anonymous);
+        let service = auth_session("gateway", Vec::new(), AuthMethod::ServicePrincipal(ServicePrincipal { client_id: "gateway".to_string(), scopes: vec!["users:read".to_string()] }));
+        let parts = parts("/");
+        let invalid_credentials = GuardContext { auth_session: &anonymous, parts: &parts, role_check: &None, invalid_credentials: true };
+
+        assert_eq!(evaluate(&Privileges::Allow.into(), &anonymous, "/").await, Ok(true));
+        assert_eq!(evaluate(&Privileges::Anonymous.into(), &anonymous, "/").await, Ok(true));
+        assert_eq!(evaluate(&Privileges::Authenticated.into(), &anonymous, "/").await, Ok(false));
+        assert_eq!(GuardExpression::from(Privileges::Allow).evaluate(&invalid_credentials).await, Ok(false));
+        assert_eq!(GuardExpression::from(Privileges::Anonymous).evaluate(&invalid_credentials).await, Ok(true));
+        assert_eq!(evaluate(&Privileges::Scope("users:read".to_string()).into(), &service, "/").await, Ok(true));
+        assert_eq!(evaluate(&Privileges::Authenticated.into(), &service, "/").await, Ok(false));
+    }
+}
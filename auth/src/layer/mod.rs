@@ -1 +1,10 @@
-pub mod claims;
\ No newline at end of file
+pub mod claims;
+pub mod client_information;
+
+pub mod revocation;
+pub mod personal_access_tokens;
+
+pub mod roles;
+pub mod guard_expression;
+
+pub mod resource_owner;
@@ -0,0 +1,140 @@
+use std::time::Duration;
+use futures_util::future::BoxFuture;
+use mongodb::bson::DateTime;
+use crate::datastore::{AuthDatastore, PersonalAccessTokenDatastore};
+use crate::entities::error::AuthError;
+use crate::entities::{AuthMethod, AuthSession, PersonalAccessToken};
+
+/// Check used by `AuthGuardLayer` to authenticate requests with a personal access token
+///
+/// It's object safe to be shared between routers of every modules
+pub trait PersonalAccessTokenCheck: Send + Sync {
+    fn authenticate<'a>(&'a self, token: &'a str) -> BoxFuture<'a, Result<AuthSession, AuthError>>;
+}
+
+/// Look up the hash of the personal access token and the current role of its owner
+///
+/// The last used date is only written when older than `LAST_USED_PRECISION` to avoid a write on each request.
+pub struct PersonalAccessTokenChecker<AuthDatastoreImpl: AuthDatastore, PersonalAccessTokenDatastoreImpl: PersonalAccessTokenDatastore> {
+    auth_datastore: AuthDatastoreImpl,
+    personal_access_token_datastore: PersonalAccessTokenDatastoreImpl,
+}
+
+impl<AuthDatastoreImpl: AuthDatastore, PersonalAccessTokenDatastoreImpl: PersonalAccessTokenDatastore> PersonalAccessTokenChecker<AuthDatastoreImpl, PersonalAccessTokenDatastoreImpl> {
+    const LAST_USED_PRECISION: Duration = Duration::from_secs(60);
+
+    pub fn new(auth_datastore: AuthDatastoreImpl, personal_access_token_datastore: PersonalAccessTokenDatastoreImpl) -> Self {
+        Self {
+            auth_datastore,
+            personal_access_token_datastore,
+        }
+    }
+}
+
+impl<AuthDatastoreImpl, PersonalAccessTokenDatastoreImpl> PersonalAccessTokenCheck for PersonalAccessTokenChecker<AuthDatastoreImpl, PersonalAccessTokenDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore + Send + Sync,
+    PersonalAccessTokenDatastoreImpl: PersonalAccessTokenDatastore + Send + Sync,
+{
+    fn authenticate<'a>(&'a self, token: &'a str) -> BoxFuture<'a, Result<AuthSession, AuthError>> {
+        Box::pin(async move {
+            let personal_access_token = self.personal_access_token_datastore.get_personal_access_token(&PersonalAccessToken::hash_token(token))
+                .await
+                .map_err(|_| AuthError::ServerError)?
+                .filter(|personal_access_token| personal_access_token.is_valid())
+                .ok_or(AuthError::InvalidToken)?;
+
+            // Role is read on each request : a demoted user can't keep privileges through its tokens
+            let user = self.auth_datastore.get_user_by_username(&personal_access_token.username)
+                .await
+                .map_err(|_| AuthError::ServerError)?
+                .ok_or(AuthError::InvalidToken)?;
+
+            let last_used_threshold = DateTime::from_millis(DateTime::now().timestamp_millis() - Self::LAST_USED_PRECISION.as_millis() as i64);
+            if personal_access_token.last_used_at.is_none_or(|last_used_at| last_used_at < last_used_threshold) {
+                self.personal_access_token_datastore.update_last_used(&personal_access_token.token_id)
+                    .await
+                    .map_err(|_| AuthError::ServerError)?;
+            }
+
+            Ok(AuthSession {
+                username: user.username,
+                roles: user.roles,
+                token_identifier: Some(personal_access_token.token_id),
+                auth_method: AuthMethod::PersonalAccessToken,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+    use fake::{Fake, Faker};
+    use mongodb::bson::DateTime;
+    use crate::datastore::{MockAuthDatastore, MockPersonalAccessTokenDatastore};
+    use crate::entities::error::AuthError;
+    use crate::entities::{AuthMethod, PersonalAccessToken, Roles, UserCredentials};
+    use crate::layer::personal_access_tokens::{PersonalAccessTokenCheck, PersonalAccessTokenChecker};
+
+    #[tokio::test]
+    async fn test_authenticate_with_personal_access_token() {
+        let (personal_access_token, token) = PersonalAccessToken::generate("username", "ci", None);
+        let token_id = personal_access_token.token_id.clone();
+        let mut mock_personal_access_token_datastore = MockPersonalAccessTokenDatastore::new();
+        mock_personal_access_token_datastore.expect_get_personal_access_token()
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(personal_access_token.clone())))));
+        mock_personal_access_token_datastore.expect_update_last_used()
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        mock_auth_datastore.expect_get_user_by_username()
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(Some(UserCredentials { username: "username".to_string(), roles: vec![Roles::Admin], ..Faker.fake() })))));
+
+        let personal_access_token_checker = PersonalAccessTokenChecker::new(mock_auth_datastore, mock_personal_access_token_datastore);
+        let auth_session = personal_access_token_checker.authenticate(&token).await.unwrap();
+
+        assert_eq!(auth_session.username, "username");
+        assert_eq!(auth_session.roles, vec![Roles::Admin]);
+        assert_eq!(auth_session.token_identifier, Some(token_id));
+        assert_eq!(auth_session.auth_method, AuthMethod::PersonalAccessToken);
+    }
+
+    #[tokio::test]
+    async fn test_recently_used_personal_access_token_is_not_updated() {
+        let (personal_access_token, token) = PersonalAccessToken::generate("username", "ci", None);
+        let personal_access_token = PersonalAccessToken { last_used_at: Some(DateTime::now()), ..personal_access_token };
+        let mut mock_personal_access_token_datastore = MockPersonalAccessTokenDatastore::new();
+        mock_personal_access_token_datastore.expect_get_personal_access_token()
+            .returning(move |_| Box::pin(future::ready(Ok(Some(personal_access_token.clone())))));
+        mock_personal_access_token_datastore.expect_update_last_used().never();
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        mock_auth_datastore.expect_get_user_by_username()
+            .returning(|_| Box::pin(future::ready(Ok(Some(Faker.fake())))));
+
+        let personal_access_token_checker = PersonalAccessTokenChecker::new(mock_auth_datastore, mock_personal_access_token_datastore);
+
+        assert!(personal_access_token_checker.authenticate(&token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_expired_or_revoked_personal_access_token_is_rejected() {
+        let (personal_access_token, token) = PersonalAccessToken::generate("username", "ci", Some(DateTime::from_millis(DateTime::now().timestamp_millis() - 1000)));
+        let revoked_personal_access_token = PersonalAccessToken { expired_at: None, revoked_at: Some(DateTime::now()), ..personal_access_token.clone() };
+        let mut mock_personal_access_token_datastore = MockPersonalAccessTokenDatastore::new();
+        mock_personal_access_token_datastore.expect_get_personal_access_token()
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(personal_access_token.clone())))));
+        mock_personal_access_token_datastore.expect_get_personal_access_token()
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(revoked_personal_access_token.clone())))));
+        mock_personal_access_token_datastore.expect_update_last_used().never();
+
+        let personal_access_token_checker = PersonalAccessTokenChecker::new(MockAuthDatastore::new(), mock_personal_access_token_datastore);
+
+        assert_eq!(personal_access_token_checker.authenticate(&token).await.map(|_| ()), Err(AuthError::InvalidToken));
+        assert_eq!(personal_access_token_checker.authenticate(&token).await.map(|_| ()), Err(AuthError::InvalidToken));
+    }
+}
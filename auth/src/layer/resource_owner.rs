@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use axum::extract::{FromRequestParts, Path};
+use axum::http::request::Parts;
+use futures_util::future::BoxFuture;
+use crate::entities::error::AuthError;
+use crate::entities::{AuthMethod, AuthSession};
+use crate::layer::guard_expression::GuardPredicate;
+
+/// Allow users owning the resource named by a path parameter, e.g. `username` for `/users/{username}`
+///
+/// Only users logged in with an access token or a personal access token own resources, services and third-party apps don't.
+/// See `GuardExpression::owner_or` to also allow privileged users.
+pub struct ResourceOwner {
+    path_parameter: String,
+}
+
+impl ResourceOwner {
+    pub fn new(path_parameter: &str) -> Self {
+        Self { path_parameter: path_parameter.to_string() }
+    }
+}
+
+impl GuardPredicate for ResourceOwner {
+    fn check<'a>(&'a self, auth_session: &'a AuthSession, parts: &'a Parts) -> BoxFuture<'a, Result<bool, AuthError>> {
+        Box::pin(async move {
+            if !matches!(auth_session.auth_method, AuthMethod::AccessToken | AuthMethod::PersonalAccessToken) {
+                return Ok(false);
+            }
+
+            // Path parameters are known once the route matched : the guard must be a layer of the route
+            let Path(path_parameters) = Path::<HashMap<String, String>>::from_request_parts(&mut parts.clone(), &())
+                .await
+                .map_err(|_| AuthError::ServerError)?;
+            let owner = path_parameters.get(&self.path_parameter).ok_or(AuthError::ServerError)?;
+
+            Ok(owner == &auth_session.username)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use fake::{Fake, Faker};
+    use tower::ServiceExt;
+    use crate::entities::{Privileges, Roles, Token, UserCredentials};
+    use crate::layer::claims::AuthGuardLayer;
+    use crate::layer::guard_expression::GuardExpression;
+    use crate::utils::auth_config::AuthConfig;
+
+    async fn get_user(path: &str, username: &str, roles: Vec<Roles>) -> StatusCode {
+        get_user_with_privileges(path, username, roles, Privileges::ModeratorPrivileges).await
+    }
+
+    async fn get_user_with_privileges(path: &str, username: &str, roles: Vec<Roles>, privileges: Privileges) -> StatusCode {
+        let user = UserCredentials { username: username.to_string(), roles, ..Faker.fake() };
+        let (access_token, _, _) = Token::generate_tokens(&AuthConfig::fake(), &user).await.expect("Unable generate tokens");
+        let guard = AuthGuardLayer::new(AuthConfig::fake(), GuardExpression::owner_or("username", privileges));
+        let router: Router = Router::new()
+            .route("/users/{username}", get(|| async { "profile" }).layer(guard.clone()))
+            .route("/clients/{client_id}", get(|| async { "client" }).layer(guard));
+
+        let request = Request::builder().uri(path).header("Authorization", format!("Bearer {access_token}")).body(Body::empty()).unwrap();
+
+        router.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_owner_or_privileged_user_allowed() {
+        assert_eq!(get_user("/users/username", "username", vec![Roles::User]).await, StatusCode::OK);
+        assert_eq!(get_user("/users/username", "moderator", vec![Roles::Moderator]).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_other_user_unauthorized() {
+        assert_eq!(get_user("/users/username", "other_username", vec![Roles::User]).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_missing_path_parameter_rejected() {
+        assert_eq!(get_user("/clients/username", "username", vec![Roles::User]).await, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_deny_rejects_owner() {
+        assert_eq!(get_user_with_privileges("/users/username", "username", vec![Roles::User], Privileges::Deny).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(get_user_with_privileges("/users/username", "moderator", vec![Roles::SuperAdmin], Privileges::Deny).await, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use futures_util::future::BoxFuture;
+use crate::datastore::TokenDatastore;
+use crate::entities::error::AuthError;
+
+/// Check used by `AuthGuardLayer` to reject access tokens of revoked sessions
+///
+/// It's object safe to be shared between routers of every modules
+pub trait TokenRevocationCheck: Send + Sync {
+    fn is_revoked<'a>(&'a self, token_access_identifier: &'a str) -> BoxFuture<'a, Result<bool, AuthError>>;
+}
+
+/// Look up the access token identifier (`jti`) in the `TokenDatastore`
+///
+/// Results are kept in memory during `cache_ttl` to avoid a datastore request on each call.
+/// A revoked session can so stay usable up to `cache_ttl` after revocation.
+pub struct TokenRevocationChecker<TokenDatastoreImpl: TokenDatastore> {
+    token_datastore: TokenDatastoreImpl,
+    cache_ttl: Duration,
+    cache: Mutex<HashMap<String, (Instant, bool)>>,
+}
+
+impl<TokenDatastoreImpl: TokenDatastore> TokenRevocationChecker<TokenDatastoreImpl> {
+    const MAX_CACHE_ENTRIES: usize = 10_000;
+
+    pub fn new(token_datastore: TokenDatastoreImpl, cache_ttl: Duration) -> Self {
+        Self {
+            token_datastore,
+            cache_ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get_cached(&self, token_access_identifier: &str) -> Option<bool> {
+        let cache = self.cache.lock().expect("Revocation cache is poisoned");
+
+        cache.get(token_access_identifier)
+            .filter(|(cached_at, _)| cached_at.elapsed() < self.cache_ttl)
+            .map(|(_, is_revoked)| *is_revoked)
+    }
+
+    fn set_cached(&self, token_access_identifier: &str, is_revoked: bool) {
+        let mut cache = self.cache.lock().expect("Revocation cache is poisoned");
+
+        if cache.len() >= Self::MAX_CACHE_ENTRIES {
+            cache.retain(|_, (cached_at, _)| cached_at.elapsed() < self.cache_ttl);
+        }
+
+        cache.insert(token_access_identifier.to_string(), (Instant::now(), is_revoked));
+    }
+}
+
+impl<TokenDatastoreImpl> TokenRevocationCheck for TokenRevocationChecker<TokenDatastoreImpl>
+where
+    TokenDatastoreImpl: TokenDatastore + Send + Sync,
+{
+    fn is_revoked<'a>(&'a self, token_access_identifier: &'a str) -> BoxFuture<'a, Result<bool, AuthError>> {
+        Box::pin(async move {
+            if let Some(is_revoked) = self.get_cached(token_access_identifier) {
+                return Ok(is_revoked);
+            }
+
+            let token = self.token_datastore.get_token_by_access_identifier(token_access_identifier)
+                .await
+                .map_err(|_| AuthError::ServerError)?;
+
+            // Unknown token is handled as revoked : it can't be linked to a session
+            let is_revoked = token.map(|token| token.revoked_at.is_some()).unwrap_or(true);
+            self.set_cached(token_access_identifier, is_revoked);
+
+            Ok(is_revoked)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+    use std::time::Duration;
+    use fake::{Fake, Faker};
+    use mongodb::bson::DateTime;
+    use crate::datastore::MockTokenDatastore;
+    use crate::entities::Token;
+    use crate::layer::revocation::{TokenRevocationCheck, TokenRevocationChecker};
+
+    #[tokio::test]
+    async fn test_revoked_token_is_cached() {
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        mock_tokens_datastore.expect_get_token_by_access_identifier()
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(Some(Token { revoked_at: Some(DateTime::now()), ..Faker.fake() })))));
+
+        let revocation_checker = TokenRevocationChecker::new(mock_tokens_datastore, Duration::from_secs(60));
+
+        assert_eq!(revocation_checker.is_revoked("token_id").await, Ok(true));
+        assert_eq!(revocation_checker.is_revoked("token_id").await, Ok(true));
+    }
+
+    #[tokio::test]
+    async fn test_cache_expired_request_datastore() {
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        mock_tokens_datastore.expect_get_token_by_access_identifier()
+            .times(2)
+            .returning(|_| Box::pin(future::ready(Ok(Some(Faker.fake())))));
+
+        let revocation_checker = TokenRevocationChecker::new(mock_tokens_datastore, Duration::ZERO);
+
+        assert_eq!(revocation_checker.is_revoked("token_id").await, Ok(false));
+        assert_eq!(revocation_checker.is_revoked("token_id").await, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_token_is_revoked() {
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        mock_tokens_datastore.expect_get_token_by_access_identifier()
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(None))));
+
+        let revocation_checker = TokenRevocationChecker::new(mock_tokens_datastore, Duration::from_secs(60));
+
+        assert_eq!(revocation_checker.is_revoked("unknown_token_id").await, Ok(true));
+    }
+}
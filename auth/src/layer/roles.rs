@@ -0,0 +1,135 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use futures_util::future::BoxFuture;
+use crate::datastore::RoleDatastore;
+use crate::entities::error::AuthError;
+use crate::entities::{Privileges, Role, Roles};
+
+/// Role definitions used by `AuthGuardLayer` for runtime roles and `Privileges::Permission`
+///
+/// It's object safe to be shared between routers of every modules
+pub trait RoleCheck: Send + Sync {
+    /// Every role defined, built-in roles included
+    fn get_roles(&self) -> BoxFuture<'_, Result<Vec<Role>, AuthError>>;
+}
+
+/// Read the roles of the `RoleDatastore`, completed with `Role::defaults`
+///
+/// Roles are kept in memory during `cache_ttl` to avoid a datastore request on each call.
+/// A role changed can so grant its previous permissions up to `cache_ttl` after the change.
+pub struct RoleChecker<RoleDatastoreImpl: RoleDatastore> {
+    role_datastore: RoleDatastoreImpl,
+    cache_ttl: Duration,
+    cache: Mutex<Option<(Instant, Vec<Role>)>>,
+}
+
+impl<RoleDatastoreImpl: RoleDatastore> RoleChecker<RoleDatastoreImpl> {
+    pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+    pub fn new(role_datastore: RoleDatastoreImpl, cache_ttl: Duration) -> Self {
+        Self {
+            role_datastore,
+            cache_ttl,
+            cache: Mutex::new(None),
+        }
+    }
+
+    fn get_cached(&self) -> Option<Vec<Role>> {
+        let cache = self.cache.lock().expect("Role cache is poisoned");
+
+        cache.as_ref()
+            .filter(|(cached_at, _)| cached_at.elapsed() < self.cache_ttl)
+            .map(|(_, roles)| roles.clone())
+    }
+
+    fn set_cached(&self, roles: Vec<Role>) {
+        *self.cache.lock().expect("Role cache is poisoned") = Some((Instant::now(), roles));
+    }
+}
+
+impl<RoleDatastoreImpl> RoleCheck for RoleChecker<RoleDatastoreImpl>
+where
+    RoleDatastoreImpl: RoleDatastore + Send + Sync,
+{
+    fn get_roles(&self) -> BoxFuture<'_, Result<Vec<Role>, AuthError>> {
+        Box::pin(async move {
+            if let Some(roles) = self.get_cached() {
+                return Ok(roles);
+            }
+
+            let roles = Role::with_defaults(self.role_datastore.get_roles().await.map_err(|_| AuthError::ServerError)?);
+            self.set_cached(roles.clone());
+
+            Ok(roles)
+        })
+    }
+}
+
+/// Check the roles of a user, the role definitions are only read for runtime roles and permissions.
+/// Without `RoleCheck`, only the default roles are known
+pub(crate) async fn is_authorized(roles: &[Roles], privileges: &Privileges, role_check: &Option<Arc<dyn RoleCheck>>) -> Result<bool, AuthError> {
+    if roles.iter().any(|role| role.is_authorized(privileges.clone())) {
+        return Ok(true);
+    }
+    if !matches!(privileges, Privileges::Permission(_)) && roles.iter().all(Roles::is_built_in) {
+        return Ok(false);
+    }
+
+    let definitions = match role_check {
+        Some(role_check) => role_check.get_roles().await?,
+        None => Role::defaults(),
+    };
+
+    Ok(Role::is_authorized(roles, &definitions, privileges))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use crate::datastore::MockRoleDatastore;
+    use crate::entities::{Privileges, Role, Roles};
+    use crate::layer::roles::{is_authorized, RoleCheck, RoleChecker};
+
+    fn support_role() -> Role {
+        Role::new(Roles::Custom("support".to_string()), Some(Roles::Moderator), vec!["user:ban".to_string()])
+    }
+
+    #[tokio::test]
+    async fn test_roles_are_cached_with_defaults() {
+        let mut mock_role_datastore = MockRoleDatastore::new();
+        mock_role_datastore.expect_get_roles()
+            .times(1)
+            .returning(|| Box::pin(future::ready(Ok(vec![support_role()]))));
+
+        let role_checker = RoleChecker::new(mock_role_datastore, Duration::from_secs(60));
+
+        assert_eq!(role_checker.get_roles().await.unwrap().len(), 5);
+        assert_eq!(role_checker.get_roles().await.unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_is_authorized_with_runtime_role() {
+        let mut mock_role_datastore = MockRoleDatastore::new();
+        mock_role_datastore.expect_get_roles()
+            .returning(|| Box::pin(future::ready(Ok(vec![support_role()]))));
+        let role_check: Option<Arc<dyn RoleCheck>> = Some(Arc::new(RoleChecker::new(mock_role_datastore, Duration::from_secs(60))));
+        let roles = [Roles::User, Roles::Custom("support".to_string())];
+
+        assert_eq!(is_authorized(&roles, &Privileges::Permission("user:ban".to_string()), &role_check).await, Ok(true));
+        assert_eq!(is_authorized(&roles, &Privileges::ModeratorPrivileges, &role_check).await, Ok(true));
+        assert_eq!(is_authorized(&roles, &Privileges::AdminPrivileges, &role_check).await, Ok(false));
+        assert_eq!(is_authorized(&roles, &Privileges::Permission("user:ban".to_string()), &None).await, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn test_built_in_roles_checked_without_datastore() {
+        let mut mock_role_datastore = MockRoleDatastore::new();
+        mock_role_datastore.expect_get_roles().never();
+        let role_check: Option<Arc<dyn RoleCheck>> = Some(Arc::new(RoleChecker::new(mock_role_datastore, Duration::from_secs(60))));
+
+        assert_eq!(is_authorized(&[Roles::Admin], &Privileges::ModeratorPrivileges, &role_check).await, Ok(true));
+        assert_eq!(is_authorized(&[Roles::Moderator], &Privileges::AdminPrivileges, &role_check).await, Ok(false));
+    }
+}
@@ -0,0 +1,319 @@
+use chrono::Utc;
+use jsonwebtoken::jwk::JwkSet;
+use mongodb::bson::DateTime;
+use reqwest::Url;
+use crate::datastore::{OAuthAuthorizationDatastore, OAuthClientDatastore};
+use crate::entities::error::{AuthError, OAuthError};
+use crate::entities::{AuthorizationCode, OAuthClient, OAuthConsent, Token};
+use crate::services::{AuthAuthorizationCodeService, AuthorizationServerService, OAuthClientService};
+use crate::utils::authorization_server::AuthorizationServer;
+use crate::views::payload::AuthorizationRequestPayload;
+use crate::views::response::{AuthorizationBody, AuthorizationCodeTokenBody, AuthorizationRedirectBody, ConsentRequiredBody, OAuthConsentDetails, OpenIdConfiguration, UserInfoBody};
+
+impl<OAuthClientDatastoreImpl, OAuthAuthorizationDatastoreImpl> AuthorizationServerService<OAuthClientDatastoreImpl, OAuthAuthorizationDatastoreImpl>
+where
+    OAuthClientDatastoreImpl: OAuthClientDatastore,
+    OAuthAuthorizationDatastoreImpl: OAuthAuthorizationDatastore,
+{
+    const CODE_RESPONSE_TYPE: &'static str = "code";
+    const S256_CODE_CHALLENGE_METHOD: &'static str = "S256";
+
+    fn redirect(redirect_uri: &str, parameters: &[(&str, &str)], state: Option<&str>) -> Result<AuthorizationBody, OAuthError> {
+        let mut redirect_url = Url::parse(redirect_uri).map_err(|_| OAuthError::InvalidRequest)?;
+        redirect_url.query_pairs_mut().extend_pairs(parameters);
+        if let Some(state) = state {
+            redirect_url.query_pairs_mut().append_pair("state", state);
+        }
+
+        Ok(AuthorizationBody::Redirect(AuthorizationRedirectBody { redirect_to: redirect_url.to_string() }))
+    }
+
+    /// Scopes of OpenID Connect, and the ones the client can request for itself
+    fn is_allowed_scope(oauth_client: &OAuthClient, scope: &String) -> bool {
+        AuthorizationServer::supported_scopes().contains(scope) || oauth_client.scopes.contains(scope)
+    }
+
+    /// Once the redirection URI is trusted, errors are given to the app by redirection
+    async fn authorize_client(&self, username: &str, oauth_client: &OAuthClient, payload: &AuthorizationRequestPayload) -> Result<AuthorizationBody, OAuthError> {
+        if payload.response_type != Self::CODE_RESPONSE_TYPE {
+            return Err(OAuthError::UnsupportedResponseType);
+        }
+        // PKCE is required : only S256, the plain method doesn't protect the code
+        let code_challenge = payload.code_challenge.as_ref()
+            .filter(|_| payload.code_challenge_method.as_deref() == Some(Self::S256_CODE_CHALLENGE_METHOD))
+            .ok_or(OAuthError::InvalidRequest)?;
+
+        let scopes = OAuthClient::split_scopes(std::slice::from_ref(&payload.scope));
+        if scopes.is_empty() || !scopes.iter().all(|scope| Self::is_allowed_scope(oauth_client, scope)) {
+            return Err(OAuthError::InvalidScope);
+        }
+
+        let oauth_consent = self.oauth_authorization_datastore.get_consent(username, &oauth_client.client_id).await.map_err(|_| OAuthError::ServerError)?;
+        match (payload.consent, oauth_consent) {
+            (Some(false), _) => return Err(OAuthError::AccessDenied),
+            (Some(true), oauth_consent) => {
+                let (mut consented_scopes, created_at) = oauth_consent
+                    .map(|oauth_consent| (oauth_consent.scopes, oauth_consent.created_at))
+                    .unwrap_or_else(|| (Vec::new(), DateTime::now()));
+                consented_scopes.extend(scopes.iter().filter(|scope| !consented_scopes.contains(scope)).cloned().collect::<Vec<String>>());
+
+                self.oauth_authorization_datastore.save_consent(OAuthConsent {
+                    id: None,
+                    username: username.to_string(),
+                    client_id: oauth_client.client_id.clone(),
+                    scopes: consented_scopes,
+                    created_at,
+                    last_modified_at: DateTime::now(),
+                }).await.map_err(|_| OAuthError::ServerError)?;
+            }
+            (None, Some(oauth_consent)) if oauth_consent.covers(&scopes) => {}
+            (None, _) => {
+                return Ok(AuthorizationBody::ConsentRequired(ConsentRequiredBody {
+                    client_id: oauth_client.client_id.clone(),
+                    client_name: oauth_client.name.clone(),
+                    scopes,
+                }));
+            }
+        }
+
+        let (authorization_code, code) = AuthorizationCode::generate(&oauth_client.client_id, username, &payload.redirect_uri, scopes, code_challenge, payload.nonce.clone());
+        self.oauth_authorization_datastore.add_authorization_code(authorization_code).await.map_err(|_| OAuthError::ServerError)?;
+
+        Self::redirect(&payload.redirect_uri, &[("code", &code)], payload.state.as_deref())
+    }
+}
+
+impl<OAuthClientDatastoreImpl, OAuthAuthorizationDatastoreImpl> AuthAuthorizationCodeService for AuthorizationServerService<OAuthClientDatastoreImpl, OAuthAuthorizationDatastoreImpl>
+where
+    OAuthClientDatastoreImpl: OAuthClientDatastore,
+    OAuthAuthorizationDatastoreImpl: OAuthAuthorizationDatastore,
+{
+    async fn authorize(&self, username: &str, payload: AuthorizationRequestPayload) -> Result<AuthorizationBody, OAuthError> {
+        // RFC 6749 section 4.1.2.1 : never redirect to an unknown redirection URI
+        let oauth_client = self.oauth_client_datastore.get_client(&payload.client_id)
+            .await
+            .map_err(|_| OAuthError::ServerError)?
+            .filter(|oauth_client| oauth_client.is_valid_redirect_uri(&payload.redirect_uri))
+            .ok_or(OAuthError::InvalidRequest)?;
+
+        match self.authorize_client(username, &oauth_client, &payload).await {
+            Ok(authorization_body) => Ok(authorization_body),
+            Err(error) => Self::redirect(&payload.redirect_uri, &[("error", &error.to_string())], payload.state.as_deref()),
+        }
+    }
+
+    async fn get_consents(&self, username: &str) -> Result<Vec<OAuthConsentDetails>, AuthError> {
+        let oauth_consents = self.oauth_authorization_datastore.get_consents(username)
+            .await
+            .map_err(|_| AuthError::ServerError)?;
+
+        Ok(oauth_consents.into_iter().map(OAuthConsentDetails::from).collect())
+    }
+
+    async fn revoke_consent(&self, username: &str, client_id: &str) -> Result<(), AuthError> {
+        self.oauth_authorization_datastore.delete_consent(username, client_id).await.map_err(|_| AuthError::NotFound)
+    }
+
+    async fn issue_authorization_code_token(&self, client_id: &str, client_secret: &str, code: &str, redirect_uri: &str, code_verifier: &str) -> Result<AuthorizationCodeTokenBody, OAuthError> {
+        let oauth_client = self.oauth_client_datastore.get_client(client_id)
+            .await
+            .map_err(|_| OAuthError::ServerError)?
+            .filter(|oauth_client| oauth_client.is_valid_secret(client_secret))
+            .ok_or(OAuthError::InvalidClient)?;
+
+        let authorization_code = self.oauth_authorization_datastore.take_authorization_code(&AuthorizationCode::hash_code(code))
+            .await
+            .map_err(|_| OAuthError::ServerError)?
+            .filter(|authorization_code| authorization_code.is_valid(client_id, redirect_uri, code_verifier))
+            .ok_or(OAuthError::InvalidGrant)?;
+
+        let (expired_at, access_token) = Token::generate_delegated_access_token(&self.auth_config, &oauth_client, &authorization_code.username, &authorization_code.scopes).await.map_err(|_| OAuthError::ServerError)?;
+        let id_token = match authorization_code.scopes.iter().any(|scope| scope == AuthorizationServer::OPENID_SCOPE) {
+            true => {
+                let user_claims = self.authorization_server.get_user_claims(&authorization_code.username, &authorization_code.scopes).await.map_err(|_| OAuthError::ServerError)?;
+                Some(self.authorization_server.sign_id_token(&self.auth_config, client_id, &authorization_code.username, authorization_code.nonce, user_claims).map_err(|_| OAuthError::ServerError)?)
+            }
+            false => None,
+        };
+
+        Ok(AuthorizationCodeTokenBody {
+            access_token,
+            token_type: OAuthClientService::<OAuthClientDatastoreImpl>::TOKEN_TYPE.to_string(),
+            expires_in: (expired_at - Utc::now()).num_seconds(),
+            scope: authorization_code.scopes.join(" "),
+            id_token,
+        })
+    }
+
+    async fn get_user_info(&self, username: &str, scopes: &[String]) -> Result<UserInfoBody, AuthError> {
+        Ok(UserInfoBody {
+            sub: username.to_string(),
+            user_claims: self.authorization_server.get_user_claims(username, scopes).await?,
+        })
+    }
+
+    fn get_openid_configuration(&self) -> OpenIdConfiguration {
+        self.authorization_server.discovery_document()
+    }
+
+    fn get_json_web_key_set(&self) -> JwkSet {
+        AuthorizationServer::json_web_key_set(&self.auth_config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use std::sync::{Arc, Mutex};
+    use base64::Engine;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use mockall::predicate::eq;
+    use mongodb::bson::DateTime;
+    use reqwest::Url;
+    use sha2::{Digest, Sha256};
+    use crate::datastore::{MockOAuthAuthorizationDatastore, MockOAuthClientDatastore};
+    use crate::entities::error::OAuthError;
+    use crate::entities::{AuthorizationCode, OAuthClient, OAuthConsent, TokenType};
+    use crate::services::{AuthAuthorizationCodeService, MockAuthorizationServerService};
+    use crate::utils::auth_claims::AuthClaims;
+    use crate::utils::authorization_server::AuthorizationServer;
+    use crate::utils::validate_token::{IntoClaims, TokenString};
+    use crate::views::payload::AuthorizationRequestPayload;
+    use crate::views::response::AuthorizationBody;
+
+    const REDIRECT_URI: &str = "https://app.example.com/callback";
+    const CODE_VERIFIER: &str = "code_verifier_of_the_third_party_app";
+
+    fn oauth_client() -> (OAuthClient, String) {
+        OAuthClient::generate("app", Vec::new(), vec![REDIRECT_URI.to_string()])
+    }
+
+    fn authorization_request(oauth_client: &OAuthClient, consent: Option<bool>) -> AuthorizationRequestPayload {
+        AuthorizationRequestPayload {
+            response_type: "code".to_string(),
+            client_id: oauth_client.client_id.clone(),
+            redirect_uri: REDIRECT_URI.to_string(),
+            scope: "openid email".to_string(),
+            state: Some("state".to_string()),
+            code_challenge: Some(URL_SAFE_NO_PAD.encode(Sha256::digest(CODE_VERIFIER.as_bytes()))),
+            code_challenge_method: Some("S256".to_string()),
+            nonce: Some("nonce".to_string()),
+            consent,
+        }
+    }
+
+    fn authorization_server_service(oauth_client: &OAuthClient, mock_oauth_authorization_datastore: MockOAuthAuthorizationDatastore) -> MockAuthorizationServerService {
+        let mut mock_oauth_client_datastore = MockOAuthClientDatastore::new();
+        let oauth_client = oauth_client.clone();
+        mock_oauth_client_datastore.expect_get_client().returning(move |client_id| Box::pin(future::ready(Ok(Some(oauth_client.clone()).filter(|oauth_client| oauth_client.client_id == client_id)))));
+
+        MockAuthorizationServerService::new(mock_oauth_client_datastore, mock_oauth_authorization_datastore, AuthorizationServer::default(), AuthConfig::fake())
+    }
+
+    /// Authorization codes are kept in the mock like the datastore would, to be exchanged by the token endpoint
+    fn expect_authorization_code(mock_oauth_authorization_datastore: &mut MockOAuthAuthorizationDatastore) {
+        let authorization_codes: Arc<Mutex<Vec<AuthorizationCode>>> = Arc::new(Mutex::new(Vec::new()));
+        let added_authorization_codes = authorization_codes.clone();
+
+        mock_oauth_authorization_datastore.expect_add_authorization_code().times(1).returning(move |authorization_code| {
+            added_authorization_codes.lock().unwrap().push(authorization_code);
+            Box::pin(future::ready(Ok(())))
+        });
+        mock_oauth_authorization_datastore.expect_take_authorization_code().returning(move |code_hash| {
+            let mut authorization_codes = authorization_codes.lock().unwrap();
+            let authorization_code = authorization_codes.iter().position(|authorization_code| authorization_code.code_hash == code_hash).map(|position| authorization_codes.remove(position));
+            Box::pin(future::ready(Ok(authorization_code)))
+        });
+    }
+
+    fn redirect_parameter(authorization_body: &AuthorizationBody, name: &str) -> Option<String> {
+        let AuthorizationBody::Redirect(authorization_redirect_body) = authorization_body else {
+            panic!("The user must be redirected to the app");
+        };
+
+        Url::parse(&authorization_redirect_body.redirect_to).unwrap().query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_authorize_ask_consent_first() {
+        let (oauth_client, _) = oauth_client();
+        let mut mock_oauth_authorization_datastore = MockOAuthAuthorizationDatastore::new();
+        mock_oauth_authorization_datastore.expect_get_consent().with(eq("juliana"), eq(oauth_client.client_id.clone())).times(1).returning(|_, _| Box::pin(future::ready(Ok(None))));
+        mock_oauth_authorization_datastore.expect_add_authorization_code().never();
+        let authorization_server_service = authorization_server_service(&oauth_client, mock_oauth_authorization_datastore);
+
+        let authorization_body = authorization_server_service.authorize("juliana", authorization_request(&oauth_client, None)).await.unwrap();
+
+        let AuthorizationBody::ConsentRequired(consent_required_body) = authorization_body else {
+            panic!("The consent of the user must be asked");
+        };
+        assert_eq!(consent_required_body.client_name, "app");
+        assert_eq!(consent_required_body.scopes, vec!["openid".to_string(), "email".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_authorize_and_exchange_code() {
+        let (oauth_client, client_secret) = oauth_client();
+        let mut mock_oauth_authorization_datastore = MockOAuthAuthorizationDatastore::new();
+        expect_authorization_code(&mut mock_oauth_authorization_datastore);
+        mock_oauth_authorization_datastore.expect_get_consent().times(1).returning(|_, _| Box::pin(future::ready(Ok(None))));
+        mock_oauth_authorization_datastore.expect_save_consent()
+            .withf(|oauth_consent| oauth_consent.username == "juliana" && oauth_consent.scopes == vec!["openid".to_string(), "email".to_string()])
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+        let authorization_server_service = authorization_server_service(&oauth_client, mock_oauth_authorization_datastore);
+
+        let authorization_body = authorization_server_service.authorize("juliana", authorization_request(&oauth_client, Some(true))).await.unwrap();
+        let code = redirect_parameter(&authorization_body, "code").unwrap();
+        assert_eq!(redirect_parameter(&authorization_body, "state"), Some("state".to_string()));
+
+        let token_body = authorization_server_service.issue_authorization_code_token(&oauth_client.client_id, &client_secret, &code, REDIRECT_URI, CODE_VERIFIER).await.unwrap();
+        let auth_claims = AuthClaims::try_from(&TokenString(token_body.access_token).try_into_claims(&AuthConfig::fake()).await.unwrap()).unwrap();
+        assert_eq!(auth_claims.claim_type, TokenType::Delegated);
+        assert_eq!(auth_claims.username, "juliana");
+        assert_eq!(auth_claims.client_id, Some(oauth_client.client_id.clone()));
+        assert!(token_body.id_token.is_some());
+
+        // Authorization codes are single-use
+        assert_eq!(authorization_server_service.issue_authorization_code_token(&oauth_client.client_id, &client_secret, &code, REDIRECT_URI, CODE_VERIFIER).await, Err(OAuthError::InvalidGrant));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_code_with_wrong_code_verifier() {
+        let (oauth_client, client_secret) = oauth_client();
+        let mut mock_oauth_authorization_datastore = MockOAuthAuthorizationDatastore::new();
+        expect_authorization_code(&mut mock_oauth_authorization_datastore);
+        mock_oauth_authorization_datastore.expect_get_consent().times(1).returning(|username, client_id| Box::pin(future::ready(Ok(Some(OAuthConsent {
+            id: None,
+            username: username.to_string(),
+            client_id: client_id.to_string(),
+            scopes: vec!["openid".to_string(), "email".to_string(), "profile".to_string()],
+            created_at: DateTime::now(),
+            last_modified_at: DateTime::now(),
+        })))));
+        let authorization_server_service = authorization_server_service(&oauth_client, mock_oauth_authorization_datastore);
+
+        let authorization_body = authorization_server_service.authorize("juliana", authorization_request(&oauth_client, None)).await.unwrap();
+        let code = redirect_parameter(&authorization_body, "code").unwrap();
+
+        assert_eq!(authorization_server_service.issue_authorization_code_token(&oauth_client.client_id, &client_secret, &code, REDIRECT_URI, "other_code_verifier").await, Err(OAuthError::InvalidGrant));
+    }
+
+    #[tokio::test]
+    async fn test_authorize_invalid_requests() {
+        let (oauth_client, _) = oauth_client();
+        let authorization_server_service = authorization_server_service(&oauth_client, MockOAuthAuthorizationDatastore::new());
+
+        let unknown_redirect_uri = AuthorizationRequestPayload { redirect_uri: "https://attacker.example.com".to_string(), ..authorization_request(&oauth_client, None) };
+        assert_eq!(authorization_server_service.authorize("juliana", unknown_redirect_uri).await, Err(OAuthError::InvalidRequest));
+
+        let without_pkce = AuthorizationRequestPayload { code_challenge: None, ..authorization_request(&oauth_client, None) };
+        let authorization_body = authorization_server_service.authorize("juliana", without_pkce).await.unwrap();
+        assert_eq!(redirect_parameter(&authorization_body, "error"), Some("invalid_request".to_string()));
+
+        let unknown_scope = AuthorizationRequestPayload { scope: "openid users:write".to_string(), ..authorization_request(&oauth_client, None) };
+        let authorization_body = authorization_server_service.authorize("juliana", unknown_scope).await.unwrap();
+        assert_eq!(redirect_parameter(&authorization_body, "error"), Some("invalid_scope".to_string()));
+    }
+}
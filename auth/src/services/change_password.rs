@@ -0,0 +1,128 @@
+use crate::datastore::{AuthDatastore, TokenDatastore};
+use crate::entities::error::AuthError;
+use crate::entities::{AuthSession, UserCredentials};
+use crate::services::{AuthChangePasswordService, AuthService};
+use crate::views::payload::ChangePasswordPayload;
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl> AuthChangePasswordService for AuthService<AuthDatastoreImpl, TokenDatastoreImpl>
+    where AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore
+{
+    async fn change_password(&self, auth_session: &AuthSession, change_password_payload: ChangePasswordPayload) -> Result<(), AuthError> {
+        if change_password_payload.current_password.is_empty() || change_password_payload.new_password.is_empty() {
+            return Err(AuthError::MissingCredentials);
+        }
+
+        let user_credentials = self.auth_datastore.get_user_by_username(&auth_session.username)
+            .await
+            .map_err(|_| AuthError::ServerError)?
+            .ok_or(AuthError::Unauthorized)?;
+
+        user_credentials.verify_password(&change_password_payload.current_password, &self.auth_config)?;
+        UserCredentials::validate_password(&user_credentials.username, &change_password_payload.new_password, &self.auth_config)?;
+
+        let password_hash = UserCredentials::hash_password(change_password_payload.new_password, &self.auth_config);
+        self.auth_datastore.update_password(&user_credentials.username, &password_hash).await.map_err(|_| AuthError::ServerError)?;
+
+        if change_password_payload.revoke_other_sessions {
+            let tokens = self.token_datastore.get_tokens_for_user(&user_credentials.username).await.map_err(|_| AuthError::ServerError)?;
+
+            for token in tokens.iter().filter(|token| token.revoked_at.is_none() && Some(&token.token_access_identifiers) != auth_session.token_identifier.as_ref()) {
+                self.token_datastore.revoke_token(&token.token_refresh_identifiers).await.map_err(|_| AuthError::ServerError)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use fake::{Fake, Faker};
+    use fake::faker::internet::en::Password;
+    use mockall::predicate::eq;
+    use once_cell::sync::Lazy;
+    use crate::datastore::{MockAuthDatastore, MockTokenDatastore};
+    use crate::entities::error::AuthError;
+    use crate::entities::{AuthMethod, AuthSession, Roles, Token, UserCredentials};
+    use crate::services::{AuthChangePasswordService, MockAuthService};
+    use crate::views::payload::ChangePasswordPayload;
+
+    static PASSWORD: Lazy<String> = Lazy::new(|| Password(10..50).fake());
+    static USER_CREDENTIALS: Lazy<UserCredentials> = Lazy::new(|| UserCredentials {
+        password: UserCredentials::hash_password(PASSWORD.clone(), &AuthConfig::fake()),
+        ..Faker.fake()
+    });
+
+    fn auth_session(token_identifier: &str) -> AuthSession {
+        AuthSession {
+            username: USER_CREDENTIALS.username.clone(),
+            roles: vec![Roles::User],
+            token_identifier: Some(token_identifier.to_string()),
+            auth_method: AuthMethod::AccessToken,
+        }
+    }
+
+    fn mock_auth_datastore() -> MockAuthDatastore {
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        mock_auth_datastore.expect_get_user_by_username()
+            .with(eq(USER_CREDENTIALS.username.clone()))
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(Some(USER_CREDENTIALS.clone())))));
+
+        mock_auth_datastore
+    }
+
+    #[tokio::test]
+    async fn test_change_password_revoke_other_sessions() {
+        let mut mock_auth_datastore = mock_auth_datastore();
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        let current_token: Token = Faker.fake();
+        let other_token: Token = Faker.fake();
+
+        mock_auth_datastore.expect_update_password()
+            .withf(|username, password_hash| {
+                username == USER_CREDENTIALS.username && UserCredentials { password: password_hash.to_string(), ..USER_CREDENTIALS.clone() }.verify_password("my_new_password", &AuthConfig::fake()).is_ok()
+            })
+            .times(1)
+            .returning(|_, _| Box::pin(future::ready(Ok(()))));
+        let tokens = vec![current_token.clone(), other_token.clone()];
+        mock_tokens_datastore.expect_get_tokens_for_user()
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(tokens.clone()))));
+        mock_tokens_datastore.expect_revoke_token()
+            .with(eq(other_token.token_refresh_identifiers.clone()))
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+
+        let auth_service = MockAuthService::new(mock_auth_datastore, mock_tokens_datastore, AuthConfig::fake());
+        let result = auth_service.change_password(&auth_session(&current_token.token_access_identifiers), ChangePasswordPayload {
+            current_password: PASSWORD.clone(),
+            new_password: "my_new_password".to_string(),
+            revoke_other_sessions: true,
+        }).await;
+
+        auth_service.checkpoint();
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_change_password_with_wrong_current_password() {
+        let mut mock_auth_datastore = mock_auth_datastore();
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+
+        mock_auth_datastore.expect_update_password().times(0);
+        mock_tokens_datastore.expect_get_tokens_for_user().times(0);
+
+        let auth_service = MockAuthService::new(mock_auth_datastore, mock_tokens_datastore, AuthConfig::fake());
+        let result = auth_service.change_password(&auth_session("token_identifier"), ChangePasswordPayload {
+            current_password: PASSWORD.clone() + " ",
+            new_password: "my_new_password".to_string(),
+            revoke_other_sessions: true,
+        }).await;
+
+        auth_service.checkpoint();
+        assert_eq!(result, Err(AuthError::WrongCredentials));
+    }
+}
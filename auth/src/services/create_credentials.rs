@@ -15,6 +15,8 @@ where
         &self,
         auth_payload: LoginPayload,
     ) -> Result<UserCredentials, Box<dyn Error + Send + Sync + 'static>> {
+        UserCredentials::validate_password(&auth_payload.username, &auth_payload.password, &self.auth_config)?;
+
         if self
             .auth_datastore
             .get_user_by_username(&auth_payload.username)
@@ -25,7 +27,7 @@ where
         }
 
         self.auth_datastore
-            .add_user(auth_payload.into())
+            .add_user(auth_payload.into_user_credentials(&self.auth_config))
             .await
             .map_err(|error| Box::from(error))
     }
@@ -33,9 +35,11 @@ where
 
 #[cfg(test)]
 mod tests {
+    use crate::utils::auth_config::AuthConfig;
     use super::*;
     use crate::datastore::{MockAuthDatastore, MockTokenDatastore};
     use crate::services::MockAuthService;
+    use crate::utils::password_policy::{PasswordPolicyError, PasswordPolicyViolation};
     use fake::{Fake, Faker};
     use mongodb::bson::oid::ObjectId;
     use std::future;
@@ -58,7 +62,7 @@ mod tests {
             });
 
         let login_payload: LoginPayload = Faker.fake();
-        let auth_service = MockAuthService::new(mock, MockTokenDatastore::new());
+        let auth_service = MockAuthService::new(mock, MockTokenDatastore::new(), AuthConfig::fake());
         let result = auth_service
             .create_credentials(login_payload.clone())
             .await
@@ -66,7 +70,7 @@ mod tests {
 
         auth_service.checkpoint();
         assert_eq!(&result.username, &login_payload.username);
-        assert!(result.verify_password(&login_payload.password).is_ok());
+        assert!(result.verify_password(&login_payload.password, &AuthConfig::fake()).is_ok());
     }
 
     #[tokio::test]
@@ -86,7 +90,7 @@ mod tests {
         mock.expect_add_user().times(0);
 
         let login_payload: LoginPayload = Faker.fake();
-        let auth_service = MockAuthService::new(mock, MockTokenDatastore::new());
+        let auth_service = MockAuthService::new(mock, MockTokenDatastore::new(), AuthConfig::fake());
         let result = auth_service.create_credentials(login_payload.clone()).await;
 
         auth_service.checkpoint();
@@ -95,4 +99,25 @@ mod tests {
             AuthError::Duplicated.to_string()
         );
     }
+
+    #[tokio::test]
+    async fn test_create_credential_with_weak_password() {
+        let mut mock = MockAuthDatastore::new();
+
+        mock.expect_get_user_by_username().times(0);
+        mock.expect_add_user().times(0);
+
+        let login_payload = LoginPayload {
+            password: "short".to_string(),
+            ..Faker.fake()
+        };
+        let auth_service = MockAuthService::new(mock, MockTokenDatastore::new(), AuthConfig::fake());
+        let result = auth_service.create_credentials(login_payload).await;
+
+        auth_service.checkpoint();
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<AuthError>(),
+            Some(&AuthError::WeakPassword(PasswordPolicyError { violations: vec![PasswordPolicyViolation::TooShort { min_length: 8 }] }))
+        );
+    }
 }
@@ -0,0 +1,152 @@
+use chrono::DateTime;
+use crate::datastore::{AuthDatastore, OAuthClientDatastore, TokenDatastore};
+use crate::entities::error::OAuthError;
+use crate::entities::{Roles, TokenType};
+use crate::services::{AuthTokenIntrospectionService, IntrospectionService};
+use crate::utils::auth_claims::AuthClaims;
+use crate::utils::validate_token::{IntoClaims, TokenString};
+use crate::views::response::IntrospectionBody;
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, OAuthClientDatastoreImpl> AuthTokenIntrospectionService for IntrospectionService<AuthDatastoreImpl, TokenDatastoreImpl, OAuthClientDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    OAuthClientDatastoreImpl: OAuthClientDatastore,
+{
+    async fn introspect(&self, client_id: &str, client_secret: &str, token: &str) -> Result<IntrospectionBody, OAuthError> {
+        let oauth_client = self.oauth_client_datastore.get_client(client_id)
+            .await
+            .map_err(|_| OAuthError::ServerError)?
+            .filter(|oauth_client| oauth_client.is_valid_secret(client_secret))
+            .ok_or(OAuthError::InvalidClient)?;
+        // Tokens give the identity and roles of users : only clients registered for it can read them
+        if !oauth_client.can_introspect() {
+            return Err(OAuthError::AccessDenied);
+        }
+
+        let Ok(claims) = TokenString(token.to_string()).try_into_claims(&self.auth_service.auth_config).await else {
+            return Ok(IntrospectionBody::default());
+        };
+        let Ok(auth_claims) = AuthClaims::try_from(&claims) else {
+            return Ok(IntrospectionBody::default());
+        };
+
+        // Tokens of a session are checked against their record, service and delegated tokens have none and can't be revoked
+        let token_record = match auth_claims.claim_type {
+            TokenType::Access => self.auth_service.token_datastore.get_token_by_access_identifier(&auth_claims.token_identifier).await,
+            TokenType::Refresh => self.auth_service.token_datastore.get_token(&auth_claims.token_identifier).await,
+            TokenType::Service | TokenType::Delegated => Ok(None),
+        }.map_err(|_| OAuthError::ServerError)?;
+        let expired_at = match (&auth_claims.claim_type, token_record) {
+            (_, Some(token)) if token.revoked_at.is_some() => return Ok(IntrospectionBody::default()),
+            (TokenType::Access, Some(token)) => token.token_access_expired_at.timestamp_millis() / 1000,
+            (TokenType::Refresh, Some(token)) => token.token_refresh_expired_at.timestamp_millis() / 1000,
+            (TokenType::Access | TokenType::Refresh, None) => return Ok(IntrospectionBody::default()),
+            _ => claims.get_claim("exp")
+                .and_then(|exp| exp.as_str())
+                .and_then(|exp| DateTime::parse_from_rfc3339(exp).ok())
+                .map(|exp| exp.timestamp())
+                .ok_or(OAuthError::ServerError)?,
+        };
+
+        let roles: Vec<String> = auth_claims.roles.iter().map(Roles::to_string).collect();
+
+        Ok(IntrospectionBody {
+            active: true,
+            sub: Some(auth_claims.username.clone()),
+            username: Some(auth_claims.username).filter(|_| auth_claims.claim_type != TokenType::Service),
+            role: Some(roles.join(" ")).filter(|role| !role.is_empty()),
+            roles: Some(roles).filter(|roles| !roles.is_empty()),
+            exp: Some(expired_at),
+            jti: Some(auth_claims.token_identifier),
+            client_id: auth_claims.client_id,
+            scope: Some(auth_claims.scopes.join(" ")).filter(|scope| !scope.is_empty()),
+            token_type: Some(auth_claims.claim_type.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use std::sync::Arc;
+    use fake::{Fake, Faker};
+    use mongodb::bson::DateTime;
+    use crate::datastore::{MockAuthDatastore, MockOAuthClientDatastore, MockTokenDatastore};
+    use crate::entities::error::OAuthError;
+    use crate::entities::{OAuthClient, Token, UserCredentials};
+    use crate::services::{AuthService, AuthTokenIntrospectionService, MockIntrospectionService};
+    use crate::views::response::IntrospectionBody;
+
+    fn introspection_service(oauth_client: OAuthClient, token_datastore: MockTokenDatastore) -> MockIntrospectionService {
+        let mut mock_oauth_client_datastore = MockOAuthClientDatastore::new();
+        mock_oauth_client_datastore.expect_get_client()
+            .returning(move |_| Box::pin(future::ready(Ok(Some(oauth_client.clone())))));
+
+        MockIntrospectionService::new(Arc::new(AuthService::new(MockAuthDatastore::new(), token_datastore, AuthConfig::fake())), mock_oauth_client_datastore)
+    }
+
+    async fn generate_tokens(revoked_at: Option<DateTime>) -> (UserCredentials, String, MockTokenDatastore) {
+        let user: UserCredentials = Faker.fake();
+        let (access_token, _, token) = Token::generate_tokens(&AuthConfig::fake(), &user).await.expect("Unable generate tokens");
+        let token = Token { revoked_at, ..token };
+        let mut mock_token_datastore = MockTokenDatastore::new();
+        mock_token_datastore.expect_get_token_by_access_identifier()
+            .returning(move |_| Box::pin(future::ready(Ok(Some(token.clone())))));
+
+        (user, access_token, mock_token_datastore)
+    }
+
+    #[tokio::test]
+    async fn test_introspect_active_access_token() {
+        let (oauth_client, client_secret) = OAuthClient::generate("gateway", vec![OAuthClient::INTROSPECT_SCOPE.to_string()], Vec::new());
+        let client_id = oauth_client.client_id.clone();
+        let (user, access_token, mock_token_datastore) = generate_tokens(None).await;
+
+        let introspection_body = introspection_service(oauth_client, mock_token_datastore).introspect(&client_id, &client_secret, &access_token).await.unwrap();
+
+        assert!(introspection_body.active);
+        assert_eq!(introspection_body.sub, Some(user.username.clone()));
+        assert_eq!(introspection_body.username, Some(user.username));
+        assert_eq!(introspection_body.role, Some(user.roles.iter().map(|role| role.to_string()).collect::<Vec<String>>().join(" ")));
+        assert_eq!(introspection_body.roles, Some(user.roles.iter().map(|role| role.to_string()).collect()));
+        assert!(introspection_body.exp.unwrap() > chrono::Utc::now().timestamp());
+        assert!(introspection_body.jti.is_some());
+        assert_eq!(introspection_body.token_type, Some("access".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_introspect_revoked_or_invalid_token_is_inactive() {
+        let (oauth_client, client_secret) = OAuthClient::generate("gateway", vec![OAuthClient::INTROSPECT_SCOPE.to_string()], Vec::new());
+        let client_id = oauth_client.client_id.clone();
+        let (_, access_token, mock_token_datastore) = generate_tokens(Some(DateTime::now())).await;
+        let introspection_service = introspection_service(oauth_client, mock_token_datastore);
+
+        assert_eq!(introspection_service.introspect(&client_id, &client_secret, &access_token).await, Ok(IntrospectionBody::default()));
+        assert_eq!(introspection_service.introspect(&client_id, &client_secret, "v4.public.invalid").await, Ok(IntrospectionBody::default()));
+        assert_eq!(serde_json::to_string(&IntrospectionBody::default()).unwrap(), r#"{"active":false}"#);
+    }
+
+    #[tokio::test]
+    async fn test_introspect_with_wrong_client_secret() {
+        let (oauth_client, _) = OAuthClient::generate("gateway", vec![OAuthClient::INTROSPECT_SCOPE.to_string()], Vec::new());
+        let client_id = oauth_client.client_id.clone();
+        let (_, access_token, mock_token_datastore) = generate_tokens(None).await;
+
+        let result = introspection_service(oauth_client, mock_token_datastore).introspect(&client_id, "wrong_secret", &access_token).await;
+
+        assert_eq!(result, Err(OAuthError::InvalidClient));
+    }
+
+    #[tokio::test]
+    async fn test_introspect_without_introspect_scope() {
+        let (oauth_client, client_secret) = OAuthClient::generate("billing", vec!["users:read".to_string()], Vec::new());
+        let client_id = oauth_client.client_id.clone();
+        let (_, access_token, mock_token_datastore) = generate_tokens(None).await;
+
+        let result = introspection_service(oauth_client, mock_token_datastore).introspect(&client_id, &client_secret, &access_token).await;
+
+        assert_eq!(result, Err(OAuthError::AccessDenied));
+    }
+}
@@ -14,15 +14,30 @@ impl<AuthDatastoreImpl, TokenDatastoreImpl> AuthValidCredentialsService for Auth
 
         let user_credentials_option = self.auth_datastore.get_user_by_username(&username).await.map_err(|_| AuthError::ServerError)?;
 
-        user_credentials_option
-            .filter(|user_credentials| user_credentials.verify_password(&password).is_ok())
-            .ok_or(AuthError::WrongCredentials)
+        let user_credentials = user_credentials_option
+            .filter(|user_credentials| user_credentials.verify_password(&password, &self.auth_config).is_ok())
+            .ok_or(AuthError::WrongCredentials)?;
+
+        // Upgrade legacy hashes while the clear password is known. A failure must not block the login
+        if user_credentials.needs_password_rehash(&self.auth_config) {
+            let password_hash = UserCredentials::hash_password(password, &self.auth_config);
+
+            if self.auth_datastore.update_password(&user_credentials.username, &password_hash).await.is_ok() {
+                return Ok(UserCredentials {
+                    password: password_hash,
+                    ..user_credentials
+                });
+            }
+        }
+
+        Ok(user_credentials)
     }
 }
 
 
 #[cfg(test)]
 mod test {
+    use crate::utils::auth_config::AuthConfig;
     use std::future;
     use fake::{Fake, Faker};
     use fake::faker::internet::en::{Password, Username};
@@ -32,6 +47,7 @@ mod test {
     use crate::entities::error::AuthError;
     use crate::entities::UserCredentials;
     use crate::services::{AuthValidCredentialsService, MockAuthService};
+    use crate::utils::password_hashing::{PasswordHashAlgorithm, PasswordHashing};
 
     #[tokio::test]
     pub async fn test_is_valid_auth_without_user_serverside() {
@@ -47,7 +63,7 @@ mod test {
                 ))
             });
 
-        let auth_service = MockAuthService::new(mock_auth_datastore, MockTokenDatastore::new());
+        let auth_service = MockAuthService::new(mock_auth_datastore, MockTokenDatastore::new(), AuthConfig::fake());
         let result = auth_service.is_valid_credentials(username, password).await;
 
         auth_service.checkpoint();
@@ -75,7 +91,7 @@ mod test {
                 ))
             });
 
-        let auth_service = MockAuthService::new(mock_auth_datastore, MockTokenDatastore::new());
+        let auth_service = MockAuthService::new(mock_auth_datastore, MockTokenDatastore::new(), AuthConfig::fake());
         let result = auth_service.is_valid_credentials(username, password).await;
 
         auth_service.checkpoint();
@@ -98,18 +114,53 @@ mod test {
                         UserCredentials {
                             id: Some(ObjectId::new()),
                             username: username.to_string(),
-                            password: UserCredentials::hash_password(PASSWORD.to_string()),
+                            password: UserCredentials::hash_password(PASSWORD.to_string(), &AuthConfig::fake()),
                             ..Faker.fake()
                         }
                     ))
                 ))
             });
 
-        let auth_service = MockAuthService::new(mock_auth_datastore, MockTokenDatastore::new());
+        let auth_service = MockAuthService::new(mock_auth_datastore, MockTokenDatastore::new(), AuthConfig::fake());
         let result = auth_service.is_valid_credentials(username, PASSWORD.clone()).await;
 
         auth_service.checkpoint();
 
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    pub async fn test_is_valid_credentials_upgrade_legacy_hash() {
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        let username: String = Username().fake();
+        static PASSWORD: Lazy<String> = Lazy::new(|| Password(10..500).fake());
+
+        mock_auth_datastore.expect_get_user_by_username()
+            .times(1)
+            .returning(|username|  {
+                Box::pin(future::ready(
+                    Ok(Some(
+                        UserCredentials {
+                            id: Some(ObjectId::new()),
+                            username: username.to_string(),
+                            password: PasswordHashing::new(PasswordHashAlgorithm::Pbkdf2Sha256).hash_password(&PASSWORD).unwrap(),
+                            ..Faker.fake()
+                        }
+                    ))
+                ))
+            });
+        mock_auth_datastore.expect_update_password()
+            .withf(|_, password_hash| password_hash.starts_with("$argon2id$"))
+            .times(1)
+            .returning(|_, _| Box::pin(future::ready(Ok(()))));
+
+        let auth_service = MockAuthService::new(mock_auth_datastore, MockTokenDatastore::new(), AuthConfig::fake());
+        let result = auth_service.is_valid_credentials(username, PASSWORD.clone()).await;
+
+        auth_service.checkpoint();
+
+        let user_credentials = result.unwrap();
+        assert!(user_credentials.password.starts_with("$argon2id$"));
+        assert!(user_credentials.verify_password(&PASSWORD, &AuthConfig::fake()).is_ok());
+    }
 }
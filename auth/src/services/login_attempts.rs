@@ -0,0 +1,178 @@
+use chrono::Utc;
+use mongodb::bson::DateTime;
+use crate::datastore::LoginAttemptDatastore;
+use crate::entities::error::AuthError;
+use crate::entities::{ClientInformation, LoginAttempts};
+use crate::services::{AuthLoginAttemptsService, LoginAttemptsService};
+
+/// Key tracked for a login, with the failures allowed before a lock and the error returned when locked
+type TrackedKey = (String, u32, fn(i64) -> AuthError);
+
+impl<LoginAttemptDatastoreImpl: LoginAttemptDatastore> LoginAttemptsService<LoginAttemptDatastoreImpl> {
+    /// The client IP is only tracked when known, it's the peer address or given by a trusted proxy. See `TrustedProxies`
+    ///
+    /// It's unknown behind a proxy missing from `TrustedProxies` : all clients would share the address of the proxy
+    fn tracked_keys(&self, username: &str, client_information: &ClientInformation) -> Vec<TrackedKey> {
+        let mut tracked_keys: Vec<TrackedKey> = vec![
+            (LoginAttempts::username_key(username), self.login_throttling.username_max_failures(), AuthError::AccountLocked),
+        ];
+
+        if let Some(client_ip) = &client_information.client_ip {
+            tracked_keys.push((LoginAttempts::client_ip_key(client_ip), self.login_throttling.client_ip_max_failures(), AuthError::TooManyAttempts));
+        }
+
+        tracked_keys
+    }
+
+    async fn add_login_failure(&self, key: &str, max_failures: u32) -> Result<(), AuthError> {
+        let previous_login_attempts = self.login_attempt_datastore.get_login_attempts(key).await.map_err(|_| AuthError::ServerError)?;
+
+        // Old failures are forgotten, unless the key is still locked
+        let failure_window_start = DateTime::parse_rfc3339_str((Utc::now() - self.login_throttling.failure_window()).to_rfc3339()).unwrap();
+        if previous_login_attempts.is_some_and(|login_attempts| login_attempts.last_failure_at < failure_window_start && login_attempts.locked_for_seconds().is_none()) {
+            self.login_attempt_datastore.clear_login_attempts(key).await.map_err(|_| AuthError::ServerError)?;
+        }
+
+        let login_attempts = self.login_attempt_datastore.add_login_failure(key).await.map_err(|_| AuthError::ServerError)?;
+
+        if let Some(lock_duration) = self.login_throttling.lock_duration(login_attempts.failures, max_failures) {
+            let locked_until = DateTime::parse_rfc3339_str((Utc::now() + lock_duration).to_rfc3339()).unwrap();
+            self.login_attempt_datastore.lock_login_attempts(key, locked_until).await.map_err(|_| AuthError::ServerError)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<LoginAttemptDatastoreImpl: LoginAttemptDatastore> AuthLoginAttemptsService for LoginAttemptsService<LoginAttemptDatastoreImpl> {
+    async fn check_login_allowed(&self, username: &str, client_information: &ClientInformation) -> Result<(), AuthError> {
+        for (key, _, locked_error) in self.tracked_keys(username, client_information) {
+            let login_attempts = self.login_attempt_datastore.get_login_attempts(&key).await.map_err(|_| AuthError::ServerError)?;
+
+            if let Some(locked_for_seconds) = login_attempts.and_then(|login_attempts| login_attempts.locked_for_seconds()) {
+                return Err(locked_error(locked_for_seconds));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn record_login_failure(&self, username: &str, client_information: &ClientInformation) -> Result<(), AuthError> {
+        for (key, max_failures, _) in self.tracked_keys(username, client_information) {
+            self.add_login_failure(&key, max_failures).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn clear_login_failures(&self, username: &str) -> Result<(), AuthError> {
+        self.login_attempt_datastore.clear_login_attempts(&LoginAttempts::username_key(username)).await.map_err(|_| AuthError::ServerError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+    use mockall::predicate::eq;
+    use mongodb::bson::DateTime;
+    use crate::datastore::MockLoginAttemptDatastore;
+    use crate::entities::error::AuthError;
+    use crate::entities::{ClientInformation, LoginAttempts};
+    use crate::services::{AuthLoginAttemptsService, MockLoginAttemptsService};
+    use crate::utils::login_throttling::LoginThrottling;
+
+    fn client_information() -> ClientInformation {
+        ClientInformation {
+            user_agent: None,
+            client_ip: Some("127.0.0.1".to_string()),
+        }
+    }
+
+    fn login_attempts(key: &str, failures: u32, locked_until: Option<DateTime>) -> LoginAttempts {
+        LoginAttempts {
+            id: None,
+            key: key.to_string(),
+            failures,
+            last_failure_at: DateTime::now(),
+            locked_until,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_login_allowed_with_locked_username() {
+        let mut mock_login_attempt_datastore = MockLoginAttemptDatastore::new();
+
+        mock_login_attempt_datastore.expect_get_login_attempts()
+            .with(eq(LoginAttempts::username_key("username")))
+            .times(1)
+            .returning(|key| Box::pin(future::ready(Ok(Some(login_attempts(key, 5, Some(DateTime::from_millis(DateTime::now().timestamp_millis() + 30_000))))))));
+
+        let login_attempts_service = MockLoginAttemptsService::new(mock_login_attempt_datastore, LoginThrottling::default());
+        let result = login_attempts_service.check_login_allowed("username", &client_information()).await;
+
+        assert!(matches!(result, Err(AuthError::AccountLocked(seconds)) if seconds > 0 && seconds <= 30));
+    }
+
+    #[tokio::test]
+    async fn test_check_login_allowed_with_locked_client_ip() {
+        let mut mock_login_attempt_datastore = MockLoginAttemptDatastore::new();
+
+        mock_login_attempt_datastore.expect_get_login_attempts()
+            .with(eq(LoginAttempts::username_key("username")))
+            .times(1)
+            .returning(|key| Box::pin(future::ready(Ok(Some(login_attempts(key, 2, Some(DateTime::now())))))));
+        mock_login_attempt_datastore.expect_get_login_attempts()
+            .with(eq(LoginAttempts::client_ip_key("127.0.0.1")))
+            .times(1)
+            .returning(|key| Box::pin(future::ready(Ok(Some(login_attempts(key, 20, Some(DateTime::from_millis(DateTime::now().timestamp_millis() + 60_000))))))));
+
+        let login_attempts_service = MockLoginAttemptsService::new(mock_login_attempt_datastore, LoginThrottling::default());
+        let result = login_attempts_service.check_login_allowed("username", &client_information()).await;
+
+        assert!(matches!(result, Err(AuthError::TooManyAttempts(_))));
+    }
+
+    #[tokio::test]
+    async fn test_record_login_failure_lock_at_max_failures() {
+        let mut mock_login_attempt_datastore = MockLoginAttemptDatastore::new();
+
+        mock_login_attempt_datastore.expect_get_login_attempts()
+            .times(1)
+            .returning(|key| Box::pin(future::ready(Ok(Some(login_attempts(key, 2, None))))));
+        mock_login_attempt_datastore.expect_clear_login_attempts().times(0);
+        mock_login_attempt_datastore.expect_add_login_failure()
+            .with(eq(LoginAttempts::username_key("username")))
+            .times(1)
+            .returning(|key| Box::pin(future::ready(Ok(login_attempts(key, 3, None)))));
+        mock_login_attempt_datastore.expect_lock_login_attempts()
+            .withf(|key, locked_until| key == LoginAttempts::username_key("username") && *locked_until > DateTime::now())
+            .times(1)
+            .returning(|_, _| Box::pin(future::ready(Ok(()))));
+
+        let login_attempts_service = MockLoginAttemptsService::new(mock_login_attempt_datastore, LoginThrottling::default().with_max_failures(3, 20));
+        let result = login_attempts_service.record_login_failure("username", &ClientInformation::default()).await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_record_login_failure_forget_old_failures() {
+        let mut mock_login_attempt_datastore = MockLoginAttemptDatastore::new();
+
+        mock_login_attempt_datastore.expect_get_login_attempts()
+            .times(1)
+            .returning(|key| Box::pin(future::ready(Ok(Some(LoginAttempts { last_failure_at: DateTime::from_millis(0), ..login_attempts(key, 4, None) })))));
+        mock_login_attempt_datastore.expect_clear_login_attempts()
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+        mock_login_attempt_datastore.expect_add_login_failure()
+            .times(1)
+            .returning(|key| Box::pin(future::ready(Ok(login_attempts(key, 1, None)))));
+        mock_login_attempt_datastore.expect_lock_login_attempts().times(0);
+
+        let login_attempts_service = MockLoginAttemptsService::new(mock_login_attempt_datastore, LoginThrottling::default());
+        let result = login_attempts_service.record_login_failure("username", &ClientInformation::default()).await;
+
+        assert!(result.is_ok());
+    }
+}
@@ -1,19 +1,39 @@
 use std::error::Error;
-use crate::datastore::{AuthDatastore, TokenDatastore};
-use crate::entities::error::AuthError;
-use crate::entities::{Token, UserCredentials};
+use std::sync::Arc;
+use crate::datastore::{AuthDatastore, LoginAttemptDatastore, OAuthAuthorizationDatastore, OAuthClientDatastore, OidcDatastore, PasswordResetDatastore, PersonalAccessTokenDatastore, RoleDatastore, TokenDatastore, TotpDatastore, WebAuthnDatastore};
+use crate::entities::error::{AuthError, OAuthError};
+use crate::entities::{AuthSession, ClientInformation, Roles, Token, UserCredentials};
 use crate::utils::auth_claims::AuthClaims;
-use crate::views::payload::{LoginPayload, RefreshTokenPayload};
-use crate::views::response::AuthBody;
+use crate::utils::auth_config::AuthConfig;
+use crate::utils::authorization_server::AuthorizationServer;
+use crate::utils::login_throttling::LoginThrottling;
+use crate::utils::password_reset_sender::PasswordResetSender;
+use crate::utils::oidc::{OidcProvider, OidcUserProvisioning};
+use crate::utils::webauthn::RelyingParty;
+use crate::views::payload::{ChangePasswordPayload, CreatePersonalAccessTokenPayload, AuthorizationRequestPayload, LoginPayload, OidcCallbackPayload, PasswordResetConfirmPayload, RefreshTokenPayload, RegisterOAuthClientPayload, SaveRolePayload, WebAuthnAssertionPayload, WebAuthnRegistrationPayload};
+use crate::views::response::{AuthBody, AuthorizationBody, AuthorizationCodeTokenBody, ClientCredentialsTokenBody, IntrospectionBody, MfaTicketBody, OAuthClientBody, OAuthClientDetails, OAuthConsentDetails, OpenIdConfiguration, UserInfoBody, PersonalAccessTokenBody, PersonalAccessTokenDetails, SessionDetails, RoleDetails, TotpEnrolmentBody, UserRolesDetails, WebAuthnCreationOptions, WebAuthnCredentialDetails, WebAuthnRequestOptions};
 #[cfg(test)]
 use mockall::automock;
 #[cfg(test)]
-use crate::datastore::{MockAuthDatastore, MockTokenDatastore};
+use crate::datastore::{MockAuthDatastore, MockLoginAttemptDatastore, MockOAuthAuthorizationDatastore, MockOAuthClientDatastore, MockOidcDatastore, MockPasswordResetDatastore, MockPersonalAccessTokenDatastore, MockRoleDatastore, MockTokenDatastore, MockTotpDatastore, MockWebAuthnDatastore};
 
 pub mod is_valid_credentials;
 mod get_credentials_from_username;
 pub(crate) mod create_credentials;
 mod tokens;
+mod revoke_tokens;
+mod sessions;
+mod change_password;
+mod roles;
+mod password_reset;
+mod login_attempts;
+mod totp;
+mod webauthn;
+mod personal_access_tokens;
+mod oauth_clients;
+mod oidc;
+mod authorization_server;
+mod introspection;
 
 #[cfg_attr(test, automock)]
 pub trait AuthGetCredentialsService {
@@ -32,17 +52,139 @@ pub trait AuthValidCredentialsService {
 
 
 pub trait AuthTokensService {
-    fn parse_auth_claims_from_refresh_payload(refresh_token_payload: RefreshTokenPayload) -> Result<AuthClaims, AuthError>;
+    fn parse_auth_claims_from_refresh_payload(&self, refresh_token_payload: RefreshTokenPayload) -> impl std::future::Future<Output=Result<AuthClaims, AuthError>>;
     fn validate_token(&self, auth_claims: &AuthClaims) -> impl std::future::Future<Output=Result<Token, AuthError>>;
     fn try_get_user_token(&self, token: &Token) -> impl std::future::Future<Output=Result<UserCredentials, AuthError>>;
-    fn generate_token(&self, user: &UserCredentials) -> impl std::future::Future<Output=Result<AuthBody, AuthError>>;
-    fn refresh_tokens(&self, refresh_token_payload: RefreshTokenPayload) -> impl std::future::Future<Output=Result<AuthBody, AuthError>>;
+    fn generate_token(&self, user: &UserCredentials, client_information: &ClientInformation) -> impl std::future::Future<Output=Result<AuthBody, AuthError>>;
+    fn refresh_tokens(&self, refresh_token_payload: RefreshTokenPayload, client_information: &ClientInformation) -> impl std::future::Future<Output=Result<AuthBody, AuthError>>;
+}
+
+pub trait AuthRevokeTokensService {
+    /// Revoke the token pair holding the given access token identifier (`jti`)
+    fn revoke_session(&self, auth_session: &AuthSession) -> impl std::future::Future<Output=Result<(), AuthError>>;
+    /// Revoke every token pair still active for the user
+    fn revoke_all_sessions(&self, username: &str) -> impl std::future::Future<Output=Result<(), AuthError>>;
+}
+
+pub trait AuthSessionsService {
+    /// List the sessions of the user which are not expired, one entry by session
+    fn get_sessions(&self, auth_session: &AuthSession) -> impl std::future::Future<Output=Result<Vec<SessionDetails>, AuthError>>;
+    /// Revoke every token of the session `session_id` owned by the user
+    fn revoke_session_by_id(&self, username: &str, session_id: &str) -> impl std::future::Future<Output=Result<(), AuthError>>;
+}
+
+pub trait AuthChangePasswordService {
+    /// Replace the password of the authenticated user after verifying the current one
+    fn change_password(&self, auth_session: &AuthSession, change_password_payload: ChangePasswordPayload) -> impl std::future::Future<Output=Result<(), AuthError>>;
+}
+
+/// Roles granted by administrators and roles defined at runtime. An Admin can grant every role below SuperAdmin
+pub trait AuthRolesService {
+    fn get_user_roles(&self, username: &str) -> impl std::future::Future<Output=Result<UserRolesDetails, AuthError>>;
+    /// Replace the roles of `username` and revoke its sessions, so the next tokens carry the new roles
+    fn update_user_roles(&self, auth_session: &AuthSession, username: &str, roles: Vec<Roles>) -> impl std::future::Future<Output=Result<UserRolesDetails, AuthError>>;
+    /// Roles stored and built-in roles
+    fn get_roles(&self) -> impl std::future::Future<Output=Result<Vec<RoleDetails>, AuthError>>;
+    /// Create or replace a runtime role, or change the permissions of a built-in role
+    fn save_role(&self, name: &str, payload: SaveRolePayload) -> impl std::future::Future<Output=Result<RoleDetails, AuthError>>;
+    /// Users keep the deleted role, it grants nothing anymore. Built-in roles can't be deleted
+    fn delete_role(&self, name: &str) -> impl std::future::Future<Output=Result<(), AuthError>>;
+}
+
+pub trait AuthPasswordResetService {
+    /// Send a reset token to the user. Succeed the same way when the username is unknown
+    fn request_password_reset(&self, username: &str) -> impl std::future::Future<Output=Result<(), AuthError>>;
+    /// Replace the password of the reset token owner and revoke all its sessions
+    fn confirm_password_reset(&self, password_reset_confirm_payload: PasswordResetConfirmPayload) -> impl std::future::Future<Output=Result<(), AuthError>>;
+}
+
+#[cfg_attr(test, automock)]
+pub trait AuthLoginAttemptsService {
+    /// Fail when the username or the client IP is locked by too many failed logins
+    fn check_login_allowed(&self, username: &str, client_information: &ClientInformation) -> impl std::future::Future<Output=Result<(), AuthError>>;
+    /// Count a failed login for the username and the client IP, and lock them when the limit is reached
+    fn record_login_failure(&self, username: &str, client_information: &ClientInformation) -> impl std::future::Future<Output=Result<(), AuthError>>;
+    /// Forget the failed logins and unlock the username. Failures of client IPs are kept
+    fn clear_login_failures(&self, username: &str) -> impl std::future::Future<Output=Result<(), AuthError>>;
+}
+
+/// TOTP second factor : enrolled by the user, then a code is asked after the password on every login
+#[cfg_attr(test, automock)]
+pub trait AuthTotpService {
+    /// Start an enrolment, replacing any pending one. Inactive until confirmed with a first code
+    fn enrol_totp(&self, username: &str) -> impl std::future::Future<Output=Result<TotpEnrolmentBody, AuthError>>;
+    fn confirm_totp(&self, username: &str, code: &str) -> impl std::future::Future<Output=Result<(), AuthError>>;
+    /// Remove the second factor, a TOTP or recovery code is required
+    fn disable_totp(&self, username: &str, code: &str) -> impl std::future::Future<Output=Result<(), AuthError>>;
+    fn is_totp_enabled(&self, username: &str) -> impl std::future::Future<Output=Result<bool, AuthError>>;
+    /// Give a ticket for `/login/mfa`, replacing the previous one of the user
+    fn generate_mfa_ticket(&self, username: &str) -> impl std::future::Future<Output=Result<MfaTicketBody, AuthError>>;
+    /// Consume the ticket, usable only once, and return its username
+    fn verify_mfa_ticket(&self, mfa_ticket: &str) -> impl std::future::Future<Output=Result<String, AuthError>>;
+    /// Check a TOTP or recovery code, both usable only once, and return the user
+    fn verify_mfa_code(&self, username: &str, code: &str) -> impl std::future::Future<Output=Result<UserCredentials, AuthError>>;
+}
+
+/// Passwordless login with WebAuthn authenticators (passkeys), registered by authenticated users
+pub trait AuthWebAuthnService {
+    fn start_registration(&self, username: &str) -> impl std::future::Future<Output=Result<WebAuthnCreationOptions, AuthError>>;
+    fn finish_registration(&self, username: &str, registration_payload: WebAuthnRegistrationPayload) -> impl std::future::Future<Output=Result<WebAuthnCredentialDetails, AuthError>>;
+    /// Without username, any discoverable credential of the authenticator is accepted
+    fn start_authentication(&self, username: Option<String>) -> impl std::future::Future<Output=Result<WebAuthnRequestOptions, AuthError>>;
+    fn finish_authentication(&self, assertion_payload: WebAuthnAssertionPayload, client_information: &ClientInformation) -> impl std::future::Future<Output=Result<AuthBody, AuthError>>;
+    fn get_credentials(&self, username: &str) -> impl std::future::Future<Output=Result<Vec<WebAuthnCredentialDetails>, AuthError>>;
+    fn delete_credential(&self, username: &str, credential_id: &str) -> impl std::future::Future<Output=Result<(), AuthError>>;
+}
+
+/// Long-lived tokens created by users for scripts, accepted by `AuthGuardLayer` like access tokens
+pub trait AuthPersonalAccessTokensService {
+    fn create_personal_access_token(&self, username: &str, payload: CreatePersonalAccessTokenPayload) -> impl std::future::Future<Output=Result<PersonalAccessTokenBody, AuthError>>;
+    fn get_personal_access_tokens(&self, username: &str) -> impl std::future::Future<Output=Result<Vec<PersonalAccessTokenDetails>, AuthError>>;
+    fn revoke_personal_access_token(&self, username: &str, token_id: &str) -> impl std::future::Future<Output=Result<(), AuthError>>;
+}
+
+/// Confidential clients registered by administrators, authenticated with the `client_credentials` grant
+pub trait AuthClientCredentialsService {
+    fn register_client(&self, payload: RegisterOAuthClientPayload) -> impl std::future::Future<Output=Result<OAuthClientBody, AuthError>>;
+    fn get_clients(&self) -> impl std::future::Future<Output=Result<Vec<OAuthClientDetails>, AuthError>>;
+    fn delete_client(&self, client_id: &str) -> impl std::future::Future<Output=Result<(), AuthError>>;
+    /// Without `scope`, every scope allowed to the client is granted
+    fn issue_client_credentials_token(&self, client_id: &str, client_secret: &str, scope: Option<String>) -> impl std::future::Future<Output=Result<ClientCredentialsTokenBody, OAuthError>>;
+}
+
+/// Check tokens for other services and gateways, without sharing the keys (RFC 7662)
+pub trait AuthTokenIntrospectionService {
+    /// A token not verified, expired or revoked is not an error, it is only inactive
+    fn introspect(&self, client_id: &str, client_secret: &str, token: &str) -> impl std::future::Future<Output=Result<IntrospectionBody, OAuthError>>;
+}
+
+/// Login with upstream OpenID Connect providers, creating the local user at its first login
+#[cfg_attr(test, automock)]
+pub trait AuthOidcService {
+    /// URL of the provider the user is redirected to
+    fn start_login(&self, provider: &str) -> impl std::future::Future<Output=Result<String, AuthError>>;
+    /// User authenticated by the provider. Tokens are only given once its second factor is checked, like a password login
+    fn finish_login(&self, provider: &str, callback_payload: OidcCallbackPayload) -> impl std::future::Future<Output=Result<UserCredentials, AuthError>>;
+}
+
+/// OpenID provider of third-party apps : authorization codes with PKCE given with the consent of users, exchanged for tokens
+pub trait AuthAuthorizationCodeService {
+    /// `username` is the user logged in on the authorization page
+    fn authorize(&self, username: &str, payload: AuthorizationRequestPayload) -> impl std::future::Future<Output=Result<AuthorizationBody, OAuthError>>;
+    fn get_consents(&self, username: &str) -> impl std::future::Future<Output=Result<Vec<OAuthConsentDetails>, AuthError>>;
+    fn revoke_consent(&self, username: &str, client_id: &str) -> impl std::future::Future<Output=Result<(), AuthError>>;
+    fn issue_authorization_code_token(&self, client_id: &str, client_secret: &str, code: &str, redirect_uri: &str, code_verifier: &str) -> impl std::future::Future<Output=Result<AuthorizationCodeTokenBody, OAuthError>>;
+    /// Claims of the user for the scopes granted to the app
+    fn get_user_info(&self, username: &str, scopes: &[String]) -> impl std::future::Future<Output=Result<UserInfoBody, AuthError>>;
+    fn get_openid_configuration(&self) -> OpenIdConfiguration;
+    fn get_json_web_key_set(&self) -> jsonwebtoken::jwk::JwkSet;
 }
 
 #[derive(Clone)]
 pub struct AuthService<AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore> {
     auth_datastore: AuthDatastoreImpl,
     token_datastore: TokenDatastoreImpl,
+    auth_config: AuthConfig,
 }
 
 #[cfg(test)]
@@ -57,10 +199,229 @@ impl MockAuthService {
 }
 
 impl<AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore> AuthService<AuthDatastoreImpl, TokenDatastoreImpl> {
-    pub fn new(auth_datastore: AuthDatastoreImpl, token_datastore: TokenDatastoreImpl) -> Self {
+    pub fn new(auth_datastore: AuthDatastoreImpl, token_datastore: TokenDatastoreImpl, auth_config: AuthConfig) -> Self {
         Self {
             auth_datastore,
             token_datastore,
+            auth_config,
+        }
+    }
+}
+
+/// Password reset flow, kept apart from `AuthService` because it needs its own datastore and sender
+pub struct PasswordResetService<AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore, PasswordResetDatastoreImpl: PasswordResetDatastore> {
+    auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>,
+    password_reset_datastore: PasswordResetDatastoreImpl,
+    password_reset_sender: Option<Arc<dyn PasswordResetSender>>,
+}
+
+#[cfg(test)]
+pub type MockPasswordResetService = PasswordResetService<MockAuthDatastore, MockTokenDatastore, MockPasswordResetDatastore>;
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, PasswordResetDatastoreImpl> PasswordResetService<AuthDatastoreImpl, TokenDatastoreImpl, PasswordResetDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    PasswordResetDatastoreImpl: PasswordResetDatastore,
+{
+    pub fn new(auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>, password_reset_datastore: PasswordResetDatastoreImpl, password_reset_sender: Option<Arc<dyn PasswordResetSender>>) -> Self {
+        Self {
+            auth_service,
+            password_reset_datastore,
+            password_reset_sender,
+        }
+    }
+}
+
+/// Login attempts tracking, kept apart from `AuthService` because it needs its own datastore
+pub struct LoginAttemptsService<LoginAttemptDatastoreImpl: LoginAttemptDatastore> {
+    login_attempt_datastore: LoginAttemptDatastoreImpl,
+    login_throttling: LoginThrottling,
+}
+
+#[cfg(test)]
+pub type MockLoginAttemptsService = LoginAttemptsService<MockLoginAttemptDatastore>;
+
+impl<LoginAttemptDatastoreImpl: LoginAttemptDatastore> LoginAttemptsService<LoginAttemptDatastoreImpl> {
+    pub fn new(login_attempt_datastore: LoginAttemptDatastoreImpl, login_throttling: LoginThrottling) -> Self {
+        Self {
+            login_attempt_datastore,
+            login_throttling,
+        }
+    }
+}
+
+/// Roles management, kept apart from `AuthService` because it needs the role definitions
+pub struct RoleService<AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore, RoleDatastoreImpl: RoleDatastore> {
+    auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>,
+    role_datastore: RoleDatastoreImpl,
+}
+
+#[cfg(test)]
+pub type MockRoleService = RoleService<MockAuthDatastore, MockTokenDatastore, MockRoleDatastore>;
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, RoleDatastoreImpl> RoleService<AuthDatastoreImpl, TokenDatastoreImpl, RoleDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    RoleDatastoreImpl: RoleDatastore,
+{
+    pub fn new(auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>, role_datastore: RoleDatastoreImpl) -> Self {
+        Self {
+            auth_service,
+            role_datastore,
+        }
+    }
+}
+
+/// TOTP second factor, kept apart from `AuthService` because it needs its own datastore
+pub struct TotpService<AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore, TotpDatastoreImpl: TotpDatastore> {
+    auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>,
+    totp_datastore: TotpDatastoreImpl,
+    totp_issuer: String,
+}
+
+#[cfg(test)]
+pub type MockTotpService = TotpService<MockAuthDatastore, MockTokenDatastore, MockTotpDatastore>;
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, TotpDatastoreImpl> TotpService<AuthDatastoreImpl, TokenDatastoreImpl, TotpDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    TotpDatastoreImpl: TotpDatastore,
+{
+    /// `totp_issuer` is the name displayed by authenticator apps
+    pub fn new(auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>, totp_datastore: TotpDatastoreImpl, totp_issuer: String) -> Self {
+        Self {
+            auth_service,
+            totp_datastore,
+            totp_issuer,
+        }
+    }
+}
+
+/// WebAuthn ceremonies, kept apart from `AuthService` because it needs its own datastore
+pub struct WebAuthnService<AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore, WebAuthnDatastoreImpl: WebAuthnDatastore> {
+    auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>,
+    webauthn_datastore: WebAuthnDatastoreImpl,
+    relying_party: RelyingParty,
+}
+
+#[cfg(test)]
+pub type MockWebAuthnService = WebAuthnService<MockAuthDatastore, MockTokenDatastore, MockWebAuthnDatastore>;
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, WebAuthnDatastoreImpl> WebAuthnService<AuthDatastoreImpl, TokenDatastoreImpl, WebAuthnDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    WebAuthnDatastoreImpl: WebAuthnDatastore,
+{
+    pub fn new(auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>, webauthn_datastore: WebAuthnDatastoreImpl, relying_party: RelyingParty) -> Self {
+        Self {
+            auth_service,
+            webauthn_datastore,
+            relying_party,
+        }
+    }
+}
+
+pub struct PersonalAccessTokenService<PersonalAccessTokenDatastoreImpl: PersonalAccessTokenDatastore> {
+    personal_access_token_datastore: PersonalAccessTokenDatastoreImpl,
+}
+
+#[cfg(test)]
+pub type MockPersonalAccessTokenService = PersonalAccessTokenService<MockPersonalAccessTokenDatastore>;
+
+impl<PersonalAccessTokenDatastoreImpl: PersonalAccessTokenDatastore> PersonalAccessTokenService<PersonalAccessTokenDatastoreImpl> {
+    pub fn new(personal_access_token_datastore: PersonalAccessTokenDatastoreImpl) -> Self {
+        Self {
+            personal_access_token_datastore,
+        }
+    }
+}
+
+pub struct OAuthClientService<OAuthClientDatastoreImpl: OAuthClientDatastore> {
+    oauth_client_datastore: OAuthClientDatastoreImpl,
+    auth_config: AuthConfig,
+}
+
+#[cfg(test)]
+pub type MockOAuthClientService = OAuthClientService<MockOAuthClientDatastore>;
+
+impl<OAuthClientDatastoreImpl: OAuthClientDatastore> OAuthClientService<OAuthClientDatastoreImpl> {
+    pub fn new(oauth_client_datastore: OAuthClientDatastoreImpl, auth_config: AuthConfig) -> Self {
+        Self {
+            oauth_client_datastore,
+            auth_config,
+        }
+    }
+}
+
+pub struct IntrospectionService<AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore, OAuthClientDatastoreImpl: OAuthClientDatastore> {
+    auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>,
+    oauth_client_datastore: OAuthClientDatastoreImpl,
+}
+
+#[cfg(test)]
+pub type MockIntrospectionService = IntrospectionService<MockAuthDatastore, MockTokenDatastore, MockOAuthClientDatastore>;
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, OAuthClientDatastoreImpl> IntrospectionService<AuthDatastoreImpl, TokenDatastoreImpl, OAuthClientDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    OAuthClientDatastoreImpl: OAuthClientDatastore,
+{
+    pub fn new(auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>, oauth_client_datastore: OAuthClientDatastoreImpl) -> Self {
+        Self {
+            auth_service,
+            oauth_client_datastore,
+        }
+    }
+}
+
+pub struct OidcService<AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore, OidcDatastoreImpl: OidcDatastore> {
+    auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>,
+    oidc_datastore: OidcDatastoreImpl,
+    oidc_providers: Vec<Arc<OidcProvider>>,
+    user_provisioning: Arc<dyn OidcUserProvisioning>,
+}
+
+#[cfg(test)]
+pub type MockOidcService = OidcService<MockAuthDatastore, MockTokenDatastore, MockOidcDatastore>;
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, OidcDatastoreImpl> OidcService<AuthDatastoreImpl, TokenDatastoreImpl, OidcDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    OidcDatastoreImpl: OidcDatastore,
+{
+    pub fn new(auth_service: Arc<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>>, oidc_datastore: OidcDatastoreImpl, oidc_providers: Vec<Arc<OidcProvider>>, user_provisioning: Arc<dyn OidcUserProvisioning>) -> Self {
+        Self {
+            auth_service,
+            oidc_datastore,
+            oidc_providers,
+            user_provisioning,
+        }
+    }
+}
+
+pub struct AuthorizationServerService<OAuthClientDatastoreImpl: OAuthClientDatastore, OAuthAuthorizationDatastoreImpl: OAuthAuthorizationDatastore> {
+    oauth_client_datastore: OAuthClientDatastoreImpl,
+    oauth_authorization_datastore: OAuthAuthorizationDatastoreImpl,
+    authorization_server: AuthorizationServer,
+    auth_config: AuthConfig,
+}
+
+#[cfg(test)]
+pub type MockAuthorizationServerService = AuthorizationServerService<MockOAuthClientDatastore, MockOAuthAuthorizationDatastore>;
+
+impl<OAuthClientDatastoreImpl: OAuthClientDatastore, OAuthAuthorizationDatastoreImpl: OAuthAuthorizationDatastore> AuthorizationServerService<OAuthClientDatastoreImpl, OAuthAuthorizationDatastoreImpl> {
+    pub fn new(oauth_client_datastore: OAuthClientDatastoreImpl, oauth_authorization_datastore: OAuthAuthorizationDatastoreImpl, authorization_server: AuthorizationServer, auth_config: AuthConfig) -> Self {
+        Self {
+            oauth_client_datastore,
+            oauth_authorization_datastore,
+            authorization_server,
+            auth_config,
         }
     }
 }
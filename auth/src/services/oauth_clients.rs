@@ -0,0 +1,126 @@
+use chrono::Utc;
+use crate::datastore::OAuthClientDatastore;
+use crate::entities::error::{AuthError, OAuthError};
+use crate::entities::{OAuthClient, Token};
+use crate::services::{AuthClientCredentialsService, OAuthClientService};
+use crate::views::payload::RegisterOAuthClientPayload;
+use crate::views::response::{ClientCredentialsTokenBody, OAuthClientBody, OAuthClientDetails};
+
+impl<OAuthClientDatastoreImpl: OAuthClientDatastore> OAuthClientService<OAuthClientDatastoreImpl> {
+    pub(crate) const TOKEN_TYPE: &'static str = "Bearer";
+}
+
+impl<OAuthClientDatastoreImpl: OAuthClientDatastore> AuthClientCredentialsService for OAuthClientService<OAuthClientDatastoreImpl> {
+    async fn register_client(&self, payload: RegisterOAuthClientPayload) -> Result<OAuthClientBody, AuthError> {
+        let (oauth_client, client_secret) = OAuthClient::generate(&payload.name, OAuthClient::split_scopes(&payload.scopes), payload.redirect_uris);
+
+        let oauth_client = self.oauth_client_datastore.add_client(oauth_client)
+            .await
+            .map_err(|_| AuthError::ServerError)?;
+
+        Ok(OAuthClientBody {
+            client_secret,
+            details: oauth_client.into(),
+        })
+    }
+
+    async fn get_clients(&self) -> Result<Vec<OAuthClientDetails>, AuthError> {
+        let oauth_clients = self.oauth_client_datastore.get_clients()
+            .await
+            .map_err(|_| AuthError::ServerError)?;
+
+        Ok(oauth_clients.into_iter().map(OAuthClientDetails::from).collect())
+    }
+
+    async fn delete_client(&self, client_id: &str) -> Result<(), AuthError> {
+        self.oauth_client_datastore.delete_client(client_id).await.map_err(|_| AuthError::NotFound)
+    }
+
+    async fn issue_client_credentials_token(&self, client_id: &str, client_secret: &str, scope: Option<String>) -> Result<ClientCredentialsTokenBody, OAuthError> {
+        let oauth_client = self.oauth_client_datastore.get_client(client_id)
+            .await
+            .map_err(|_| OAuthError::ServerError)?
+            .filter(|oauth_client| oauth_client.is_valid_secret(client_secret))
+            .ok_or(OAuthError::InvalidClient)?;
+
+        let scopes = match scope {
+            Some(scope) => OAuthClient::split_scopes(&[scope]),
+            None => oauth_client.scopes.clone(),
+        };
+        if !scopes.iter().all(|scope| oauth_client.scopes.contains(scope)) {
+            return Err(OAuthError::InvalidScope);
+        }
+
+        let (expired_at, access_token) = Token::generate_service_access_token(&self.auth_config, &oauth_client, &scopes).await.map_err(|_| OAuthError::ServerError)?;
+
+        Ok(ClientCredentialsTokenBody {
+            access_token,
+            token_type: Self::TOKEN_TYPE.to_string(),
+            expires_in: (expired_at - Utc::now()).num_seconds(),
+            scope: scopes.join(" "),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use crate::datastore::MockOAuthClientDatastore;
+    use crate::entities::error::OAuthError;
+    use crate::entities::{OAuthClient, TokenType};
+    use crate::services::{AuthClientCredentialsService, MockOAuthClientService};
+    use crate::utils::auth_claims::AuthClaims;
+    use crate::utils::validate_token::{IntoClaims, TokenString};
+    use crate::views::payload::RegisterOAuthClientPayload;
+
+    fn oauth_client_service(oauth_client: OAuthClient) -> MockOAuthClientService {
+        let mut mock_oauth_client_datastore = MockOAuthClientDatastore::new();
+        mock_oauth_client_datastore.expect_get_client()
+            .returning(move |_| Box::pin(future::ready(Ok(Some(oauth_client.clone())))));
+
+        MockOAuthClientService::new(mock_oauth_client_datastore, AuthConfig::fake())
+    }
+
+    #[tokio::test]
+    async fn test_register_client_split_scopes() {
+        let mut mock_oauth_client_datastore = MockOAuthClientDatastore::new();
+        mock_oauth_client_datastore.expect_add_client()
+            .times(1)
+            .withf(|oauth_client| oauth_client.scopes == vec!["users:read".to_string(), "users:write".to_string()])
+            .returning(|oauth_client| Box::pin(future::ready(Ok(oauth_client))));
+
+        let oauth_client_service = MockOAuthClientService::new(mock_oauth_client_datastore, AuthConfig::fake());
+        let oauth_client_body = oauth_client_service.register_client(RegisterOAuthClientPayload { name: "billing".to_string(), scopes: vec!["users:read users:write".to_string(), "users:read".to_string()], redirect_uris: Vec::new() }).await.unwrap();
+
+        assert!(!oauth_client_body.client_secret.is_empty());
+        assert_eq!(oauth_client_body.details.name, "billing");
+    }
+
+    #[tokio::test]
+    async fn test_issue_token_for_service_principal() {
+        let (oauth_client, client_secret) = OAuthClient::generate("billing", vec!["users:read".to_string(), "users:write".to_string()], Vec::new());
+        let client_id = oauth_client.client_id.clone();
+        let oauth_client_service = oauth_client_service(oauth_client);
+
+        let token_body = oauth_client_service.issue_client_credentials_token(&client_id, &client_secret, Some("users:read".to_string())).await.unwrap();
+        let auth_claims = AuthClaims::try_from(&TokenString(token_body.access_token).try_into_claims(&AuthConfig::fake()).await.unwrap()).unwrap();
+
+        assert_eq!(token_body.token_type, "Bearer");
+        assert_eq!(token_body.scope, "users:read");
+        assert!(token_body.expires_in > 0);
+        assert_eq!(auth_claims.claim_type, TokenType::Service);
+        assert_eq!(auth_claims.username, client_id);
+        assert_eq!(auth_claims.scopes, vec!["users:read".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_issue_token_with_wrong_secret_or_scope() {
+        let (oauth_client, client_secret) = OAuthClient::generate("billing", vec!["users:read".to_string()], Vec::new());
+        let client_id = oauth_client.client_id.clone();
+        let oauth_client_service = oauth_client_service(oauth_client);
+
+        assert_eq!(oauth_client_service.issue_client_credentials_token(&client_id, "wrong_secret", None).await, Err(OAuthError::InvalidClient));
+        assert_eq!(oauth_client_service.issue_client_credentials_token(&client_id, &client_secret, Some("users:write".to_string())).await, Err(OAuthError::InvalidScope));
+    }
+}
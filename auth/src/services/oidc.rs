@@ -0,0 +1,256 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use mongodb::bson::DateTime;
+use rand::{Rng, RngCore};
+use crate::datastore::{AuthDatastore, OidcDatastore, TokenDatastore};
+use crate::entities::error::AuthError;
+use crate::entities::{OidcLink, OidcLoginState, Roles, UserCredentials};
+use crate::services::{AuthOidcService, OidcService};
+use crate::utils::oidc::{OidcIdentity, OidcProvider};
+use crate::views::payload::OidcCallbackPayload;
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, OidcDatastoreImpl> OidcService<AuthDatastoreImpl, TokenDatastoreImpl, OidcDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    OidcDatastoreImpl: OidcDatastore,
+{
+    const MAX_USERNAME_ATTEMPTS: usize = 5;
+
+    fn get_provider(&self, provider: &str) -> Result<&OidcProvider, AuthError> {
+        self.oidc_providers.iter()
+            .find(|oidc_provider| oidc_provider.name() == provider)
+            .map(|oidc_provider| oidc_provider.as_ref())
+            .ok_or(AuthError::NotFound)
+    }
+
+    /// Username wished by the provider, suffixed when already used : an upstream account never takes over a local user
+    async fn find_available_username(&self, identity: &OidcIdentity) -> Result<String, AuthError> {
+        let wished_username: String = identity.preferred_username.clone()
+            .or_else(|| identity.email.as_ref().and_then(|email| email.split('@').next().map(str::to_string)))
+            .unwrap_or_else(|| format!("{}_{}", identity.provider, identity.subject))
+            .chars()
+            .filter(|character| character.is_alphanumeric() || ['.', '_', '-'].contains(character))
+            .collect();
+        let wished_username = if wished_username.is_empty() { identity.provider.clone() } else { wished_username };
+
+        for attempt in 0..Self::MAX_USERNAME_ATTEMPTS {
+            let username = match attempt {
+                0 => wished_username.clone(),
+                _ => format!("{}_{:04}", wished_username, rand::thread_rng().gen_range(0..10_000)),
+            };
+
+            if self.auth_service.auth_datastore.get_user_by_username(&username).await.map_err(|_| AuthError::ServerError)?.is_none() {
+                return Ok(username);
+            }
+        }
+
+        Err(AuthError::Duplicated)
+    }
+
+    /// Credentials with a random password nobody knows : the user can set one with a password reset
+    async fn create_user(&self, identity: &OidcIdentity) -> Result<UserCredentials, AuthError> {
+        let mut password_bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut password_bytes);
+
+        let user = self.auth_service.auth_datastore.add_user(UserCredentials {
+            id: None,
+            username: self.find_available_username(identity).await?,
+            password: UserCredentials::hash_password(URL_SAFE_NO_PAD.encode(password_bytes), &self.auth_service.auth_config),
+            roles: vec![Roles::User],
+            created_at: DateTime::now(),
+            last_modified_at: DateTime::now(),
+        }).await.map_err(|_| AuthError::ServerError)?;
+
+        self.oidc_datastore.add_link(OidcLink {
+            id: None,
+            provider: identity.provider.clone(),
+            subject: identity.subject.clone(),
+            username: user.username.clone(),
+            created_at: DateTime::now(),
+        }).await.map_err(|_| AuthError::ServerError)?;
+
+        self.user_provisioning.provision_user(&user.username, identity).await?;
+
+        Ok(user)
+    }
+}
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, OidcDatastoreImpl> AuthOidcService for OidcService<AuthDatastoreImpl, TokenDatastoreImpl, OidcDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    OidcDatastoreImpl: OidcDatastore,
+{
+    async fn start_login(&self, provider: &str) -> Result<String, AuthError> {
+        let oidc_provider = self.get_provider(provider)?;
+        let oidc_login_state = OidcLoginState::generate(provider);
+        let authorization_url = oidc_provider.authorization_url(&oidc_login_state).await?;
+
+        self.oidc_datastore.add_login_state(oidc_login_state).await.map_err(|_| AuthError::ServerError)?;
+
+        Ok(authorization_url)
+    }
+
+    async fn finish_login(&self, provider: &str, callback_payload: OidcCallbackPayload) -> Result<UserCredentials, AuthError> {
+        let oidc_provider = self.get_provider(provider)?;
+        // The state is consumed even when the provider returns an error
+        let oidc_login_state = self.oidc_datastore.take_login_state(&callback_payload.state)
+            .await
+            .map_err(|_| AuthError::ServerError)?
+            .filter(|oidc_login_state| oidc_login_state.is_valid(provider))
+            .ok_or(AuthError::InvalidToken)?;
+
+        if callback_payload.error.is_some() {
+            return Err(AuthError::Unauthorized);
+        }
+        let code = callback_payload.code.ok_or(AuthError::MissingCredentials)?;
+        let identity = oidc_provider.authenticate(&code, &oidc_login_state).await?;
+
+        let oidc_link = self.oidc_datastore.get_link(provider, &identity.subject).await.map_err(|_| AuthError::ServerError)?;
+        match oidc_link {
+            Some(oidc_link) => self.auth_service.auth_datastore.get_user_by_username(&oidc_link.username)
+                .await
+                .map_err(|_| AuthError::ServerError)?
+                .ok_or(AuthError::WrongCredentials),
+            None => self.create_user(&identity).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use std::sync::{Arc, Mutex};
+    use fake::{Fake, Faker};
+    use futures_util::future::BoxFuture;
+    use mockall::predicate::eq;
+    use mongodb::bson::DateTime;
+    use crate::datastore::{MockAuthDatastore, MockOidcDatastore, MockTokenDatastore};
+    use crate::entities::error::AuthError;
+    use crate::entities::{OidcLink, OidcLoginState, Roles, UserCredentials};
+    use crate::services::{AuthOidcService, AuthService, MockOidcService};
+    use crate::utils::oidc::mock_identity_provider::{MockIdentityProvider, CLIENT_ID, CLIENT_SECRET};
+    use crate::utils::oidc::{NoUserProvisioning, OidcIdentity, OidcProvider, OidcUserProvisioning};
+    use crate::views::payload::OidcCallbackPayload;
+
+    /// Remember the users provisioned, like the user module would create their profile
+    #[derive(Default)]
+    struct RecordUserProvisioning {
+        provisioned_usernames: Mutex<Vec<String>>,
+    }
+
+    impl OidcUserProvisioning for RecordUserProvisioning {
+        fn provision_user<'a>(&'a self, username: &'a str, _identity: &'a OidcIdentity) -> BoxFuture<'a, Result<(), AuthError>> {
+            self.provisioned_usernames.lock().unwrap().push(username.to_string());
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn oidc_service(mock_identity_provider: &MockIdentityProvider, auth_datastore: MockAuthDatastore, token_datastore: MockTokenDatastore, oidc_datastore: MockOidcDatastore, user_provisioning: Arc<dyn OidcUserProvisioning>) -> MockOidcService {
+        let oidc_provider = OidcProvider::new("mock", &mock_identity_provider.issuer(), CLIENT_ID, CLIENT_SECRET, "http://localhost/callback");
+
+        MockOidcService::new(Arc::new(AuthService::new(auth_datastore, token_datastore, AuthConfig::fake())), oidc_datastore, vec![Arc::new(oidc_provider)], user_provisioning)
+    }
+
+    /// Login states are kept in the mock like the datastore would, to be taken back by the callback
+    fn expect_login_state(mock_oidc_datastore: &mut MockOidcDatastore) {
+        let login_states: Arc<Mutex<Vec<OidcLoginState>>> = Arc::new(Mutex::new(Vec::new()));
+        let added_login_states = login_states.clone();
+
+        mock_oidc_datastore.expect_add_login_state().times(1).returning(move |oidc_login_state| {
+            added_login_states.lock().unwrap().push(oidc_login_state);
+            Box::pin(future::ready(Ok(())))
+        });
+        mock_oidc_datastore.expect_take_login_state().times(1).returning(move |state| {
+            let mut login_states = login_states.lock().unwrap();
+            let oidc_login_state = login_states.iter().position(|oidc_login_state| oidc_login_state.state == state).map(|position| login_states.remove(position));
+            Box::pin(future::ready(Ok(oidc_login_state)))
+        });
+    }
+
+    #[tokio::test]
+    async fn test_first_login_create_user() {
+        let mock_identity_provider = MockIdentityProvider::start().await;
+        let mut mock_oidc_datastore = MockOidcDatastore::new();
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        expect_login_state(&mut mock_oidc_datastore);
+        mock_oidc_datastore.expect_get_link().with(eq("mock"), eq("subject")).times(1).returning(|_, _| Box::pin(future::ready(Ok(None))));
+        mock_oidc_datastore.expect_add_link()
+            .withf(|oidc_link| oidc_link.provider == "mock" && oidc_link.subject == "subject" && oidc_link.username == "juliana")
+            .times(1)
+            .returning(|oidc_link| Box::pin(future::ready(Ok(oidc_link))));
+        mock_auth_datastore.expect_get_user_by_username().with(eq("juliana")).times(1).returning(|_| Box::pin(future::ready(Ok(None))));
+        mock_auth_datastore.expect_add_user()
+            .withf(|user| user.username == "juliana" && user.roles == vec![Roles::User])
+            .times(1)
+            .returning(|user| Box::pin(future::ready(Ok(user))));
+        let user_provisioning = Arc::new(RecordUserProvisioning::default());
+        let oidc_service = oidc_service(&mock_identity_provider, mock_auth_datastore, MockTokenDatastore::new(), mock_oidc_datastore, user_provisioning.clone());
+
+        let authorization_url = oidc_service.start_login("mock").await.unwrap();
+        let (code, state) = mock_identity_provider.authorize(&authorization_url, "subject", "juliana");
+        let user = oidc_service.finish_login("mock", OidcCallbackPayload { code: Some(code), state, error: None }).await.unwrap();
+
+        assert_eq!(user.username, "juliana");
+        assert_eq!(*user_provisioning.provisioned_usernames.lock().unwrap(), vec!["juliana".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_first_login_never_take_over_local_user() {
+        let mock_identity_provider = MockIdentityProvider::start().await;
+        let mut mock_oidc_datastore = MockOidcDatastore::new();
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        expect_login_state(&mut mock_oidc_datastore);
+        mock_oidc_datastore.expect_get_link().times(1).returning(|_, _| Box::pin(future::ready(Ok(None))));
+        mock_oidc_datastore.expect_add_link().times(1).returning(|oidc_link| Box::pin(future::ready(Ok(oidc_link))));
+        mock_auth_datastore.expect_get_user_by_username().with(eq("juliana")).times(1).returning(|username| Box::pin(future::ready(Ok(Some(UserCredentials { username: username.to_string(), ..Faker.fake() })))));
+        mock_auth_datastore.expect_get_user_by_username().times(1).returning(|_| Box::pin(future::ready(Ok(None))));
+        mock_auth_datastore.expect_add_user()
+            .withf(|user| user.username.starts_with("juliana_"))
+            .times(1)
+            .returning(|user| Box::pin(future::ready(Ok(user))));
+        let oidc_service = oidc_service(&mock_identity_provider, mock_auth_datastore, MockTokenDatastore::new(), mock_oidc_datastore, Arc::new(NoUserProvisioning));
+
+        let authorization_url = oidc_service.start_login("mock").await.unwrap();
+        let (code, state) = mock_identity_provider.authorize(&authorization_url, "subject", "juliana");
+
+        assert!(oidc_service.finish_login("mock", OidcCallbackPayload { code: Some(code), state, error: None }).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_linked_user_login() {
+        let mock_identity_provider = MockIdentityProvider::start().await;
+        let mut mock_oidc_datastore = MockOidcDatastore::new();
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        expect_login_state(&mut mock_oidc_datastore);
+        mock_oidc_datastore.expect_get_link().times(1).returning(|provider, subject| Box::pin(future::ready(Ok(Some(OidcLink {
+            id: None,
+            provider: provider.to_string(),
+            subject: subject.to_string(),
+            username: "linked_username".to_string(),
+            created_at: DateTime::now(),
+        })))));
+        mock_oidc_datastore.expect_add_link().never();
+        mock_auth_datastore.expect_get_user_by_username().with(eq("linked_username")).times(1).returning(|username| Box::pin(future::ready(Ok(Some(UserCredentials { username: username.to_string(), ..Faker.fake() })))));
+        mock_auth_datastore.expect_add_user().never();
+        let oidc_service = oidc_service(&mock_identity_provider, mock_auth_datastore, MockTokenDatastore::new(), mock_oidc_datastore, Arc::new(NoUserProvisioning));
+
+        let authorization_url = oidc_service.start_login("mock").await.unwrap();
+        let (code, state) = mock_identity_provider.authorize(&authorization_url, "subject", "juliana");
+
+        assert!(oidc_service.finish_login("mock", OidcCallbackPayload { code: Some(code), state, error: None }).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_callback_with_unknown_state_or_provider() {
+        let mock_identity_provider = MockIdentityProvider::start().await;
+        let mut mock_oidc_datastore = MockOidcDatastore::new();
+        mock_oidc_datastore.expect_take_login_state().times(1).returning(|_| Box::pin(future::ready(Ok(None))));
+        let oidc_service = oidc_service(&mock_identity_provider, MockAuthDatastore::new(), MockTokenDatastore::new(), mock_oidc_datastore, Arc::new(NoUserProvisioning));
+
+        assert_eq!(oidc_service.start_login("unknown").await, Err(AuthError::NotFound));
+        assert_eq!(oidc_service.finish_login("mock", OidcCallbackPayload { code: Some("code".to_string()), state: "state".to_string(), error: None }).await.map(|_| ()), Err(AuthError::InvalidToken));
+    }
+}
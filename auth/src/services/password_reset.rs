@@ -0,0 +1,278 @@
+use crate::datastore::{AuthDatastore, PasswordResetDatastore, TokenDatastore};
+use crate::entities::error::AuthError;
+use crate::entities::{PasswordReset, UserCredentials};
+use crate::services::{AuthPasswordResetService, AuthRevokeTokensService, PasswordResetService};
+use crate::views::payload::PasswordResetConfirmPayload;
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, PasswordResetDatastoreImpl> AuthPasswordResetService for PasswordResetService<AuthDatastoreImpl, TokenDatastoreImpl, PasswordResetDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    PasswordResetDatastoreImpl: PasswordResetDatastore,
+{
+    async fn request_password_reset(&self, username: &str) -> Result<(), AuthError> {
+        // Refused before the lookup, so the answer is the same for every username
+        let Some(password_reset_sender) = &self.password_reset_sender else {
+            return Err(AuthError::ServerError);
+        };
+
+        let user_credentials = self.auth_service.auth_datastore.get_user_by_username(username)
+            .await
+            .map_err(|_| AuthError::ServerError)?;
+
+        // Unknown username answer the same way to not reveal which accounts exist
+        let Some(user_credentials) = user_credentials else {
+            return Ok(());
+        };
+
+        let (password_reset, reset_token) = PasswordReset::generate(&user_credentials.username);
+        let reset_token_hash = password_reset.reset_token_hash.clone();
+
+        // Failures for this user are only logged, an error would reveal the account exists
+        if let Err(error) = self.password_reset_datastore.add_password_reset(password_reset).await {
+            tracing::warn!("Unable store password reset of {} : {}", user_credentials.username, error);
+            return Ok(());
+        }
+
+        if let Err(error) = password_reset_sender.send_reset_token(&user_credentials.username, &reset_token).await {
+            tracing::warn!("Unable send password reset token to {} : {}", user_credentials.username, error);
+
+            if let Err(error) = self.password_reset_datastore.delete_password_reset(&reset_token_hash).await {
+                tracing::warn!("Unable delete unsent password reset of {} : {}", user_credentials.username, error);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn confirm_password_reset(&self, password_reset_confirm_payload: PasswordResetConfirmPayload) -> Result<(), AuthError> {
+        if password_reset_confirm_payload.reset_token.is_empty() || password_reset_confirm_payload.new_password.is_empty() {
+            return Err(AuthError::MissingCredentials);
+        }
+
+        let reset_token_hash = PasswordReset::hash_reset_token(&password_reset_confirm_payload.reset_token);
+        let password_reset = self.password_reset_datastore.get_password_reset(&reset_token_hash)
+            .await
+            .map_err(|_| AuthError::ServerError)?
+            .filter(|password_reset| password_reset.is_valid())
+            .ok_or(AuthError::InvalidToken)?;
+
+        // Checked before using the reset token, so the user can retry with another password
+        UserCredentials::validate_password(&password_reset.username, &password_reset_confirm_payload.new_password, &self.auth_service.auth_config)?;
+
+        // Fails when the same reset token is used concurrently
+        self.password_reset_datastore.use_password_reset(&reset_token_hash).await.map_err(|_| AuthError::InvalidToken)?;
+
+        let password_hash = UserCredentials::hash_password(password_reset_confirm_payload.new_password, &self.auth_service.auth_config);
+        self.auth_service.auth_datastore.update_password(&password_reset.username, &password_hash).await.map_err(|_| AuthError::ServerError)?;
+
+        self.auth_service.revoke_all_sessions(&password_reset.username).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use std::sync::{Arc, Mutex};
+    use fake::{Fake, Faker};
+    use futures_util::future::BoxFuture;
+    use mockall::predicate::eq;
+    use mongodb::bson::DateTime;
+    use crate::datastore::{MockAuthDatastore, MockPasswordResetDatastore, MockTokenDatastore, PasswordResetDatastoreError};
+    use crate::entities::error::AuthError;
+    use crate::entities::{PasswordReset, Token, UserCredentials};
+    use crate::services::{AuthPasswordResetService, AuthService, MockPasswordResetService};
+    use crate::utils::password_reset_sender::PasswordResetSender;
+    use crate::views::payload::PasswordResetConfirmPayload;
+
+    #[derive(Default)]
+    struct MemoryPasswordResetSender {
+        reset_tokens: Mutex<Vec<(String, String)>>,
+        failing: bool,
+    }
+
+    impl PasswordResetSender for MemoryPasswordResetSender {
+        fn send_reset_token<'a>(&'a self, username: &'a str, reset_token: &'a str) -> BoxFuture<'a, Result<(), AuthError>> {
+            if self.failing {
+                return Box::pin(future::ready(Err(AuthError::ServerError)));
+            }
+            self.reset_tokens.lock().unwrap().push((username.to_string(), reset_token.to_string()));
+            Box::pin(future::ready(Ok(())))
+        }
+    }
+
+    fn password_reset_service(mock_auth_datastore: MockAuthDatastore, mock_tokens_datastore: MockTokenDatastore, mock_password_reset_datastore: MockPasswordResetDatastore, sender: Arc<MemoryPasswordResetSender>) -> MockPasswordResetService {
+        MockPasswordResetService::new(Arc::new(AuthService::new(mock_auth_datastore, mock_tokens_datastore, AuthConfig::fake())), mock_password_reset_datastore, Some(sender))
+    }
+
+    #[tokio::test]
+    async fn test_request_password_reset_send_token() {
+        let user_credentials: UserCredentials = Faker.fake();
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        let mut mock_password_reset_datastore = MockPasswordResetDatastore::new();
+        let sender = Arc::new(MemoryPasswordResetSender::default());
+
+        let user_credentials_found = user_credentials.clone();
+        mock_auth_datastore.expect_get_user_by_username()
+            .with(eq(user_credentials.username.clone()))
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(user_credentials_found.clone())))));
+        mock_password_reset_datastore.expect_add_password_reset()
+            .times(1)
+            .returning(|password_reset| Box::pin(future::ready(Ok(password_reset))));
+
+        let password_reset_service = password_reset_service(mock_auth_datastore, MockTokenDatastore::new(), mock_password_reset_datastore, sender.clone());
+        let result = password_reset_service.request_password_reset(&user_credentials.username).await;
+
+        assert!(result.is_ok());
+        let reset_tokens = sender.reset_tokens.lock().unwrap();
+        assert_eq!(reset_tokens.len(), 1);
+        assert_eq!(reset_tokens[0].0, user_credentials.username);
+    }
+
+    #[tokio::test]
+    async fn test_request_password_reset_without_sender() {
+        let user_credentials: UserCredentials = Faker.fake();
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        let mut mock_password_reset_datastore = MockPasswordResetDatastore::new();
+
+        mock_auth_datastore.expect_get_user_by_username().times(0);
+        mock_password_reset_datastore.expect_add_password_reset().times(0);
+
+        let password_reset_service = MockPasswordResetService::new(Arc::new(AuthService::new(mock_auth_datastore, MockTokenDatastore::new(), AuthConfig::fake())), mock_password_reset_datastore, None);
+        let known_username_result = password_reset_service.request_password_reset(&user_credentials.username).await;
+        let unknown_username_result = password_reset_service.request_password_reset("unknown_username").await;
+
+        assert_eq!(known_username_result, Err(AuthError::ServerError));
+        assert_eq!(known_username_result, unknown_username_result);
+    }
+
+    #[tokio::test]
+    async fn test_request_password_reset_delete_unsent_reset() {
+        let user_credentials: UserCredentials = Faker.fake();
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        let mut mock_password_reset_datastore = MockPasswordResetDatastore::new();
+        let sender = Arc::new(MemoryPasswordResetSender { failing: true, ..MemoryPasswordResetSender::default() });
+        let stored_reset_token_hash = Arc::new(Mutex::new(String::new()));
+
+        mock_auth_datastore.expect_get_user_by_username()
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(user_credentials.clone())))));
+        let stored_reset_token_hash_added = stored_reset_token_hash.clone();
+        mock_password_reset_datastore.expect_add_password_reset()
+            .times(1)
+            .returning(move |password_reset| {
+                *stored_reset_token_hash_added.lock().unwrap() = password_reset.reset_token_hash.clone();
+                Box::pin(future::ready(Ok(password_reset)))
+            });
+        let stored_reset_token_hash_deleted = stored_reset_token_hash.clone();
+        mock_password_reset_datastore.expect_delete_password_reset()
+            .withf(move |reset_token_hash| reset_token_hash == *stored_reset_token_hash_deleted.lock().unwrap())
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+
+        let password_reset_service = password_reset_service(mock_auth_datastore, MockTokenDatastore::new(), mock_password_reset_datastore, sender);
+        let result = password_reset_service.request_password_reset("username").await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_request_password_reset_storage_error_hidden() {
+        let user_credentials: UserCredentials = Faker.fake();
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        let mut mock_password_reset_datastore = MockPasswordResetDatastore::new();
+        let sender = Arc::new(MemoryPasswordResetSender::default());
+
+        mock_auth_datastore.expect_get_user_by_username()
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(user_credentials.clone())))));
+        mock_password_reset_datastore.expect_add_password_reset()
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Err(PasswordResetDatastoreError::ProvidersError))));
+
+        let password_reset_service = password_reset_service(mock_auth_datastore, MockTokenDatastore::new(), mock_password_reset_datastore, sender.clone());
+        let result = password_reset_service.request_password_reset("username").await;
+
+        assert!(result.is_ok());
+        assert!(sender.reset_tokens.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_password_reset_unknown_username() {
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        let mut mock_password_reset_datastore = MockPasswordResetDatastore::new();
+        let sender = Arc::new(MemoryPasswordResetSender::default());
+
+        mock_auth_datastore.expect_get_user_by_username()
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(None))));
+        mock_password_reset_datastore.expect_add_password_reset().times(0);
+
+        let password_reset_service = password_reset_service(mock_auth_datastore, MockTokenDatastore::new(), mock_password_reset_datastore, sender.clone());
+        let result = password_reset_service.request_password_reset("unknown_username").await;
+
+        assert!(result.is_ok());
+        assert!(sender.reset_tokens.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_confirm_password_reset_update_password_and_revoke_sessions() {
+        let (password_reset, reset_token) = PasswordReset::generate("username");
+        let token = Token { username: "username".to_string(), ..Faker.fake() };
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        let mut mock_password_reset_datastore = MockPasswordResetDatastore::new();
+
+        mock_password_reset_datastore.expect_get_password_reset()
+            .with(eq(password_reset.reset_token_hash.clone()))
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(password_reset.clone())))));
+        mock_password_reset_datastore.expect_use_password_reset()
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+        mock_auth_datastore.expect_update_password()
+            .withf(|username, _| username == "username")
+            .times(1)
+            .returning(|_, _| Box::pin(future::ready(Ok(()))));
+        let tokens = vec![token.clone()];
+        mock_tokens_datastore.expect_get_tokens_for_user()
+            .with(eq("username".to_string()))
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(tokens.clone()))));
+        mock_tokens_datastore.expect_revoke_token()
+            .with(eq(token.token_refresh_identifiers.clone()))
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+
+        let password_reset_service = password_reset_service(mock_auth_datastore, mock_tokens_datastore, mock_password_reset_datastore, Arc::new(MemoryPasswordResetSender::default()));
+        let result = password_reset_service.confirm_password_reset(PasswordResetConfirmPayload {
+            reset_token,
+            new_password: "my_new_password".to_string(),
+        }).await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_confirm_password_reset_already_used() {
+        let (password_reset, reset_token) = PasswordReset::generate("username");
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        let mut mock_password_reset_datastore = MockPasswordResetDatastore::new();
+
+        mock_password_reset_datastore.expect_get_password_reset()
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(PasswordReset { used_at: Some(DateTime::now()), ..password_reset.clone() })))));
+        mock_password_reset_datastore.expect_use_password_reset().times(0);
+        mock_auth_datastore.expect_update_password().times(0);
+
+        let password_reset_service = password_reset_service(mock_auth_datastore, MockTokenDatastore::new(), mock_password_reset_datastore, Arc::new(MemoryPasswordResetSender::default()));
+        let result = password_reset_service.confirm_password_reset(PasswordResetConfirmPayload {
+            reset_token,
+            new_password: "my_new_password".to_string(),
+        }).await;
+
+        assert_eq!(result, Err(AuthError::InvalidToken));
+    }
+}
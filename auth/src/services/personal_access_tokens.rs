@@ -0,0 +1,92 @@
+use mongodb::bson::DateTime;
+use crate::datastore::PersonalAccessTokenDatastore;
+use crate::entities::error::AuthError;
+use crate::entities::PersonalAccessToken;
+use crate::services::{AuthPersonalAccessTokensService, PersonalAccessTokenService};
+use crate::views::payload::CreatePersonalAccessTokenPayload;
+use crate::views::response::{PersonalAccessTokenBody, PersonalAccessTokenDetails};
+
+impl<PersonalAccessTokenDatastoreImpl: PersonalAccessTokenDatastore> AuthPersonalAccessTokensService for PersonalAccessTokenService<PersonalAccessTokenDatastoreImpl> {
+    async fn create_personal_access_token(&self, username: &str, payload: CreatePersonalAccessTokenPayload) -> Result<PersonalAccessTokenBody, AuthError> {
+        let expired_at = payload.expires_in_days
+            .map(|expires_in_days| DateTime::from_millis(DateTime::now().timestamp_millis() + i64::from(expires_in_days) * 24 * 60 * 60 * 1000));
+        let (personal_access_token, token) = PersonalAccessToken::generate(username, &payload.name, expired_at);
+
+        let personal_access_token = self.personal_access_token_datastore.add_personal_access_token(personal_access_token)
+            .await
+            .map_err(|_| AuthError::ServerError)?;
+
+        Ok(PersonalAccessTokenBody {
+            token,
+            details: personal_access_token.into(),
+        })
+    }
+
+    async fn get_personal_access_tokens(&self, username: &str) -> Result<Vec<PersonalAccessTokenDetails>, AuthError> {
+        let personal_access_tokens = self.personal_access_token_datastore.get_personal_access_tokens(username)
+            .await
+            .map_err(|_| AuthError::ServerError)?;
+
+        Ok(personal_access_tokens.into_iter().map(PersonalAccessTokenDetails::from).collect())
+    }
+
+    async fn revoke_personal_access_token(&self, username: &str, token_id: &str) -> Result<(), AuthError> {
+        self.personal_access_token_datastore.revoke_personal_access_token(username, token_id).await.map_err(|_| AuthError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+    use crate::datastore::{MockPersonalAccessTokenDatastore, PersonalAccessTokenDatastoreError};
+    use crate::entities::error::AuthError;
+    use crate::entities::PersonalAccessToken;
+    use crate::services::{AuthPersonalAccessTokensService, MockPersonalAccessTokenService};
+    use crate::views::payload::CreatePersonalAccessTokenPayload;
+
+    #[tokio::test]
+    async fn test_create_personal_access_token_store_hash() {
+        let mut mock_personal_access_token_datastore = MockPersonalAccessTokenDatastore::new();
+        mock_personal_access_token_datastore.expect_add_personal_access_token()
+            .times(1)
+            .withf(|personal_access_token| personal_access_token.username == "username" && personal_access_token.name == "ci" && personal_access_token.expired_at.is_some())
+            .returning(|personal_access_token| Box::pin(future::ready(Ok(personal_access_token))));
+
+        let personal_access_token_service = MockPersonalAccessTokenService::new(mock_personal_access_token_datastore);
+        let personal_access_token_body = personal_access_token_service.create_personal_access_token("username", CreatePersonalAccessTokenPayload { name: "ci".to_string(), expires_in_days: Some(30) }).await.unwrap();
+
+        assert!(PersonalAccessToken::is_personal_access_token(&personal_access_token_body.token));
+        assert_eq!(personal_access_token_body.details.name, "ci");
+        assert!(personal_access_token_body.details.expired_at.is_some());
+        assert!(!personal_access_token_body.details.revoked);
+    }
+
+    #[tokio::test]
+    async fn test_list_personal_access_tokens_without_hash() {
+        let (personal_access_token, _) = PersonalAccessToken::generate("username", "ci", None);
+        let token_id = personal_access_token.token_id.clone();
+        let mut mock_personal_access_token_datastore = MockPersonalAccessTokenDatastore::new();
+        mock_personal_access_token_datastore.expect_get_personal_access_tokens()
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(vec![personal_access_token.clone()]))));
+
+        let personal_access_token_service = MockPersonalAccessTokenService::new(mock_personal_access_token_datastore);
+        let personal_access_tokens = personal_access_token_service.get_personal_access_tokens("username").await.unwrap();
+
+        assert_eq!(personal_access_tokens.len(), 1);
+        assert_eq!(personal_access_tokens[0].token_id, token_id);
+        assert_eq!(personal_access_tokens[0].expired_at, None);
+    }
+
+    #[tokio::test]
+    async fn test_revoke_unknown_personal_access_token() {
+        let mut mock_personal_access_token_datastore = MockPersonalAccessTokenDatastore::new();
+        mock_personal_access_token_datastore.expect_revoke_personal_access_token()
+            .times(1)
+            .returning(|_, _| Box::pin(future::ready(Err(PersonalAccessTokenDatastoreError::InternalError))));
+
+        let personal_access_token_service = MockPersonalAccessTokenService::new(mock_personal_access_token_datastore);
+
+        assert_eq!(personal_access_token_service.revoke_personal_access_token("username", "unknown").await, Err(AuthError::NotFound));
+    }
+}
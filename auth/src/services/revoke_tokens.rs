@@ -0,0 +1,125 @@
+use crate::datastore::{AuthDatastore, TokenDatastore};
+use crate::entities::error::AuthError;
+use crate::entities::AuthSession;
+use crate::services::{AuthRevokeTokensService, AuthService};
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl> AuthRevokeTokensService for AuthService<AuthDatastoreImpl, TokenDatastoreImpl>
+    where AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore
+{
+    async fn revoke_session(&self, auth_session: &AuthSession) -> Result<(), AuthError> {
+        let token_access_identifier = auth_session.token_identifier.as_ref().ok_or(AuthError::Unauthorized)?;
+
+        let token = self.token_datastore.get_token_by_access_identifier(token_access_identifier)
+            .await
+            .map_err(|_| AuthError::ServerError)?
+            .filter(|token| token.username == auth_session.username)
+            .ok_or(AuthError::InvalidToken)?;
+
+        if token.revoked_at.is_some() {
+            return Ok(());
+        }
+
+        self.token_datastore.revoke_token(&token.token_refresh_identifiers).await.map_err(|_| AuthError::ServerError)
+    }
+
+    async fn revoke_all_sessions(&self, username: &str) -> Result<(), AuthError> {
+        let tokens = self.token_datastore.get_tokens_for_user(username).await.map_err(|_| AuthError::ServerError)?;
+
+        for token in tokens.iter().filter(|token| token.revoked_at.is_none()) {
+            self.token_datastore.revoke_token(&token.token_refresh_identifiers).await.map_err(|_| AuthError::ServerError)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use fake::{Fake, Faker};
+    use fake::faker::internet::en::Username;
+    use mockall::predicate::eq;
+    use mongodb::bson::DateTime;
+    use mongodb::bson::oid::ObjectId;
+    use crate::datastore::{MockAuthDatastore, MockTokenDatastore};
+    use crate::entities::{AuthMethod, AuthSession, Roles, Token};
+    use crate::entities::error::AuthError;
+    use crate::services::{AuthRevokeTokensService, MockAuthService};
+
+    #[tokio::test]
+    async fn test_revoke_session() {
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        let token = Token { id: Some(ObjectId::new()), ..Faker.fake() };
+        let auth_session = AuthSession {
+            username: token.username.clone(),
+            roles: vec![Roles::User],
+            token_identifier: Some(token.token_access_identifiers.clone()),
+            auth_method: AuthMethod::AccessToken,
+        };
+
+        let token_found = token.clone();
+        mock_tokens_datastore.expect_get_token_by_access_identifier()
+            .with(eq(token.token_access_identifiers.clone()))
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(token_found.clone())))));
+        mock_tokens_datastore.expect_revoke_token()
+            .with(eq(token.token_refresh_identifiers.clone()))
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+
+        let auth_service = MockAuthService::new(MockAuthDatastore::new(), mock_tokens_datastore, AuthConfig::fake());
+        let result = auth_service.revoke_session(&auth_session).await;
+
+        auth_service.checkpoint();
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_session_of_other_user() {
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        let token = Token { id: Some(ObjectId::new()), ..Faker.fake() };
+        let auth_session = AuthSession {
+            username: Username().fake(),
+            roles: vec![Roles::User],
+            token_identifier: Some(token.token_access_identifiers.clone()),
+            auth_method: AuthMethod::AccessToken,
+        };
+
+        mock_tokens_datastore.expect_get_token_by_access_identifier()
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(token.clone())))));
+        mock_tokens_datastore.expect_revoke_token().times(0);
+
+        let auth_service = MockAuthService::new(MockAuthDatastore::new(), mock_tokens_datastore, AuthConfig::fake());
+        let result = auth_service.revoke_session(&auth_session).await;
+
+        auth_service.checkpoint();
+        assert_eq!(result, Err(AuthError::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_sessions() {
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        let username: String = Username().fake();
+
+        let username_owner = username.clone();
+        mock_tokens_datastore.expect_get_tokens_for_user()
+            .with(eq(username.clone()))
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(vec![
+                Token { username: username_owner.clone(), ..Faker.fake() },
+                Token { username: username_owner.clone(), ..Faker.fake() },
+                Token { username: username_owner.clone(), revoked_at: Some(DateTime::now()), ..Faker.fake() },
+            ]))));
+        mock_tokens_datastore.expect_revoke_token()
+            .times(2)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+
+        let auth_service = MockAuthService::new(MockAuthDatastore::new(), mock_tokens_datastore, AuthConfig::fake());
+        let result = auth_service.revoke_all_sessions(&username).await;
+
+        auth_service.checkpoint();
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,328 @@
+use crate::datastore::{AuthDatastore, RoleDatastore, RoleDatastoreError, TokenDatastore};
+use crate::entities::error::AuthError;
+use crate::entities::{AuthSession, Privileges, Role, Roles};
+use crate::services::{AuthRevokeTokensService, AuthRolesService, RoleService};
+use crate::views::payload::SaveRolePayload;
+use crate::views::response::{RoleDetails, UserRolesDetails};
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, RoleDatastoreImpl> RoleService<AuthDatastoreImpl, TokenDatastoreImpl, RoleDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    RoleDatastoreImpl: RoleDatastore,
+{
+    async fn get_definitions(&self) -> Result<Vec<Role>, AuthError> {
+        let roles = self.role_datastore.get_roles().await.map_err(|_| AuthError::ServerError)?;
+
+        Ok(Role::with_defaults(roles))
+    }
+}
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, RoleDatastoreImpl> AuthRolesService for RoleService<AuthDatastoreImpl, TokenDatastoreImpl, RoleDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    RoleDatastoreImpl: RoleDatastore,
+{
+    async fn get_user_roles(&self, username: &str) -> Result<UserRolesDetails, AuthError> {
+        let user_credentials = self.auth_service.auth_datastore.get_user_by_username(username)
+            .await
+            .map_err(|_| AuthError::ServerError)?
+            .ok_or(AuthError::NotFound)?;
+
+        Ok(UserRolesDetails::from(user_credentials))
+    }
+
+    async fn update_user_roles(&self, auth_session: &AuthSession, username: &str, roles: Vec<Roles>) -> Result<UserRolesDetails, AuthError> {
+        let definitions = self.get_definitions().await?;
+        let has_privileges = |roles: &[Roles], privileges: Privileges| Role::is_authorized(roles, &definitions, &privileges);
+
+        if !has_privileges(&auth_session.roles, Privileges::AdminPrivileges) {
+            return Err(AuthError::Unauthorized);
+        }
+        if roles.is_empty() || roles.iter().any(|role| !definitions.iter().any(|definition| &definition.name == role)) {
+            return Err(AuthError::InvalidRole);
+        }
+
+        let user_credentials = self.auth_service.auth_datastore.get_user_by_username(username)
+            .await
+            .map_err(|_| AuthError::ServerError)?
+            .ok_or(AuthError::NotFound)?;
+
+        // Only a SuperAdmin grants or removes the SuperAdmin privileges, runtime roles inheriting them included
+        let is_super_admin = has_privileges(&auth_session.roles, Privileges::SuperAdminPrivileges);
+        if !is_super_admin && (has_privileges(&roles, Privileges::SuperAdminPrivileges) || has_privileges(&user_credentials.roles, Privileges::SuperAdminPrivileges)) {
+            return Err(AuthError::Unauthorized);
+        }
+
+        if roles.len() == user_credentials.roles.len() && roles.iter().all(|role| user_credentials.roles.contains(role)) {
+            return Ok(UserRolesDetails::from(user_credentials));
+        }
+
+        if has_privileges(&user_credentials.roles, Privileges::SuperAdminPrivileges) && !has_privileges(&roles, Privileges::SuperAdminPrivileges) {
+            // Another user must keep a role with the SuperAdmin privileges, checked atomically with the update
+            let super_admin_roles: Vec<Roles> = definitions.iter()
+                .map(|definition| definition.name.clone())
+                .filter(|role| has_privileges(std::slice::from_ref(role), Privileges::SuperAdminPrivileges))
+                .collect();
+            let updated = self.auth_service.auth_datastore.update_roles_keeping_one_of(username, &roles, &super_admin_roles).await.map_err(|_| AuthError::ServerError)?;
+            if !updated {
+                return Err(AuthError::LastSuperAdmin);
+            }
+        } else {
+            self.auth_service.auth_datastore.update_roles(username, &roles).await.map_err(|_| AuthError::ServerError)?;
+        }
+        self.auth_service.revoke_all_sessions(username).await?;
+
+        Ok(UserRolesDetails { username: user_credentials.username, roles })
+    }
+
+    async fn get_roles(&self) -> Result<Vec<RoleDetails>, AuthError> {
+        Ok(self.get_definitions().await?.into_iter().map(RoleDetails::from).collect())
+    }
+
+    async fn save_role(&self, name: &str, payload: SaveRolePayload) -> Result<RoleDetails, AuthError> {
+        let name = Roles::from(name.to_string());
+        if name == Roles::None || name.to_string().is_empty() || name.to_string().chars().any(char::is_whitespace) {
+            return Err(AuthError::InvalidRole);
+        }
+
+        let definitions = self.get_definitions().await?;
+        let saved_role = definitions.iter().find(|definition| definition.name == name);
+
+        // Built-in roles keep their place in the hierarchy checked by `Roles::is_authorized`
+        if name.is_built_in() && saved_role.map(|saved_role| &saved_role.parent) != Some(&payload.parent) {
+            return Err(AuthError::InvalidRole);
+        }
+        if let Some(parent) = &payload.parent {
+            let ancestors = Role::resolve(std::slice::from_ref(parent), &definitions);
+            if ancestors.is_empty() || ancestors.iter().any(|ancestor| ancestor.name == name) {
+                return Err(AuthError::InvalidRole);
+            }
+        }
+
+        let mut role = Role::new(name, payload.parent, payload.permissions);
+        if let Some(saved_role) = saved_role {
+            role.created_at = saved_role.created_at;
+        }
+        self.role_datastore.save_role(role.clone()).await.map_err(|_| AuthError::ServerError)?;
+
+        Ok(RoleDetails::from(role))
+    }
+
+    async fn delete_role(&self, name: &str) -> Result<(), AuthError> {
+        let name = Roles::from(name.to_string());
+        if name.is_built_in() {
+            return Err(AuthError::InvalidRole);
+        }
+
+        // Children would lose the permissions inherited through this role
+        let definitions = self.get_definitions().await?;
+        if definitions.iter().any(|definition| definition.parent.as_ref() == Some(&name)) {
+            return Err(AuthError::InvalidRole);
+        }
+
+        self.role_datastore.delete_role(&name).await.map_err(|error| match error {
+            RoleDatastoreError::InternalError => AuthError::NotFound,
+            RoleDatastoreError::ProvidersError => AuthError::ServerError,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use std::sync::Arc;
+    use fake::{Fake, Faker};
+    use fake::faker::internet::en::Username;
+    use mockall::predicate::eq;
+    use crate::datastore::{MockAuthDatastore, MockRoleDatastore, MockTokenDatastore};
+    use crate::entities::error::AuthError;
+    use crate::entities::{AuthMethod, AuthSession, Role, Roles, Token, UserCredentials};
+    use crate::services::{AuthRolesService, AuthService, MockRoleService};
+    use crate::views::payload::SaveRolePayload;
+
+    fn support() -> Roles {
+        Roles::Custom("support".to_string())
+    }
+
+    fn auth_session(roles: Vec<Roles>) -> AuthSession {
+        AuthSession {
+            username: Username().fake(),
+            roles,
+            token_identifier: Some(Faker.fake()),
+            auth_method: AuthMethod::AccessToken,
+        }
+    }
+
+    fn mock_auth_datastore(user_credentials: &UserCredentials) -> MockAuthDatastore {
+        let user_found = user_credentials.clone();
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        mock_auth_datastore.expect_get_user_by_username()
+            .with(eq(user_credentials.username.clone()))
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(user_found.clone())))));
+
+        mock_auth_datastore
+    }
+
+    fn mock_role_datastore(roles: Vec<Role>) -> MockRoleDatastore {
+        let mut mock_role_datastore = MockRoleDatastore::new();
+        mock_role_datastore.expect_get_roles()
+            .returning(move || Box::pin(future::ready(Ok(roles.clone()))));
+
+        mock_role_datastore
+    }
+
+    fn role_service(auth_datastore: MockAuthDatastore, token_datastore: MockTokenDatastore, role_datastore: MockRoleDatastore) -> MockRoleService {
+        MockRoleService::new(Arc::new(AuthService::new(auth_datastore, token_datastore, AuthConfig::fake())), role_datastore)
+    }
+
+    fn role_service_for(user_credentials: &UserCredentials) -> MockRoleService {
+        role_service(mock_auth_datastore(user_credentials), MockTokenDatastore::new(), mock_role_datastore(Vec::new()))
+    }
+
+    #[tokio::test]
+    async fn test_update_user_roles_revoke_sessions() {
+        let user_credentials = UserCredentials { roles: vec![Roles::User], ..Faker.fake() };
+        let token = Token { username: user_credentials.username.clone(), revoked_at: None, ..Faker.fake() };
+        let mut mock_auth_datastore = mock_auth_datastore(&user_credentials);
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        let roles = vec![Roles::Moderator, support()];
+
+        mock_auth_datastore.expect_update_roles()
+            .with(eq(user_credentials.username.clone()), eq(roles.clone()))
+            .times(1)
+            .returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_tokens_datastore.expect_get_tokens_for_user()
+            .with(eq(user_credentials.username.clone()))
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(vec![token.clone()]))));
+        mock_tokens_datastore.expect_revoke_token()
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+
+        let role_service = role_service(mock_auth_datastore, mock_tokens_datastore, mock_role_datastore(vec![Role::new(support(), Some(Roles::Moderator), Vec::new())]));
+        let result = role_service.update_user_roles(&auth_session(vec![Roles::Admin]), &user_credentials.username, roles.clone()).await;
+
+        assert_eq!(result.unwrap().roles, roles);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_roles_with_unknown_role() {
+        let role_service = role_service(MockAuthDatastore::new(), MockTokenDatastore::new(), mock_role_datastore(Vec::new()));
+
+        let result = role_service.update_user_roles(&auth_session(vec![Roles::Admin]), &Username().fake::<String>(), vec![support()]).await;
+
+        assert_eq!(result.unwrap_err(), AuthError::InvalidRole);
+    }
+
+    #[tokio::test]
+    async fn test_admin_cannot_grant_super_admin() {
+        let user_credentials = UserCredentials { roles: vec![Roles::User], ..Faker.fake() };
+        let super_admin_credentials = UserCredentials { roles: vec![Roles::SuperAdmin], ..Faker.fake() };
+        let root = Roles::Custom("root".to_string());
+        let role_service = role_service(mock_auth_datastore(&user_credentials), MockTokenDatastore::new(), mock_role_datastore(vec![Role::new(root.clone(), Some(Roles::SuperAdmin), Vec::new())]));
+        let super_admin_role_service = role_service_for(&super_admin_credentials);
+
+        let grant_result = role_service.update_user_roles(&auth_session(vec![Roles::Admin]), &user_credentials.username, vec![Roles::User, root]).await;
+        let demote_result = super_admin_role_service.update_user_roles(&auth_session(vec![Roles::Admin]), &super_admin_credentials.username, vec![Roles::User]).await;
+
+        assert_eq!(grant_result.unwrap_err(), AuthError::Unauthorized);
+        assert_eq!(demote_result.unwrap_err(), AuthError::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn test_moderator_cannot_update_user_roles() {
+        let role_service = role_service(MockAuthDatastore::new(), MockTokenDatastore::new(), mock_role_datastore(Vec::new()));
+
+        let result = role_service.update_user_roles(&auth_session(vec![Roles::Moderator]), &Username().fake::<String>(), vec![Roles::Moderator]).await;
+
+        assert_eq!(result.unwrap_err(), AuthError::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn test_cannot_demote_last_super_admin() {
+        let user_credentials = UserCredentials { roles: vec![Roles::SuperAdmin], ..Faker.fake() };
+        let mut mock_auth_datastore = mock_auth_datastore(&user_credentials);
+        mock_auth_datastore.expect_update_roles_keeping_one_of()
+            .withf(|_, roles, kept_roles| roles == [Roles::Admin] && kept_roles == [Roles::SuperAdmin])
+            .times(1)
+            .returning(|_, _, _| Box::pin(future::ready(Ok(false))));
+        mock_auth_datastore.expect_update_roles().never();
+
+        let role_service = role_service(mock_auth_datastore, MockTokenDatastore::new(), mock_role_datastore(Vec::new()));
+        let result = role_service.update_user_roles(&auth_session(vec![Roles::SuperAdmin]), &user_credentials.username, vec![Roles::Admin]).await;
+
+        assert_eq!(result.unwrap_err(), AuthError::LastSuperAdmin);
+    }
+
+    #[tokio::test]
+    async fn test_demote_super_admin_keeps_runtime_super_admin_roles() {
+        let root = Roles::Custom("root".to_string());
+        let user_credentials = UserCredentials { roles: vec![root.clone()], ..Faker.fake() };
+        let mut mock_auth_datastore = mock_auth_datastore(&user_credentials);
+        let kept_root = root.clone();
+        mock_auth_datastore.expect_update_roles_keeping_one_of()
+            .withf(move |_, _, kept_roles| kept_roles.contains(&Roles::SuperAdmin) && kept_roles.contains(&kept_root))
+            .times(1)
+            .returning(|_, _, _| Box::pin(future::ready(Ok(false))));
+        mock_auth_datastore.expect_update_roles().never();
+
+        let role_service = role_service(mock_auth_datastore, MockTokenDatastore::new(), mock_role_datastore(vec![Role::new(root, Some(Roles::SuperAdmin), Vec::new())]));
+        let result = role_service.update_user_roles(&auth_session(vec![Roles::SuperAdmin]), &user_credentials.username, vec![Roles::Admin]).await;
+
+        assert_eq!(result.unwrap_err(), AuthError::LastSuperAdmin);
+    }
+
+    #[tokio::test]
+    async fn test_get_roles_of_unknown_user() {
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        mock_auth_datastore.expect_get_user_by_username()
+            .returning(|_| Box::pin(future::ready(Ok(None))));
+
+        let role_service = role_service(mock_auth_datastore, MockTokenDatastore::new(), MockRoleDatastore::new());
+
+        assert_eq!(role_service.get_user_roles("unknown_username").await.unwrap_err(), AuthError::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_get_roles_with_defaults() {
+        let role_service = role_service(MockAuthDatastore::new(), MockTokenDatastore::new(), mock_role_datastore(vec![Role::new(support(), Some(Roles::Moderator), Vec::new())]));
+
+        let roles = role_service.get_roles().await.unwrap();
+
+        assert_eq!(roles.len(), 5);
+        assert_eq!(roles.iter().filter(|role| role.built_in).count(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_save_role() {
+        let mut mock_role_datastore = mock_role_datastore(Vec::new());
+        mock_role_datastore.expect_save_role()
+            .withf(|role| role.name == support() && role.parent == Some(Roles::Moderator))
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+
+        let role_service = role_service(MockAuthDatastore::new(), MockTokenDatastore::new(), mock_role_datastore);
+        let role_details = role_service.save_role("support", SaveRolePayload { parent: Some(Roles::Moderator), permissions: vec!["user:ban".to_string()] }).await.unwrap();
+
+        assert_eq!(role_details.name, support());
+        assert!(!role_details.built_in);
+    }
+
+    #[tokio::test]
+    async fn test_save_role_rejects_cycle_and_moved_built_in_role() {
+        let mut mock_role_datastore = mock_role_datastore(vec![Role::new(support(), Some(Roles::Moderator), Vec::new()), Role::new(Roles::Custom("trainee".to_string()), Some(support()), Vec::new())]);
+        mock_role_datastore.expect_save_role().never();
+
+        let role_service = role_service(MockAuthDatastore::new(), MockTokenDatastore::new(), mock_role_datastore);
+
+        assert_eq!(role_service.save_role("support", SaveRolePayload { parent: Some(Roles::Custom("trainee".to_string())), permissions: Vec::new() }).await.unwrap_err(), AuthError::InvalidRole);
+        assert_eq!(role_service.save_role("support", SaveRolePayload { parent: Some(Roles::Custom("unknown".to_string())), permissions: Vec::new() }).await.unwrap_err(), AuthError::InvalidRole);
+        assert_eq!(role_service.save_role("Moderator", SaveRolePayload { parent: Some(Roles::Admin), permissions: Vec::new() }).await.unwrap_err(), AuthError::InvalidRole);
+        assert_eq!(role_service.delete_role("Moderator").await.unwrap_err(), AuthError::InvalidRole);
+        assert_eq!(role_service.delete_role("support").await.unwrap_err(), AuthError::InvalidRole);
+    }
+}
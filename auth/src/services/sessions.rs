@@ -0,0 +1,158 @@
+use mongodb::bson::DateTime;
+use crate::datastore::{AuthDatastore, TokenDatastore};
+use crate::entities::error::AuthError;
+use crate::entities::{AuthSession, Token};
+use crate::services::{AuthService, AuthSessionsService};
+use crate::views::response::SessionDetails;
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl> AuthSessionsService for AuthService<AuthDatastoreImpl, TokenDatastoreImpl>
+    where AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore
+{
+    async fn get_sessions(&self, auth_session: &AuthSession) -> Result<Vec<SessionDetails>, AuthError> {
+        let mut tokens = self.token_datastore.get_tokens_for_user(&auth_session.username).await.map_err(|_| AuthError::ServerError)?;
+        let now = DateTime::now();
+
+        // Each refresh create a new token in the same session, the last one give the session state
+        tokens.retain(|token| token.token_refresh_expired_at > now);
+        tokens.sort_by_key(|token| token.created_at);
+
+        let mut sessions: Vec<(DateTime, Token)> = Vec::new();
+        for token in tokens {
+            match sessions.iter_mut().find(|(_, last_token)| last_token.session_id == token.session_id) {
+                Some(session) => session.1 = token,
+                None => sessions.push((token.created_at, token)),
+            }
+        }
+
+        Ok(sessions.into_iter().map(|(session_created_at, last_token)| SessionDetails {
+            current: auth_session.token_identifier.as_ref() == Some(&last_token.token_access_identifiers),
+            session_id: last_token.session_id,
+            user_agent: last_token.user_agent,
+            client_ip: last_token.client_ip,
+            created_at: session_created_at.try_to_rfc3339_string().unwrap(),
+            expired_at: last_token.token_refresh_expired_at.try_to_rfc3339_string().unwrap(),
+            revoked: last_token.revoked_at.is_some(),
+        }).collect())
+    }
+
+    async fn revoke_session_by_id(&self, username: &str, session_id: &str) -> Result<(), AuthError> {
+        let tokens = self.token_datastore.get_tokens_for_user(username).await.map_err(|_| AuthError::ServerError)?;
+        let session_tokens: Vec<&Token> = tokens.iter().filter(|token| token.session_id == session_id).collect();
+
+        if session_tokens.is_empty() {
+            return Err(AuthError::NotFound);
+        }
+
+        for token in session_tokens.into_iter().filter(|token| token.revoked_at.is_none()) {
+            self.token_datastore.revoke_token(&token.token_refresh_identifiers).await.map_err(|_| AuthError::ServerError)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use chrono::Duration;
+    use fake::{Fake, Faker};
+    use fake::faker::internet::en::Username;
+    use mockall::predicate::eq;
+    use mongodb::bson::DateTime;
+    use crate::datastore::{MockAuthDatastore, MockTokenDatastore};
+    use crate::entities::{AuthMethod, AuthSession, Roles, Token};
+    use crate::entities::error::AuthError;
+    use crate::services::{AuthSessionsService, MockAuthService};
+
+    fn date_from_now(duration: Duration) -> DateTime {
+        DateTime::parse_rfc3339_str((chrono::Utc::now() + duration).to_rfc3339()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_sessions_group_refreshed_tokens() {
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        let username: String = Username().fake();
+        let first_token = Token {
+            username: username.clone(),
+            created_at: date_from_now(Duration::hours(-2)),
+            revoked_at: Some(date_from_now(Duration::hours(-1))),
+            ..Faker.fake()
+        };
+        let refreshed_token = Token {
+            username: username.clone(),
+            session_id: first_token.session_id.clone(),
+            created_at: date_from_now(Duration::hours(-1)),
+            user_agent: Some("Mozilla/5.0".to_string()),
+            ..Faker.fake()
+        };
+        let expired_token = Token {
+            username: username.clone(),
+            token_refresh_expired_at: date_from_now(Duration::hours(-1)),
+            ..Faker.fake()
+        };
+        let auth_session = AuthSession {
+            username: username.clone(),
+            roles: vec![Roles::User],
+            token_identifier: Some(refreshed_token.token_access_identifiers.clone()),
+            auth_method: AuthMethod::AccessToken,
+        };
+
+        let tokens = vec![refreshed_token.clone(), expired_token, first_token.clone()];
+        mock_tokens_datastore.expect_get_tokens_for_user()
+            .with(eq(username.clone()))
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(tokens.clone()))));
+
+        let auth_service = MockAuthService::new(MockAuthDatastore::new(), mock_tokens_datastore, AuthConfig::fake());
+        let sessions = auth_service.get_sessions(&auth_session).await.expect("Unable list sessions");
+
+        auth_service.checkpoint();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, first_token.session_id);
+        assert_eq!(sessions[0].created_at, first_token.created_at.try_to_rfc3339_string().unwrap());
+        assert_eq!(sessions[0].user_agent, refreshed_token.user_agent);
+        assert!(!sessions[0].revoked);
+        assert!(sessions[0].current);
+    }
+
+    #[tokio::test]
+    async fn test_revoke_session_by_id() {
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        let username: String = Username().fake();
+        let session_token = Token { username: username.clone(), ..Faker.fake() };
+        let session_id = session_token.session_id.clone();
+
+        let tokens = vec![session_token.clone(), Token { username: username.clone(), ..Faker.fake() }];
+        mock_tokens_datastore.expect_get_tokens_for_user()
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(tokens.clone()))));
+        mock_tokens_datastore.expect_revoke_token()
+            .with(eq(session_token.token_refresh_identifiers.clone()))
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+
+        let auth_service = MockAuthService::new(MockAuthDatastore::new(), mock_tokens_datastore, AuthConfig::fake());
+        let result = auth_service.revoke_session_by_id(&username, &session_id).await;
+
+        auth_service.checkpoint();
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_unknown_session() {
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+        let username: String = Username().fake();
+
+        mock_tokens_datastore.expect_get_tokens_for_user()
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(vec![Faker.fake()]))));
+        mock_tokens_datastore.expect_revoke_token().times(0);
+
+        let auth_service = MockAuthService::new(MockAuthDatastore::new(), mock_tokens_datastore, AuthConfig::fake());
+        let result = auth_service.revoke_session_by_id(&username, "unknown_session").await;
+
+        auth_service.checkpoint();
+        assert_eq!(result, Err(AuthError::NotFound));
+    }
+}
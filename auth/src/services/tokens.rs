@@ -1,7 +1,8 @@
+use chrono::{Duration, TimeDelta};
 use mongodb::bson::DateTime;
 use crate::datastore::{AuthDatastore, TokenDatastore};
 use crate::entities::error::AuthError;
-use crate::entities::{Token, UserCredentials};
+use crate::entities::{ClientInformation, Token, UserCredentials};
 use crate::services::{AuthTokensService, AuthService};
 use crate::utils::auth_claims::AuthClaims;
 use crate::utils::validate_token::{IntoClaims, TokenString};
@@ -11,9 +12,9 @@ use crate::views::response::AuthBody;
 impl<AuthDatastoreImpl, TokenDatastoreImpl> AuthTokensService for AuthService<AuthDatastoreImpl, TokenDatastoreImpl>
     where AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore
 {
-    fn parse_auth_claims_from_refresh_payload(refresh_token_payload: RefreshTokenPayload) -> Result<AuthClaims, AuthError> {
+    async fn parse_auth_claims_from_refresh_payload(&self, refresh_token_payload: RefreshTokenPayload) -> Result<AuthClaims, AuthError> {
         let untrusted_token = TokenString(refresh_token_payload.refresh_token);
-        let claims = untrusted_token.try_into_claims()?;
+        let claims = untrusted_token.try_into_claims(&self.auth_config).await?;
 
         AuthClaims::try_from(&claims).map_err(|_| AuthError::InvalidToken)
     }
@@ -28,7 +29,7 @@ impl<AuthDatastoreImpl, TokenDatastoreImpl> AuthTokensService for AuthService<Au
 
         let token_state = token_to_validate.unwrap();
 
-        if token_state.revoked_at.is_some() || token_state.token_refresh_expired_at > DateTime::now() {
+        if token_state.revoked_at.is_some() || token_state.token_refresh_expired_at < DateTime::now() {
             return Err(AuthError::InvalidToken);
         }
 
@@ -47,8 +48,47 @@ impl<AuthDatastoreImpl, TokenDatastoreImpl> AuthTokensService for AuthService<Au
         Ok(user.unwrap())
     }
 
-    async fn generate_token(&self, user: &UserCredentials) -> Result<AuthBody, AuthError> {
-        let (access_token, refresh_token, tokens) = Token::generate_tokens(user).await.map_err(|_| AuthError::ServerError)?;
+    async fn generate_token(&self, user: &UserCredentials, client_information: &ClientInformation) -> Result<AuthBody, AuthError> {
+        self.store_tokens(user, None, client_information).await
+    }
+
+    async fn refresh_tokens(&self, refresh_token_payload: RefreshTokenPayload, client_information: &ClientInformation) -> Result<AuthBody, AuthError> {
+        let auth_claims = self.parse_auth_claims_from_refresh_payload(refresh_token_payload).await?;
+
+        let token_state = match self.validate_token(&auth_claims).await {
+            Ok(token_state) => {
+                // Revoke actual token and generate new token from actual token
+                self.token_datastore.revoke_token(&auth_claims.token_identifier).await.map_err(|_| AuthError::ServerError)?;
+                token_state
+            }
+            Err(AuthError::InvalidToken) => self.check_refresh_token_reuse(&auth_claims).await?,
+            Err(error) => return Err(error),
+        };
+
+        let user = self.try_get_user_token(&token_state).await?;
+
+        self.store_tokens(&user, Some(&token_state), client_information).await
+    }
+}
+
+/// Time while an already rotated refresh token is still accepted.
+/// It avoid to revoke the session when two clients (Like browser tabs) refresh at the same time
+const REFRESH_TOKEN_REUSE_GRACE_PERIOD: TimeDelta = Duration::seconds(30);
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl> AuthService<AuthDatastoreImpl, TokenDatastoreImpl>
+    where AuthDatastoreImpl: AuthDatastore, TokenDatastoreImpl: TokenDatastore
+{
+    /// Generate a new token pair and save it.
+    /// The pair join the family of `parent` token when refreshed, otherwise a new session is opened
+    async fn store_tokens(&self, user: &UserCredentials, parent: Option<&Token>, client_information: &ClientInformation) -> Result<AuthBody, AuthError> {
+        let (access_token, refresh_token, tokens) = Token::generate_tokens(&self.auth_config, user).await.map_err(|_| AuthError::ServerError)?;
+        let tokens = Token {
+            session_id: parent.map(|parent| parent.session_id.clone()).unwrap_or(tokens.session_id),
+            parent_token_identifier: parent.map(|parent| parent.token_refresh_identifiers.clone()),
+            user_agent: client_information.user_agent.clone(),
+            client_ip: client_information.client_ip.clone(),
+            ..tokens
+        };
         self.token_datastore.add_tokens(tokens).await.map_err(|_| AuthError::ServerError)?;
 
         Ok(AuthBody {
@@ -57,17 +97,172 @@ impl<AuthDatastoreImpl, TokenDatastoreImpl> AuthTokensService for AuthService<Au
         })
     }
 
-    async fn refresh_tokens(&self, refresh_token_payload: RefreshTokenPayload) -> Result<AuthBody, AuthError> {
-        let auth_claims = Self::parse_auth_claims_from_refresh_payload(refresh_token_payload)?;
+    /// Called when a refresh token is not valid anymore.
+    ///
+    /// A revoked refresh token presented again means it has been stolen or replayed (OAuth 2.1 refresh token rotation).
+    /// All the token family (same `session_id`) is then revoked.
+    /// The rotated token is still accepted during `REFRESH_TOKEN_REUSE_GRACE_PERIOD` if its family is still active.
+    async fn check_refresh_token_reuse(&self, auth_claims: &AuthClaims) -> Result<Token, AuthError> {
+        let token_state = self.token_datastore.get_token(&auth_claims.token_identifier)
+            .await
+            .map_err(|_| AuthError::ServerError)?
+            .ok_or(AuthError::InvalidToken)?;
 
-        let token_state = self.validate_token(&auth_claims).await?;
+        let revoked_at = match token_state.revoked_at {
+            Some(revoked_at) if token_state.token_refresh_expired_at > DateTime::now() => revoked_at,
+            _ => return Err(AuthError::InvalidToken),
+        };
 
-        let user = self.try_get_user_token(&token_state).await?;
+        let family_tokens: Vec<Token> = self.token_datastore.get_tokens_for_user(&token_state.username)
+            .await
+            .map_err(|_| AuthError::ServerError)?
+            .into_iter()
+            .filter(|token| token.session_id == token_state.session_id)
+            .collect();
+
+        let grace_period_start = DateTime::from_millis(DateTime::now().timestamp_millis() - REFRESH_TOKEN_REUSE_GRACE_PERIOD.num_milliseconds());
+        let is_rotated_recently = revoked_at > grace_period_start && family_tokens.iter().any(|token| {
+            token.parent_token_identifier.as_ref() == Some(&token_state.token_refresh_identifiers) && token.revoked_at.is_none()
+        });
 
-        // Revoke actual token and generate new token from actual token
-        self.token_datastore.revoke_token(&auth_claims.token_identifier).await.map_err(|_| AuthError::ServerError)?;
+        if is_rotated_recently {
+            return Ok(token_state);
+        }
+
+        for token in family_tokens.iter().filter(|token| token.revoked_at.is_none()) {
+            self.token_datastore.revoke_token(&token.token_refresh_identifiers).await.map_err(|_| AuthError::ServerError)?;
+        }
 
-        self.generate_token(&user).await
+        Err(AuthError::InvalidToken)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use fake::{Fake, Faker};
+    use mockall::predicate::eq;
+    use mongodb::bson::DateTime;
+    use mongodb::bson::oid::ObjectId;
+    use crate::datastore::{MockAuthDatastore, MockTokenDatastore};
+    use crate::entities::error::AuthError;
+    use crate::entities::{ClientInformation, Token, UserCredentials};
+    use crate::services::{AuthTokensService, MockAuthService};
+    use crate::views::payload::RefreshTokenPayload;
+
+    async fn generate_refresh_token(user: &UserCredentials) -> (RefreshTokenPayload, Token) {
+        let (_, refresh_token, token) = Token::generate_tokens(&AuthConfig::fake(), user).await.expect("Unable generate tokens");
+
+        (RefreshTokenPayload { refresh_token }, Token { id: Some(ObjectId::new()), ..token })
+    }
+
+    fn mock_auth_datastore_with_user(user: &UserCredentials) -> MockAuthDatastore {
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        let user = user.clone();
+        mock_auth_datastore.expect_get_user_by_username()
+            .returning(move |_| Box::pin(future::ready(Ok(Some(user.clone())))));
+
+        mock_auth_datastore
+    }
+
+    #[tokio::test]
+    async fn test_refresh_tokens_keep_token_family() {
+        let user: UserCredentials = Faker.fake();
+        let (refresh_token_payload, token) = generate_refresh_token(&user).await;
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+
+        let token_found = token.clone();
+        mock_tokens_datastore.expect_get_token()
+            .with(eq(token.token_refresh_identifiers.clone()))
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(token_found.clone())))));
+        mock_tokens_datastore.expect_revoke_token()
+            .with(eq(token.token_refresh_identifiers.clone()))
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+        let parent_token = token.clone();
+        mock_tokens_datastore.expect_add_tokens()
+            .withf(move |new_token| {
+                new_token.session_id == parent_token.session_id
+                    && new_token.parent_token_identifier == Some(parent_token.token_refresh_identifiers.clone())
+            })
+            .times(1)
+            .returning(|token| Box::pin(future::ready(Ok(token))));
+
+        let auth_service = MockAuthService::new(mock_auth_datastore_with_user(&user), mock_tokens_datastore, AuthConfig::fake());
+        let result = auth_service.refresh_tokens(refresh_token_payload, &ClientInformation::default()).await;
+
+        auth_service.checkpoint();
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_reuse_revoke_token_family() {
+        let user: UserCredentials = Faker.fake();
+        let (refresh_token_payload, token) = generate_refresh_token(&user).await;
+        let reused_token = Token { revoked_at: Some(DateTime::from_millis(DateTime::now().timestamp_millis() - 60_000)), ..token };
+        let child_token = Token {
+            username: reused_token.username.clone(),
+            session_id: reused_token.session_id.clone(),
+            parent_token_identifier: Some(reused_token.token_refresh_identifiers.clone()),
+            ..Faker.fake()
+        };
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+
+        let token_found = reused_token.clone();
+        mock_tokens_datastore.expect_get_token()
+            .times(2)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(token_found.clone())))));
+        let family_tokens = vec![reused_token.clone(), child_token.clone(), Faker.fake()];
+        mock_tokens_datastore.expect_get_tokens_for_user()
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(family_tokens.clone()))));
+        mock_tokens_datastore.expect_revoke_token()
+            .with(eq(child_token.token_refresh_identifiers.clone()))
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(()))));
+        mock_tokens_datastore.expect_add_tokens().times(0);
+
+        let auth_service = MockAuthService::new(MockAuthDatastore::new(), mock_tokens_datastore, AuthConfig::fake());
+        let result = auth_service.refresh_tokens(refresh_token_payload, &ClientInformation::default()).await;
+
+        auth_service.checkpoint();
+        assert_eq!(result.unwrap_err(), AuthError::InvalidToken);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token_reuse_during_grace_period() {
+        let user: UserCredentials = Faker.fake();
+        let (refresh_token_payload, token) = generate_refresh_token(&user).await;
+        let reused_token = Token { revoked_at: Some(DateTime::now()), ..token };
+        let child_token = Token {
+            username: reused_token.username.clone(),
+            session_id: reused_token.session_id.clone(),
+            parent_token_identifier: Some(reused_token.token_refresh_identifiers.clone()),
+            ..Faker.fake()
+        };
+        let mut mock_tokens_datastore = MockTokenDatastore::new();
+
+        let token_found = reused_token.clone();
+        mock_tokens_datastore.expect_get_token()
+            .times(2)
+            .returning(move |_| Box::pin(future::ready(Ok(Some(token_found.clone())))));
+        let family_tokens = vec![reused_token.clone(), child_token];
+        mock_tokens_datastore.expect_get_tokens_for_user()
+            .times(1)
+            .returning(move |_| Box::pin(future::ready(Ok(family_tokens.clone()))));
+        mock_tokens_datastore.expect_revoke_token().times(0);
+        let session_id = reused_token.session_id.clone();
+        mock_tokens_datastore.expect_add_tokens()
+            .withf(move |new_token| new_token.session_id == session_id)
+            .times(1)
+            .returning(|token| Box::pin(future::ready(Ok(token))));
+
+        let auth_service = MockAuthService::new(mock_auth_datastore_with_user(&user), mock_tokens_datastore, AuthConfig::fake());
+        let result = auth_service.refresh_tokens(refresh_token_payload, &ClientInformation::default()).await;
+
+        auth_service.checkpoint();
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,231 @@
+use chrono::Utc;
+use crate::datastore::{AuthDatastore, TokenDatastore, TotpDatastore};
+use crate::entities::error::AuthError;
+use crate::entities::{TotpCredentials, UserCredentials};
+use crate::services::{AuthTotpService, TotpService};
+use crate::utils::mfa_ticket::MfaTicket;
+use crate::views::response::{MfaTicketBody, TotpEnrolmentBody};
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, TotpDatastoreImpl> TotpService<AuthDatastoreImpl, TokenDatastoreImpl, TotpDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    TotpDatastoreImpl: TotpDatastore,
+{
+    async fn get_totp(&self, username: &str) -> Result<Option<TotpCredentials>, AuthError> {
+        self.totp_datastore.get_totp(username).await.map_err(|_| AuthError::ServerError)
+    }
+
+    /// Consume a TOTP code or, when it's not one, a recovery code
+    async fn use_code(&self, totp_credentials: &TotpCredentials, code: &str) -> Result<(), AuthError> {
+        let code = code.trim();
+
+        if let Some(step) = totp_credentials.verify_code(code, Utc::now().timestamp() as u64) {
+            // Fails when the same code is used concurrently
+            return self.totp_datastore.use_totp_step(&totp_credentials.username, step).await.map_err(|_| AuthError::WrongCredentials);
+        }
+
+        let recovery_code_hash = TotpCredentials::hash_recovery_code(code);
+        if !totp_credentials.recovery_code_hashes.contains(&recovery_code_hash) {
+            return Err(AuthError::WrongCredentials);
+        }
+
+        self.totp_datastore.use_recovery_code(&totp_credentials.username, &recovery_code_hash).await.map_err(|_| AuthError::WrongCredentials)
+    }
+}
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, TotpDatastoreImpl> AuthTotpService for TotpService<AuthDatastoreImpl, TokenDatastoreImpl, TotpDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    TotpDatastoreImpl: TotpDatastore,
+{
+    async fn enrol_totp(&self, username: &str) -> Result<TotpEnrolmentBody, AuthError> {
+        if self.get_totp(username).await?.is_some_and(|totp_credentials| totp_credentials.enabled) {
+            return Err(AuthError::Duplicated);
+        }
+
+        let (totp_credentials, recovery_codes) = TotpCredentials::generate(username);
+        let otpauth_uri = totp_credentials.totp(&self.totp_issuer)?.get_url();
+        let secret = totp_credentials.secret.clone();
+
+        self.totp_datastore.save_totp(totp_credentials).await.map_err(|_| AuthError::ServerError)?;
+
+        Ok(TotpEnrolmentBody { secret, otpauth_uri, recovery_codes })
+    }
+
+    async fn confirm_totp(&self, username: &str, code: &str) -> Result<(), AuthError> {
+        let totp_credentials = self.get_totp(username).await?.ok_or(AuthError::NotFound)?;
+
+        if totp_credentials.enabled {
+            return Err(AuthError::Duplicated);
+        }
+
+        // Only a TOTP code proves the authenticator app is set up, recovery codes are refused
+        let step = totp_credentials.verify_code(code.trim(), Utc::now().timestamp() as u64).ok_or(AuthError::WrongCredentials)?;
+        self.totp_datastore.use_totp_step(username, step).await.map_err(|_| AuthError::WrongCredentials)?;
+
+        self.totp_datastore.enable_totp(username).await.map_err(|_| AuthError::ServerError)
+    }
+
+    async fn disable_totp(&self, username: &str, code: &str) -> Result<(), AuthError> {
+        let totp_credentials = self.get_totp(username).await?
+            .filter(|totp_credentials| totp_credentials.enabled)
+            .ok_or(AuthError::NotFound)?;
+
+        self.use_code(&totp_credentials, code).await?;
+
+        self.totp_datastore.delete_totp(username).await.map_err(|_| AuthError::ServerError)
+    }
+
+    async fn is_totp_enabled(&self, username: &str) -> Result<bool, AuthError> {
+        Ok(self.get_totp(username).await?.is_some_and(|totp_credentials| totp_credentials.enabled))
+    }
+
+    async fn generate_mfa_ticket(&self, username: &str) -> Result<MfaTicketBody, AuthError> {
+        let (mfa_ticket, ticket_identifier, expired_at) = MfaTicket::generate(&self.auth_service.auth_config, username)?;
+
+        self.totp_datastore.save_mfa_ticket(username, &ticket_identifier).await.map_err(|_| AuthError::ServerError)?;
+
+        Ok(MfaTicketBody { mfa_ticket, expired_at: expired_at.to_rfc3339() })
+    }
+
+    async fn verify_mfa_ticket(&self, mfa_ticket: &str) -> Result<String, AuthError> {
+        let (username, ticket_identifier) = MfaTicket::verify(&self.auth_service.auth_config, mfa_ticket)?;
+
+        // Fails when the ticket was already exchanged, even with a wrong code, or replaced by a new login
+        self.totp_datastore.use_mfa_ticket(&username, &ticket_identifier).await.map_err(|_| AuthError::InvalidToken)?;
+
+        Ok(username)
+    }
+
+    async fn verify_mfa_code(&self, username: &str, code: &str) -> Result<UserCredentials, AuthError> {
+        let totp_credentials = self.get_totp(username).await?
+            .filter(|totp_credentials| totp_credentials.enabled)
+            .ok_or(AuthError::InvalidToken)?;
+
+        self.use_code(&totp_credentials, code).await?;
+
+        self.auth_service.auth_datastore.get_user_by_username(username)
+            .await
+            .map_err(|_| AuthError::ServerError)?
+            .ok_or(AuthError::WrongCredentials)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use std::sync::Arc;
+    use chrono::Utc;
+    use fake::{Fake, Faker};
+    use mockall::predicate::{always, eq};
+    use crate::datastore::{MockAuthDatastore, MockTokenDatastore, MockTotpDatastore, TotpDatastoreError};
+    use crate::entities::error::AuthError;
+    use crate::entities::{TotpCredentials, UserCredentials};
+    use crate::services::{AuthService, AuthTotpService, MockTotpService};
+
+    fn totp_service(auth_datastore: MockAuthDatastore, totp_datastore: MockTotpDatastore) -> MockTotpService {
+        MockTotpService::new(Arc::new(AuthService::new(auth_datastore, MockTokenDatastore::new(), AuthConfig::fake())), totp_datastore, "Issuer".to_string())
+    }
+
+    fn enabled_totp() -> (TotpCredentials, Vec<String>) {
+        let (totp_credentials, recovery_codes) = TotpCredentials::generate("username");
+
+        (TotpCredentials { enabled: true, ..totp_credentials }, recovery_codes)
+    }
+
+    #[tokio::test]
+    async fn test_enrol_totp() {
+        let mut mock_totp_datastore = MockTotpDatastore::new();
+        mock_totp_datastore.expect_get_totp().with(eq("username")).times(1).returning(|_| Box::pin(future::ready(Ok(None))));
+        mock_totp_datastore.expect_save_totp().times(1).returning(|_| Box::pin(future::ready(Ok(()))));
+
+        let totp_enrolment = totp_service(MockAuthDatastore::new(), mock_totp_datastore).enrol_totp("username").await.unwrap();
+
+        assert!(totp_enrolment.otpauth_uri.contains(&totp_enrolment.secret));
+        assert_eq!(totp_enrolment.recovery_codes.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_enrol_totp_already_enabled() {
+        let mut mock_totp_datastore = MockTotpDatastore::new();
+        mock_totp_datastore.expect_get_totp().times(1).returning(|_| Box::pin(future::ready(Ok(Some(enabled_totp().0)))));
+        mock_totp_datastore.expect_save_totp().never();
+
+        let result = totp_service(MockAuthDatastore::new(), mock_totp_datastore).enrol_totp("username").await;
+
+        assert_eq!(result.unwrap_err(), AuthError::Duplicated);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_totp() {
+        let (totp_credentials, _) = TotpCredentials::generate("username");
+        let code = totp_credentials.totp("Issuer").unwrap().generate(Utc::now().timestamp() as u64);
+        let mut mock_totp_datastore = MockTotpDatastore::new();
+        mock_totp_datastore.expect_get_totp().times(1).returning(move |_| Box::pin(future::ready(Ok(Some(totp_credentials.clone())))));
+        mock_totp_datastore.expect_use_totp_step().times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_totp_datastore.expect_enable_totp().with(eq("username")).times(1).returning(|_| Box::pin(future::ready(Ok(()))));
+
+        let result = totp_service(MockAuthDatastore::new(), mock_totp_datastore).confirm_totp("username", &code).await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_verify_mfa_code_with_recovery_code() {
+        let (totp_credentials, recovery_codes) = enabled_totp();
+        let recovery_code_hash = TotpCredentials::hash_recovery_code(&recovery_codes[0]);
+        let mut mock_totp_datastore = MockTotpDatastore::new();
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        mock_totp_datastore.expect_get_totp().times(1).returning(move |_| Box::pin(future::ready(Ok(Some(totp_credentials.clone())))));
+        mock_totp_datastore.expect_use_recovery_code().with(eq("username"), eq(recovery_code_hash)).times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_auth_datastore.expect_get_user_by_username().times(1).returning(|username| Box::pin(future::ready(Ok(Some(UserCredentials {
+            username: username.to_string(),
+            ..Faker.fake::<UserCredentials>()
+        })))));
+
+        let user = totp_service(mock_auth_datastore, mock_totp_datastore).verify_mfa_code("username", &recovery_codes[0]).await.unwrap();
+
+        assert_eq!(user.username, "username");
+    }
+
+    #[tokio::test]
+    async fn test_verify_mfa_code_replayed() {
+        let (totp_credentials, _) = enabled_totp();
+        let code = totp_credentials.totp("Issuer").unwrap().generate(Utc::now().timestamp() as u64);
+        let mut mock_totp_datastore = MockTotpDatastore::new();
+        mock_totp_datastore.expect_get_totp().times(1).returning(move |_| Box::pin(future::ready(Ok(Some(totp_credentials.clone())))));
+        mock_totp_datastore.expect_use_totp_step().times(1).returning(|_, _| Box::pin(future::ready(Err(TotpDatastoreError::InternalError))));
+
+        let result = totp_service(MockAuthDatastore::new(), mock_totp_datastore).verify_mfa_code("username", &code).await;
+
+        assert_eq!(result.unwrap_err(), AuthError::WrongCredentials);
+    }
+
+    #[tokio::test]
+    async fn test_mfa_ticket() {
+        let mut mock_totp_datastore = MockTotpDatastore::new();
+        mock_totp_datastore.expect_save_mfa_ticket().with(eq("username"), always()).times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_totp_datastore.expect_use_mfa_ticket().with(eq("username"), always()).times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        let totp_service = totp_service(MockAuthDatastore::new(), mock_totp_datastore);
+
+        let mfa_ticket_body = totp_service.generate_mfa_ticket("username").await.unwrap();
+
+        assert_eq!(totp_service.verify_mfa_ticket(&mfa_ticket_body.mfa_ticket).await, Ok("username".to_string()));
+        assert_eq!(totp_service.verify_mfa_ticket("v4.public.invalid").await, Err(AuthError::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn test_mfa_ticket_used_twice() {
+        let mut mock_totp_datastore = MockTotpDatastore::new();
+        mock_totp_datastore.expect_save_mfa_ticket().times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_totp_datastore.expect_use_mfa_ticket().times(1).returning(|_, _| Box::pin(future::ready(Err(TotpDatastoreError::InternalError))));
+        let totp_service = totp_service(MockAuthDatastore::new(), mock_totp_datastore);
+
+        let mfa_ticket_body = totp_service.generate_mfa_ticket("username").await.unwrap();
+
+        assert_eq!(totp_service.verify_mfa_ticket(&mfa_ticket_body.mfa_ticket).await, Err(AuthError::InvalidToken));
+    }
+}
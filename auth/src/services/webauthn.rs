@@ -0,0 +1,265 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use mongodb::bson::DateTime;
+use crate::datastore::{AuthDatastore, TokenDatastore, WebAuthnDatastore};
+use crate::entities::error::AuthError;
+use crate::entities::{ClientInformation, WebAuthnCeremony, WebAuthnChallenge, WebAuthnCredential};
+use crate::services::{AuthTokensService, AuthWebAuthnService, WebAuthnService};
+use crate::views::payload::{WebAuthnAssertionPayload, WebAuthnRegistrationPayload};
+use crate::views::response::{AuthBody, WebAuthnCreationOptions, WebAuthnCredentialDetails, WebAuthnRequestOptions};
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, WebAuthnDatastoreImpl> WebAuthnService<AuthDatastoreImpl, TokenDatastoreImpl, WebAuthnDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    WebAuthnDatastoreImpl: WebAuthnDatastore,
+{
+    const DEFAULT_CREDENTIAL_NAME: &'static str = "Passkey";
+
+    async fn get_user_credentials(&self, username: &str) -> Result<Vec<WebAuthnCredential>, AuthError> {
+        self.webauthn_datastore.get_credentials(username).await.map_err(|_| AuthError::ServerError)
+    }
+
+    /// Consume the challenge signed by the authenticator, whatever the result of the ceremony
+    async fn take_challenge(&self, challenge: &str, ceremony: WebAuthnCeremony) -> Result<WebAuthnChallenge, AuthError> {
+        self.webauthn_datastore.take_challenge(challenge)
+            .await
+            .map_err(|_| AuthError::ServerError)?
+            .filter(|webauthn_challenge| webauthn_challenge.is_valid(ceremony))
+            .ok_or(AuthError::InvalidToken)
+    }
+}
+
+impl<AuthDatastoreImpl, TokenDatastoreImpl, WebAuthnDatastoreImpl> AuthWebAuthnService for WebAuthnService<AuthDatastoreImpl, TokenDatastoreImpl, WebAuthnDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore,
+    TokenDatastoreImpl: TokenDatastore,
+    WebAuthnDatastoreImpl: WebAuthnDatastore,
+{
+    async fn start_registration(&self, username: &str) -> Result<WebAuthnCreationOptions, AuthError> {
+        let registered_credentials = self.get_user_credentials(username).await?;
+        let webauthn_challenge = WebAuthnChallenge::generate(Some(username), WebAuthnCeremony::Registration);
+        let creation_options = self.relying_party.creation_options(&webauthn_challenge, username, &registered_credentials);
+
+        self.webauthn_datastore.add_challenge(webauthn_challenge).await.map_err(|_| AuthError::ServerError)?;
+
+        Ok(creation_options)
+    }
+
+    async fn finish_registration(&self, username: &str, registration_payload: WebAuthnRegistrationPayload) -> Result<WebAuthnCredentialDetails, AuthError> {
+        let (client_data, _) = self.relying_party.collect_client_data(&registration_payload.response.client_data_json, WebAuthnCeremony::Registration)?;
+        let webauthn_challenge = self.take_challenge(&client_data.challenge, WebAuthnCeremony::Registration).await?;
+
+        if webauthn_challenge.username.as_deref() != Some(username) {
+            return Err(AuthError::InvalidToken);
+        }
+
+        let registered_credential = self.relying_party.verify_registration(&registration_payload.response.attestation_object)?;
+        if registered_credential.credential_id != registration_payload.id {
+            return Err(AuthError::InvalidToken);
+        }
+        if self.webauthn_datastore.get_credential(&registered_credential.credential_id).await.map_err(|_| AuthError::ServerError)?.is_some() {
+            return Err(AuthError::Duplicated);
+        }
+
+        let webauthn_credential = self.webauthn_datastore.add_credential(WebAuthnCredential {
+            id: None,
+            username: username.to_string(),
+            credential_id: registered_credential.credential_id,
+            public_key: registered_credential.public_key,
+            sign_count: registered_credential.sign_count,
+            name: registration_payload.name.unwrap_or_else(|| Self::DEFAULT_CREDENTIAL_NAME.to_string()),
+            created_at: DateTime::now(),
+            last_used_at: None,
+        }).await.map_err(|_| AuthError::ServerError)?;
+
+        Ok(webauthn_credential.into())
+    }
+
+    async fn start_authentication(&self, username: Option<String>) -> Result<WebAuthnRequestOptions, AuthError> {
+        // Passkeys are discoverable : the username only binds the challenge, its credentials are never listed to not reveal which accounts exist
+        let webauthn_challenge = WebAuthnChallenge::generate(username.as_deref(), WebAuthnCeremony::Authentication);
+        let request_options = self.relying_party.request_options(&webauthn_challenge);
+
+        self.webauthn_datastore.add_challenge(webauthn_challenge).await.map_err(|_| AuthError::ServerError)?;
+
+        Ok(request_options)
+    }
+
+    async fn finish_authentication(&self, assertion_payload: WebAuthnAssertionPayload, client_information: &ClientInformation) -> Result<AuthBody, AuthError> {
+        let (client_data, client_data_bytes) = self.relying_party.collect_client_data(&assertion_payload.response.client_data_json, WebAuthnCeremony::Authentication)?;
+        let webauthn_challenge = self.take_challenge(&client_data.challenge, WebAuthnCeremony::Authentication).await?;
+
+        let webauthn_credential = self.webauthn_datastore.get_credential(&assertion_payload.id)
+            .await
+            .map_err(|_| AuthError::ServerError)?
+            .ok_or(AuthError::WrongCredentials)?;
+
+        let user_handle = assertion_payload.response.user_handle.as_ref()
+            .map(|user_handle| URL_SAFE_NO_PAD.decode(user_handle.trim_end_matches('=')).map_err(|_| AuthError::InvalidToken))
+            .transpose()?;
+        if webauthn_challenge.username.is_some_and(|username| username != webauthn_credential.username)
+            || user_handle.is_some_and(|user_handle| user_handle != webauthn_credential.username.as_bytes()) {
+            return Err(AuthError::WrongCredentials);
+        }
+
+        let sign_count = self.relying_party.verify_assertion(&webauthn_credential, &client_data_bytes, &assertion_payload.response)?;
+        self.webauthn_datastore.update_credential_usage(&webauthn_credential.credential_id, sign_count).await.map_err(|_| AuthError::ServerError)?;
+
+        let user = self.auth_service.auth_datastore.get_user_by_username(&webauthn_credential.username)
+            .await
+            .map_err(|_| AuthError::ServerError)?
+            .ok_or(AuthError::WrongCredentials)?;
+
+        self.auth_service.generate_token(&user, client_information).await
+    }
+
+    async fn get_credentials(&self, username: &str) -> Result<Vec<WebAuthnCredentialDetails>, AuthError> {
+        Ok(self.get_user_credentials(username).await?.into_iter().map(WebAuthnCredentialDetails::from).collect())
+    }
+
+    async fn delete_credential(&self, username: &str, credential_id: &str) -> Result<(), AuthError> {
+        self.webauthn_datastore.delete_credential(username, credential_id).await.map_err(|_| AuthError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth_config::AuthConfig;
+    use std::future;
+    use std::sync::{Arc, Mutex};
+    use fake::{Fake, Faker};
+    use mockall::predicate::eq;
+    use mongodb::bson::oid::ObjectId;
+    use crate::datastore::{MockAuthDatastore, MockTokenDatastore, MockWebAuthnDatastore};
+    use crate::entities::error::AuthError;
+    use crate::entities::{ClientInformation, Token, UserCredentials, WebAuthnCeremony, WebAuthnChallenge, WebAuthnCredential};
+    use crate::services::{AuthService, AuthWebAuthnService, MockWebAuthnService};
+    use crate::utils::webauthn::{RelyingParty, SoftwareAuthenticator};
+
+    const RP_ID: &str = "localhost";
+    const ORIGIN: &str = "http://localhost:8000";
+
+    fn webauthn_service(auth_datastore: MockAuthDatastore, token_datastore: MockTokenDatastore, webauthn_datastore: MockWebAuthnDatastore) -> MockWebAuthnService {
+        MockWebAuthnService::new(Arc::new(AuthService::new(auth_datastore, token_datastore, AuthConfig::fake())), webauthn_datastore, RelyingParty::default())
+    }
+
+    /// Challenges are kept in the mock like the datastore would, to be taken back by the second step of the ceremony
+    fn expect_challenge(mock_webauthn_datastore: &mut MockWebAuthnDatastore) {
+        let challenges: Arc<Mutex<Vec<WebAuthnChallenge>>> = Arc::new(Mutex::new(Vec::new()));
+        let added_challenges = challenges.clone();
+
+        mock_webauthn_datastore.expect_add_challenge().times(1).returning(move |webauthn_challenge| {
+            added_challenges.lock().unwrap().push(webauthn_challenge);
+            Box::pin(future::ready(Ok(())))
+        });
+        mock_webauthn_datastore.expect_take_challenge().times(1).returning(move |challenge| {
+            let mut challenges = challenges.lock().unwrap();
+            let webauthn_challenge = challenges.iter().position(|webauthn_challenge| webauthn_challenge.challenge == challenge).map(|position| challenges.remove(position));
+            Box::pin(future::ready(Ok(webauthn_challenge)))
+        });
+    }
+
+    #[tokio::test]
+    async fn test_registration_ceremony() {
+        let authenticator = SoftwareAuthenticator::new();
+        let mut mock_webauthn_datastore = MockWebAuthnDatastore::new();
+        expect_challenge(&mut mock_webauthn_datastore);
+        mock_webauthn_datastore.expect_get_credentials().with(eq("username")).times(1).returning(|_| Box::pin(future::ready(Ok(Vec::new()))));
+        mock_webauthn_datastore.expect_get_credential().times(1).returning(|_| Box::pin(future::ready(Ok(None))));
+        mock_webauthn_datastore.expect_add_credential().times(1).returning(|webauthn_credential| Box::pin(future::ready(Ok(WebAuthnCredential {
+            id: Some(ObjectId::new()),
+            ..webauthn_credential
+        }))));
+        let webauthn_service = webauthn_service(MockAuthDatastore::new(), MockTokenDatastore::new(), mock_webauthn_datastore);
+
+        let creation_options = webauthn_service.start_registration("username").await.unwrap();
+        let registration_payload = authenticator.register(RP_ID, ORIGIN, &creation_options.challenge);
+        let webauthn_credential_details = webauthn_service.finish_registration("username", registration_payload).await.unwrap();
+
+        assert_eq!(creation_options.authenticator_selection.resident_key, "required");
+        assert_eq!(webauthn_credential_details.credential_id, authenticator.credential_id());
+        assert_eq!(webauthn_credential_details.name, "Software authenticator");
+    }
+
+    #[tokio::test]
+    async fn test_registration_challenge_of_another_user() {
+        let mut mock_webauthn_datastore = MockWebAuthnDatastore::new();
+        expect_challenge(&mut mock_webauthn_datastore);
+        mock_webauthn_datastore.expect_get_credentials().times(1).returning(|_| Box::pin(future::ready(Ok(Vec::new()))));
+        mock_webauthn_datastore.expect_add_credential().never();
+        let webauthn_service = webauthn_service(MockAuthDatastore::new(), MockTokenDatastore::new(), mock_webauthn_datastore);
+
+        let creation_options = webauthn_service.start_registration("username").await.unwrap();
+        let registration_payload = SoftwareAuthenticator::new().register(RP_ID, ORIGIN, &creation_options.challenge);
+
+        assert_eq!(webauthn_service.finish_registration("other_username", registration_payload).await.unwrap_err(), AuthError::InvalidToken);
+    }
+
+    #[tokio::test]
+    async fn test_authentication_ceremony() {
+
+        let mut authenticator = SoftwareAuthenticator::new();
+        let registration_challenge = WebAuthnChallenge::generate(Some("username"), WebAuthnCeremony::Registration);
+        let registration_payload = authenticator.register(RP_ID, ORIGIN, &registration_challenge.challenge);
+        let registered_credential = RelyingParty::default().verify_registration(&registration_payload.response.attestation_object).unwrap();
+        let webauthn_credential = WebAuthnCredential {
+            id: Some(ObjectId::new()),
+            username: "username".to_string(),
+            credential_id: registered_credential.credential_id,
+            public_key: registered_credential.public_key,
+            sign_count: registered_credential.sign_count,
+            name: "name".to_string(),
+            created_at: mongodb::bson::DateTime::now(),
+            last_used_at: None,
+        };
+
+        let mut mock_webauthn_datastore = MockWebAuthnDatastore::new();
+        let mut mock_auth_datastore = MockAuthDatastore::new();
+        let mut mock_token_datastore = MockTokenDatastore::new();
+        expect_challenge(&mut mock_webauthn_datastore);
+        mock_webauthn_datastore.expect_get_credential().with(eq(authenticator.credential_id())).times(1).returning(move |_| Box::pin(future::ready(Ok(Some(webauthn_credential.clone())))));
+        mock_webauthn_datastore.expect_update_credential_usage().with(eq(authenticator.credential_id()), eq(1)).times(1).returning(|_, _| Box::pin(future::ready(Ok(()))));
+        mock_auth_datastore.expect_get_user_by_username().with(eq("username")).times(1).returning(|username| Box::pin(future::ready(Ok(Some(UserCredentials {
+            username: username.to_string(),
+            ..Faker.fake::<UserCredentials>()
+        })))));
+        mock_token_datastore.expect_add_tokens().times(1).returning(|token| Box::pin(future::ready(Ok(Token {
+            id: Some(ObjectId::new()),
+            ..token
+        }))));
+        let webauthn_service = webauthn_service(mock_auth_datastore, mock_token_datastore, mock_webauthn_datastore);
+
+        let request_options = webauthn_service.start_authentication(None).await.unwrap();
+        assert!(request_options.allow_credentials.is_empty());
+        let assertion_payload = authenticator.authenticate(RP_ID, ORIGIN, &request_options.challenge);
+
+        assert!(webauthn_service.finish_authentication(assertion_payload, &ClientInformation::default()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_start_authentication_same_for_known_and_unknown_usernames() {
+        let mut mock_webauthn_datastore = MockWebAuthnDatastore::new();
+        mock_webauthn_datastore.expect_get_credentials().never();
+        mock_webauthn_datastore.expect_add_challenge().times(2).returning(|_| Box::pin(future::ready(Ok(()))));
+        let webauthn_service = webauthn_service(MockAuthDatastore::new(), MockTokenDatastore::new(), mock_webauthn_datastore);
+
+        let known_username_options = webauthn_service.start_authentication(Some("username".to_string())).await.unwrap();
+        let unknown_username_options = webauthn_service.start_authentication(Some("unknown_username".to_string())).await.unwrap();
+
+        assert!(known_username_options.allow_credentials.is_empty());
+        assert_eq!(known_username_options.allow_credentials, unknown_username_options.allow_credentials);
+    }
+
+    #[tokio::test]
+    async fn test_authentication_with_unknown_challenge() {
+        let mut mock_webauthn_datastore = MockWebAuthnDatastore::new();
+        mock_webauthn_datastore.expect_take_challenge().times(1).returning(|_| Box::pin(future::ready(Ok(None))));
+        mock_webauthn_datastore.expect_get_credential().never();
+        let webauthn_service = webauthn_service(MockAuthDatastore::new(), MockTokenDatastore::new(), mock_webauthn_datastore);
+
+        let assertion_payload = SoftwareAuthenticator::new().authenticate(RP_ID, ORIGIN, "unknown_challenge");
+
+        assert_eq!(webauthn_service.finish_authentication(assertion_payload, &ClientInformation::default()).await.unwrap_err(), AuthError::InvalidToken);
+    }
+}
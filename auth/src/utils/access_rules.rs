@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::fs;
+use std::hash::Hash;
+use std::path::Path;
+use serde::de::{DeserializeOwned, IntoDeserializer};
+use serde::Deserialize;
+use thiserror::Error;
+use crate::entities::Privileges;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AccessRulesError {
+    #[error("Unable read rules file : {0}")]
+    UnreadableFile(String),
+    #[error("Rules file is not valid : {0}")]
+    InvalidFile(String),
+    #[error("Unknown module {0} in rules file")]
+    UnknownModule(String),
+    #[error("Unknown action {action} of module {module} in rules file")]
+    UnknownAction { module: String, action: String },
+}
+
+/// Actions of a module whose privileges can be set by `AccessRules`, named in snake case in the rules file
+pub trait ModuleActions: DeserializeOwned + Eq + Hash {
+    /// Section of the module in the rules file
+    const MODULE: &'static str;
+
+    /// Privileges required when the rules file doesn't set the action
+    fn default_privileges(&self) -> Privileges;
+}
+
+#[derive(Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    environments: HashMap<String, HashMap<String, HashMap<String, Privileges>>>,
+    #[serde(flatten)]
+    modules: HashMap<String, HashMap<String, Privileges>>,
+}
+
+/// Privileges required by the routes of each module, given to `AuthRouterBuilder::with_access_rules` and `UserRouterBuilder::with_access_rules`
+///
+/// A TOML file has a section by module, mapping its actions to privileges, and an optional `environments` section overriding them :
+///
+/// ```toml
+/// [auth]
+/// create_credentials = "Deny"
+/// get_roles = { Permission = "role:read" }
+///
+/// [environments.production.auth]
+/// get_openid_configuration = "Deny"
+/// ```
+///
+/// Actions not set keep the privileges of the module.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccessRules {
+    modules: HashMap<String, HashMap<String, Privileges>>,
+}
+
+impl AccessRules {
+    /// Rules of a TOML file, with the overrides of the environment if it has some
+    pub fn from_file(path: impl AsRef<Path>, environment: Option<&str>) -> Result<Self, AccessRulesError> {
+        let rules_file = fs::read_to_string(path).map_err(|error| AccessRulesError::UnreadableFile(error.to_string()))?;
+
+        Self::from_toml(&rules_file, environment)
+    }
+
+    pub fn from_toml(rules_file: &str, environment: Option<&str>) -> Result<Self, AccessRulesError> {
+        let RulesFile { mut environments, mut modules } = toml::from_str(rules_file).map_err(|error| AccessRulesError::InvalidFile(error.to_string()))?;
+
+        let overrides = environment.and_then(|environment| environments.remove(environment)).unwrap_or_default();
+        for (module, rules) in overrides {
+            modules.entry(module).or_default().extend(rules);
+        }
+
+        Ok(Self { modules })
+    }
+
+    /// Reject sections of modules not deployed, likely a typo
+    pub fn check_modules(&self, modules: &[&str]) -> Result<(), AccessRulesError> {
+        match self.modules.keys().find(|module| !modules.contains(&module.as_str())) {
+            Some(module) => Err(AccessRulesError::UnknownModule(module.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Privileges set for the actions of a module, an action it doesn't have is rejected
+    pub fn module_rules<Actions: ModuleActions>(&self) -> Result<HashMap<Actions, Privileges>, AccessRulesError> {
+        self.modules.get(Actions::MODULE)
+            .into_iter()
+            .flatten()
+            .map(|(action, privileges)| {
+                let module_action = Actions::deserialize(IntoDeserializer::<serde::de::value::Error>::into_deserializer(action.as_str()))
+                    .map_err(|_| AccessRulesError::UnknownAction { module: Actions::MODULE.to_string(), action: action.clone() })?;
+
+                Ok((module_action, privileges.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth_router_builder::AuthActions;
+    use super::*;
+
+    const RULES_FILE: &str = r#"
+        [auth]
+        create_credentials = "Deny"
+        get_roles = { Permission = "role:read" }
+
+        [user]
+        subscribe = "Anonymous"
+
+        [environments.production.auth]
+        create_credentials = "AdminPrivileges"
+        get_openid_configuration = "Deny"
+    "#;
+
+    #[test]
+    fn test_module_rules() {
+        let access_rules = AccessRules::from_toml(RULES_FILE, None).unwrap();
+
+        let rules = access_rules.module_rules::<AuthActions>().unwrap();
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules.get(&AuthActions::CreateCredentials), Some(&Privileges::Deny));
+        assert_eq!(rules.get(&AuthActions::GetRoles), Some(&Privileges::Permission("role:read".to_string())));
+        assert!(access_rules.check_modules(&["auth", "user"]).is_ok());
+    }
+
+    #[test]
+    fn test_module_rules_with_environment_overrides() {
+        let rules = AccessRules::from_toml(RULES_FILE, Some("production")).unwrap().module_rules::<AuthActions>().unwrap();
+
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules.get(&AuthActions::CreateCredentials), Some(&Privileges::AdminPrivileges));
+        assert_eq!(rules.get(&AuthActions::GetOpenidConfiguration), Some(&Privileges::Deny));
+        assert_eq!(AccessRules::from_toml(RULES_FILE, Some("staging")), AccessRules::from_toml(RULES_FILE, None));
+    }
+
+    #[test]
+    fn test_unknown_action_and_module_rejected() {
+        let access_rules = AccessRules::from_toml("[auth]\ncreate_role = \"Deny\"\n\n[users]\nsubscribe = \"Anonymous\"", None).unwrap();
+
+        assert_eq!(access_rules.module_rules::<AuthActions>(), Err(AccessRulesError::UnknownAction { module: "auth".to_string(), action: "create_role".to_string() }));
+        assert_eq!(access_rules.check_modules(&["auth", "user"]), Err(AccessRulesError::UnknownModule("users".to_string())));
+    }
+
+    #[test]
+    fn test_invalid_privileges_rejected() {
+        assert!(matches!(AccessRules::from_toml("[auth]\nlogin = \"Everyone\"", None), Err(AccessRulesError::InvalidFile(_))));
+        assert!(matches!(AccessRules::from_file("missing_rules.toml", None), Err(AccessRulesError::UnreadableFile(_))));
+    }
+}
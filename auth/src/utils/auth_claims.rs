@@ -7,8 +7,13 @@ use crate::entities::{Roles, TokenType};
 pub struct AuthClaims {
     pub claim_type: TokenType,
     pub username: String,
-    pub role: Option<Roles>,
+    /// Roles of the user, empty for refresh, service and delegated tokens
+    pub roles: Vec<Roles>,
     pub token_identifier: String,
+    /// Scopes granted to a service principal, `username` is then its client id
+    pub scopes: Vec<String>,
+    /// Client of service and delegated tokens
+    pub client_id: Option<String>,
 }
 
 impl Display for AuthClaims {
@@ -19,19 +24,26 @@ impl Display for AuthClaims {
 
 impl AuthClaims {
     fn new_access_token(trusted_token: &Claims) -> Result<Self, ()> {
-        if trusted_token.get_claim("jti").is_none() || trusted_token.get_claim("username").is_none() || trusted_token.get_claim("role").is_none() {
+        if trusted_token.get_claim("jti").is_none() || trusted_token.get_claim("username").is_none() {
             return Err(());
         }
 
         let token_identifier = trusted_token.get_claim("jti").unwrap().to_string().trim_matches('"').to_string();
         let username = trusted_token.get_claim("username").unwrap().to_string().trim_matches('"').to_string();
-        let role: Option<Roles> = trusted_token.get_claim("role").map(|value| value.to_string().trim_matches('"').to_string().parse().unwrap());
+        // Access tokens issued before several roles were supported have a single `role` claim
+        let roles: Vec<Roles> = match (trusted_token.get_claim("roles"), trusted_token.get_claim("role")) {
+            (Some(roles), _) => roles.as_array().ok_or(())?.iter().filter_map(|role| role.as_str()).map(|role| Roles::from(role.to_string())).collect(),
+            (None, Some(role)) => vec![Roles::from(role.as_str().ok_or(())?.to_string())],
+            (None, None) => return Err(()),
+        };
 
         Ok(Self {
             claim_type: TokenType::Access,
             token_identifier,
             username,
-            role,
+            roles,
+            scopes: Vec::new(),
+            client_id: None,
         })
     }
 
@@ -47,7 +59,48 @@ impl AuthClaims {
             claim_type: TokenType::Refresh,
             token_identifier,
             username,
-            role: None,
+            roles: Vec::new(),
+            scopes: Vec::new(),
+            client_id: None,
+        })
+    }
+
+    fn new_service_token(trusted_token: &Claims) -> Result<Self, ()> {
+        if trusted_token.get_claim("jti").is_none() || trusted_token.get_claim("client_id").is_none() || trusted_token.get_claim("scope").is_none() {
+            return Err(());
+        }
+
+        let token_identifier = trusted_token.get_claim("jti").unwrap().to_string().trim_matches('"').to_string();
+        let client_id = trusted_token.get_claim("client_id").unwrap().to_string().trim_matches('"').to_string();
+        let scopes = trusted_token.get_claim("scope").unwrap().to_string().trim_matches('"').split_whitespace().map(str::to_string).collect();
+
+        Ok(Self {
+            claim_type: TokenType::Service,
+            token_identifier,
+            username: client_id.clone(),
+            roles: Vec::new(),
+            scopes,
+            client_id: Some(client_id),
+        })
+    }
+
+    fn new_delegated_token(trusted_token: &Claims) -> Result<Self, ()> {
+        if trusted_token.get_claim("jti").is_none() || trusted_token.get_claim("username").is_none() || trusted_token.get_claim("client_id").is_none() || trusted_token.get_claim("scope").is_none() {
+            return Err(());
+        }
+
+        let token_identifier = trusted_token.get_claim("jti").unwrap().to_string().trim_matches('"').to_string();
+        let username = trusted_token.get_claim("username").unwrap().to_string().trim_matches('"').to_string();
+        let client_id = trusted_token.get_claim("client_id").unwrap().to_string().trim_matches('"').to_string();
+        let scopes = trusted_token.get_claim("scope").unwrap().to_string().trim_matches('"').split_whitespace().map(str::to_string).collect();
+
+        Ok(Self {
+            claim_type: TokenType::Delegated,
+            token_identifier,
+            username,
+            roles: Vec::new(),
+            scopes,
+            client_id: Some(client_id),
         })
     }
 }
@@ -66,6 +119,8 @@ impl TryFrom<&Claims> for AuthClaims {
         match claim_type {
             TokenType::Refresh => Self::new_refresh_token(trusted_token),
             TokenType::Access => Self::new_access_token(trusted_token),
+            TokenType::Service => Self::new_service_token(trusted_token),
+            TokenType::Delegated => Self::new_delegated_token(trusted_token),
         }
     }
 }
@@ -93,7 +148,20 @@ mod tests {
         assert_eq!(auth_claims.claim_type, TokenType::Access);
         assert_eq!(auth_claims.token_identifier, token_id);
         assert!(!auth_claims.username.is_empty());
-        assert!(!auth_claims.role.is_none());
+        assert_eq!(auth_claims.roles.len(), 1);
+    }
+
+    #[test]
+    pub fn test_new_access_token_with_roles() {
+        let mut claims = Claims::new().unwrap();
+        claims.token_identifier("my_access_token_id").expect("Unable to insert token id");
+        claims.subject(&TokenType::Access.to_string()).unwrap();
+        claims.add_additional("username", Username().fake::<String>()).unwrap();
+        claims.add_additional("roles", vec!["Moderator", "support"]).unwrap();
+
+        let auth_claims = AuthClaims::try_from(&claims).expect("Unable convert claims to AuthClaims");
+
+        assert_eq!(auth_claims.roles, vec![Roles::Moderator, Roles::Custom("support".to_string())]);
     }
 
     #[test]
@@ -110,6 +178,39 @@ mod tests {
         assert_eq!(auth_claims.claim_type, TokenType::Refresh);
         assert_eq!(auth_claims.token_identifier, token_id);
         assert!(!auth_claims.username.is_empty());
-        assert!(auth_claims.role.is_none());
+        assert!(auth_claims.roles.is_empty());
+    }
+
+    #[test]
+    pub fn test_new_service_token() {
+        let mut claims = Claims::new().unwrap();
+        claims.token_identifier("my_service_token_id").expect("Unable to insert token id");
+        claims.subject(&TokenType::Service.to_string()).unwrap();
+        claims.add_additional("client_id", "my_client_id").unwrap();
+        claims.add_additional("scope", "users:read users:write").unwrap();
+
+        let auth_claims = AuthClaims::try_from(&claims).expect("Unable convert claims to AuthClaims");
+
+        assert_eq!(auth_claims.claim_type, TokenType::Service);
+        assert_eq!(auth_claims.username, "my_client_id");
+        assert!(auth_claims.roles.is_empty());
+        assert_eq!(auth_claims.scopes, vec!["users:read".to_string(), "users:write".to_string()]);
+    }
+
+    #[test]
+    pub fn test_new_delegated_token() {
+        let mut claims = Claims::new().unwrap();
+        claims.token_identifier("my_delegated_token_id").expect("Unable to insert token id");
+        claims.subject(&TokenType::Delegated.to_string()).unwrap();
+        claims.add_additional("username", "juliana").unwrap();
+        claims.add_additional("client_id", "my_client_id").unwrap();
+        claims.add_additional("scope", "openid email").unwrap();
+
+        let auth_claims = AuthClaims::try_from(&claims).expect("Unable convert claims to AuthClaims");
+
+        assert_eq!(auth_claims.claim_type, TokenType::Delegated);
+        assert_eq!(auth_claims.username, "juliana");
+        assert_eq!(auth_claims.client_id, Some("my_client_id".to_string()));
+        assert_eq!(auth_claims.scopes, vec!["openid".to_string(), "email".to_string()]);
     }
 }
\ No newline at end of file
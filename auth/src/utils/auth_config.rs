@@ -0,0 +1,280 @@
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use chrono::{Duration, TimeDelta};
+use pasetors::claims::{Claims, ClaimsValidationRules};
+use pasetors::errors::Error as PasetoError;
+use crate::entities::error::AuthError;
+use crate::utils::key_ring::{KeyRing, KeyRingError};
+use crate::utils::password_hashing::PasswordHashing;
+use crate::utils::password_policy::PasswordPolicy;
+use crate::utils::token_codec::{PasetoPublicCodec, TokenCodec};
+
+/// Token and password settings of an auth deployment, given to `AuthRouterBuilder`, `UserRouterBuilder` and `AuthGuardLayer`
+///
+/// Clones share the same key ring : a key added or retired at runtime is seen by every router.
+#[derive(Clone)]
+pub struct AuthConfig {
+    key_ring: Arc<RwLock<KeyRing>>,
+    access_token_lifetime: TimeDelta,
+    refresh_token_lifetime: TimeDelta,
+    implicit_assertion: Vec<u8>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    token_codec: Arc<dyn TokenCodec>,
+    password_hashing: PasswordHashing,
+    password_policy: Arc<PasswordPolicy>,
+}
+
+impl AuthConfig {
+    const DEFAULT_ACCESS_TOKEN_LIFETIME: TimeDelta = Duration::minutes(10);
+    const DEFAULT_REFRESH_TOKEN_LIFETIME: TimeDelta = Duration::days(1);
+    const DEFAULT_IMPLICIT_ASSERTION: &'static [u8] = b"implicit assertion";
+
+    pub fn new(key_ring: KeyRing) -> Self {
+        Self {
+            key_ring: Arc::new(RwLock::new(key_ring)),
+            access_token_lifetime: Self::DEFAULT_ACCESS_TOKEN_LIFETIME,
+            refresh_token_lifetime: Self::DEFAULT_REFRESH_TOKEN_LIFETIME,
+            implicit_assertion: Self::DEFAULT_IMPLICIT_ASSERTION.to_vec(),
+            issuer: None,
+            audience: None,
+            token_codec: Arc::new(PasetoPublicCodec),
+            password_hashing: PasswordHashing::default(),
+            password_policy: Arc::new(PasswordPolicy::default()),
+        }
+    }
+
+    /// 10 minutes by default. Access tokens are not revoked on logout without `TokenRevocationChecker` : keep it short
+    pub fn with_access_token_lifetime(mut self, access_token_lifetime: TimeDelta) -> Self {
+        self.access_token_lifetime = access_token_lifetime;
+        self
+    }
+
+    /// 1 day by default, the session is closed when the refresh token expires
+    pub fn with_refresh_token_lifetime(mut self, refresh_token_lifetime: TimeDelta) -> Self {
+        self.refresh_token_lifetime = refresh_token_lifetime;
+        self
+    }
+
+    /// `iss` claim written in the tokens, tokens of other issuers are rejected
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.issuer = Some(issuer.to_string());
+        self
+    }
+
+    /// `aud` claim written in the tokens, tokens for other audiences are rejected
+    pub fn with_audience(mut self, audience: &str) -> Self {
+        self.audience = Some(audience.to_string());
+        self
+    }
+
+    /// Bind tokens to this deployment : tokens signed with another implicit assertion are rejected, even with the same keys
+    pub fn with_implicit_assertion(mut self, implicit_assertion: &[u8]) -> Self {
+        self.implicit_assertion = implicit_assertion.to_vec();
+        self
+    }
+
+    /// PASETO v4.public by default. See `PasetoLocalCodec`, `JwtCodec` and `ReferenceTokenCodec`
+    pub fn with_token_codec(mut self, token_codec: Arc<dyn TokenCodec>) -> Self {
+        self.token_codec = token_codec;
+        self
+    }
+
+    /// Argon2id without pepper by default. Changing the pepper invalidates every stored password
+    pub fn with_password_hashing(mut self, password_hashing: PasswordHashing) -> Self {
+        self.password_hashing = password_hashing;
+        self
+    }
+
+    /// Rules checked on every new password, see `PasswordPolicy::default`
+    pub fn with_password_policy(mut self, password_policy: PasswordPolicy) -> Self {
+        self.password_policy = Arc::new(password_policy);
+        self
+    }
+
+    /// Add a secret key and sign the next tokens with it. See `KeyRing::add_signing_key`
+    pub fn add_signing_key(&self, secret: &[u8]) -> Result<String, KeyRingError> {
+        self.key_ring.write().expect("Cannot lock key ring to write it").add_signing_key(secret)
+    }
+
+    /// Stop accepting tokens signed by a key, by its PASERK id
+    pub fn retire_key(&self, key_id: &str) -> Result<(), KeyRingError> {
+        self.key_ring.write().expect("Cannot lock key ring to write it").retire_key(key_id)
+    }
+
+    pub(crate) fn key_ring(&self) -> RwLockReadGuard<'_, KeyRing> {
+        self.key_ring.read().expect("Cannot lock key ring to read it")
+    }
+
+    pub(crate) fn implicit_assertion(&self) -> &[u8] {
+        &self.implicit_assertion
+    }
+
+    pub(crate) fn access_token_lifetime(&self) -> TimeDelta {
+        self.access_token_lifetime
+    }
+
+    pub(crate) fn refresh_token_lifetime(&self) -> TimeDelta {
+        self.refresh_token_lifetime
+    }
+
+    pub(crate) fn password_hashing(&self) -> &PasswordHashing {
+        &self.password_hashing
+    }
+
+    pub(crate) fn password_policy(&self) -> &PasswordPolicy {
+        &self.password_policy
+    }
+
+    /// Claims of a new token, with the issuer and the audience configured
+    pub(crate) fn new_claims(&self) -> Result<Claims, PasetoError> {
+        let mut claims = Claims::new()?;
+        if let Some(issuer) = &self.issuer {
+            claims.issuer(issuer)?;
+        }
+        if let Some(audience) = &self.audience {
+            claims.audience(audience)?;
+        }
+
+        Ok(claims)
+    }
+
+    /// Expiration and not before checked, plus the issuer and the audience configured
+    pub(crate) fn validation_rules(&self) -> ClaimsValidationRules {
+        let mut validation_rules = ClaimsValidationRules::new();
+        if let Some(issuer) = &self.issuer {
+            validation_rules.validate_issuer_with(issuer);
+        }
+        if let Some(audience) = &self.audience {
+            validation_rules.validate_audience_with(audience);
+        }
+
+        validation_rules
+    }
+
+    /// Encode access, refresh, service and delegated tokens with the token codec
+    pub(crate) async fn encode(&self, claims: &Claims) -> Result<String, AuthError> {
+        self.token_codec.encode(self, claims).await
+    }
+
+    /// Claims of a token encoded with the token codec, validated with `validation_rules`
+    pub(crate) async fn decode(&self, token: &str) -> Result<Claims, AuthError> {
+        self.token_codec.decode(self, token).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use base64::engine::general_purpose;
+    use pasetors::keys::{AsymmetricKeyPair, Generate};
+    use pasetors::version4::V4;
+    use super::*;
+
+    const FAKE_SECRET_KEY: &[u8] = b"y8zar2SZhQoufiUpYSGF94eTzqJ8Q6xo4nFb3TeImqzVX9Bs0xCfK0fpt0g7OcrrQXnTgo2Sz3xBGOoc7ZJ50Q==";
+    const FAKE_PUBLIC_KEY: &[u8] = b"1V/QbNMQnytH6bdIOznK60F504KNks98QRjqHO2SedE=";
+
+    impl AuthConfig {
+        pub fn fake() -> Self {
+            let mut key_ring = KeyRing::new();
+            key_ring.add_signing_key(&general_purpose::STANDARD.decode(FAKE_SECRET_KEY).expect("Unable decode key to init AuthConfig")).unwrap();
+            key_ring.add_verification_key(&general_purpose::STANDARD.decode(FAKE_PUBLIC_KEY).expect("Unable decode key to init AuthConfig")).unwrap();
+
+            Self::new(key_ring)
+        }
+    }
+
+    async fn sign_claims(auth_config: &AuthConfig) -> String {
+        let mut claims = auth_config.new_claims().unwrap();
+        claims.subject("access").unwrap();
+        auth_config.encode(&claims).await.expect("Unable sign claims")
+    }
+
+    async fn verify_token(auth_config: &AuthConfig, token: &str) -> Result<Claims, AuthError> {
+        auth_config.decode(token).await
+    }
+
+    #[test]
+    fn test_set_secret_key() {
+        let auth_config = AuthConfig::fake();
+
+        auth_config.key_ring().active_secret_key();
+        assert!(auth_config.key_ring().active_key_id().is_some());
+    }
+
+    #[test]
+    fn test_set_public_key() {
+        let auth_config = AuthConfig::fake();
+
+        auth_config.key_ring().active_public_key();
+        assert!(!auth_config.key_ring().published_keys().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Secret key not configured")]
+    fn test_get_secret_key_not_set() {
+        AuthConfig::new(KeyRing::new()).key_ring().active_secret_key();
+    }
+
+    #[test]
+    #[should_panic(expected = "Public key not configured")]
+    fn test_get_public_key_not_set() {
+        AuthConfig::new(KeyRing::new()).key_ring().active_public_key();
+    }
+
+    #[tokio::test]
+    async fn test_configurations_coexist() {
+        let auth_config = AuthConfig::fake();
+        let other_auth_config = AuthConfig::fake().with_implicit_assertion(b"other deployment");
+
+        assert!(verify_token(&auth_config, &sign_claims(&auth_config).await).await.is_ok());
+        assert!(verify_token(&other_auth_config, &sign_claims(&other_auth_config).await).await.is_ok());
+        assert!(verify_token(&auth_config, &sign_claims(&other_auth_config).await).await.is_err());
+        assert!(verify_token(&other_auth_config, &sign_claims(&auth_config).await).await.is_err());
+    }
+
+    #[test]
+    fn test_password_settings_coexist() {
+        let auth_config = AuthConfig::fake();
+        let other_auth_config = AuthConfig::fake()
+            .with_password_hashing(PasswordHashing::default().with_pepper(b"other_pepper"))
+            .with_password_policy(PasswordPolicy::default().with_denied_passwords(["correct horse battery"]));
+
+        let password_hash = other_auth_config.password_hashing().hash_password("correct horse battery").unwrap();
+
+        assert!(other_auth_config.password_hashing().verify_password("correct horse battery", &password_hash).is_ok());
+        assert!(auth_config.password_hashing().verify_password("correct horse battery", &password_hash).is_err());
+        assert!(auth_config.password_policy().validate("username", "correct horse battery").is_ok());
+        assert!(other_auth_config.password_policy().validate("username", "correct horse battery").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_key_ring() {
+        let auth_config = AuthConfig::fake();
+        let previous_token = sign_claims(&auth_config).await;
+        let previous_key_id = auth_config.key_ring().active_key_id().unwrap().to_string();
+        let secret_key = AsymmetricKeyPair::<V4>::generate().unwrap().secret;
+
+        let active_key_id = auth_config.clone().add_signing_key(secret_key.as_bytes()).unwrap();
+        auth_config.clone().retire_key(&previous_key_id).unwrap();
+
+        assert_eq!(auth_config.key_ring().active_key_id(), Some(active_key_id.as_str()));
+        assert!(verify_token(&auth_config, &previous_token).await.is_err());
+        assert!(verify_token(&auth_config, &sign_claims(&auth_config).await).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_issuer_and_audience_enforced() {
+        let auth_config = AuthConfig::fake().with_issuer("https://auth.example.com").with_audience("https://api.example.com");
+        let staging_auth_config = AuthConfig::fake().with_issuer("https://auth.staging.example.com").with_audience("https://api.example.com");
+        let other_api_auth_config = AuthConfig::fake().with_issuer("https://auth.example.com").with_audience("https://other.example.com");
+
+        let claims = verify_token(&auth_config, &sign_claims(&auth_config).await).await.expect("Token should be verified");
+
+        assert_eq!(claims.get_claim("iss").unwrap().as_str(), Some("https://auth.example.com"));
+        assert_eq!(claims.get_claim("aud").unwrap().as_str(), Some("https://api.example.com"));
+        assert!(verify_token(&auth_config, &sign_claims(&staging_auth_config).await).await.is_err());
+        assert!(verify_token(&auth_config, &sign_claims(&other_api_auth_config).await).await.is_err());
+        // Tokens without issuer and audience, signed before they were configured
+        assert!(verify_token(&auth_config, &sign_claims(&AuthConfig::fake()).await).await.is_err());
+    }
+}
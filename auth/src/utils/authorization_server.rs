@@ -0,0 +1,216 @@
+use std::sync::Arc;
+use chrono::Utc;
+use futures_util::future::BoxFuture;
+use jsonwebtoken::jwk::JwkSet;
+use serde::{Deserialize, Serialize};
+use crate::entities::error::AuthError;
+use crate::utils::auth_config::AuthConfig;
+use crate::views::response::OpenIdConfiguration;
+
+/// Standard claims of a user given to third-party apps, in the ID token and by the userinfo endpoint
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UserClaims {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+    /// Seconds since the epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+}
+
+impl UserClaims {
+    /// Only the claims of the `profile` and `email` scopes granted are given
+    fn for_scopes(self, scopes: &[String]) -> Self {
+        let profile = scopes.iter().any(|scope| scope == AuthorizationServer::PROFILE_SCOPE);
+        let email = scopes.iter().any(|scope| scope == AuthorizationServer::EMAIL_SCOPE);
+
+        Self {
+            preferred_username: self.preferred_username.filter(|_| profile),
+            name: self.name.filter(|_| profile),
+            updated_at: self.updated_at.filter(|_| profile),
+            email: self.email.filter(|_| email),
+            email_verified: self.email_verified.filter(|_| email),
+        }
+    }
+}
+
+/// Read the claims of a user, owned by the module managing user profiles
+pub trait UserClaimsProvider: Send + Sync {
+    fn get_user_claims<'a>(&'a self, username: &'a str) -> BoxFuture<'a, Result<UserClaims, AuthError>>;
+}
+
+/// Give only the username, when no module manages user profiles
+#[derive(Default)]
+pub struct UsernameClaims;
+
+impl UserClaimsProvider for UsernameClaims {
+    fn get_user_claims<'a>(&'a self, username: &'a str) -> BoxFuture<'a, Result<UserClaims, AuthError>> {
+        Box::pin(async move {
+            Ok(UserClaims {
+                preferred_username: Some(username.to_string()),
+                ..UserClaims::default()
+            })
+        })
+    }
+}
+
+/// Claims of the ID token (OpenID Connect Core section 2)
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct IdTokenClaims {
+    pub(crate) iss: String,
+    pub(crate) sub: String,
+    pub(crate) aud: String,
+    pub(crate) exp: i64,
+    pub(crate) iat: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) nonce: Option<String>,
+    #[serde(flatten)]
+    pub(crate) user_claims: UserClaims,
+}
+
+/// OpenID provider for third-party apps : this service authenticates users on their behalf
+///
+/// ID tokens are JWT signed with EdDSA by the Ed25519 key of the PASETO tokens, published as JWKS.
+/// The authorization page is the page of the front-end logging in the user and asking its consent,
+/// which forwards the authorization request to `POST /authorize`.
+#[derive(Clone)]
+pub struct AuthorizationServer {
+    issuer: String,
+    authorization_page: String,
+    user_claims_provider: Arc<dyn UserClaimsProvider>,
+}
+
+impl Default for AuthorizationServer {
+    /// Local development on `http://localhost:8000`, with the auth routes under `/auth`
+    fn default() -> Self {
+        Self::new("http://localhost:8000/auth")
+    }
+}
+
+impl AuthorizationServer {
+    pub(crate) const OPENID_SCOPE: &'static str = "openid";
+    pub(crate) const PROFILE_SCOPE: &'static str = "profile";
+    pub(crate) const EMAIL_SCOPE: &'static str = "email";
+    /// `issuer` is the public URL of the auth routes, without trailing slash
+    pub fn new(issuer: &str) -> Self {
+        let issuer = issuer.trim_end_matches('/');
+
+        Self {
+            issuer: issuer.to_string(),
+            authorization_page: format!("{issuer}/authorize"),
+            user_claims_provider: Arc::new(UsernameClaims),
+        }
+    }
+
+    /// Page of the front-end third-party apps redirect users to, `{issuer}/authorize` by default
+    pub fn with_authorization_page(mut self, authorization_page: &str) -> Self {
+        self.authorization_page = authorization_page.to_string();
+        self
+    }
+
+    /// Claims given to third-party apps, only the username by default
+    pub fn with_user_claims_provider(mut self, user_claims_provider: Arc<dyn UserClaimsProvider>) -> Self {
+        self.user_claims_provider = user_claims_provider;
+        self
+    }
+
+    pub(crate) fn supported_scopes() -> Vec<String> {
+        vec![Self::OPENID_SCOPE.to_string(), Self::PROFILE_SCOPE.to_string(), Self::EMAIL_SCOPE.to_string()]
+    }
+
+    pub(crate) async fn get_user_claims(&self, username: &str, scopes: &[String]) -> Result<UserClaims, AuthError> {
+        Ok(self.user_claims_provider.get_user_claims(username).await?.for_scopes(scopes))
+    }
+
+    pub(crate) fn sign_id_token(&self, auth_config: &AuthConfig, client_id: &str, username: &str, nonce: Option<String>, user_claims: UserClaims) -> Result<String, AuthError> {
+        let now = Utc::now();
+        let id_token_claims = IdTokenClaims {
+            iss: self.issuer.clone(),
+            sub: username.to_string(),
+            aud: client_id.to_string(),
+            exp: (now + auth_config.access_token_lifetime()).timestamp(),
+            iat: now.timestamp(),
+            nonce,
+            user_claims,
+        };
+
+        auth_config.key_ring().sign_jwt(&id_token_claims).map_err(|_| AuthError::TokenCreation)
+    }
+
+    /// Every key of the key ring not retired, to verify ID tokens signed before a rotation
+    pub(crate) fn json_web_key_set(auth_config: &AuthConfig) -> JwkSet {
+        auth_config.key_ring().json_web_key_set()
+    }
+
+    /// Discovery document of OpenID Connect Discovery section 3
+    pub(crate) fn discovery_document(&self) -> OpenIdConfiguration {
+        OpenIdConfiguration {
+            issuer: self.issuer.clone(),
+            authorization_endpoint: self.authorization_page.clone(),
+            token_endpoint: format!("{}/token", self.issuer),
+            userinfo_endpoint: format!("{}/userinfo", self.issuer),
+            jwks_uri: format!("{}/.well-known/jwks.json", self.issuer),
+            scopes_supported: Self::supported_scopes(),
+            response_types_supported: vec!["code".to_string()],
+            grant_types_supported: vec!["authorization_code".to_string(), "client_credentials".to_string()],
+            subject_types_supported: vec!["public".to_string()],
+            id_token_signing_alg_values_supported: vec!["EdDSA".to_string()],
+            token_endpoint_auth_methods_supported: vec!["client_secret_basic".to_string(), "client_secret_post".to_string()],
+            code_challenge_methods_supported: vec!["S256".to_string()],
+            claims_supported: ["sub", "iss", "aud", "exp", "iat", "nonce", "preferred_username", "name", "email", "email_verified", "updated_at"].map(str::to_string).to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+    use jsonwebtoken::jwk::AlgorithmParameters;
+    use super::*;
+
+    #[test]
+    fn test_id_token_verified_with_json_web_key_set() {
+        let auth_config = AuthConfig::fake();
+        let authorization_server = AuthorizationServer::new("https://auth.example.com/auth/");
+        let user_claims = UserClaims { preferred_username: Some("juliana".to_string()), ..UserClaims::default() };
+
+        let id_token = authorization_server.sign_id_token(&auth_config, "client_id", "juliana", Some("nonce".to_string()), user_claims.clone()).unwrap();
+
+        let json_web_key_set = AuthorizationServer::json_web_key_set(&auth_config);
+        let kid = jsonwebtoken::decode_header(&id_token).unwrap().kid.unwrap();
+        let AlgorithmParameters::OctetKeyPair(octet_key_pair_parameters) = &json_web_key_set.find(&kid).unwrap().algorithm else {
+            panic!("JWKS must contain the Ed25519 key");
+        };
+        let mut validation = Validation::new(Algorithm::EdDSA);
+        validation.set_audience(&["client_id"]);
+        validation.set_issuer(&["https://auth.example.com/auth"]);
+        let id_token_claims = jsonwebtoken::decode::<IdTokenClaims>(&id_token, &DecodingKey::from_ed_components(&octet_key_pair_parameters.x).unwrap(), &validation).unwrap().claims;
+
+        assert_eq!(id_token_claims.sub, "juliana");
+        assert_eq!(id_token_claims.nonce, Some("nonce".to_string()));
+        assert_eq!(id_token_claims.user_claims, user_claims);
+    }
+
+    #[test]
+    fn test_user_claims_for_scopes() {
+        let user_claims = UserClaims {
+            preferred_username: Some("juliana".to_string()),
+            name: None,
+            email: Some("juliana@example.com".to_string()),
+            email_verified: Some(false),
+            updated_at: Some(0),
+        };
+
+        let openid_claims = user_claims.clone().for_scopes(&["openid".to_string()]);
+        let email_claims = user_claims.clone().for_scopes(&["openid".to_string(), "email".to_string()]);
+
+        assert_eq!(openid_claims, UserClaims::default());
+        assert_eq!(email_claims.email, user_claims.email);
+        assert_eq!(email_claims.preferred_username, None);
+    }
+}
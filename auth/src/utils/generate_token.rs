@@ -1,50 +1,72 @@
 use std::error::Error;
 use std::ops::Add;
-use chrono::{DateTime, Duration, TimeDelta, Utc};
+use chrono::{DateTime, Utc};
 use mongodb::bson;
-use pasetors::claims::{Claims};
-use pasetors::public;
 use crate::entities::error::AuthError;
-use crate::entities::{Token, TokenType, UserCredentials};
+use crate::entities::{OAuthClient, Roles, Token, TokenType, UserCredentials};
 
-use crate::utils::settings::AuthSettings;
+use crate::utils::auth_config::AuthConfig;
 
 impl Token {
-    const ACCESS_TOKEN_LIFETIME: TimeDelta = Duration::minutes(10);
-    const REFRESH_TOKEN_LIFETIME: TimeDelta = Duration::days(1);
-
     fn generate_token_id() -> String {
         uuid::Uuid::new_v4().to_string()
     }
-    fn generate_access_token(user: &UserCredentials) -> Result<(String, DateTime<Utc>, String), AuthError> {
+    async fn generate_access_token(auth_config: &AuthConfig, user: &UserCredentials) -> Result<(String, DateTime<Utc>, String), AuthError> {
         let token_id = Self::generate_token_id();
-        let expiration = Utc::now().add(Self::ACCESS_TOKEN_LIFETIME);
-        let mut claims = Claims::new().map_err(|_| AuthError::TokenCreation)?;
+        let expiration = Utc::now().add(auth_config.access_token_lifetime());
+        let mut claims = auth_config.new_claims().map_err(|_| AuthError::TokenCreation)?;
         claims.token_identifier(&token_id.clone()).expect("Unable to insert token id");
         claims.subject(&TokenType::Access.to_string()).map_err(|_| AuthError::TokenCreation)?;
         claims.expiration(&expiration.to_rfc3339()).expect("Cannot define expiration");
         claims.add_additional("username", user.username.to_string()).map_err(|_| AuthError::TokenCreation)?;
-        claims.add_additional("role", user.roles.to_string()).map_err(|_| AuthError::TokenCreation)?;
+        claims.add_additional("roles", user.roles.iter().map(Roles::to_string).collect::<Vec<String>>()).map_err(|_| AuthError::TokenCreation)?;
 
         // Send the authorized token
-        Ok((token_id, expiration, public::sign(&AuthSettings::get_secret_key(), &claims, None, Some(b"implicit assertion")).map_err(|_| AuthError::TokenCreation)?))
+        Ok((token_id, expiration, auth_config.encode(&claims).await?))
     }
-    fn generate_refresh_token(user: &UserCredentials) -> Result<(String, DateTime<Utc>, String), AuthError> {
+    async fn generate_refresh_token(auth_config: &AuthConfig, user: &UserCredentials) -> Result<(String, DateTime<Utc>, String), AuthError> {
         let token_id = Self::generate_token_id();
-        let expiration = Utc::now().add(Self::REFRESH_TOKEN_LIFETIME);
-        let mut claims = Claims::new().map_err(|_| AuthError::TokenCreation)?;
+        let expiration = Utc::now().add(auth_config.refresh_token_lifetime());
+        let mut claims = auth_config.new_claims().map_err(|_| AuthError::TokenCreation)?;
         claims.subject(&TokenType::Refresh.to_string()).map_err(|_| AuthError::TokenCreation)?;
         claims.expiration(&expiration.to_rfc3339()).expect("Cannot define expiration");
         claims.token_identifier(&token_id.clone()).expect("Unable to insert token id");
         claims.add_additional("username", user.username.to_string()).map_err(|_| AuthError::TokenCreation)?;
 
         // Send the authorized token
-        Ok((token_id, expiration, public::sign(&AuthSettings::get_secret_key(), &claims, None, Some(b"implicit assertion")).map_err(|_| AuthError::TokenCreation)?))
+        Ok((token_id, expiration, auth_config.encode(&claims).await?))
+    }
+
+    /// Access token of a service principal, without refresh token : the client authenticates again when it expires
+    pub(crate) async fn generate_service_access_token(auth_config: &AuthConfig, oauth_client: &OAuthClient, scopes: &[String]) -> Result<(DateTime<Utc>, String), AuthError> {
+        let expiration = Utc::now().add(auth_config.access_token_lifetime());
+        let mut claims = auth_config.new_claims().map_err(|_| AuthError::TokenCreation)?;
+        claims.token_identifier(&Self::generate_token_id()).expect("Unable to insert token id");
+        claims.subject(&TokenType::Service.to_string()).map_err(|_| AuthError::TokenCreation)?;
+        claims.expiration(&expiration.to_rfc3339()).expect("Cannot define expiration");
+        claims.add_additional("client_id", oauth_client.client_id.to_string()).map_err(|_| AuthError::TokenCreation)?;
+        claims.add_additional("scope", scopes.join(" ")).map_err(|_| AuthError::TokenCreation)?;
+
+        Ok((expiration, auth_config.encode(&claims).await?))
+    }
+
+    /// Access token of a third-party app on behalf of a user, without refresh token : the app asks the user again when it expires
+    pub(crate) async fn generate_delegated_access_token(auth_config: &AuthConfig, oauth_client: &OAuthClient, username: &str, scopes: &[String]) -> Result<(DateTime<Utc>, String), AuthError> {
+        let expiration = Utc::now().add(auth_config.access_token_lifetime());
+        let mut claims = auth_config.new_claims().map_err(|_| AuthError::TokenCreation)?;
+        claims.token_identifier(&Self::generate_token_id()).expect("Unable to insert token id");
+        claims.subject(&TokenType::Delegated.to_string()).map_err(|_| AuthError::TokenCreation)?;
+        claims.expiration(&expiration.to_rfc3339()).expect("Cannot define expiration");
+        claims.add_additional("username", username.to_string()).map_err(|_| AuthError::TokenCreation)?;
+        claims.add_additional("client_id", oauth_client.client_id.to_string()).map_err(|_| AuthError::TokenCreation)?;
+        claims.add_additional("scope", scopes.join(" ")).map_err(|_| AuthError::TokenCreation)?;
+
+        Ok((expiration, auth_config.encode(&claims).await?))
     }
 
-    pub async fn generate_tokens(user: &UserCredentials) -> Result<(String, String, Self), Box<dyn Error>> {
-        let (access_token_id, access_expired_at, access_token) = Self::generate_access_token(&user).map_err(|error| Box::new(error))?;
-        let (refresh_token_id, refresh_expired_at, refresh_token) = Self::generate_refresh_token(&user).map_err(|error| Box::new(error))?;
+    pub async fn generate_tokens(auth_config: &AuthConfig, user: &UserCredentials) -> Result<(String, String, Self), Box<dyn Error>> {
+        let (access_token_id, access_expired_at, access_token) = Self::generate_access_token(auth_config, user).await.map_err(|error| Box::new(error))?;
+        let (refresh_token_id, refresh_expired_at, refresh_token) = Self::generate_refresh_token(auth_config, user).await.map_err(|error| Box::new(error))?;
 
         Ok((access_token, refresh_token, Self {
             id: None,
@@ -54,7 +76,11 @@ impl Token {
             token_access_expired_at: bson::DateTime::parse_rfc3339_str(access_expired_at.to_rfc3339()).unwrap(),
             token_refresh_expired_at: bson::DateTime::parse_rfc3339_str(refresh_expired_at.to_rfc3339()).unwrap(),
             created_at: bson::DateTime::now(),
-            revoked_at: None
+            revoked_at: None,
+            session_id: Self::generate_token_id(),
+            parent_token_identifier: None,
+            user_agent: None,
+            client_ip: None,
         }))
     }
 }
@@ -62,62 +88,81 @@ impl Token {
 #[cfg(test)]
 mod test {
     use super::*;
+    use chrono::Duration;
     use fake::{Fake, Faker};
+    use crate::utils::validate_token::{IntoClaims, TokenString};
     use pasetors::claims::ClaimsValidationRules;
-    use pasetors::Public;
-    use pasetors::token::UntrustedToken;
-    use pasetors::version4::V4;
 
-    fn validate_access_token(token_id: String, token: String, user_credential: UserCredentials) {
-        let validation_rules = ClaimsValidationRules::new();
-        let untrusted_token = UntrustedToken::<Public, V4>::try_from(&token).expect("Unable parse string to token");
-        let trusted_token = public::verify(&AuthSettings::get_public_key(), &untrusted_token, &validation_rules, None, Some(b"implicit assertion")).expect("Unable to verify token with this public key");
+    async fn validate_access_token(auth_config: &AuthConfig, token_id: String, token: String, user_credential: UserCredentials) {
+        let claims = auth_config.decode(&token).await.expect("Unable to verify token with this public key");
 
 
         let mut validation_rules = ClaimsValidationRules::new();
         validation_rules.validate_subject_with(&TokenType::Access.to_string());
 
-        assert_eq!(trusted_token.payload_claims().unwrap().get_claim("jti").unwrap().to_string().trim_matches('"'), token_id);
-        assert!(validation_rules.validate_claims(&trusted_token.payload_claims().unwrap()).is_ok());
-        assert_eq!(trusted_token.payload_claims().unwrap().get_claim("username").unwrap().to_string().trim_matches('"'), user_credential.username);
-        assert_eq!(trusted_token.payload_claims().unwrap().get_claim("role").unwrap().to_string().trim_matches('"'), user_credential.roles.to_string());
+        assert_eq!(claims.get_claim("jti").unwrap().to_string().trim_matches('"'), token_id);
+        assert!(validation_rules.validate_claims(&claims).is_ok());
+        assert_eq!(claims.get_claim("username").unwrap().to_string().trim_matches('"'), user_credential.username);
+        let roles: Vec<String> = user_credential.roles.iter().map(Roles::to_string).collect();
+        assert_eq!(claims.get_claim("roles").unwrap(), &serde_json::json!(roles));
     }
 
 
-    fn validate_refresh_token(token_id: String, token: String, user_credential: UserCredentials) {
-        let validation_rules = ClaimsValidationRules::new();
-        let untrusted_token = UntrustedToken::<Public, V4>::try_from(&token).expect("Unable parse string to token");
-        let trusted_token = public::verify(&AuthSettings::get_public_key(), &untrusted_token, &validation_rules, None, Some(b"implicit assertion")).expect("Unable to verify token with this public key");
+    async fn validate_refresh_token(auth_config: &AuthConfig, token_id: String, token: String, user_credential: UserCredentials) {
+        let claims = auth_config.decode(&token).await.expect("Unable to verify token with this public key");
 
 
         let mut validation_rules = ClaimsValidationRules::new();
         validation_rules.validate_subject_with(&TokenType::Refresh.to_string());
 
-        assert_eq!(trusted_token.payload_claims().unwrap().get_claim("jti").unwrap().to_string().trim_matches('"'), token_id);
-        assert!(validation_rules.validate_claims(&trusted_token.payload_claims().unwrap()).is_ok());
-        assert_eq!(trusted_token.payload_claims().unwrap().get_claim("username").unwrap().to_string().trim_matches('"'), user_credential.username);
+        assert_eq!(claims.get_claim("jti").unwrap().to_string().trim_matches('"'), token_id);
+        assert!(validation_rules.validate_claims(&claims).is_ok());
+        assert_eq!(claims.get_claim("username").unwrap().to_string().trim_matches('"'), user_credential.username);
 
     }
 
-    #[test]
-    fn test_generate_access_token() {
-        AuthSettings::init_fake();
+    #[tokio::test]
+    async fn test_generate_access_token() {
+        let auth_config = AuthConfig::fake();
         let user_credential = Faker.fake();
 
 
-        let (token_id, expiration, token_generated) = Token::generate_access_token(&user_credential).expect("Unable to generate access token");
+        let (token_id, expiration, token_generated) = Token::generate_access_token(&auth_config, &user_credential).await.expect("Unable to generate access token");
         assert!(Utc::now() < expiration);
-        validate_access_token(token_id, token_generated, user_credential);
+        validate_access_token(&auth_config, token_id, token_generated, user_credential).await;
    }
 
-    #[test]
-    fn test_generate_refresh_token() {
-        AuthSettings::init_fake();
+    #[tokio::test]
+    async fn test_generate_refresh_token() {
+        let auth_config = AuthConfig::fake();
         let user_credential = Faker.fake();
 
 
-        let (token_id, expiration, token_generated) = Token::generate_refresh_token(&user_credential).expect("Unable to generate access token");
+        let (token_id, expiration, token_generated) = Token::generate_refresh_token(&auth_config, &user_credential).await.expect("Unable to generate access token");
         assert!(Utc::now() < expiration);
-        validate_refresh_token(token_id, token_generated, user_credential);
+        validate_refresh_token(&auth_config, token_id, token_generated, user_credential).await;
    }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_generate_tokens_with_configured_lifetimes_issuer_and_audience() {
+        let auth_config = AuthConfig::fake()
+            .with_access_token_lifetime(Duration::minutes(2))
+            .with_refresh_token_lifetime(Duration::hours(3))
+            .with_issuer("https://auth.example.com")
+            .with_audience("https://api.example.com");
+        let user_credential: UserCredentials = Faker.fake();
+
+        let (access_token, refresh_token, token) = Token::generate_tokens(&auth_config, &user_credential).await.unwrap();
+
+        let access_expired_in = token.token_access_expired_at.timestamp_millis() - Utc::now().timestamp_millis();
+        let refresh_expired_in = token.token_refresh_expired_at.timestamp_millis() - Utc::now().timestamp_millis();
+        assert!(access_expired_in > Duration::minutes(1).num_milliseconds() && access_expired_in <= Duration::minutes(2).num_milliseconds());
+        assert!(refresh_expired_in > Duration::hours(2).num_milliseconds() && refresh_expired_in <= Duration::hours(3).num_milliseconds());
+        for token in [access_token.clone(), refresh_token] {
+            let claims = TokenString(token).try_into_claims(&auth_config).await.expect("Token should be valid for its deployment");
+            assert_eq!(claims.get_claim("iss").unwrap().as_str(), Some("https://auth.example.com"));
+            assert_eq!(claims.get_claim("aud").unwrap().as_str(), Some("https://api.example.com"));
+        }
+        assert_eq!(TokenString(access_token).try_into_claims(&AuthConfig::fake().with_issuer("https://auth.staging.example.com")).await, Err(AuthError::WrongCredentials));
+    }
+}
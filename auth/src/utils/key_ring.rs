@@ -0,0 +1,304 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::jwk::{AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse};
+use jsonwebtoken::errors::{Error as JwtError, ErrorKind as JwtErrorKind};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use pasetors::claims::{Claims, ClaimsValidationRules};
+use pasetors::errors::Error as PasetoError;
+use pasetors::footer::Footer;
+use pasetors::keys::{AsymmetricPublicKey, AsymmetricSecretKey};
+use pasetors::paserk::{FormatAsPaserk, Id};
+use pasetors::token::{TrustedToken, UntrustedToken};
+use pasetors::version4::V4;
+use pasetors::{public, Public};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum KeyRingError {
+    #[error("Key is not a valid Ed25519 key")]
+    InvalidKey,
+    #[error("Key not found in the key ring")]
+    UnknownKey,
+    #[error("Active signing key can't be retired")]
+    ActiveKey,
+}
+
+#[derive(Clone)]
+struct KeyRingEntry {
+    key_id: String,
+    secret_key: Option<AsymmetricSecretKey<V4>>,
+    public_key: AsymmetricPublicKey<V4>,
+    retired: bool,
+}
+
+/// Public key of the ring, as published to the services verifying tokens themselves
+#[derive(Clone, Debug, PartialEq)]
+pub struct PublishedKey {
+    /// PASERK id (`k4.pid.`), written in the `kid` claim of token footers
+    pub key_id: String,
+    /// PASERK public key (`k4.public.`)
+    pub public_key: String,
+    pub active: bool,
+}
+
+/// Ed25519 keys of PASETO v4.public tokens, also used for EdDSA JWTs
+///
+/// Tokens are signed with the active key, and their footer (or JWT header) holds its id.
+/// Every key not retired is accepted on verification : rotate by adding a new signing key,
+/// then retire the previous one once its tokens are expired.
+#[derive(Clone, Default)]
+pub struct KeyRing {
+    keys: Vec<KeyRingEntry>,
+    active_key_id: Option<String>,
+}
+
+impl KeyRing {
+    /// PKCS #8 prefix of an Ed25519 private key, followed by its 32 bytes seed (RFC 8410)
+    const ED25519_PKCS8_PREFIX: [u8; 16] = [0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20];
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a secret key (seed and public key, 64 bytes) and sign the next tokens with it
+    pub fn add_signing_key(&mut self, secret: &[u8]) -> Result<String, KeyRingError> {
+        let secret_key = AsymmetricSecretKey::<V4>::from(secret).map_err(|_| KeyRingError::InvalidKey)?;
+        let public_key = AsymmetricPublicKey::<V4>::try_from(&secret_key).map_err(|_| KeyRingError::InvalidKey)?;
+        let key_id = Self::key_id_of(&public_key);
+
+        self.keys.retain(|entry| entry.key_id != key_id);
+        self.keys.push(KeyRingEntry { key_id: key_id.clone(), secret_key: Some(secret_key), public_key, retired: false });
+        self.active_key_id = Some(key_id.clone());
+
+        Ok(key_id)
+    }
+
+    /// Add a public key only accepted on verification, e.g. the key of another instance
+    pub fn add_verification_key(&mut self, public: &[u8]) -> Result<String, KeyRingError> {
+        let public_key = AsymmetricPublicKey::<V4>::from(public).map_err(|_| KeyRingError::InvalidKey)?;
+        let key_id = Self::key_id_of(&public_key);
+
+        if !self.keys.iter().any(|entry| entry.key_id == key_id) {
+            self.keys.push(KeyRingEntry { key_id: key_id.clone(), secret_key: None, public_key, retired: false });
+        }
+
+        Ok(key_id)
+    }
+
+    /// Stop accepting tokens signed by this key
+    pub fn retire_key(&mut self, key_id: &str) -> Result<(), KeyRingError> {
+        if self.active_key_id.as_deref() == Some(key_id) {
+            return Err(KeyRingError::ActiveKey);
+        }
+
+        let entry = self.keys.iter_mut().find(|entry| entry.key_id == key_id).ok_or(KeyRingError::UnknownKey)?;
+        entry.retired = true;
+        entry.secret_key = None;
+
+        Ok(())
+    }
+
+    pub fn active_key_id(&self) -> Option<&str> {
+        self.active_key_id.as_deref()
+    }
+
+    pub fn published_keys(&self) -> Vec<PublishedKey> {
+        self.verification_keys()
+            .map(|entry| {
+                let mut public_key = String::new();
+                entry.public_key.fmt(&mut public_key).expect("Cannot format public key as PASERK");
+
+                PublishedKey {
+                    key_id: entry.key_id.clone(),
+                    public_key,
+                    active: self.active_key_id.as_ref() == Some(&entry.key_id),
+                }
+            })
+            .collect()
+    }
+
+    fn key_id_of(public_key: &AsymmetricPublicKey<V4>) -> String {
+        let mut key_id = String::new();
+        Id::from(public_key).fmt(&mut key_id).expect("Cannot format key id as PASERK");
+        key_id
+    }
+
+    fn active_key(&self) -> Option<&KeyRingEntry> {
+        self.active_key_id.as_ref().and_then(|key_id| self.keys.iter().find(|entry| &entry.key_id == key_id))
+    }
+
+    fn verification_keys(&self) -> impl Iterator<Item = &KeyRingEntry> {
+        self.keys.iter().filter(|entry| !entry.retired)
+    }
+
+    pub(crate) fn active_secret_key(&self) -> AsymmetricSecretKey<V4> {
+        self.active_key().and_then(|entry| entry.secret_key.clone()).expect("Secret key not configured")
+    }
+
+    pub(crate) fn active_public_key(&self) -> AsymmetricPublicKey<V4> {
+        self.active_key().map(|entry| entry.public_key.clone()).expect("Public key not configured")
+    }
+
+    /// Sign with the active key, its id in the footer
+    pub(crate) fn sign(&self, claims: &Claims, implicit_assertion: &[u8]) -> Result<String, PasetoError> {
+        let active_key = self.active_key().expect("Secret key not configured");
+        let secret_key = active_key.secret_key.as_ref().expect("Secret key not configured");
+        let mut footer = Footer::new();
+        footer.key_id(&Id::try_from(active_key.key_id.as_str())?);
+
+        public::sign(secret_key, claims, Some(&footer), Some(implicit_assertion))
+    }
+
+    /// Verify with the key named in the footer. Tokens signed before the key ring, without footer, are tried with every key
+    pub(crate) fn verify(&self, untrusted_token: &UntrustedToken<Public, V4>, validation_rules: &ClaimsValidationRules, implicit_assertion: &[u8]) -> Result<TrustedToken, PasetoError> {
+        if self.keys.is_empty() {
+            panic!("Public key not configured");
+        }
+
+        let key_id = if untrusted_token.untrusted_footer().is_empty() {
+            None
+        } else {
+            let mut footer = Footer::new();
+            footer.parse_bytes(untrusted_token.untrusted_footer())?;
+            Some(footer.get_claim("kid").and_then(|kid| kid.as_str()).ok_or(PasetoError::TokenValidation)?.to_string())
+        };
+
+        let mut last_error = PasetoError::TokenValidation;
+        for entry in self.verification_keys().filter(|entry| key_id.is_none() || key_id.as_ref() == Some(&entry.key_id)) {
+            match public::verify(&entry.public_key, untrusted_token, validation_rules, None, Some(implicit_assertion)) {
+                Ok(trusted_token) => return Ok(trusted_token),
+                Err(error) => last_error = error,
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// Sign a JWT with the active key, the JWK thumbprint of the key in the `kid` header
+    pub(crate) fn sign_jwt<T: Serialize>(&self, claims: &T) -> Result<String, JwtError> {
+        let mut private_key = Self::ED25519_PKCS8_PREFIX.to_vec();
+        private_key.extend_from_slice(&self.active_secret_key().as_bytes()[..32]);
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(Self::json_web_key_id(&Self::octet_key_pair_parameters(&self.active_public_key())));
+
+        jsonwebtoken::encode(&header, claims, &EncodingKey::from_ed_der(&private_key))
+    }
+
+    /// Verify a JWT with the key named in its `kid` header, among the keys not retired
+    pub(crate) fn verify_jwt<T: DeserializeOwned>(&self, token: &str, validation: &Validation) -> Result<T, JwtError> {
+        let kid = jsonwebtoken::decode_header(token)?.kid.ok_or(JwtErrorKind::InvalidToken)?;
+        let json_web_key_set = self.json_web_key_set();
+        let json_web_key = json_web_key_set.find(&kid).ok_or(JwtErrorKind::InvalidToken)?;
+
+        Ok(jsonwebtoken::decode::<T>(token, &DecodingKey::from_jwk(json_web_key)?, validation)?.claims)
+    }
+
+    /// Every key not retired, to verify JWTs signed before a rotation
+    pub(crate) fn json_web_key_set(&self) -> JwkSet {
+        JwkSet {
+            keys: self.verification_keys()
+                .map(|entry| {
+                    let octet_key_pair_parameters = Self::octet_key_pair_parameters(&entry.public_key);
+
+                    Jwk {
+                        common: CommonParameters {
+                            public_key_use: Some(PublicKeyUse::Signature),
+                            key_algorithm: Some(KeyAlgorithm::EdDSA),
+                            key_id: Some(Self::json_web_key_id(&octet_key_pair_parameters)),
+                            ..CommonParameters::default()
+                        },
+                        algorithm: AlgorithmParameters::OctetKeyPair(octet_key_pair_parameters),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn octet_key_pair_parameters(public_key: &AsymmetricPublicKey<V4>) -> OctetKeyPairParameters {
+        OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(public_key.as_bytes()),
+        }
+    }
+
+    /// JWK thumbprint of the public key (RFC 7638)
+    fn json_web_key_id(octet_key_pair_parameters: &OctetKeyPairParameters) -> String {
+        let thumbprint_input = format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, octet_key_pair_parameters.x);
+
+        URL_SAFE_NO_PAD.encode(Sha256::digest(thumbprint_input.as_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use base64::engine::general_purpose;
+    use pasetors::keys::{AsymmetricKeyPair, Generate};
+    use super::*;
+
+    const FAKE_SECRET_KEY: &[u8] = b"y8zar2SZhQoufiUpYSGF94eTzqJ8Q6xo4nFb3TeImqzVX9Bs0xCfK0fpt0g7OcrrQXnTgo2Sz3xBGOoc7ZJ50Q==";
+    const IMPLICIT_ASSERTION: &[u8] = b"implicit assertion";
+
+    fn generate_secret_key() -> Vec<u8> {
+        AsymmetricKeyPair::<V4>::generate().expect("Unable generate key pair").secret.as_bytes().to_vec()
+    }
+
+    fn sign_claims(key_ring: &KeyRing) -> String {
+        let mut claims = Claims::new().unwrap();
+        claims.subject("access").unwrap();
+        key_ring.sign(&claims, IMPLICIT_ASSERTION).expect("Unable sign claims")
+    }
+
+    fn verify_token(key_ring: &KeyRing, token: &str) -> Result<TrustedToken, PasetoError> {
+        let untrusted_token = UntrustedToken::<Public, V4>::try_from(token).unwrap();
+        key_ring.verify(&untrusted_token, &ClaimsValidationRules::new(), IMPLICIT_ASSERTION)
+    }
+
+    #[test]
+    fn test_sign_writes_active_key_id_in_footer() {
+        let mut key_ring = KeyRing::new();
+        let key_id = key_ring.add_signing_key(&general_purpose::STANDARD.decode(FAKE_SECRET_KEY).unwrap()).unwrap();
+
+        let trusted_token = verify_token(&key_ring, &sign_claims(&key_ring)).expect("Token should be verified");
+        let mut footer = Footer::new();
+        footer.parse_bytes(trusted_token.footer()).unwrap();
+
+        assert!(key_id.starts_with("k4.pid."));
+        assert_eq!(footer.get_claim("kid").unwrap().as_str(), Some(key_id.as_str()));
+    }
+
+    #[test]
+    fn test_rotation_keeps_previous_tokens_until_retired() {
+        let mut key_ring = KeyRing::new();
+        let previous_key_id = key_ring.add_signing_key(&generate_secret_key()).unwrap();
+        let previous_token = sign_claims(&key_ring);
+        let active_key_id = key_ring.add_signing_key(&generate_secret_key()).unwrap();
+        let active_token = sign_claims(&key_ring);
+
+        assert!(verify_token(&key_ring, &previous_token).is_ok());
+        assert!(verify_token(&key_ring, &active_token).is_ok());
+        assert_eq!(key_ring.retire_key(&active_key_id), Err(KeyRingError::ActiveKey));
+
+        key_ring.retire_key(&previous_key_id).unwrap();
+
+        assert!(verify_token(&key_ring, &previous_token).is_err());
+        assert!(verify_token(&key_ring, &active_token).is_ok());
+        assert_eq!(key_ring.published_keys().len(), 1);
+        assert!(key_ring.published_keys()[0].public_key.starts_with("k4.public."));
+        assert!(key_ring.published_keys()[0].active);
+    }
+
+    #[test]
+    fn test_verify_rejects_unknown_key() {
+        let mut key_ring = KeyRing::new();
+        key_ring.add_signing_key(&generate_secret_key()).unwrap();
+        let mut other_key_ring = KeyRing::new();
+        other_key_ring.add_signing_key(&generate_secret_key()).unwrap();
+
+        assert!(verify_token(&key_ring, &sign_claims(&other_key_ring)).is_err());
+    }
+}
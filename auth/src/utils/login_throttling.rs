@@ -0,0 +1,91 @@
+use chrono::{Duration, TimeDelta};
+
+/// Limits applied to failed logins, counted by username and by client IP
+///
+/// Once `max_failures` is reached, each new failure locks the key for an exponential duration :
+/// `base_lock_duration`, then twice longer, up to `max_lock_duration`.
+/// Failures older than `failure_window` are forgotten when the key is not locked.
+#[derive(Clone, Debug)]
+pub struct LoginThrottling {
+    username_max_failures: u32,
+    client_ip_max_failures: u32,
+    base_lock_duration: TimeDelta,
+    max_lock_duration: TimeDelta,
+    failure_window: TimeDelta,
+}
+
+impl Default for LoginThrottling {
+    fn default() -> Self {
+        Self {
+            username_max_failures: 5,
+            client_ip_max_failures: 20,
+            base_lock_duration: Duration::seconds(30),
+            max_lock_duration: Duration::minutes(15),
+            failure_window: Duration::minutes(15),
+        }
+    }
+}
+
+impl LoginThrottling {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_failures(mut self, username_max_failures: u32, client_ip_max_failures: u32) -> Self {
+        self.username_max_failures = username_max_failures;
+        self.client_ip_max_failures = client_ip_max_failures;
+        self
+    }
+
+    pub fn with_lock_duration(mut self, base_lock_duration: TimeDelta, max_lock_duration: TimeDelta) -> Self {
+        self.base_lock_duration = base_lock_duration;
+        self.max_lock_duration = max_lock_duration;
+        self
+    }
+
+    pub fn with_failure_window(mut self, failure_window: TimeDelta) -> Self {
+        self.failure_window = failure_window;
+        self
+    }
+
+    pub(crate) fn username_max_failures(&self) -> u32 {
+        self.username_max_failures
+    }
+
+    pub(crate) fn client_ip_max_failures(&self) -> u32 {
+        self.client_ip_max_failures
+    }
+
+    pub(crate) fn failure_window(&self) -> TimeDelta {
+        self.failure_window
+    }
+
+    /// Duration of the lock after `failures` failed logins, `None` while `max_failures` is not reached
+    pub(crate) fn lock_duration(&self, failures: u32, max_failures: u32) -> Option<TimeDelta> {
+        if failures < max_failures {
+            return None;
+        }
+
+        let exponent = (failures - max_failures).min(16);
+        let lock_duration = self.base_lock_duration.checked_mul(2i32.pow(exponent)).unwrap_or(self.max_lock_duration);
+
+        Some(lock_duration.min(self.max_lock_duration))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use crate::utils::login_throttling::LoginThrottling;
+
+    #[test]
+    fn test_lock_duration_is_exponential_and_capped() {
+        let login_throttling = LoginThrottling::default();
+
+        assert_eq!(login_throttling.lock_duration(4, 5), None);
+        assert_eq!(login_throttling.lock_duration(5, 5), Some(Duration::seconds(30)));
+        assert_eq!(login_throttling.lock_duration(6, 5), Some(Duration::seconds(60)));
+        assert_eq!(login_throttling.lock_duration(8, 5), Some(Duration::seconds(240)));
+        assert_eq!(login_throttling.lock_duration(100, 5), Some(Duration::minutes(15)));
+    }
+}
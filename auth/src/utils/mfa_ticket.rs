@@ -0,0 +1,74 @@
+use std::ops::Add;
+use chrono::{DateTime, Duration, TimeDelta, Utc};
+use pasetors::claims::{Claims, ClaimsValidationRules};
+use pasetors::token::UntrustedToken;
+use pasetors::version4::V4;
+use pasetors::Public;
+use crate::entities::error::AuthError;
+use crate::utils::auth_config::AuthConfig;
+
+/// Short-lived ticket given by `/login` when a second factor is required, exchanged on `/login/mfa`
+///
+/// It's signed with another implicit assertion than access tokens : it can't be used as an access token.
+/// Its identifier is saved with the TOTP of the user, so a ticket is exchanged once and a new one replaces it.
+pub(crate) struct MfaTicket;
+
+impl MfaTicket {
+    const MFA_TICKET_LIFETIME: TimeDelta = Duration::minutes(5);
+    const MFA_TICKET_SUBJECT: &'static str = "mfa";
+    const MFA_TICKET_IMPLICIT_ASSERTION: &'static [u8] = b"mfa ticket";
+
+    /// Return the ticket, its identifier and its expiration
+    pub(crate) fn generate(auth_config: &AuthConfig, username: &str) -> Result<(String, String, DateTime<Utc>), AuthError> {
+        let expiration = Utc::now().add(Self::MFA_TICKET_LIFETIME);
+        let ticket_identifier = uuid::Uuid::new_v4().to_string();
+        let mut claims = Claims::new().map_err(|_| AuthError::TokenCreation)?;
+        claims.token_identifier(&ticket_identifier).map_err(|_| AuthError::TokenCreation)?;
+        claims.subject(Self::MFA_TICKET_SUBJECT).map_err(|_| AuthError::TokenCreation)?;
+        claims.expiration(&expiration.to_rfc3339()).map_err(|_| AuthError::TokenCreation)?;
+        claims.add_additional("username", username.to_string()).map_err(|_| AuthError::TokenCreation)?;
+
+        let mfa_ticket = auth_config.key_ring().sign(&claims, Self::MFA_TICKET_IMPLICIT_ASSERTION).map_err(|_| AuthError::TokenCreation)?;
+
+        Ok((mfa_ticket, ticket_identifier, expiration))
+    }
+
+    /// Return the username and the identifier of a valid ticket
+    pub(crate) fn verify(auth_config: &AuthConfig, mfa_ticket: &str) -> Result<(String, String), AuthError> {
+        let mut validation_rules = ClaimsValidationRules::new();
+        validation_rules.validate_subject_with(Self::MFA_TICKET_SUBJECT);
+        let untrusted_token = UntrustedToken::<Public, V4>::try_from(mfa_ticket).map_err(|_| AuthError::InvalidToken)?;
+
+        let trusted_token = auth_config.key_ring().verify(&untrusted_token, &validation_rules, Self::MFA_TICKET_IMPLICIT_ASSERTION)
+            .map_err(|_| AuthError::InvalidToken)?;
+
+        let claims = trusted_token.payload_claims().ok_or(AuthError::InvalidToken)?;
+        let username = claims.get_claim("username").and_then(|username| username.as_str()).ok_or(AuthError::InvalidToken)?;
+        let ticket_identifier = claims.get_claim("jti").and_then(|jti| jti.as_str()).ok_or(AuthError::InvalidToken)?;
+
+        Ok((username.to_string(), ticket_identifier.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fake::{Fake, Faker};
+    use crate::entities::error::AuthError;
+    use crate::entities::{Token, UserCredentials};
+    use crate::utils::mfa_ticket::MfaTicket;
+    use crate::utils::auth_config::AuthConfig;
+    use crate::utils::validate_token::{IntoClaims, TokenString};
+
+    #[tokio::test]
+    async fn test_mfa_ticket_generate_and_verify() {
+        let auth_config = AuthConfig::fake();
+
+        let (mfa_ticket, ticket_identifier, _) = MfaTicket::generate(&auth_config, "username").unwrap();
+
+        assert_eq!(MfaTicket::verify(&auth_config, &mfa_ticket), Ok(("username".to_string(), ticket_identifier)));
+        // A MFA ticket is not an access token, and an access token is not a MFA ticket
+        assert!(TokenString(mfa_ticket).try_into_claims(&auth_config).await.is_err());
+        let (access_token, _, _) = Token::generate_tokens(&auth_config, &Faker.fake::<UserCredentials>()).await.unwrap();
+        assert_eq!(MfaTicket::verify(&auth_config, &access_token), Err(AuthError::InvalidToken));
+    }
+}
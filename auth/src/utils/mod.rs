@@ -1,4 +1,17 @@
 pub(crate) mod generate_token;
-pub mod settings;
+pub mod key_ring;
+pub mod auth_config;
+pub mod token_codec;
 pub(crate) mod validate_token;
-pub(crate) mod auth_claims;
\ No newline at end of file
+pub(crate) mod auth_claims;
+pub mod password_reset_sender;
+pub mod password_hashing;
+pub mod password_policy;
+pub mod login_throttling;
+pub mod totp;
+pub(crate) mod mfa_ticket;
+pub mod webauthn;
+pub mod oidc;
+pub mod authorization_server;
+pub mod access_rules;
+pub mod trusted_proxies;
@@ -0,0 +1,422 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use futures_util::future::BoxFuture;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use reqwest::Url;
+use serde::Deserialize;
+use crate::entities::error::AuthError;
+use crate::entities::OidcLoginState;
+
+/// Identity of a user authenticated by an upstream OpenID provider, read from a verified ID token
+#[derive(Debug, Clone, PartialEq)]
+pub struct OidcIdentity {
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+}
+
+/// Called once when the first login with an OpenID provider creates the local `UserCredentials`
+///
+/// It's object safe to let other modules create their own data (user profile, ...) without auth depending on them
+pub trait OidcUserProvisioning: Send + Sync {
+    fn provision_user<'a>(&'a self, username: &'a str, identity: &'a OidcIdentity) -> BoxFuture<'a, Result<(), AuthError>>;
+}
+
+/// Only the credentials are created, for deployments without user profiles
+#[derive(Default)]
+pub struct NoUserProvisioning;
+
+impl OidcUserProvisioning for NoUserProvisioning {
+    fn provision_user<'a>(&'a self, _username: &'a str, _identity: &'a OidcIdentity) -> BoxFuture<'a, Result<(), AuthError>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Endpoints of the provider, from its discovery document
+#[derive(Debug, Clone, Deserialize)]
+struct OidcProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct OidcTokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct OidcIdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    email_verified: Option<bool>,
+    preferred_username: Option<String>,
+    name: Option<String>,
+}
+
+/// Upstream OpenID Connect provider users can sign in with (authorization code flow with PKCE)
+///
+/// The discovery document and the keys of the provider are fetched at first use, keys are fetched again
+/// when an ID token is signed by an unknown key.
+pub struct OidcProvider {
+    name: String,
+    issuer: String,
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+    scopes: Vec<String>,
+    http_client: reqwest::Client,
+    metadata: Mutex<Option<OidcProviderMetadata>>,
+    jwks: Mutex<Option<JwkSet>>,
+}
+
+impl OidcProvider {
+    const DEFAULT_SCOPES: [&'static str; 3] = ["openid", "email", "profile"];
+    const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+    /// Asymmetric algorithms only, the keys of the provider are public
+    const ALLOWED_ALGORITHMS: [Algorithm; 9] = [
+        Algorithm::RS256, Algorithm::RS384, Algorithm::RS512,
+        Algorithm::PS256, Algorithm::PS384, Algorithm::PS512,
+        Algorithm::ES256, Algorithm::ES384, Algorithm::EdDSA,
+    ];
+
+    /// `name` is used in the login routes (`/oidc/{name}/...`), `redirect_uri` must be registered on the provider
+    pub fn new(name: &str, issuer: &str, client_id: &str, client_secret: &str, redirect_uri: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            issuer: issuer.trim_end_matches('/').to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            redirect_uri: redirect_uri.to_string(),
+            scopes: Self::DEFAULT_SCOPES.iter().map(|scope| scope.to_string()).collect(),
+            http_client: reqwest::Client::builder().timeout(Self::HTTP_TIMEOUT).build().expect("Unable build HTTP client"),
+            metadata: Mutex::new(None),
+            jwks: Mutex::new(None),
+        }
+    }
+
+    /// Replace the default scopes `openid email profile`
+    pub fn with_scopes(mut self, scopes: &[&str]) -> Self {
+        self.scopes = scopes.iter().map(|scope| scope.to_string()).collect();
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn get_metadata(&self) -> Result<OidcProviderMetadata, AuthError> {
+        if let Some(metadata) = self.metadata.lock().expect("OpenID metadata is poisoned").clone() {
+            return Ok(metadata);
+        }
+
+        let metadata: OidcProviderMetadata = self.http_client.get(format!("{}/.well-known/openid-configuration", self.issuer))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|_| AuthError::ServerError)?
+            .json()
+            .await
+            .map_err(|_| AuthError::ServerError)?;
+
+        if metadata.issuer.trim_end_matches('/') != self.issuer {
+            return Err(AuthError::ServerError);
+        }
+
+        *self.metadata.lock().expect("OpenID metadata is poisoned") = Some(metadata.clone());
+
+        Ok(metadata)
+    }
+
+    async fn get_jwks(&self, refresh: bool) -> Result<JwkSet, AuthError> {
+        if !refresh {
+            if let Some(jwks) = self.jwks.lock().expect("OpenID keys are poisoned").clone() {
+                return Ok(jwks);
+            }
+        }
+
+        let metadata = self.get_metadata().await?;
+        let jwks: JwkSet = self.http_client.get(metadata.jwks_uri)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|_| AuthError::ServerError)?
+            .json()
+            .await
+            .map_err(|_| AuthError::ServerError)?;
+
+        *self.jwks.lock().expect("OpenID keys are poisoned") = Some(jwks.clone());
+
+        Ok(jwks)
+    }
+
+    /// URL of the provider to redirect the user to
+    pub(crate) async fn authorization_url(&self, oidc_login_state: &OidcLoginState) -> Result<String, AuthError> {
+        let metadata = self.get_metadata().await?;
+        let authorization_url = Url::parse_with_params(&metadata.authorization_endpoint, &[
+            ("response_type", "code"),
+            ("client_id", &self.client_id),
+            ("redirect_uri", &self.redirect_uri),
+            ("scope", &self.scopes.join(" ")),
+            ("state", &oidc_login_state.state),
+            ("nonce", &oidc_login_state.nonce),
+            ("code_challenge", &oidc_login_state.code_challenge()),
+            ("code_challenge_method", "S256"),
+        ]).map_err(|_| AuthError::ServerError)?;
+
+        Ok(authorization_url.to_string())
+    }
+
+    /// Exchange the authorization code given to the callback, and verify the ID token returned
+    pub(crate) async fn authenticate(&self, code: &str, oidc_login_state: &OidcLoginState) -> Result<OidcIdentity, AuthError> {
+        let metadata = self.get_metadata().await?;
+        let response = self.http_client.post(metadata.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.redirect_uri),
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret),
+                ("code_verifier", &oidc_login_state.code_verifier),
+            ])
+            .send()
+            .await
+            .map_err(|_| AuthError::ServerError)?;
+
+        // The provider rejects invalid or replayed codes with a client error
+        if response.status().is_client_error() {
+            return Err(AuthError::InvalidToken);
+        }
+        let token_response: OidcTokenResponse = response.error_for_status()
+            .map_err(|_| AuthError::ServerError)?
+            .json()
+            .await
+            .map_err(|_| AuthError::ServerError)?;
+
+        self.verify_id_token(&token_response.id_token, &oidc_login_state.nonce).await
+    }
+
+    async fn verify_id_token(&self, id_token: &str, nonce: &str) -> Result<OidcIdentity, AuthError> {
+        let header = jsonwebtoken::decode_header(id_token).map_err(|_| AuthError::InvalidToken)?;
+        if !Self::ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(AuthError::InvalidToken);
+        }
+        let kid = header.kid.ok_or(AuthError::InvalidToken)?;
+
+        let jwk = match self.get_jwks(false).await?.find(&kid) {
+            Some(jwk) => jwk.clone(),
+            // Keys of the provider rotated since they were fetched
+            None => self.get_jwks(true).await?.find(&kid).cloned().ok_or(AuthError::InvalidToken)?,
+        };
+        let decoding_key = DecodingKey::from_jwk(&jwk).map_err(|_| AuthError::InvalidToken)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = jsonwebtoken::decode::<OidcIdTokenClaims>(id_token, &decoding_key, &validation)
+            .map_err(|_| AuthError::InvalidToken)?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(AuthError::InvalidToken);
+        }
+
+        Ok(OidcIdentity {
+            provider: self.name.clone(),
+            subject: claims.sub,
+            email: claims.email,
+            email_verified: claims.email_verified.unwrap_or(false),
+            preferred_username: claims.preferred_username,
+            name: claims.name,
+        })
+    }
+}
+
+/// Local OpenID provider answering the discovery, keys and token endpoints, to test logins without network
+#[cfg(test)]
+pub(crate) mod mock_identity_provider {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::routing::{get, post};
+    use axum::{Form, Json, Router};
+    use base64::Engine;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+    use p256::ecdsa::SigningKey;
+    use p256::pkcs8::{EncodePrivateKey, LineEnding};
+    use reqwest::Url;
+    use serde_json::{json, Value};
+    use sha2::{Digest, Sha256};
+
+    pub(crate) const CLIENT_ID: &str = "mock_client_id";
+    pub(crate) const CLIENT_SECRET: &str = "mock_client_secret";
+    const KID: &str = "mock_key";
+
+    struct AuthorizationCode {
+        code_challenge: String,
+        claims: Value,
+    }
+
+    struct MockIdentityProviderState {
+        issuer: String,
+        signing_key: SigningKey,
+        authorization_codes: Mutex<HashMap<String, AuthorizationCode>>,
+    }
+
+    pub(crate) struct MockIdentityProvider {
+        state: Arc<MockIdentityProviderState>,
+    }
+
+    impl MockIdentityProvider {
+        pub(crate) async fn start() -> Self {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("Unable bind mock identity provider");
+            let state = Arc::new(MockIdentityProviderState {
+                issuer: format!("http://{}", listener.local_addr().unwrap()),
+                signing_key: SigningKey::random(&mut rand::thread_rng()),
+                authorization_codes: Mutex::new(HashMap::new()),
+            });
+
+            let router = Router::new()
+                .route("/.well-known/openid-configuration", get(discovery))
+                .route("/jwks", get(jwks))
+                .route("/token", post(token))
+                .with_state(state.clone());
+            tokio::spawn(async move { axum::serve(listener, router).await });
+
+            Self { state }
+        }
+
+        pub(crate) fn issuer(&self) -> String {
+            self.state.issuer.clone()
+        }
+
+        /// Simulate a user signing in on the provider : return the code and state given to the callback
+        pub(crate) fn authorize(&self, authorization_url: &str, subject: &str, preferred_username: &str) -> (String, String) {
+            let authorization_url = Url::parse(authorization_url).unwrap();
+            let query: HashMap<String, String> = authorization_url.query_pairs().into_owned().collect();
+            let code = uuid::Uuid::new_v4().to_string();
+            let claims = json!({
+                "iss": self.state.issuer,
+                "aud": query["client_id"],
+                "sub": subject,
+                "exp": chrono::Utc::now().timestamp() + 300,
+                "iat": chrono::Utc::now().timestamp(),
+                "nonce": query["nonce"],
+                "email": format!("{}@example.com", preferred_username),
+                "email_verified": true,
+                "preferred_username": preferred_username,
+            });
+
+            self.state.authorization_codes.lock().unwrap().insert(code.clone(), AuthorizationCode { code_challenge: query["code_challenge"].clone(), claims });
+
+            (code, query["state"].clone())
+        }
+    }
+
+    async fn discovery(State(state): State<Arc<MockIdentityProviderState>>) -> Json<Value> {
+        Json(json!({
+            "issuer": state.issuer,
+            "authorization_endpoint": format!("{}/authorize", state.issuer),
+            "token_endpoint": format!("{}/token", state.issuer),
+            "jwks_uri": format!("{}/jwks", state.issuer),
+        }))
+    }
+
+    async fn jwks(State(state): State<Arc<MockIdentityProviderState>>) -> Json<Value> {
+        let public_key = state.signing_key.verifying_key().to_encoded_point(false);
+
+        Json(json!({
+            "keys": [{
+                "kty": "EC",
+                "crv": "P-256",
+                "use": "sig",
+                "alg": "ES256",
+                "kid": KID,
+                "x": URL_SAFE_NO_PAD.encode(public_key.x().unwrap()),
+                "y": URL_SAFE_NO_PAD.encode(public_key.y().unwrap()),
+            }]
+        }))
+    }
+
+    async fn token(State(state): State<Arc<MockIdentityProviderState>>, Form(form): Form<HashMap<String, String>>) -> Result<Json<Value>, StatusCode> {
+        if form.get("client_id").map(String::as_str) != Some(CLIENT_ID) || form.get("client_secret").map(String::as_str) != Some(CLIENT_SECRET) {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+
+        let authorization_code = state.authorization_codes.lock().unwrap().remove(form.get("code").ok_or(StatusCode::BAD_REQUEST)?).ok_or(StatusCode::BAD_REQUEST)?;
+        let code_verifier = form.get("code_verifier").ok_or(StatusCode::BAD_REQUEST)?;
+        if URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes())) != authorization_code.code_challenge {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(KID.to_string());
+        let private_key = state.signing_key.to_pkcs8_pem(LineEnding::LF).unwrap();
+        let id_token = jsonwebtoken::encode(&header, &authorization_code.claims, &EncodingKey::from_ec_pem(private_key.as_bytes()).unwrap()).unwrap();
+
+        Ok(Json(json!({ "access_token": "mock_access_token", "token_type": "Bearer", "id_token": id_token })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entities::error::AuthError;
+    use crate::entities::OidcLoginState;
+    use crate::utils::oidc::mock_identity_provider::{MockIdentityProvider, CLIENT_ID, CLIENT_SECRET};
+    use crate::utils::oidc::OidcProvider;
+
+    #[tokio::test]
+    async fn test_authenticate_with_mock_identity_provider() {
+        let mock_identity_provider = MockIdentityProvider::start().await;
+        let oidc_provider = OidcProvider::new("mock", &mock_identity_provider.issuer(), CLIENT_ID, CLIENT_SECRET, "http://localhost/callback");
+        let oidc_login_state = OidcLoginState::generate("mock");
+
+        let authorization_url = oidc_provider.authorization_url(&oidc_login_state).await.unwrap();
+        let (code, state) = mock_identity_provider.authorize(&authorization_url, "subject", "juliana");
+        let identity = oidc_provider.authenticate(&code, &oidc_login_state).await.unwrap();
+
+        assert_eq!(state, oidc_login_state.state);
+        assert_eq!(identity.provider, "mock");
+        assert_eq!(identity.subject, "subject");
+        assert_eq!(identity.preferred_username, Some("juliana".to_string()));
+        assert!(identity.email_verified);
+        // Authorization codes are usable once
+        assert_eq!(oidc_provider.authenticate(&code, &oidc_login_state).await, Err(AuthError::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn test_reject_id_token_with_other_nonce() {
+        let mock_identity_provider = MockIdentityProvider::start().await;
+        let oidc_provider = OidcProvider::new("mock", &mock_identity_provider.issuer(), CLIENT_ID, CLIENT_SECRET, "http://localhost/callback");
+        let oidc_login_state = OidcLoginState::generate("mock");
+
+        let authorization_url = oidc_provider.authorization_url(&oidc_login_state).await.unwrap();
+        let (code, _) = mock_identity_provider.authorize(&authorization_url, "subject", "juliana");
+        let other_login_state = OidcLoginState { code_verifier: oidc_login_state.code_verifier.clone(), ..OidcLoginState::generate("mock") };
+
+        assert_eq!(oidc_provider.authenticate(&code, &other_login_state).await, Err(AuthError::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn test_reject_wrong_code_verifier() {
+        let mock_identity_provider = MockIdentityProvider::start().await;
+        let oidc_provider = OidcProvider::new("mock", &mock_identity_provider.issuer(), CLIENT_ID, CLIENT_SECRET, "http://localhost/callback");
+        let oidc_login_state = OidcLoginState::generate("mock");
+
+        let authorization_url = oidc_provider.authorization_url(&oidc_login_state).await.unwrap();
+        let (code, _) = mock_identity_provider.authorize(&authorization_url, "subject", "juliana");
+        let other_login_state = OidcLoginState { nonce: oidc_login_state.nonce.clone(), ..OidcLoginState::generate("mock") };
+
+        assert_eq!(oidc_provider.authenticate(&code, &other_login_state).await, Err(AuthError::InvalidToken));
+    }
+}
@@ -0,0 +1,167 @@
+use argon2::{Argon2, Params, Version};
+use pbkdf2::password_hash::rand_core::OsRng;
+use pbkdf2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use pbkdf2::Pbkdf2;
+use crate::entities::error::AuthError;
+
+/// Algorithm used to hash new passwords
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum PasswordHashAlgorithm {
+    #[default]
+    Argon2id,
+    /// Legacy algorithm, kept to verify the passwords hashed before Argon2id
+    Pbkdf2Sha256,
+}
+
+/// Hashing strategy of the passwords
+///
+/// New passwords are hashed with `algorithm`, but every supported PHC string can be verified :
+/// `$argon2id$`, `$argon2i$`, `$argon2d$`, `$pbkdf2-sha256$` and `$pbkdf2-sha512$`.
+///
+/// The optional pepper is a server-side secret given to Argon2 as its secret key.
+/// It's never stored with the hashes, so changing it invalidate every Argon2 password.
+#[derive(Clone, Debug)]
+pub struct PasswordHashing {
+    algorithm: PasswordHashAlgorithm,
+    argon2_params: Params,
+    pbkdf2_params: pbkdf2::Params,
+    pepper: Option<Vec<u8>>,
+}
+
+impl Default for PasswordHashing {
+    /// Argon2id with the OWASP minimal parameters (19 MiB, 2 iterations, 1 degree of parallelism)
+    fn default() -> Self {
+        Self::new(PasswordHashAlgorithm::Argon2id)
+    }
+}
+
+impl PasswordHashing {
+    const ARGON2_DEFAULT_MEMORY_COST_KIB: u32 = 19 * 1024;
+    const ARGON2_DEFAULT_TIME_COST: u32 = 2;
+    const ARGON2_DEFAULT_PARALLELISM: u32 = 1;
+
+    pub fn new(algorithm: PasswordHashAlgorithm) -> Self {
+        Self {
+            algorithm,
+            argon2_params: Params::new(Self::ARGON2_DEFAULT_MEMORY_COST_KIB, Self::ARGON2_DEFAULT_TIME_COST, Self::ARGON2_DEFAULT_PARALLELISM, None)
+                .expect("Default Argon2 parameters are valid"),
+            pbkdf2_params: pbkdf2::Params::default(),
+            pepper: None,
+        }
+    }
+
+    pub fn with_argon2_params(mut self, memory_cost_kib: u32, time_cost: u32, parallelism: u32) -> Self {
+        self.argon2_params = Params::new(memory_cost_kib, time_cost, parallelism, None).expect("Cannot create Argon2 parameters from values given");
+        self
+    }
+
+    pub fn with_pbkdf2_rounds(mut self, rounds: u32) -> Self {
+        self.pbkdf2_params.rounds = rounds;
+        self
+    }
+
+    pub fn with_pepper(mut self, pepper: &[u8]) -> Self {
+        self.pepper = Some(pepper.to_vec());
+        self
+    }
+
+    fn argon2(&self, algorithm: argon2::Algorithm, params: Params) -> Result<Argon2<'_>, AuthError> {
+        match &self.pepper {
+            Some(pepper) => Argon2::new_with_secret(pepper, algorithm, Version::V0x13, params).map_err(|_| AuthError::ServerError),
+            None => Ok(Argon2::new(algorithm, Version::V0x13, params)),
+        }
+    }
+
+    /// Hash the password to a PHC string with the configured algorithm
+    pub fn hash_password(&self, password: &str) -> Result<String, AuthError> {
+        let salt = SaltString::generate(&mut OsRng);
+
+        let password_hash = match self.algorithm {
+            PasswordHashAlgorithm::Argon2id => self.argon2(argon2::Algorithm::Argon2id, self.argon2_params.clone())?
+                .hash_password(password.as_bytes(), &salt),
+            PasswordHashAlgorithm::Pbkdf2Sha256 => Pbkdf2.hash_password_customized(password.as_bytes(), None, None, self.pbkdf2_params, &salt),
+        };
+
+        password_hash.map(|password_hash| password_hash.to_string()).map_err(|_| AuthError::ServerError)
+    }
+
+    /// Verify the password against any supported PHC string
+    pub fn verify_password(&self, password: &str, password_hash: &str) -> Result<(), AuthError> {
+        let password_hash = PasswordHash::new(password_hash).map_err(|_| AuthError::WrongCredentials)?;
+
+        let verified = match password_hash.algorithm.as_str() {
+            "argon2id" | "argon2i" | "argon2d" => {
+                let algorithm = argon2::Algorithm::try_from(password_hash.algorithm).map_err(|_| AuthError::WrongCredentials)?;
+                let params = Params::try_from(&password_hash).map_err(|_| AuthError::WrongCredentials)?;
+                self.argon2(algorithm, params)?.verify_password(password.as_bytes(), &password_hash)
+            }
+            "pbkdf2-sha256" | "pbkdf2-sha512" => Pbkdf2.verify_password(password.as_bytes(), &password_hash),
+            _ => return Err(AuthError::WrongCredentials),
+        };
+
+        verified.map_err(|_| AuthError::WrongCredentials)
+    }
+
+    /// Check if the hash was made with another algorithm or weaker parameters than configured
+    pub fn needs_rehash(&self, password_hash: &str) -> bool {
+        let Ok(password_hash) = PasswordHash::new(password_hash) else {
+            return true;
+        };
+
+        match self.algorithm {
+            PasswordHashAlgorithm::Argon2id => password_hash.algorithm.as_str() != "argon2id" || Params::try_from(&password_hash)
+                .map(|params| params.m_cost() < self.argon2_params.m_cost() || params.t_cost() < self.argon2_params.t_cost() || params.p_cost() < self.argon2_params.p_cost())
+                .unwrap_or(true),
+            PasswordHashAlgorithm::Pbkdf2Sha256 => password_hash.algorithm.as_str() != "pbkdf2-sha256" || pbkdf2::Params::try_from(&password_hash)
+                .map(|params| params.rounds < self.pbkdf2_params.rounds)
+                .unwrap_or(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::password_hashing::{PasswordHashAlgorithm, PasswordHashing};
+
+    const PASSWORD: &str = "my_secret_password";
+
+    #[test]
+    fn test_argon2id_hash_and_verify() {
+        let password_hashing = PasswordHashing::default();
+        let password_hash = password_hashing.hash_password(PASSWORD).unwrap();
+
+        assert!(password_hash.starts_with("$argon2id$v=19$m=19456,t=2,p=1$"));
+        assert!(password_hashing.verify_password(PASSWORD, &password_hash).is_ok());
+        assert!(password_hashing.verify_password("wrong_password", &password_hash).is_err());
+        assert!(!password_hashing.needs_rehash(&password_hash));
+    }
+
+    #[test]
+    fn test_verify_legacy_pbkdf2_and_needs_rehash() {
+        let legacy_password_hash = PasswordHashing::new(PasswordHashAlgorithm::Pbkdf2Sha256).hash_password(PASSWORD).unwrap();
+        let password_hashing = PasswordHashing::default();
+
+        assert!(legacy_password_hash.starts_with("$pbkdf2-sha256$"));
+        assert!(password_hashing.verify_password(PASSWORD, &legacy_password_hash).is_ok());
+        assert!(password_hashing.needs_rehash(&legacy_password_hash));
+    }
+
+    #[test]
+    fn test_pepper_is_required_to_verify() {
+        let password_hashing = PasswordHashing::default().with_argon2_params(8 * 1024, 1, 1).with_pepper(b"server_side_pepper");
+        let password_hash = password_hashing.hash_password(PASSWORD).unwrap();
+
+        assert!(password_hashing.verify_password(PASSWORD, &password_hash).is_ok());
+        assert!(PasswordHashing::default().verify_password(PASSWORD, &password_hash).is_err());
+        assert!(PasswordHashing::default().with_pepper(b"other_pepper").verify_password(PASSWORD, &password_hash).is_err());
+    }
+
+    #[test]
+    fn test_needs_rehash_with_stronger_params() {
+        let password_hash = PasswordHashing::default().with_argon2_params(8 * 1024, 1, 1).hash_password(PASSWORD).unwrap();
+
+        assert!(PasswordHashing::default().needs_rehash(&password_hash));
+        assert!(!PasswordHashing::default().with_argon2_params(8 * 1024, 1, 1).needs_rehash(&password_hash));
+        assert!(PasswordHashing::default().needs_rehash("not a PHC string"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde::Serialize;
+use thiserror::Error;
+
+/// Rule of the `PasswordPolicy` broken by a password
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum PasswordPolicyViolation {
+    TooShort { min_length: usize },
+    TooLong { max_length: usize },
+    MissingLowercase,
+    MissingUppercase,
+    MissingDigit,
+    MissingSymbol,
+    SimilarToUsername,
+    DeniedPassword,
+}
+
+#[derive(Error, Serialize, Clone, Debug, PartialEq)]
+#[error("Password does not respect the password policy")]
+pub struct PasswordPolicyError {
+    pub violations: Vec<PasswordPolicyViolation>,
+}
+
+/// Rules checked on every new password, before hashing
+///
+/// By default only the length (8 to 128 characters) and the similarity with the username are checked.
+/// Character classes and the deny-list of breached or common passwords are opt-in.
+#[derive(Clone, Debug)]
+pub struct PasswordPolicy {
+    min_length: usize,
+    max_length: usize,
+    require_lowercase: bool,
+    require_uppercase: bool,
+    require_digit: bool,
+    require_symbol: bool,
+    reject_similar_to_username: bool,
+    denied_passwords: HashSet<String>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            max_length: 128,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            reject_similar_to_username: true,
+            denied_passwords: HashSet::new(),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    const MIN_USERNAME_LENGTH_FOR_SIMILARITY: usize = 3;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_length(mut self, min_length: usize, max_length: usize) -> Self {
+        self.min_length = min_length;
+        self.max_length = max_length;
+        self
+    }
+
+    pub fn with_character_classes(mut self, lowercase: bool, uppercase: bool, digit: bool, symbol: bool) -> Self {
+        self.require_lowercase = lowercase;
+        self.require_uppercase = uppercase;
+        self.require_digit = digit;
+        self.require_symbol = symbol;
+        self
+    }
+
+    pub fn with_reject_similar_to_username(mut self, reject_similar_to_username: bool) -> Self {
+        self.reject_similar_to_username = reject_similar_to_username;
+        self
+    }
+
+    /// Deny the passwords given, compared without case
+    pub fn with_denied_passwords<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, denied_passwords: I) -> Self {
+        self.denied_passwords.extend(
+            denied_passwords.into_iter()
+                .map(|denied_password| denied_password.as_ref().trim().to_lowercase())
+                .filter(|denied_password| !denied_password.is_empty())
+        );
+        self
+    }
+
+    /// Load a deny-list file with one password by line
+    pub fn with_deny_list_file(self, path: impl AsRef<Path>) -> io::Result<Self> {
+        let deny_list = fs::read_to_string(path)?;
+
+        Ok(self.with_denied_passwords(deny_list.lines()))
+    }
+
+    fn is_similar_to_username(password: &str, username: &str) -> bool {
+        let password = password.to_lowercase();
+        let username = username.to_lowercase();
+
+        if username.chars().count() < Self::MIN_USERNAME_LENGTH_FOR_SIMILARITY {
+            return password == username;
+        }
+
+        let reversed_username: String = username.chars().rev().collect();
+        password.contains(&username) || password.contains(&reversed_username) || username.contains(&password)
+    }
+
+    /// Check every rule, and return all the violations found
+    pub fn validate(&self, username: &str, password: &str) -> Result<(), PasswordPolicyError> {
+        let length = password.chars().count();
+        let mut violations = Vec::new();
+
+        if length < self.min_length {
+            violations.push(PasswordPolicyViolation::TooShort { min_length: self.min_length });
+        }
+        if length > self.max_length {
+            violations.push(PasswordPolicyViolation::TooLong { max_length: self.max_length });
+        }
+        if self.require_lowercase && !password.chars().any(char::is_lowercase) {
+            violations.push(PasswordPolicyViolation::MissingLowercase);
+        }
+        if self.require_uppercase && !password.chars().any(char::is_uppercase) {
+            violations.push(PasswordPolicyViolation::MissingUppercase);
+        }
+        if self.require_digit && !password.chars().any(|character| character.is_ascii_digit()) {
+            violations.push(PasswordPolicyViolation::MissingDigit);
+        }
+        if self.require_symbol && password.chars().all(char::is_alphanumeric) {
+            violations.push(PasswordPolicyViolation::MissingSymbol);
+        }
+        if self.reject_similar_to_username && Self::is_similar_to_username(password, username) {
+            violations.push(PasswordPolicyViolation::SimilarToUsername);
+        }
+        if self.denied_passwords.contains(&password.to_lowercase()) {
+            violations.push(PasswordPolicyViolation::DeniedPassword);
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(PasswordPolicyError { violations })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::utils::password_policy::{PasswordPolicy, PasswordPolicyViolation};
+
+    #[test]
+    fn test_default_policy() {
+        let password_policy = PasswordPolicy::default();
+
+        assert!(password_policy.validate("username", "correct horse battery staple").is_ok());
+        assert_eq!(password_policy.validate("username", "short").unwrap_err().violations, vec![PasswordPolicyViolation::TooShort { min_length: 8 }]);
+        assert_eq!(password_policy.validate("username", &"a".repeat(129)).unwrap_err().violations, vec![PasswordPolicyViolation::TooLong { max_length: 128 }]);
+    }
+
+    #[test]
+    fn test_character_classes_return_every_violation() {
+        let password_policy = PasswordPolicy::new().with_character_classes(true, true, true, true);
+
+        assert!(password_policy.validate("username", "Secr3t-password").is_ok());
+        assert_eq!(password_policy.validate("username", "secretpassword").unwrap_err().violations, vec![
+            PasswordPolicyViolation::MissingUppercase,
+            PasswordPolicyViolation::MissingDigit,
+            PasswordPolicyViolation::MissingSymbol,
+        ]);
+    }
+
+    #[test]
+    fn test_similar_to_username() {
+        let password_policy = PasswordPolicy::default();
+
+        assert_eq!(password_policy.validate("JohnDoe", "johndoe2024").unwrap_err().violations, vec![PasswordPolicyViolation::SimilarToUsername]);
+        assert_eq!(password_policy.validate("JohnDoe", "eodnhoj!!").unwrap_err().violations, vec![PasswordPolicyViolation::SimilarToUsername]);
+        assert!(PasswordPolicy::new().with_reject_similar_to_username(false).validate("JohnDoe", "johndoe2024").is_ok());
+    }
+
+    #[test]
+    fn test_deny_list_file() {
+        let file_path = std::env::temp_dir().join(format!("deny_list_{}.txt", uuid::Uuid::new_v4()));
+        fs::write(&file_path, "123456\nPassword1\n\n").unwrap();
+
+        let password_policy = PasswordPolicy::new().with_length(6, 128).with_deny_list_file(&file_path).expect("Unable load deny-list file");
+        fs::remove_file(&file_path).unwrap();
+
+        assert_eq!(password_policy.validate("username", "password1").unwrap_err().violations, vec![PasswordPolicyViolation::DeniedPassword]);
+        assert_eq!(password_policy.validate("username", "123456").unwrap_err().violations, vec![PasswordPolicyViolation::DeniedPassword]);
+        assert!(password_policy.validate("username", "1234567").is_ok());
+    }
+}
@@ -0,0 +1,70 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use futures_util::future::BoxFuture;
+use crate::entities::error::AuthError;
+
+/// Deliver reset tokens to the users who requested a password reset
+///
+/// It's object safe to plug any delivery (mail, sms, ...) in `AuthRouterBuilder`
+pub trait PasswordResetSender: Send + Sync {
+    fn send_reset_token<'a>(&'a self, username: &'a str, reset_token: &'a str) -> BoxFuture<'a, Result<(), AuthError>>;
+}
+
+/// Write reset tokens in the logs, or append them to a file when a path is given
+///
+/// It's designed for local development only : anyone reading the logs can reset passwords
+#[derive(Default)]
+pub struct LogPasswordResetSender {
+    file_path: Option<PathBuf>,
+}
+
+impl LogPasswordResetSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file(file_path: impl Into<PathBuf>) -> Self {
+        Self {
+            file_path: Some(file_path.into()),
+        }
+    }
+}
+
+impl PasswordResetSender for LogPasswordResetSender {
+    fn send_reset_token<'a>(&'a self, username: &'a str, reset_token: &'a str) -> BoxFuture<'a, Result<(), AuthError>> {
+        Box::pin(async move {
+            let message = format!("Password reset token for {} : {}", username, reset_token);
+
+            match &self.file_path {
+                Some(file_path) => {
+                    let mut file = OpenOptions::new().create(true).append(true).open(file_path).map_err(|_| AuthError::ServerError)?;
+                    writeln!(file, "{}", message).map_err(|_| AuthError::ServerError)
+                }
+                None => {
+                    println!("{}", message);
+                    Ok(())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::utils::password_reset_sender::{LogPasswordResetSender, PasswordResetSender};
+
+    #[tokio::test]
+    async fn test_log_sender_append_to_file() {
+        let file_path = std::env::temp_dir().join(format!("password_reset_{}.log", uuid::Uuid::new_v4()));
+        let sender = LogPasswordResetSender::with_file(&file_path);
+
+        sender.send_reset_token("username", "first_token").await.expect("Unable write reset token");
+        sender.send_reset_token("username", "second_token").await.expect("Unable write reset token");
+
+        let content = fs::read_to_string(&file_path).unwrap();
+        fs::remove_file(&file_path).unwrap();
+        assert_eq!(content.lines().collect::<Vec<_>>(), vec!["Password reset token for username : first_token", "Password reset token for username : second_token"]);
+    }
+}
@@ -0,0 +1,288 @@
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use jsonwebtoken::{Algorithm, Validation};
+use mongodb::bson;
+use pasetors::claims::Claims;
+use pasetors::footer::Footer;
+use pasetors::keys::SymmetricKey;
+use pasetors::paserk::{FormatAsPaserk, Id};
+use pasetors::token::UntrustedToken;
+use pasetors::version4::V4;
+use pasetors::{local, Local, Public};
+use serde_json::{Map, Value};
+use thiserror::Error;
+use crate::datastore::TokenDatastore;
+use crate::entities::error::AuthError;
+use crate::entities::ReferenceToken;
+use crate::utils::auth_config::AuthConfig;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TokenCodecError {
+    #[error("Key is not a valid 32 bytes symmetric key")]
+    InvalidKey,
+}
+
+/// Format of the access, refresh, service and delegated tokens, configured with `AuthConfig::with_token_codec`
+///
+/// Every codec decodes tokens into the same `Claims`, so `AuthClaims` are parsed the same whichever is configured.
+/// Changing the codec invalidates the tokens issued before.
+pub trait TokenCodec: Send + Sync {
+    /// Errors with `AuthError::TokenCreation`
+    fn encode<'a>(&'a self, auth_config: &'a AuthConfig, claims: &'a Claims) -> BoxFuture<'a, Result<String, AuthError>>;
+    /// Errors with `AuthError::InvalidToken` when the token is malformed, `AuthError::WrongCredentials` when it is not trusted or expired
+    fn decode<'a>(&'a self, auth_config: &'a AuthConfig, token: &'a str) -> BoxFuture<'a, Result<Claims, AuthError>>;
+}
+
+/// PASETO v4.public tokens signed with the key ring, the default
+///
+/// Claims are readable by clients, and verifiable by other services with the keys of `/.well-known/paserk.json`
+#[derive(Clone, Default)]
+pub struct PasetoPublicCodec;
+
+impl TokenCodec for PasetoPublicCodec {
+    fn encode<'a>(&'a self, auth_config: &'a AuthConfig, claims: &'a Claims) -> BoxFuture<'a, Result<String, AuthError>> {
+        Box::pin(async move {
+            auth_config.key_ring().sign(claims, auth_config.implicit_assertion()).map_err(|_| AuthError::TokenCreation)
+        })
+    }
+
+    fn decode<'a>(&'a self, auth_config: &'a AuthConfig, token: &'a str) -> BoxFuture<'a, Result<Claims, AuthError>> {
+        Box::pin(async move {
+            let untrusted_token = UntrustedToken::<Public, V4>::try_from(token).map_err(|_| AuthError::InvalidToken)?;
+            let trusted_token = auth_config.key_ring().verify(&untrusted_token, &auth_config.validation_rules(), auth_config.implicit_assertion())
+                .map_err(|_| AuthError::WrongCredentials)?;
+
+            trusted_token.payload_claims().cloned().ok_or(AuthError::WrongCredentials)
+        })
+    }
+}
+
+/// PASETO v4.local tokens encrypted with a symmetric key : clients can't read the claims, like their role
+///
+/// Only the services holding the key can decrypt the tokens
+#[derive(Clone)]
+pub struct PasetoLocalCodec {
+    key: SymmetricKey<V4>,
+    key_id: String,
+}
+
+impl PasetoLocalCodec {
+    /// `key` is 32 random bytes
+    pub fn new(key: &[u8]) -> Result<Self, TokenCodecError> {
+        let key = SymmetricKey::<V4>::from(key).map_err(|_| TokenCodecError::InvalidKey)?;
+        let mut key_id = String::new();
+        Id::from(&key).fmt(&mut key_id).expect("Cannot format key id as PASERK");
+
+        Ok(Self { key, key_id })
+    }
+}
+
+impl TokenCodec for PasetoLocalCodec {
+    fn encode<'a>(&'a self, auth_config: &'a AuthConfig, claims: &'a Claims) -> BoxFuture<'a, Result<String, AuthError>> {
+        Box::pin(async move {
+            let mut footer = Footer::new();
+            footer.key_id(&Id::try_from(self.key_id.as_str()).map_err(|_| AuthError::TokenCreation)?);
+
+            local::encrypt(&self.key, claims, Some(&footer), Some(auth_config.implicit_assertion())).map_err(|_| AuthError::TokenCreation)
+        })
+    }
+
+    fn decode<'a>(&'a self, auth_config: &'a AuthConfig, token: &'a str) -> BoxFuture<'a, Result<Claims, AuthError>> {
+        Box::pin(async move {
+            let untrusted_token = UntrustedToken::<Local, V4>::try_from(token).map_err(|_| AuthError::InvalidToken)?;
+            let trusted_token = local::decrypt(&self.key, &untrusted_token, &auth_config.validation_rules(), None, Some(auth_config.implicit_assertion()))
+                .map_err(|_| AuthError::WrongCredentials)?;
+
+            trusted_token.payload_claims().cloned().ok_or(AuthError::WrongCredentials)
+        })
+    }
+}
+
+/// EdDSA JWTs signed with the key ring, for gateways only supporting JWT
+///
+/// Verifiable with the keys of `/.well-known/jwks.json`. `exp`, `iat` and `nbf` are seconds since the epoch,
+/// and the implicit assertion is not part of the signature
+#[derive(Clone, Default)]
+pub struct JwtCodec;
+
+impl JwtCodec {
+    const DATE_CLAIMS: [&'static str; 3] = ["exp", "iat", "nbf"];
+}
+
+impl TokenCodec for JwtCodec {
+    fn encode<'a>(&'a self, auth_config: &'a AuthConfig, claims: &'a Claims) -> BoxFuture<'a, Result<String, AuthError>> {
+        Box::pin(async move {
+            let mut jwt_claims: Map<String, Value> = serde_json::from_str(&claims.to_string().map_err(|_| AuthError::TokenCreation)?)
+                .map_err(|_| AuthError::TokenCreation)?;
+            for date_claim in Self::DATE_CLAIMS {
+                if let Some(Value::String(date)) = jwt_claims.get(date_claim) {
+                    let timestamp = DateTime::parse_from_rfc3339(date).map_err(|_| AuthError::TokenCreation)?.timestamp();
+                    jwt_claims.insert(date_claim.to_string(), Value::from(timestamp));
+                }
+            }
+
+            auth_config.key_ring().sign_jwt(&jwt_claims).map_err(|_| AuthError::TokenCreation)
+        })
+    }
+
+    fn decode<'a>(&'a self, auth_config: &'a AuthConfig, token: &'a str) -> BoxFuture<'a, Result<Claims, AuthError>> {
+        Box::pin(async move {
+            jsonwebtoken::decode_header(token).map_err(|_| AuthError::InvalidToken)?;
+            // Registered claims are checked by the validation rules, as for PASETO tokens
+            let mut validation = Validation::new(Algorithm::EdDSA);
+            validation.validate_exp = false;
+            validation.validate_aud = false;
+            validation.required_spec_claims.clear();
+
+            let mut jwt_claims: Map<String, Value> = auth_config.key_ring().verify_jwt(token, &validation).map_err(|_| AuthError::WrongCredentials)?;
+            for date_claim in Self::DATE_CLAIMS {
+                if let Some(timestamp) = jwt_claims.get(date_claim).and_then(Value::as_i64) {
+                    let date = DateTime::<Utc>::from_timestamp(timestamp, 0).ok_or(AuthError::WrongCredentials)?;
+                    jwt_claims.insert(date_claim.to_string(), Value::from(date.to_rfc3339()));
+                }
+            }
+
+            let claims = Claims::from_string(&Value::Object(jwt_claims).to_string()).map_err(|_| AuthError::WrongCredentials)?;
+            auth_config.validation_rules().validate_claims(&claims).map_err(|_| AuthError::WrongCredentials)?;
+
+            Ok(claims)
+        })
+    }
+}
+
+/// Opaque tokens (`ref_...`), their claims stored through the `TokenDatastore`
+///
+/// Nothing can be read from the token itself, and a token is resolved with a datastore lookup on every request
+#[derive(Clone)]
+pub struct ReferenceTokenCodec<TokenDatastoreImpl: TokenDatastore + Send + Sync> {
+    token_datastore: TokenDatastoreImpl,
+}
+
+impl<TokenDatastoreImpl: TokenDatastore + Send + Sync> ReferenceTokenCodec<TokenDatastoreImpl> {
+    pub fn new(token_datastore: TokenDatastoreImpl) -> Self {
+        Self { token_datastore }
+    }
+}
+
+impl<TokenDatastoreImpl: TokenDatastore + Send + Sync> TokenCodec for ReferenceTokenCodec<TokenDatastoreImpl> {
+    fn encode<'a>(&'a self, _auth_config: &'a AuthConfig, claims: &'a Claims) -> BoxFuture<'a, Result<String, AuthError>> {
+        Box::pin(async move {
+            let expiration = claims.get_claim("exp").and_then(Value::as_str).ok_or(AuthError::TokenCreation)?;
+            let expired_at = bson::DateTime::parse_rfc3339_str(expiration).map_err(|_| AuthError::TokenCreation)?;
+            let (reference_token, token) = ReferenceToken::generate(claims.to_string().map_err(|_| AuthError::TokenCreation)?, expired_at);
+
+            self.token_datastore.add_reference_token(reference_token).await.map_err(|_| AuthError::TokenCreation)?;
+
+            Ok(token)
+        })
+    }
+
+    fn decode<'a>(&'a self, auth_config: &'a AuthConfig, token: &'a str) -> BoxFuture<'a, Result<Claims, AuthError>> {
+        Box::pin(async move {
+            if !token.starts_with(ReferenceToken::TOKEN_PREFIX) {
+                return Err(AuthError::InvalidToken);
+            }
+
+            let reference_token = self.token_datastore.get_reference_token(&ReferenceToken::hash_token(token)).await
+                .map_err(|_| AuthError::ServerError)?
+                .filter(ReferenceToken::is_valid)
+                .ok_or(AuthError::WrongCredentials)?;
+            let claims = Claims::from_string(&reference_token.claims).map_err(|_| AuthError::WrongCredentials)?;
+            auth_config.validation_rules().validate_claims(&claims).map_err(|_| AuthError::WrongCredentials)?;
+
+            Ok(claims)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use fake::{Fake, Faker};
+    use futures::future;
+    use jsonwebtoken::DecodingKey;
+    use mongodb::bson::oid::ObjectId;
+    use crate::datastore::MockTokenDatastore;
+    use crate::entities::{Token, UserCredentials};
+    use crate::utils::auth_claims::AuthClaims;
+    use crate::utils::validate_token::{IntoClaims, TokenString};
+    use super::*;
+
+    const FAKE_LOCAL_KEY: [u8; 32] = [7; 32];
+
+    async fn assert_same_auth_claims(auth_config: &AuthConfig) -> String {
+        let user: UserCredentials = Faker.fake();
+        let (access_token, refresh_token, token) = Token::generate_tokens(auth_config, &user).await.expect("Unable generate tokens");
+
+        let access_claims = AuthClaims::try_from(&TokenString(access_token.clone()).try_into_claims(auth_config).await.expect("Access token should be valid")).unwrap();
+        let refresh_claims = AuthClaims::try_from(&TokenString(refresh_token).try_into_claims(auth_config).await.expect("Refresh token should be valid")).unwrap();
+
+        assert_eq!(access_claims.username, user.username);
+        assert_eq!(access_claims.roles, user.roles);
+        assert_eq!(access_claims.token_identifier, token.token_access_identifiers);
+        assert_eq!(refresh_claims.username, user.username);
+        assert_eq!(refresh_claims.token_identifier, token.token_refresh_identifiers);
+
+        access_token
+    }
+
+    #[tokio::test]
+    async fn test_paseto_public_codec() {
+        let access_token = assert_same_auth_claims(&AuthConfig::fake()).await;
+
+        assert!(access_token.starts_with("v4.public."));
+    }
+
+    #[tokio::test]
+    async fn test_paseto_local_codec_hides_claims() {
+        let auth_config = AuthConfig::fake().with_token_codec(Arc::new(PasetoLocalCodec::new(&FAKE_LOCAL_KEY).unwrap()));
+        let other_auth_config = AuthConfig::fake().with_token_codec(Arc::new(PasetoLocalCodec::new(&[8; 32]).unwrap()));
+
+        let access_token = assert_same_auth_claims(&auth_config).await;
+
+        assert!(access_token.starts_with("v4.local."));
+        assert_eq!(TokenString(access_token.clone()).try_into_claims(&AuthConfig::fake()).await, Err(AuthError::InvalidToken));
+        assert_eq!(TokenString(access_token).try_into_claims(&other_auth_config).await, Err(AuthError::WrongCredentials));
+        assert_eq!(PasetoLocalCodec::new(&[7; 16]).err(), Some(TokenCodecError::InvalidKey));
+    }
+
+    #[tokio::test]
+    async fn test_jwt_codec_verified_with_json_web_key_set() {
+        let auth_config = AuthConfig::fake().with_token_codec(Arc::new(JwtCodec)).with_issuer("https://auth.example.com");
+
+        let access_token = assert_same_auth_claims(&auth_config).await;
+
+        let kid = jsonwebtoken::decode_header(&access_token).unwrap().kid.unwrap();
+        let json_web_key_set = auth_config.key_ring().json_web_key_set();
+        let mut validation = Validation::new(Algorithm::EdDSA);
+        validation.set_issuer(&["https://auth.example.com"]);
+        let jwt_claims = jsonwebtoken::decode::<Map<String, Value>>(&access_token, &DecodingKey::from_jwk(json_web_key_set.find(&kid).unwrap()).unwrap(), &validation).unwrap().claims;
+
+        assert!(jwt_claims.get("exp").unwrap().is_i64());
+        assert_eq!(jwt_claims.get("sub").unwrap().as_str(), Some("access"));
+        assert_eq!(TokenString(access_token).try_into_claims(&AuthConfig::fake().with_token_codec(Arc::new(JwtCodec)).with_issuer("https://auth.staging.example.com")).await, Err(AuthError::WrongCredentials));
+    }
+
+    #[tokio::test]
+    async fn test_reference_token_codec_resolved_with_token_datastore() {
+        let reference_tokens: Arc<Mutex<Vec<ReferenceToken>>> = Arc::new(Mutex::new(Vec::new()));
+        let added_reference_tokens = reference_tokens.clone();
+        let mut mock_token_datastore = MockTokenDatastore::new();
+        mock_token_datastore.expect_add_reference_token().times(2).returning(move |reference_token| {
+            let reference_token = ReferenceToken { id: Some(ObjectId::new()), ..reference_token };
+            added_reference_tokens.lock().unwrap().push(reference_token.clone());
+            Box::pin(future::ready(Ok(reference_token)))
+        });
+        mock_token_datastore.expect_get_reference_token().returning(move |token_hash| {
+            let reference_token = reference_tokens.lock().unwrap().iter().find(|reference_token| reference_token.token_hash == token_hash).cloned();
+            Box::pin(future::ready(Ok(reference_token)))
+        });
+        let auth_config = AuthConfig::fake().with_token_codec(Arc::new(ReferenceTokenCodec::new(mock_token_datastore)));
+
+        let access_token = assert_same_auth_claims(&auth_config).await;
+
+        assert!(access_token.starts_with(ReferenceToken::TOKEN_PREFIX));
+        assert_eq!(TokenString("ref_unknown".to_string()).try_into_claims(&auth_config).await, Err(AuthError::WrongCredentials));
+        assert_eq!(TokenString("v4.public.token".to_string()).try_into_claims(&auth_config).await, Err(AuthError::InvalidToken));
+    }
+}
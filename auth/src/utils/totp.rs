@@ -0,0 +1,96 @@
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use totp_rs::{Algorithm, Secret, TOTP};
+use mongodb::bson::DateTime;
+use crate::entities::error::AuthError;
+use crate::entities::TotpCredentials;
+
+impl TotpCredentials {
+    const DIGITS: usize = 6;
+    const STEP_SECONDS: u64 = 30;
+    /// Codes of the previous and next time steps are also accepted, for clock drift
+    const SKEW_STEPS: i64 = 1;
+    const RECOVERY_CODES_COUNT: usize = 10;
+    const RECOVERY_CODE_ALPHABET: &'static [u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+    /// Create an inactive TOTP for the user, returned with its clear recovery codes
+    pub(crate) fn generate(username: &str) -> (Self, Vec<String>) {
+        let recovery_codes: Vec<String> = (0..Self::RECOVERY_CODES_COUNT).map(|_| Self::generate_recovery_code()).collect();
+
+        let totp_credentials = Self {
+            id: None,
+            username: username.to_string(),
+            secret: Secret::generate_secret().to_encoded().to_string(),
+            enabled: false,
+            recovery_code_hashes: recovery_codes.iter().map(|recovery_code| Self::hash_recovery_code(recovery_code)).collect(),
+            last_used_step: None,
+            mfa_ticket_identifier: None,
+            created_at: DateTime::now(),
+        };
+
+        (totp_credentials, recovery_codes)
+    }
+
+    fn generate_recovery_code() -> String {
+        let mut rng = rand::thread_rng();
+        let code: String = (0..10).map(|_| Self::RECOVERY_CODE_ALPHABET[rng.gen_range(0..Self::RECOVERY_CODE_ALPHABET.len())] as char).collect();
+
+        format!("{}-{}", &code[..5], &code[5..])
+    }
+
+    /// Recovery codes are compared without case and separator
+    pub(crate) fn hash_recovery_code(recovery_code: &str) -> String {
+        let normalized_recovery_code: String = recovery_code.chars().filter(|character| character.is_alphanumeric()).collect::<String>().to_uppercase();
+
+        format!("{:x}", Sha256::digest(normalized_recovery_code.as_bytes()))
+    }
+
+    pub(crate) fn totp(&self, issuer: &str) -> Result<TOTP, AuthError> {
+        let secret = Secret::Encoded(self.secret.clone()).to_bytes().map_err(|_| AuthError::ServerError)?;
+
+        TOTP::new(Algorithm::SHA1, Self::DIGITS, 0, Self::STEP_SECONDS, secret, Some(issuer.to_string()), self.username.clone())
+            .map_err(|_| AuthError::ServerError)
+    }
+
+    /// Return the time step of the code when it's valid at `timestamp` and not older than the last code used
+    pub(crate) fn verify_code(&self, code: &str, timestamp: u64) -> Option<i64> {
+        let totp = self.totp("").ok()?;
+        let current_step = (timestamp / Self::STEP_SECONDS) as i64;
+
+        (current_step - Self::SKEW_STEPS..=current_step + Self::SKEW_STEPS)
+            .filter(|step| *step >= 0 && self.last_used_step.is_none_or(|last_used_step| *step > last_used_step))
+            .find(|step| totp.generate(*step as u64 * Self::STEP_SECONDS) == code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entities::TotpCredentials;
+
+    const NOW: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_generate_totp_credentials() {
+        let (totp_credentials, recovery_codes) = TotpCredentials::generate("username");
+
+        assert!(!totp_credentials.enabled);
+        assert_eq!(recovery_codes.len(), 10);
+        assert_eq!(totp_credentials.recovery_code_hashes[0], TotpCredentials::hash_recovery_code(&recovery_codes[0].to_lowercase().replace('-', "")));
+        assert!(totp_credentials.totp("Issuer").unwrap().get_url().starts_with("otpauth://totp/Issuer:username?secret="));
+    }
+
+    #[test]
+    fn test_verify_code_with_skew_and_replay() {
+        let (totp_credentials, _) = TotpCredentials::generate("username");
+        let totp = totp_credentials.totp("Issuer").unwrap();
+        let current_step = (NOW / 30) as i64;
+
+        assert_eq!(totp_credentials.verify_code(&totp.generate(NOW), NOW), Some(current_step));
+        assert_eq!(totp_credentials.verify_code(&totp.generate(NOW - 30), NOW), Some(current_step - 1));
+        assert_eq!(totp_credentials.verify_code(&totp.generate(NOW - 90), NOW), None);
+
+        let used_totp_credentials = TotpCredentials { last_used_step: Some(current_step), ..totp_credentials };
+        assert_eq!(used_totp_credentials.verify_code(&totp.generate(NOW), NOW), None);
+        assert_eq!(used_totp_credentials.verify_code(&totp.generate(NOW + 30), NOW), Some(current_step + 1));
+    }
+}
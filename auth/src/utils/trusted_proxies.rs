@@ -0,0 +1,83 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+use ipnet::IpNet;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TrustedProxiesError {
+    #[error("Trusted proxy is neither an IP address nor a CIDR range : {0}")]
+    InvalidProxy(String),
+}
+
+/// Reverse proxies allowed to give the client IP in the `X-Forwarded-For` and `X-Real-IP` headers
+///
+/// Headers of other peers are ignored : any client can send them to dodge the throttling of its IP.
+/// Empty by default, the client IP is then the address of the peer.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies {
+    proxies: Vec<IpNet>,
+}
+
+impl TrustedProxies {
+    /// IP addresses or CIDR ranges (`IpNet`) of the proxies
+    pub fn new<P: Into<IpNet>>(proxies: impl IntoIterator<Item=P>) -> Self {
+        Self { proxies: proxies.into_iter().map(Into::into).collect() }
+    }
+
+    pub(crate) fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.proxies.iter().any(|proxy| proxy.contains(ip))
+    }
+
+    /// Private, shared (100.64.0.0/10), loopback and link-local addresses, where the proxies of hosting platforms connect from
+    pub(crate) fn is_private_network(ip: &IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => ip.is_private() || ip.is_loopback() || ip.is_link_local() || (ip.octets()[0] == 100 && ip.octets()[1] & 0xc0 == 64),
+            IpAddr::V6(ip) => ip.is_loopback() || ip.is_unique_local() || ip.is_unicast_link_local(),
+        }
+    }
+}
+
+/// Comma separated IP addresses and CIDR ranges, e.g. `10.0.0.0/8, 192.0.2.1`
+impl FromStr for TrustedProxies {
+    type Err = TrustedProxiesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let proxies = s.split(',')
+            .map(str::trim)
+            .filter(|proxy| !proxy.is_empty())
+            .map(|proxy| proxy.parse::<IpNet>()
+                .or_else(|_| proxy.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| TrustedProxiesError::InvalidProxy(proxy.to_string())))
+            .collect::<Result<Vec<IpNet>, TrustedProxiesError>>()?;
+
+        Ok(Self { proxies })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use crate::utils::trusted_proxies::{TrustedProxies, TrustedProxiesError};
+
+    #[test]
+    fn test_trusted_proxies_from_addresses_and_ranges() {
+        let trusted_proxies: TrustedProxies = "10.0.0.0/8, 192.0.2.1,fd00::/8".parse().unwrap();
+
+        assert!(trusted_proxies.is_trusted(&"10.12.0.3".parse().unwrap()));
+        assert!(trusted_proxies.is_trusted(&"192.0.2.1".parse().unwrap()));
+        assert!(trusted_proxies.is_trusted(&"fd12::1".parse().unwrap()));
+        assert!(!trusted_proxies.is_trusted(&"192.0.2.2".parse().unwrap()));
+        assert!(!trusted_proxies.is_trusted(&"11.0.0.1".parse().unwrap()));
+        assert_eq!("10.0.0.0/33".parse::<TrustedProxies>().unwrap_err(), TrustedProxiesError::InvalidProxy("10.0.0.0/33".to_string()));
+    }
+
+    #[test]
+    fn test_private_network() {
+        for ip in ["10.1.2.3", "172.16.0.1", "192.168.1.1", "100.64.0.1", "127.0.0.1", "::1", "fd00::1"] {
+            assert!(TrustedProxies::is_private_network(&ip.parse::<IpAddr>().unwrap()), "{} should be private", ip);
+        }
+        for ip in ["203.0.113.7", "100.128.0.1", "2001:db8::1"] {
+            assert!(!TrustedProxies::is_private_network(&ip.parse::<IpAddr>().unwrap()), "{} should be public", ip);
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ciborium::Value;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::ecdsa::signature::Verifier;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use crate::entities::error::AuthError;
+use crate::entities::{WebAuthnCeremony, WebAuthnChallenge, WebAuthnCredential};
+use crate::views::payload::WebAuthnAssertionResponse;
+use crate::views::response::{WebAuthnAuthenticatorSelection, WebAuthnCreationOptions, WebAuthnCredentialDescriptor, WebAuthnCredentialParameters, WebAuthnRelyingPartyEntity, WebAuthnRequestOptions, WebAuthnUserEntity};
+
+/// `CollectedClientData` signed by the authenticator, built by the browser
+#[derive(Deserialize)]
+pub(crate) struct CollectedClientData {
+    #[serde(rename = "type")]
+    pub(crate) ceremony_type: String,
+    pub(crate) challenge: String,
+    pub(crate) origin: String,
+}
+
+/// Public key credential extracted from a verified registration
+pub(crate) struct RegisteredCredential {
+    pub(crate) credential_id: String,
+    pub(crate) public_key: String,
+    pub(crate) sign_count: u32,
+}
+
+struct AuthenticatorData<'a> {
+    rp_id_hash: &'a [u8],
+    flags: u8,
+    sign_count: u32,
+    attested_credential_data: Option<&'a [u8]>,
+}
+
+/// WebAuthn relying party : the site passkeys are bound to
+///
+/// Only the `none` attestation and ES256 (P-256) keys are supported, which cover platform and roaming passkeys
+/// when no attestation is requested. Attestation statements are not verified.
+#[derive(Clone, Debug)]
+pub struct RelyingParty {
+    id: String,
+    name: String,
+    origin: String,
+}
+
+impl Default for RelyingParty {
+    /// Local development on `http://localhost:8000`
+    fn default() -> Self {
+        Self::new("localhost", "Auth", "http://localhost:8000")
+    }
+}
+
+impl RelyingParty {
+    const ES256: i64 = -7;
+    const FLAG_USER_PRESENT: u8 = 0x01;
+    const FLAG_USER_VERIFIED: u8 = 0x04;
+    const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+    const PUBLIC_KEY_CREDENTIAL_TYPE: &'static str = "public-key";
+    /// Authenticators must verify the user, otherwise they refuse the ceremony
+    const USER_VERIFICATION: &'static str = "required";
+
+    /// `id` is the domain of the site (without scheme nor port) and `origin` the exact origin of the pages using WebAuthn
+    pub fn new(id: &str, name: &str, origin: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            origin: origin.to_string(),
+        }
+    }
+
+    fn timeout() -> i64 {
+        WebAuthnChallenge::WEBAUTHN_CHALLENGE_LIFETIME.num_milliseconds()
+    }
+
+    fn credential_descriptors(webauthn_credentials: &[WebAuthnCredential]) -> Vec<WebAuthnCredentialDescriptor> {
+        webauthn_credentials.iter()
+            .map(|webauthn_credential| WebAuthnCredentialDescriptor { credential_type: Self::PUBLIC_KEY_CREDENTIAL_TYPE.to_string(), id: webauthn_credential.credential_id.clone() })
+            .collect()
+    }
+
+    fn decode(value: &str) -> Result<Vec<u8>, AuthError> {
+        URL_SAFE_NO_PAD.decode(value.trim_end_matches('=')).map_err(|_| AuthError::InvalidToken)
+    }
+
+    /// User handles are the username, a user can't register the same authenticator twice.
+    /// Passkeys must be discoverable : the login never lists the credentials of a user
+    pub(crate) fn creation_options(&self, webauthn_challenge: &WebAuthnChallenge, username: &str, registered_credentials: &[WebAuthnCredential]) -> WebAuthnCreationOptions {
+        WebAuthnCreationOptions {
+            challenge: webauthn_challenge.challenge.clone(),
+            rp: WebAuthnRelyingPartyEntity { id: self.id.clone(), name: self.name.clone() },
+            user: WebAuthnUserEntity { id: URL_SAFE_NO_PAD.encode(username), name: username.to_string(), display_name: username.to_string() },
+            pub_key_cred_params: vec![WebAuthnCredentialParameters { credential_type: Self::PUBLIC_KEY_CREDENTIAL_TYPE.to_string(), alg: Self::ES256 }],
+            timeout: Self::timeout(),
+            exclude_credentials: Self::credential_descriptors(registered_credentials),
+            authenticator_selection: WebAuthnAuthenticatorSelection { resident_key: "required".to_string(), require_resident_key: true, user_verification: Self::USER_VERIFICATION.to_string() },
+            attestation: "none".to_string(),
+        }
+    }
+
+    /// `allowCredentials` is always empty, the authenticator offers the discoverable credentials it holds for the site
+    pub(crate) fn request_options(&self, webauthn_challenge: &WebAuthnChallenge) -> WebAuthnRequestOptions {
+        WebAuthnRequestOptions {
+            challenge: webauthn_challenge.challenge.clone(),
+            timeout: Self::timeout(),
+            rp_id: self.id.clone(),
+            allow_credentials: Vec::new(),
+            user_verification: Self::USER_VERIFICATION.to_string(),
+        }
+    }
+
+    /// Decode the client data and check the ceremony and the origin. The challenge is checked by the caller
+    pub(crate) fn collect_client_data(&self, client_data_json: &str, ceremony: WebAuthnCeremony) -> Result<(CollectedClientData, Vec<u8>), AuthError> {
+        let client_data_bytes = Self::decode(client_data_json)?;
+        let client_data: CollectedClientData = serde_json::from_slice(&client_data_bytes).map_err(|_| AuthError::InvalidToken)?;
+
+        let expected_type = match ceremony {
+            WebAuthnCeremony::Registration => "webauthn.create",
+            WebAuthnCeremony::Authentication => "webauthn.get",
+        };
+        if client_data.ceremony_type != expected_type || client_data.origin != self.origin {
+            return Err(AuthError::InvalidToken);
+        }
+
+        Ok((client_data, client_data_bytes))
+    }
+
+    fn parse_authenticator_data<'a>(&self, authenticator_data: &'a [u8]) -> Result<AuthenticatorData<'a>, AuthError> {
+        if authenticator_data.len() < 37 {
+            return Err(AuthError::InvalidToken);
+        }
+
+        let parsed_authenticator_data = AuthenticatorData {
+            rp_id_hash: &authenticator_data[..32],
+            flags: authenticator_data[32],
+            sign_count: u32::from_be_bytes([authenticator_data[33], authenticator_data[34], authenticator_data[35], authenticator_data[36]]),
+            attested_credential_data: Some(&authenticator_data[37..]).filter(|_| authenticator_data[32] & Self::FLAG_ATTESTED_CREDENTIAL_DATA != 0),
+        };
+
+        // A passkey replaces the password : the user must be verified (PIN, biometrics), not only present
+        let user_verified = Self::FLAG_USER_PRESENT | Self::FLAG_USER_VERIFIED;
+        if parsed_authenticator_data.rp_id_hash != Sha256::digest(self.id.as_bytes()).as_slice() || parsed_authenticator_data.flags & user_verified != user_verified {
+            return Err(AuthError::WrongCredentials);
+        }
+
+        Ok(parsed_authenticator_data)
+    }
+
+    /// Convert a COSE_Key (EC2, ES256, P-256) to a SEC1 uncompressed point
+    fn parse_cose_key(cose_key: &Value) -> Result<Vec<u8>, AuthError> {
+        let entries = cose_key.as_map().ok_or(AuthError::InvalidToken)?;
+        let get = |label: i64| entries.iter()
+            .find(|(key, _)| key.as_integer().is_some_and(|key| i128::from(key) == i128::from(label)))
+            .map(|(_, value)| value);
+        let get_integer = |label: i64| get(label).and_then(|value| value.as_integer()).map(i128::from);
+
+        // kty EC2, alg ES256, crv P-256
+        if get_integer(1) != Some(2) || get_integer(3) != Some(i128::from(Self::ES256)) || get_integer(-1) != Some(1) {
+            return Err(AuthError::InvalidToken);
+        }
+        let x = get(-2).and_then(|value| value.as_bytes()).filter(|x| x.len() == 32).ok_or(AuthError::InvalidToken)?;
+        let y = get(-3).and_then(|value| value.as_bytes()).filter(|y| y.len() == 32).ok_or(AuthError::InvalidToken)?;
+
+        let public_key = [&[0x04], x.as_slice(), y.as_slice()].concat();
+        VerifyingKey::from_sec1_bytes(&public_key).map_err(|_| AuthError::InvalidToken)?;
+
+        Ok(public_key)
+    }
+
+    /// Verify the attestation object of a registration and extract the credential
+    pub(crate) fn verify_registration(&self, attestation_object: &str) -> Result<RegisteredCredential, AuthError> {
+        let attestation_object: Value = ciborium::from_reader(Self::decode(attestation_object)?.as_slice()).map_err(|_| AuthError::InvalidToken)?;
+        let entries = attestation_object.as_map().ok_or(AuthError::InvalidToken)?;
+        let get = |name: &str| entries.iter().find(|(key, _)| key.as_text() == Some(name)).map(|(_, value)| value);
+
+        if get("fmt").and_then(|fmt| fmt.as_text()) != Some("none") {
+            return Err(AuthError::InvalidToken);
+        }
+        let authenticator_data = get("authData").and_then(|authenticator_data| authenticator_data.as_bytes()).ok_or(AuthError::InvalidToken)?;
+        let authenticator_data = self.parse_authenticator_data(authenticator_data)?;
+        let attested_credential_data = authenticator_data.attested_credential_data.ok_or(AuthError::InvalidToken)?;
+
+        // AAGUID (16 bytes), credential id length (2 bytes), credential id, then the COSE public key
+        if attested_credential_data.len() < 18 {
+            return Err(AuthError::InvalidToken);
+        }
+        let credential_id_length = u16::from_be_bytes([attested_credential_data[16], attested_credential_data[17]]) as usize;
+        let credential_id = attested_credential_data.get(18..18 + credential_id_length).ok_or(AuthError::InvalidToken)?;
+        let mut cose_key_bytes = &attested_credential_data[18 + credential_id_length..];
+        let cose_key: Value = ciborium::from_reader(&mut cose_key_bytes).map_err(|_| AuthError::InvalidToken)?;
+
+        Ok(RegisteredCredential {
+            credential_id: URL_SAFE_NO_PAD.encode(credential_id),
+            public_key: URL_SAFE_NO_PAD.encode(Self::parse_cose_key(&cose_key)?),
+            sign_count: authenticator_data.sign_count,
+        })
+    }
+
+    /// Verify the signature of an assertion and return the new signature counter of the authenticator
+    pub(crate) fn verify_assertion(&self, webauthn_credential: &WebAuthnCredential, client_data_bytes: &[u8], assertion_response: &WebAuthnAssertionResponse) -> Result<u32, AuthError> {
+        let authenticator_data_bytes = Self::decode(&assertion_response.authenticator_data)?;
+        let authenticator_data = self.parse_authenticator_data(&authenticator_data_bytes)?;
+
+        let verifying_key = VerifyingKey::from_sec1_bytes(&Self::decode(&webauthn_credential.public_key)?).map_err(|_| AuthError::ServerError)?;
+        let signature = Signature::from_der(&Self::decode(&assertion_response.signature)?).map_err(|_| AuthError::WrongCredentials)?;
+        let signed_data = [authenticator_data_bytes.as_slice(), Sha256::digest(client_data_bytes).as_slice()].concat();
+        verifying_key.verify(&signed_data, &signature).map_err(|_| AuthError::WrongCredentials)?;
+
+        // Authenticators without counter always send 0, otherwise it must increase or the authenticator may be cloned
+        if (authenticator_data.sign_count != 0 || webauthn_credential.sign_count != 0) && authenticator_data.sign_count <= webauthn_credential.sign_count {
+            return Err(AuthError::WrongCredentials);
+        }
+
+        Ok(authenticator_data.sign_count)
+    }
+}
+
+/// Authenticator emulated in software, answering WebAuthn ceremonies like a browser would
+#[cfg(test)]
+pub(crate) struct SoftwareAuthenticator {
+    signing_key: p256::ecdsa::SigningKey,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+    user_verification: bool,
+}
+
+#[cfg(test)]
+impl SoftwareAuthenticator {
+    pub(crate) fn new() -> Self {
+        Self {
+            signing_key: p256::ecdsa::SigningKey::random(&mut rand::thread_rng()),
+            credential_id: uuid::Uuid::new_v4().as_bytes().to_vec(),
+            sign_count: 0,
+            user_verification: true,
+        }
+    }
+
+    /// Authenticator only checking the user presence, like a security key without PIN
+    pub(crate) fn without_user_verification(self) -> Self {
+        Self { user_verification: false, ..self }
+    }
+
+    fn flags(&self) -> u8 {
+        match self.user_verification {
+            true => RelyingParty::FLAG_USER_PRESENT | RelyingParty::FLAG_USER_VERIFIED,
+            false => RelyingParty::FLAG_USER_PRESENT,
+        }
+    }
+
+    pub(crate) fn credential_id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    fn client_data_json(ceremony_type: &str, challenge: &str, origin: &str) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::json!({ "type": ceremony_type, "challenge": challenge, "origin": origin, "crossOrigin": false }).to_string())
+    }
+
+    fn authenticator_data(rp_id: &str, flags: u8, sign_count: u32) -> Vec<u8> {
+        [Sha256::digest(rp_id.as_bytes()).as_slice(), &[flags], &sign_count.to_be_bytes()].concat()
+    }
+
+    pub(crate) fn register(&self, rp_id: &str, origin: &str, challenge: &str) -> crate::views::payload::WebAuthnRegistrationPayload {
+        let encoded_point = self.signing_key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(RelyingParty::ES256)),
+            (Value::from(-1), Value::from(1)),
+            (Value::from(-2), Value::Bytes(encoded_point.x().unwrap().to_vec())),
+            (Value::from(-3), Value::Bytes(encoded_point.y().unwrap().to_vec())),
+        ]);
+        let mut authenticator_data = Self::authenticator_data(rp_id, self.flags() | RelyingParty::FLAG_ATTESTED_CREDENTIAL_DATA, self.sign_count);
+        authenticator_data.extend_from_slice(&[0u8; 16]);
+        authenticator_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        authenticator_data.extend_from_slice(&self.credential_id);
+        ciborium::into_writer(&cose_key, &mut authenticator_data).unwrap();
+
+        let attestation_object = Value::Map(vec![
+            (Value::from("fmt"), Value::from("none")),
+            (Value::from("attStmt"), Value::Map(vec![])),
+            (Value::from("authData"), Value::Bytes(authenticator_data)),
+        ]);
+        let mut attestation_object_bytes = Vec::new();
+        ciborium::into_writer(&attestation_object, &mut attestation_object_bytes).unwrap();
+
+        crate::views::payload::WebAuthnRegistrationPayload {
+            id: self.credential_id(),
+            name: Some("Software authenticator".to_string()),
+            response: crate::views::payload::WebAuthnAttestationResponse {
+                client_data_json: Self::client_data_json("webauthn.create", challenge, origin),
+                attestation_object: URL_SAFE_NO_PAD.encode(attestation_object_bytes),
+            },
+        }
+    }
+
+    pub(crate) fn authenticate(&mut self, rp_id: &str, origin: &str, challenge: &str) -> crate::views::payload::WebAuthnAssertionPayload {
+        use p256::ecdsa::signature::Signer;
+
+        self.sign_count += 1;
+        let client_data_json = Self::client_data_json("webauthn.get", challenge, origin);
+        let authenticator_data = Self::authenticator_data(rp_id, self.flags(), self.sign_count);
+        let signed_data = [authenticator_data.as_slice(), Sha256::digest(URL_SAFE_NO_PAD.decode(&client_data_json).unwrap()).as_slice()].concat();
+        let signature: Signature = self.signing_key.sign(&signed_data);
+
+        crate::views::payload::WebAuthnAssertionPayload {
+            id: self.credential_id(),
+            response: WebAuthnAssertionResponse {
+                client_data_json,
+                authenticator_data: URL_SAFE_NO_PAD.encode(authenticator_data),
+                signature: URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+                user_handle: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::DateTime;
+    use crate::entities::error::AuthError;
+    use crate::entities::{WebAuthnCeremony, WebAuthnChallenge, WebAuthnCredential};
+    use crate::utils::webauthn::{RelyingParty, SoftwareAuthenticator};
+
+    const ORIGIN: &str = "http://localhost:8000";
+
+    fn register(relying_party: &RelyingParty, authenticator: &SoftwareAuthenticator) -> WebAuthnCredential {
+        let webauthn_challenge = WebAuthnChallenge::generate(Some("username"), WebAuthnCeremony::Registration);
+        let registration_payload = authenticator.register("localhost", ORIGIN, &webauthn_challenge.challenge);
+
+        let (client_data, _) = relying_party.collect_client_data(&registration_payload.response.client_data_json, WebAuthnCeremony::Registration).unwrap();
+        assert_eq!(client_data.challenge, webauthn_challenge.challenge);
+        let registered_credential = relying_party.verify_registration(&registration_payload.response.attestation_object).unwrap();
+
+        WebAuthnCredential {
+            id: None,
+            username: "username".to_string(),
+            credential_id: registered_credential.credential_id,
+            public_key: registered_credential.public_key,
+            sign_count: registered_credential.sign_count,
+            name: "name".to_string(),
+            created_at: DateTime::now(),
+            last_used_at: None,
+        }
+    }
+
+    #[test]
+    fn test_registration_and_assertion_with_software_authenticator() {
+        let relying_party = RelyingParty::default();
+        let mut authenticator = SoftwareAuthenticator::new();
+        let webauthn_credential = register(&relying_party, &authenticator);
+        assert_eq!(webauthn_credential.credential_id, authenticator.credential_id());
+
+        let assertion_payload = authenticator.authenticate("localhost", ORIGIN, "challenge");
+        let (_, client_data_bytes) = relying_party.collect_client_data(&assertion_payload.response.client_data_json, WebAuthnCeremony::Authentication).unwrap();
+
+        assert_eq!(relying_party.verify_assertion(&webauthn_credential, &client_data_bytes, &assertion_payload.response), Ok(1));
+        // A counter not increasing means the authenticator may be cloned
+        let used_webauthn_credential = WebAuthnCredential { sign_count: 1, ..webauthn_credential };
+        assert_eq!(relying_party.verify_assertion(&used_webauthn_credential, &client_data_bytes, &assertion_payload.response), Err(AuthError::WrongCredentials));
+    }
+
+    #[test]
+    fn test_assertion_with_other_key_or_relying_party() {
+        let relying_party = RelyingParty::default();
+        let webauthn_credential = register(&relying_party, &SoftwareAuthenticator::new());
+
+        let assertion_payload = SoftwareAuthenticator::new().authenticate("localhost", ORIGIN, "challenge");
+        let (_, client_data_bytes) = relying_party.collect_client_data(&assertion_payload.response.client_data_json, WebAuthnCeremony::Authentication).unwrap();
+        assert_eq!(relying_party.verify_assertion(&webauthn_credential, &client_data_bytes, &assertion_payload.response), Err(AuthError::WrongCredentials));
+
+        let phishing_payload = SoftwareAuthenticator::new().authenticate("evil.example", "https://evil.example", "challenge");
+        assert!(relying_party.collect_client_data(&phishing_payload.response.client_data_json, WebAuthnCeremony::Authentication).is_err());
+        assert!(relying_party.collect_client_data(&assertion_payload.response.client_data_json, WebAuthnCeremony::Registration).is_err());
+    }
+
+    #[test]
+    fn test_assertion_without_user_verification() {
+        let relying_party = RelyingParty::default();
+        let authenticator = SoftwareAuthenticator::new();
+        let webauthn_credential = register(&relying_party, &authenticator);
+        let mut authenticator = authenticator.without_user_verification();
+
+        let registration_payload = authenticator.register("localhost", ORIGIN, "challenge");
+        assert!(matches!(relying_party.verify_registration(&registration_payload.response.attestation_object), Err(AuthError::WrongCredentials)));
+        let assertion_payload = authenticator.authenticate("localhost", ORIGIN, "challenge");
+        let (_, client_data_bytes) = relying_party.collect_client_data(&assertion_payload.response.client_data_json, WebAuthnCeremony::Authentication).unwrap();
+
+        assert_eq!(relying_party.verify_assertion(&webauthn_credential, &client_data_bytes, &assertion_payload.response), Err(AuthError::WrongCredentials));
+    }
+}
@@ -1,7 +1,7 @@
 use crate::datastore::{AuthDatastoreError, TokenDatastoreError};
-use crate::entities::error::AuthError;
+use crate::entities::error::{AuthError, OAuthError};
 use axum::body::Body;
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde_json::json;
@@ -21,16 +21,55 @@ impl HttpStatusCodeError for AuthError {
             AuthError::InvalidToken => StatusCode::BAD_REQUEST,
             AuthError::ServerError => StatusCode::SERVICE_UNAVAILABLE,
             AuthError::Duplicated => StatusCode::BAD_REQUEST,
+            AuthError::NotFound => StatusCode::NOT_FOUND,
+            AuthError::WeakPassword(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AuthError::AccountLocked(_) => StatusCode::LOCKED,
+            AuthError::TooManyAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+            AuthError::LastSuperAdmin => StatusCode::CONFLICT,
+            AuthError::InvalidRole => StatusCode::UNPROCESSABLE_ENTITY,
         }
     }
 }
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
+        if let AuthError::WeakPassword(password_policy_error) = &self {
+            return (self.get_http_status_code(), Json(json!({
+                "error": self.to_string(),
+                "violations": password_policy_error.violations,
+            }))).into_response();
+        }
+
+        if let AuthError::AccountLocked(retry_after) | AuthError::TooManyAttempts(retry_after) = &self {
+            return (self.get_http_status_code(), [(header::RETRY_AFTER, retry_after.to_string())], self.to_string()).into_response();
+        }
+
         (self.get_http_status_code(), self.to_string()).into_response()
     }
 }
 
+impl HttpStatusCodeError for OAuthError {
+    fn get_http_status_code(&self) -> StatusCode {
+        match self {
+            OAuthError::InvalidClient => StatusCode::UNAUTHORIZED,
+            OAuthError::ServerError => StatusCode::SERVICE_UNAVAILABLE,
+            OAuthError::AccessDenied => StatusCode::FORBIDDEN,
+            OAuthError::InvalidRequest | OAuthError::UnsupportedGrantType | OAuthError::InvalidScope | OAuthError::InvalidGrant | OAuthError::UnsupportedResponseType => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Error codes of RFC 6749 are expected by OAuth clients, instead of the plain text of `AuthError`
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        if self == OAuthError::InvalidClient {
+            return (self.get_http_status_code(), [(header::WWW_AUTHENTICATE, "Basic")], Json(json!({ "error": self.to_string() }))).into_response();
+        }
+
+        (self.get_http_status_code(), Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
 /// Implement of IntoResponse for TokenDatastoreError
 /// Used to format axum response
 impl HttpStatusCodeError for AuthDatastoreError {
@@ -66,9 +105,16 @@ pub fn handle_error(err: Box<dyn Error + Sync + Send>) -> Response<Body> {
         StatusCode::INTERNAL_SERVER_ERROR
     };
 
-    let body = Json(json!({
-        "error": err.to_string(),
-    }));
+    let body = if let Some(AuthError::WeakPassword(password_policy_error)) = err.downcast_ref::<AuthError>() {
+        Json(json!({
+            "error": err.to_string(),
+            "violations": password_policy_error.violations,
+        }))
+    } else {
+        Json(json!({
+            "error": err.to_string(),
+        }))
+    };
     // Default case
     (status_code, body).into_response()
 }
@@ -76,6 +122,7 @@ pub fn handle_error(err: Box<dyn Error + Sync + Send>) -> Response<Body> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::password_policy::{PasswordPolicyError, PasswordPolicyViolation};
 
     #[test]
     fn test_get_http_status_code_auth_wrong_credentials() {
@@ -83,6 +130,12 @@ mod tests {
         assert_eq!(err.get_http_status_code(), StatusCode::UNAUTHORIZED);
     }
 
+    #[test]
+    fn test_oauth_error_status_code() {
+        assert_eq!(OAuthError::InvalidClient.into_response().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(OAuthError::InvalidScope.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
     #[test]
     fn test_get_http_status_code_auth_missing_credentials() {
         let err = AuthError::MissingCredentials;
@@ -104,6 +157,24 @@ mod tests {
         assert_eq!(err.get_http_status_code(), StatusCode::BAD_REQUEST);
     }
 
+    #[test]
+    fn test_get_http_status_code_auth_weak_password() {
+        let err = AuthError::WeakPassword(PasswordPolicyError { violations: vec![PasswordPolicyViolation::MissingDigit] });
+        assert_eq!(err.get_http_status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn test_get_http_status_code_auth_locked() {
+        assert_eq!(AuthError::AccountLocked(30).get_http_status_code(), StatusCode::LOCKED);
+        assert_eq!(AuthError::TooManyAttempts(30).get_http_status_code(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[test]
+    fn test_locked_response_has_retry_after() {
+        let response = AuthError::AccountLocked(30).into_response();
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "30");
+    }
+
     #[test]
     fn test_get_http_status_code_auth_server_error() {
         let err = AuthError::ServerError;
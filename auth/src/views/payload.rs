@@ -3,6 +3,7 @@ use fake::Dummy;
 use mongodb::bson::DateTime;
 use serde::Deserialize;
 use crate::entities::{Roles, UserCredentials};
+use crate::utils::auth_config::AuthConfig;
 
 #[cfg(test)]
 use serde::Serialize;
@@ -14,15 +15,16 @@ pub struct LoginPayload {
     pub password: String,
 }
 
-impl From<LoginPayload> for UserCredentials {
-    fn from(login_payload: LoginPayload) -> Self {
+impl LoginPayload {
+    /// New user credentials, with the password hashed as configured in `AuthConfig`
+    pub fn into_user_credentials(self, auth_config: &AuthConfig) -> UserCredentials {
         let now = DateTime::now();
 
-        Self {
+        UserCredentials {
             id: None,
-            username: login_payload.username,
-            roles: Roles::User,
-            password: UserCredentials::hash_password(login_payload.password),
+            username: self.username,
+            roles: vec![Roles::User],
+            password: UserCredentials::hash_password(self.password, auth_config),
             created_at: now,
             last_modified_at: now,
         }
@@ -36,6 +38,175 @@ pub struct RefreshTokenPayload {
     pub(crate) refresh_token: String
 }
 
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone))]
+pub struct UpdateUserRolesPayload {
+    pub(crate) roles: Vec<Roles>,
+}
+
+/// Definition of a runtime role, or the new permissions of a built-in role
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone))]
+pub struct SaveRolePayload {
+    pub(crate) parent: Option<Roles>,
+    #[serde(default)]
+    pub(crate) permissions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone, Dummy))]
+pub struct ChangePasswordPayload {
+    pub(crate) current_password: String,
+    pub(crate) new_password: String,
+    /// Revoke every session except the one used for this request
+    #[serde(default)]
+    pub(crate) revoke_other_sessions: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone, Dummy))]
+pub struct PasswordResetRequestPayload {
+    pub(crate) username: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone, Dummy))]
+pub struct PasswordResetConfirmPayload {
+    pub(crate) reset_token: String,
+    pub(crate) new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone, Dummy))]
+pub struct TotpCodePayload {
+    pub code: String,
+}
+
+/// Second step of the login, with the ticket given by `/login` and a TOTP or recovery code
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone, Dummy))]
+pub struct MfaLoginPayload {
+    pub mfa_ticket: String,
+    pub code: String,
+}
+
+/// `PublicKeyCredential` returned by `navigator.credentials.create()`, binary fields in base64url
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone))]
+pub struct WebAuthnRegistrationPayload {
+    pub id: String,
+    /// Name given by the user to recognize the authenticator
+    pub name: Option<String>,
+    pub response: WebAuthnAttestationResponse,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone))]
+pub struct WebAuthnAttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+/// Without username, the authenticator offers its discoverable credentials
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone))]
+pub struct WebAuthnLoginStartPayload {
+    pub username: Option<String>,
+}
+
+/// `PublicKeyCredential` returned by `navigator.credentials.get()`, binary fields in base64url
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone))]
+pub struct WebAuthnAssertionPayload {
+    pub id: String,
+    pub response: WebAuthnAssertionResponse,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone))]
+pub struct WebAuthnAssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(rename = "userHandle")]
+    pub user_handle: Option<String>,
+}
+
+/// Without `expires_in_days`, the personal access token is valid until revoked
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone, Dummy))]
+pub struct CreatePersonalAccessTokenPayload {
+    pub name: String,
+    pub expires_in_days: Option<u32>,
+}
+
+/// Scopes are the only privileges the client can request with the `client_credentials` grant.
+/// Clients acting on behalf of users also need the redirection URIs of the `authorization_code` grant
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone, Dummy))]
+pub struct RegisterOAuthClientPayload {
+    pub name: String,
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+}
+
+/// Form of the token endpoint (RFC 6749 section 4.4). Client credentials can also be given with HTTP Basic authentication
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone, Dummy))]
+pub struct TokenRequestPayload {
+    pub grant_type: String,
+    /// Space separated scopes, every scope of the client when missing
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    /// Fields of the `authorization_code` grant (RFC 6749 section 4.1.3 and RFC 7636)
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+}
+
+/// Form of the introspection endpoint (RFC 7662 section 2.1). Client credentials can also be given with HTTP Basic authentication
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone))]
+pub struct IntrospectionPayload {
+    pub token: String,
+    /// Ignored, the type is read from the token
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Authorization request of a third-party app (RFC 6749 section 4.1.1), forwarded by the login and consent page
+/// with the choice of the user in `consent` once asked
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone))]
+pub struct AuthorizationRequestPayload {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    /// Space separated scopes
+    pub scope: String,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub nonce: Option<String>,
+    pub consent: Option<bool>,
+}
+
+/// Query of the redirection from the OpenID provider, with `error` instead of `code` when the user denied the login
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Serialize, Clone))]
+pub struct OidcCallbackPayload {
+    pub code: Option<String>,
+    pub state: String,
+    pub error: Option<String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,7 +220,7 @@ mod tests {
         fn dummy_with_rng<R: Rng + ?Sized>(_: &Faker, _rng: &mut R) -> Self {
             Self {
                 username: Username().fake(),
-                password: Password(10..500).fake()
+                password: Password(10..128).fake()
             }
         }
     }
@@ -57,13 +228,14 @@ mod tests {
     #[test]
     fn test_payload_from_user_credential_login() {
         let user_payload = Faker.fake::<LoginPayload>();
-        let new_user_credentials = UserCredentials::from(user_payload.clone());
+        let auth_config = AuthConfig::fake();
+        let new_user_credentials = user_payload.clone().into_user_credentials(&auth_config);
 
         assert!(new_user_credentials.id.is_none());
         assert_eq!(new_user_credentials.username, user_payload.username);
         assert_ne!(new_user_credentials.password, user_payload.password);
-        assert!(new_user_credentials.verify_password(&user_payload.password).is_ok());
-        assert!(new_user_credentials.verify_password(&(user_payload.password + " ")).is_err());
+        assert!(new_user_credentials.verify_password(&user_payload.password, &auth_config).is_ok());
+        assert!(new_user_credentials.verify_password(&(user_payload.password + " "), &auth_config).is_err());
         assert_eq!(new_user_credentials.created_at, new_user_credentials.last_modified_at);
     }
     
@@ -1,6 +1,7 @@
 use serde::Serialize;
-use crate::entities::{Roles, UserCredentials};
-
+use crate::entities::{OAuthClient, OAuthConsent, PersonalAccessToken, Role, Roles, UserCredentials, WebAuthnCredential};
+use crate::utils::authorization_server::UserClaims;
+use crate::utils::key_ring::PublishedKey;
 #[cfg(test)]
 use serde::Deserialize;
 
@@ -8,7 +9,7 @@ use serde::Deserialize;
 #[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
 pub struct CredentialsPrivateDetails {
     pub(crate) username: String,
-    pub(crate) roles: Roles,
+    pub(crate) roles: Vec<Roles>,
     pub(crate) created_at: String,
     pub(crate) last_modified_at: String,
 }
@@ -31,6 +32,378 @@ pub struct AuthBody {
     pub(crate) refresh_token: String,
 }
 
+/// Returned by `/login` when the user has a second factor, the ticket is exchanged on `/login/mfa`
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, PartialEq))]
+pub struct MfaTicketBody {
+    pub(crate) mfa_ticket: String,
+    pub(crate) expired_at: String,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, PartialEq))]
+#[serde(untagged)]
+pub enum LoginBody {
+    Tokens(AuthBody),
+    MfaRequired(MfaTicketBody),
+}
+
+/// Given once at enrolment : the secret can't be read again and only the hashes of recovery codes are stored
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct TotpEnrolmentBody {
+    pub(crate) secret: String,
+    pub(crate) otpauth_uri: String,
+    pub(crate) recovery_codes: Vec<String>,
+}
+
+/// `PublicKeyCredentialCreationOptions` given to `navigator.credentials.create()`, binary fields in base64url
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+#[serde(rename_all = "camelCase")]
+pub struct WebAuthnCreationOptions {
+    pub(crate) challenge: String,
+    pub(crate) rp: WebAuthnRelyingPartyEntity,
+    pub(crate) user: WebAuthnUserEntity,
+    pub(crate) pub_key_cred_params: Vec<WebAuthnCredentialParameters>,
+    pub(crate) timeout: i64,
+    pub(crate) exclude_credentials: Vec<WebAuthnCredentialDescriptor>,
+    pub(crate) authenticator_selection: WebAuthnAuthenticatorSelection,
+    pub(crate) attestation: String,
+}
+
+/// `PublicKeyCredentialRequestOptions` given to `navigator.credentials.get()`, binary fields in base64url
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+#[serde(rename_all = "camelCase")]
+pub struct WebAuthnRequestOptions {
+    pub(crate) challenge: String,
+    pub(crate) timeout: i64,
+    pub(crate) rp_id: String,
+    pub(crate) allow_credentials: Vec<WebAuthnCredentialDescriptor>,
+    pub(crate) user_verification: String,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct WebAuthnRelyingPartyEntity {
+    pub(crate) id: String,
+    pub(crate) name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+#[serde(rename_all = "camelCase")]
+pub struct WebAuthnUserEntity {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) display_name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct WebAuthnCredentialParameters {
+    #[serde(rename = "type")]
+    pub(crate) credential_type: String,
+    pub(crate) alg: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct WebAuthnCredentialDescriptor {
+    #[serde(rename = "type")]
+    pub(crate) credential_type: String,
+    pub(crate) id: String,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+#[serde(rename_all = "camelCase")]
+pub struct WebAuthnAuthenticatorSelection {
+    pub(crate) resident_key: String,
+    /// Read instead of `residentKey` by WebAuthn level 1 browsers
+    pub(crate) require_resident_key: bool,
+    pub(crate) user_verification: String,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct WebAuthnCredentialDetails {
+    pub(crate) credential_id: String,
+    pub(crate) name: String,
+    pub(crate) created_at: String,
+    pub(crate) last_used_at: Option<String>,
+}
+
+impl From<WebAuthnCredential> for WebAuthnCredentialDetails {
+    fn from(webauthn_credential: WebAuthnCredential) -> Self {
+        Self {
+            credential_id: webauthn_credential.credential_id,
+            name: webauthn_credential.name,
+            created_at: webauthn_credential.created_at.try_to_rfc3339_string().unwrap(),
+            last_used_at: webauthn_credential.last_used_at.map(|last_used_at| last_used_at.try_to_rfc3339_string().unwrap()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct UserRolesDetails {
+    pub(crate) username: String,
+    pub(crate) roles: Vec<Roles>,
+}
+
+impl From<UserCredentials> for UserRolesDetails {
+    fn from(user: UserCredentials) -> Self {
+        Self {
+            username: user.username,
+            roles: user.roles,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct RoleDetails {
+    pub(crate) name: Roles,
+    pub(crate) parent: Option<Roles>,
+    /// Permissions granted by the role itself, without the ones of its parents
+    pub(crate) permissions: Vec<String>,
+    pub(crate) built_in: bool,
+}
+
+impl From<Role> for RoleDetails {
+    fn from(role: Role) -> Self {
+        Self {
+            built_in: role.name.is_built_in(),
+            name: role.name,
+            parent: role.parent,
+            permissions: role.permissions,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct PersonalAccessTokenDetails {
+    pub(crate) token_id: String,
+    pub(crate) name: String,
+    pub(crate) created_at: String,
+    pub(crate) expired_at: Option<String>,
+    pub(crate) last_used_at: Option<String>,
+    pub(crate) revoked: bool,
+}
+
+impl From<PersonalAccessToken> for PersonalAccessTokenDetails {
+    fn from(personal_access_token: PersonalAccessToken) -> Self {
+        Self {
+            token_id: personal_access_token.token_id,
+            name: personal_access_token.name,
+            created_at: personal_access_token.created_at.try_to_rfc3339_string().unwrap(),
+            expired_at: personal_access_token.expired_at.map(|expired_at| expired_at.try_to_rfc3339_string().unwrap()),
+            last_used_at: personal_access_token.last_used_at.map(|last_used_at| last_used_at.try_to_rfc3339_string().unwrap()),
+            revoked: personal_access_token.revoked_at.is_some(),
+        }
+    }
+}
+
+/// The token is only returned here, it can't be retrieved afterward
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct PersonalAccessTokenBody {
+    pub(crate) token: String,
+    #[serde(flatten)]
+    pub(crate) details: PersonalAccessTokenDetails,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct OAuthClientDetails {
+    pub(crate) client_id: String,
+    pub(crate) name: String,
+    pub(crate) scopes: Vec<String>,
+    pub(crate) redirect_uris: Vec<String>,
+    pub(crate) created_at: String,
+}
+
+impl From<OAuthClient> for OAuthClientDetails {
+    fn from(oauth_client: OAuthClient) -> Self {
+        Self {
+            client_id: oauth_client.client_id,
+            name: oauth_client.name,
+            scopes: oauth_client.scopes,
+            redirect_uris: oauth_client.redirect_uris,
+            created_at: oauth_client.created_at.try_to_rfc3339_string().unwrap(),
+        }
+    }
+}
+
+/// The client secret is only returned here, it can't be retrieved afterward
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct OAuthClientBody {
+    pub(crate) client_secret: String,
+    #[serde(flatten)]
+    pub(crate) details: OAuthClientDetails,
+}
+
+/// Successful response of the token endpoint (RFC 6749 section 5.1)
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct ClientCredentialsTokenBody {
+    pub(crate) access_token: String,
+    pub(crate) token_type: String,
+    pub(crate) expires_in: i64,
+    pub(crate) scope: String,
+}
+
+/// Response of the introspection endpoint (RFC 7662 section 2.2), only `active` for a token not active
+#[derive(Debug, Default, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct IntrospectionBody {
+    pub(crate) active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) username: Option<String>,
+    /// Roles of the user separated by spaces, as before several roles per user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) roles: Option<Vec<String>>,
+    /// Seconds since the epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) jti: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) token_type: Option<String>,
+}
+
+/// Successful response of the token endpoint to the `authorization_code` grant, with an ID token for the `openid` scope
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct AuthorizationCodeTokenBody {
+    pub(crate) access_token: String,
+    pub(crate) token_type: String,
+    pub(crate) expires_in: i64,
+    pub(crate) scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) id_token: Option<String>,
+}
+
+/// Answer of the authorization endpoint : where to redirect the user, or the consent to ask first
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, PartialEq))]
+#[serde(untagged)]
+pub enum AuthorizationBody {
+    Redirect(AuthorizationRedirectBody),
+    ConsentRequired(ConsentRequiredBody),
+}
+
+/// Redirection URI of the app with the authorization code, or with the error
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct AuthorizationRedirectBody {
+    pub(crate) redirect_to: String,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct ConsentRequiredBody {
+    pub(crate) client_id: String,
+    pub(crate) client_name: String,
+    pub(crate) scopes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct OAuthConsentDetails {
+    pub(crate) client_id: String,
+    pub(crate) scopes: Vec<String>,
+    pub(crate) created_at: String,
+    pub(crate) last_modified_at: String,
+}
+
+impl From<OAuthConsent> for OAuthConsentDetails {
+    fn from(oauth_consent: OAuthConsent) -> Self {
+        Self {
+            client_id: oauth_consent.client_id,
+            scopes: oauth_consent.scopes,
+            created_at: oauth_consent.created_at.try_to_rfc3339_string().unwrap(),
+            last_modified_at: oauth_consent.last_modified_at.try_to_rfc3339_string().unwrap(),
+        }
+    }
+}
+
+/// Response of the userinfo endpoint (OpenID Connect Core section 5.3)
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct UserInfoBody {
+    pub(crate) sub: String,
+    #[serde(flatten)]
+    pub(crate) user_claims: UserClaims,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct OpenIdConfiguration {
+    pub(crate) issuer: String,
+    pub(crate) authorization_endpoint: String,
+    pub(crate) token_endpoint: String,
+    pub(crate) userinfo_endpoint: String,
+    pub(crate) jwks_uri: String,
+    pub(crate) scopes_supported: Vec<String>,
+    pub(crate) response_types_supported: Vec<String>,
+    pub(crate) grant_types_supported: Vec<String>,
+    pub(crate) subject_types_supported: Vec<String>,
+    pub(crate) id_token_signing_alg_values_supported: Vec<String>,
+    pub(crate) token_endpoint_auth_methods_supported: Vec<String>,
+    pub(crate) code_challenge_methods_supported: Vec<String>,
+    pub(crate) claims_supported: Vec<String>,
+}
+
+/// Public key of the key ring in PASERK format, to verify tokens out of this service
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct PublicKeyDetails {
+    pub(crate) kid: String,
+    pub(crate) paserk: String,
+    pub(crate) active: bool,
+}
+
+impl From<PublishedKey> for PublicKeyDetails {
+    fn from(published_key: PublishedKey) -> Self {
+        Self {
+            kid: published_key.key_id,
+            paserk: published_key.public_key,
+            active: published_key.active,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct PublicKeySetBody {
+    pub(crate) keys: Vec<PublicKeyDetails>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize, Clone, PartialEq))]
+pub struct SessionDetails {
+    pub(crate) session_id: String,
+    pub(crate) user_agent: Option<String>,
+    pub(crate) client_ip: Option<String>,
+    pub(crate) created_at: String,
+    pub(crate) expired_at: String,
+    pub(crate) revoked: bool,
+    pub(crate) current: bool,
+}
+
 #[cfg(test)]
 mod tests {
     use fake::{Fake, Faker};
@@ -63,7 +436,7 @@ mod tests {
     fn test_user_private_details_serialization() {
         static USER_CREDENTIALS: Lazy<UserCredentials> = Lazy::new(|| Faker.fake());
         static USER_PRIVATE_DETAILS: Lazy<CredentialsPrivateDetails> = Lazy::new(|| CredentialsPrivateDetails::from(USER_CREDENTIALS.clone()));
-        static USER_PRIVATE_DETAILS_ROLE_STRING: Lazy<String> = Lazy::new(|| USER_PRIVATE_DETAILS.roles.to_string());
+        static USER_PRIVATE_DETAILS_ROLE_STRING: Lazy<String> = Lazy::new(|| USER_PRIVATE_DETAILS.roles[0].to_string());
         static USER_PRIVATE_DETAILS_CREATED_AT_STRING: Lazy<String> = Lazy::new(|| USER_PRIVATE_DETAILS.created_at.to_string());
         static USER_PRIVATE_DETAILS_LAST_MODIFIED_AT_STRING: Lazy<String> = Lazy::new(|| USER_PRIVATE_DETAILS.last_modified_at.to_string());
         assert_tokens(
@@ -73,7 +446,9 @@ mod tests {
                 Token::Str("username"),
                 Token::Str(&USER_PRIVATE_DETAILS.username),
                 Token::Str("roles"),
-                Token::UnitVariant { name: "Roles", variant: &USER_PRIVATE_DETAILS_ROLE_STRING },
+                Token::Seq { len: Some(1) },
+                Token::Str(&USER_PRIVATE_DETAILS_ROLE_STRING),
+                Token::SeqEnd,
                 Token::Str("created_at"),
                 Token::Str(&USER_PRIVATE_DETAILS_CREATED_AT_STRING),
                 Token::Str("last_modified_at"),
@@ -0,0 +1,20 @@
+use crate::services::{UserGetService};
+use auth_module::views::error_response::handle_error;
+use axum::extract::Path;
+use axum::response::ErrorResponse;
+use axum::{Extension, Json};
+use std::sync::Arc;
+use crate::views::response::UserPrivateDetails;
+
+/// Profile of a user, for the user itself or a privileged user. See `GuardExpression::owner_or`
+pub async fn get_user_profile<UserServiceImpl: UserGetService>(
+    Extension(user_service): Extension<Arc<UserServiceImpl>>,
+    Path(username): Path<String>,
+) -> Result<Json<UserPrivateDetails>, ErrorResponse> {
+    let user = user_service
+        .get_user(&username)
+        .await
+        .map_err(handle_error)?;
+
+    Ok(Json(user))
+}
@@ -1,2 +1,3 @@
 pub mod add_user;
-pub mod get_own_profile;
\ No newline at end of file
+pub mod get_own_profile;
+pub mod get_user_profile;
\ No newline at end of file
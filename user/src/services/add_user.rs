@@ -46,6 +46,8 @@ mod tests {
     use std::future;
     use mockall::mock;
     use auth_module::services::{AuthCreateCredentialsService};
+    use auth_module::utils::auth_config::AuthConfig;
+    use auth_module::utils::key_ring::KeyRing;
     use auth_module::views::payload::LoginPayload;
     use crate::datastore::MockUserDatastore;
     use crate::entities::user::User;
@@ -82,7 +84,7 @@ mod tests {
             .returning(|login_payload: LoginPayload| {
                 Box::pin(future::ready(Ok(UserCredentials {
                     id: Some(ObjectId::new()),
-                    ..login_payload.into()
+                    ..login_payload.into_user_credentials(&AuthConfig::new(KeyRing::new()))
                 })))
             });
 
@@ -104,6 +106,6 @@ mod tests {
 
         //user_service.checkpoint();
         assert_eq!(&result.username, &user_payload.username);
-        assert!(result.verify_password(&user_payload.password).is_ok());
+        assert!(result.verify_password(&user_payload.password, &AuthConfig::new(KeyRing::new())).is_ok());
     }
 }
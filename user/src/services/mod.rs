@@ -8,6 +8,8 @@ use crate::views::response::UserPrivateDetails;
 
 pub mod add_user;
 pub mod get_user;
+pub mod provision_user;
+pub mod user_claims;
 
 pub struct UserService<AuthServiceImpl, UserDatastoreImpl: UserDatastore> {
     user_datastore: UserDatastoreImpl,
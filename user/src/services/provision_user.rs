@@ -0,0 +1,87 @@
+use futures_util::future::BoxFuture;
+use mongodb::bson::DateTime;
+use auth_module::entities::error::AuthError;
+use auth_module::utils::oidc::{OidcIdentity, OidcUserProvisioning};
+use crate::datastore::UserDatastore;
+use crate::entities::user::User;
+use crate::services::UserService;
+
+/// Create the profile of users created by their first OpenID login, from the claims of the provider
+impl<AuthServiceImpl, UserDatastoreImpl> OidcUserProvisioning
+for UserService<AuthServiceImpl, UserDatastoreImpl>
+where
+    AuthServiceImpl: Send + Sync,
+    UserDatastoreImpl: UserDatastore + Send + Sync,
+{
+    fn provision_user<'a>(&'a self, username: &'a str, identity: &'a OidcIdentity) -> BoxFuture<'a, Result<(), AuthError>> {
+        Box::pin(async move {
+            if self.user_datastore.get_user_by_username(username).await.map_err(|_| AuthError::ServerError)?.is_some() {
+                return Ok(());
+            }
+
+            self.user_datastore.add_user(User {
+                id: None,
+                username: username.to_string(),
+                email: identity.email.clone().unwrap_or_default(),
+                created_at: DateTime::now(),
+            }).await.map_err(|_| AuthError::ServerError)?;
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+    use mockall::predicate::eq;
+    use mongodb::bson::DateTime;
+    use auth_module::utils::oidc::{OidcIdentity, OidcUserProvisioning};
+    use crate::datastore::MockUserDatastore;
+    use crate::entities::user::User;
+    use crate::services::UserService;
+
+    fn identity() -> OidcIdentity {
+        OidcIdentity {
+            provider: "provider".to_string(),
+            subject: "subject".to_string(),
+            email: Some("juliana@example.com".to_string()),
+            email_verified: true,
+            preferred_username: Some("juliana".to_string()),
+            name: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_provision_user_add_profile() {
+        let mut mock_user_datastore = MockUserDatastore::new();
+        mock_user_datastore.expect_get_user_by_username()
+            .with(eq("juliana"))
+            .times(1)
+            .returning(|_| Box::pin(future::ready(Ok(None))));
+        mock_user_datastore.expect_add_user()
+            .withf(|user| user.username == "juliana" && user.email == "juliana@example.com")
+            .times(1)
+            .returning(|user| Box::pin(future::ready(Ok(user))));
+        let user_service = UserService::new((), mock_user_datastore);
+
+        assert!(user_service.provision_user("juliana", &identity()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_provision_user_keep_existing_profile() {
+        let mut mock_user_datastore = MockUserDatastore::new();
+        mock_user_datastore.expect_get_user_by_username()
+            .times(1)
+            .returning(|username| Box::pin(future::ready(Ok(Some(User {
+                id: None,
+                username: username.to_string(),
+                email: "former@example.com".to_string(),
+                created_at: DateTime::now(),
+            })))));
+        mock_user_datastore.expect_add_user().never();
+        let user_service = UserService::new((), mock_user_datastore);
+
+        assert!(user_service.provision_user("juliana", &identity()).await.is_ok());
+    }
+}
@@ -0,0 +1,31 @@
+use futures_util::future::BoxFuture;
+use auth_module::entities::error::AuthError;
+use auth_module::datastore::{AuthDatastore, TokenDatastore};
+use auth_module::services::AuthService;
+use auth_module::utils::authorization_server::{UserClaims, UserClaimsProvider};
+use crate::datastore::UserDatastore;
+use crate::services::{UserGetService, UserService};
+
+/// Claims given to third-party apps, read from the private details of the user
+impl<AuthDatastoreImpl, TokenDatastoreImpl, UserDatastoreImpl> UserClaimsProvider
+for UserService<AuthService<AuthDatastoreImpl, TokenDatastoreImpl>, UserDatastoreImpl>
+where
+    AuthDatastoreImpl: AuthDatastore + 'static + Clone + Send + Sync,
+    TokenDatastoreImpl: TokenDatastore + 'static + Clone + Send + Sync,
+    UserDatastoreImpl: UserDatastore + 'static + Clone + Send + Sync,
+{
+    fn get_user_claims<'a>(&'a self, username: &'a str) -> BoxFuture<'a, Result<UserClaims, AuthError>> {
+        Box::pin(async move {
+            let user_private_details = self.get_user(username).await.map_err(|_| AuthError::NotFound)?;
+
+            Ok(UserClaims {
+                preferred_username: Some(user_private_details.username),
+                name: None,
+                email: Some(user_private_details.email).filter(|email| !email.is_empty()),
+                // Emails aren't verified at subscription
+                email_verified: Some(false),
+                updated_at: Some(user_private_details.last_modified_at.timestamp_millis() / 1000),
+            })
+        })
+    }
+}
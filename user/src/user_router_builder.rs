@@ -3,45 +3,98 @@ use std::sync::Arc;
 use axum::{Extension, Router};
 use axum::routing::{get, post};
 use mongodb::Database;
+use serde::Deserialize;
 use auth_module::datastore::{AuthDatastore, TokenDatastore};
+use auth_module::datastore::mongo::personal_access_tokens::MongoPersonalAccessTokenDatastore;
+use auth_module::datastore::mongo::roles::MongoRoleDatastore;
 use auth_module::datastore::mongo::tokens::MongoTokenDatastore;
 use auth_module::datastore::mongo::users::MongoAuthDatastore;
 use auth_module::entities::Privileges;
 use auth_module::layer::claims::AuthGuardLayer;
+use auth_module::layer::guard_expression::GuardExpression;
+use auth_module::layer::personal_access_tokens::{PersonalAccessTokenCheck, PersonalAccessTokenChecker};
+use auth_module::layer::revocation::TokenRevocationCheck;
+use auth_module::layer::roles::{RoleCheck, RoleChecker};
+use auth_module::utils::access_rules::{AccessRules, AccessRulesError, ModuleActions};
+use auth_module::utils::auth_config::AuthConfig;
+use auth_module::utils::authorization_server::UserClaimsProvider;
+use auth_module::utils::oidc::OidcUserProvisioning;
 use auth_module::services::{AuthCreateCredentialsService, AuthGetCredentialsService, AuthService, AuthTokensService, AuthValidCredentialsService};
 use crate::controller::add_user::add_user;
 use crate::controller::get_own_profile::get_own_profile;
+use crate::controller::get_user_profile::get_user_profile;
 use crate::datastore::mongo::MongoUserDatastore;
 use crate::datastore::UserDatastore;
 use crate::services::{UserService};
 
+/// Routes of the user module, named after their handler in the rules file. See `AccessRules`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
 pub enum UsersActions {
-    Create,
-    Get(String),
+    AddUser,
+    GetOwnProfile,
+    /// Owners of the profile are always allowed
+    GetUserProfile,
 }
+
+impl ModuleActions for UsersActions {
+    const MODULE: &'static str = "user";
+
+    fn default_privileges(&self) -> Privileges {
+        match self {
+            UsersActions::AddUser => Privileges::Anonymous,
+            UsersActions::GetOwnProfile => Privileges::Authenticated,
+            UsersActions::GetUserProfile => Privileges::ModeratorPrivileges,
+        }
+    }
+}
+
 pub struct UserRouterBuilder<AuthServiceImpl: AuthCreateCredentialsService + AuthGetCredentialsService, UserDatastoreImpl: UserDatastore> {
     user_service: Arc<UserService<AuthServiceImpl, UserDatastoreImpl>>,
-    rules: HashMap<UsersActions, fn() -> bool>,
+    rules: HashMap<UsersActions, Privileges>,
+    revocation_check: Option<Arc<dyn TokenRevocationCheck>>,
+    personal_access_token_check: Option<Arc<dyn PersonalAccessTokenCheck>>,
+    role_check: Option<Arc<dyn RoleCheck>>,
+    auth_config: AuthConfig,
 }
 
 impl UserRouterBuilder<AuthService<MongoAuthDatastore, MongoTokenDatastore>, MongoUserDatastore> {
-    pub fn new(auth_mongo_db: &Database, user_mongo_db: &Database) -> Self {
+    pub fn new(auth_mongo_db: &Database, user_mongo_db: &Database, auth_config: AuthConfig) -> Self {
         let user_datastore = MongoUserDatastore::new(user_mongo_db);
-        let auth_service = Self::build_auth_service(auth_mongo_db);
+        let auth_service = Self::build_auth_service(auth_mongo_db, auth_config.clone());
 
         Self {
             user_service: Arc::new(UserService::new(auth_service, user_datastore)),
             rules: Default::default(),
+            revocation_check: None,
+            personal_access_token_check: Some(Self::build_personal_access_token_check(auth_mongo_db)),
+            role_check: Some(Self::build_role_check(auth_mongo_db)),
+            auth_config,
         }
     }
 
-    fn build_auth_service(mongo_db: &Database) -> AuthService<MongoAuthDatastore, MongoTokenDatastore> {
+    fn build_personal_access_token_check(mongo_db: &Database) -> Arc<dyn PersonalAccessTokenCheck> {
+        Arc::new(PersonalAccessTokenChecker::new(
+            MongoAuthDatastore::new(mongo_db),
+            MongoPersonalAccessTokenDatastore::new(mongo_db),
+        ))
+    }
+
+    fn build_role_check(mongo_db: &Database) -> Arc<dyn RoleCheck> {
+        Arc::new(RoleChecker::new(
+            MongoRoleDatastore::new(mongo_db),
+            RoleChecker::<MongoRoleDatastore>::DEFAULT_CACHE_TTL,
+        ))
+    }
+
+    fn build_auth_service(mongo_db: &Database, auth_config: AuthConfig) -> AuthService<MongoAuthDatastore, MongoTokenDatastore> {
         let auth_datastore = MongoAuthDatastore::new(mongo_db);
         let token_datastore = MongoTokenDatastore::new(mongo_db);
 
         AuthService::new(
             auth_datastore,
             token_datastore,
+            auth_config,
         )
     }
 }
@@ -54,16 +107,100 @@ where
     AuthDatastoreImpl: AuthDatastore + Send + Sync + Clone + 'static,
     TokenDatastoreImpl: TokenDatastore + Send + Sync + Clone + 'static,
 {
+    /// Privileges of the routes set by a rules file, an action unknown to the user module is rejected
+    pub fn with_access_rules(mut self, access_rules: &AccessRules) -> Result<Self, AccessRulesError> {
+        self.rules = access_rules.module_rules::<UsersActions>()?;
+        Ok(self)
+    }
+
+    /// Reject revoked access tokens on authenticated routes. See `TokenRevocationChecker`
+    pub fn with_revocation_check(mut self, revocation_check: Arc<dyn TokenRevocationCheck>) -> Self {
+        self.revocation_check = Some(revocation_check);
+        self
+    }
+
+    /// Role definitions of runtime roles and permissions. See `RoleChecker`
+    pub fn with_role_check(mut self, role_check: Arc<dyn RoleCheck>) -> Self {
+        self.role_check = Some(role_check);
+        self
+    }
+
+    /// Create the profile of users at their first OpenID login. See `AuthRouterBuilder::with_oidc_user_provisioning`
+    pub fn oidc_user_provisioning(&self) -> Arc<dyn OidcUserProvisioning> {
+        self.user_service.clone()
+    }
+
+    /// Claims of users given to third-party apps. See `AuthorizationServer::with_user_claims_provider`
+    pub fn user_claims_provider(&self) -> Arc<dyn UserClaimsProvider> {
+        self.user_service.clone()
+    }
+
     pub fn into_router(self) -> Router {
+        let revocation_check = self.revocation_check;
+        let personal_access_token_check = self.personal_access_token_check;
+        let role_check = self.role_check;
+        let auth_config = self.auth_config;
+        let rules = self.rules;
+        let privileges = |action: UsersActions| GuardExpression::from(rules.get(&action).cloned().unwrap_or_else(|| action.default_privileges()));
+        let guard = |guard_expression: GuardExpression| AuthGuardLayer::new(auth_config.clone(), guard_expression)
+            .with_revocation_check(revocation_check.clone())
+            .with_personal_access_token_check(personal_access_token_check.clone())
+            .with_role_check(role_check.clone());
+
         Router::new()
             .route(
                 "/subscribe",
-                post(add_user::<UserService<AuthService<MongoAuthDatastore, MongoTokenDatastore>, UserDatastoreImpl>>).layer(AuthGuardLayer { privileges: Privileges::Anonymous }),
+                post(add_user::<UserService<AuthService<MongoAuthDatastore, MongoTokenDatastore>, UserDatastoreImpl>>).layer(guard(privileges(UsersActions::AddUser))),
             )
             .route(
                 "/me",
-                get(get_own_profile::<UserService<AuthService<MongoAuthDatastore, MongoTokenDatastore>, UserDatastoreImpl>>).layer(AuthGuardLayer { privileges: Privileges::Authenticated }),
+                get(get_own_profile::<UserService<AuthService<MongoAuthDatastore, MongoTokenDatastore>, UserDatastoreImpl>>).layer(guard(privileges(UsersActions::GetOwnProfile))),
+            )
+            .route(
+                "/users/{username}",
+                get(get_user_profile::<UserService<AuthService<MongoAuthDatastore, MongoTokenDatastore>, UserDatastoreImpl>>).layer(guard(GuardExpression::owner_or("username", privileges(UsersActions::GetUserProfile)))),
             )
             .layer(Extension(self.user_service))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use base64::Engine;
+    use base64::engine::general_purpose;
+    use mongodb::bson::DateTime;
+    use mongodb::Client;
+    use tower::ServiceExt;
+    use auth_module::entities::{Roles, Token, UserCredentials};
+    use auth_module::utils::key_ring::KeyRing;
+    use super::*;
+
+    const FAKE_SECRET_KEY: &[u8] = b"y8zar2SZhQoufiUpYSGF94eTzqJ8Q6xo4nFb3TeImqzVX9Bs0xCfK0fpt0g7OcrrQXnTgo2Sz3xBGOoc7ZJ50Q==";
+
+    fn auth_config() -> AuthConfig {
+        let mut key_ring = KeyRing::new();
+        key_ring.add_signing_key(&general_purpose::STANDARD.decode(FAKE_SECRET_KEY).unwrap()).unwrap();
+
+        AuthConfig::new(key_ring)
+    }
+
+    #[tokio::test]
+    async fn test_denied_user_profile_rejects_owner() {
+        let auth_config = auth_config();
+        // The client connects lazily : rejected requests never reach the database
+        let mongo_client = Client::with_uri_str("mongodb://localhost:27017").await.unwrap();
+        let access_rules = AccessRules::from_toml("[user]\nget_user_profile = \"Deny\"", None).unwrap();
+        let router = UserRouterBuilder::new(&mongo_client.database("auth"), &mongo_client.database("users"), auth_config.clone())
+            .with_access_rules(&access_rules)
+            .unwrap()
+            .into_router();
+        let user = UserCredentials { id: None, username: "john_doe".to_string(), password: String::new(), roles: vec![Roles::User], created_at: DateTime::now(), last_modified_at: DateTime::now() };
+        let (access_token, _, _) = Token::generate_tokens(&auth_config, &user).await.unwrap();
+
+        let request = Request::builder().uri("/users/john_doe").header("Authorization", format!("Bearer {access_token}")).body(Body::empty()).unwrap();
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -8,7 +8,7 @@ use crate::entities::user::User;
 pub struct UserPrivateDetails {
     pub(crate) username: String,
     pub(crate) email: String,
-    pub(crate) roles: Roles,
+    pub(crate) roles: Vec<Roles>,
     #[serde(with = "mongodb::bson::serde_helpers::bson_datetime_as_rfc3339_string")]
     pub(crate) created_at: DateTime,
     #[serde(with = "mongodb::bson::serde_helpers::bson_datetime_as_rfc3339_string")]